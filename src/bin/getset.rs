@@ -1,16 +1,67 @@
 use clap::Parser;
 use console::style;
 use getset::cli;
+use getset::interrupt::{EXIT_INTERRUPTED, Interrupted};
+use getset::runner::SHOW_DECISIONS;
+use std::fs::File;
+use std::sync::Mutex;
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::prelude::*;
+
+/// Send errors (or what `RUST_LOG` asks for) to stderr, and a debug log to `--log-file`
+fn init_tracing(app: &cli::App) -> Result<(), String> {
+    let default = if app.verbosity() >= SHOW_DECISIONS {
+        "error,getset=debug"
+    } else {
+        "error"
+    };
+    let console_filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default));
+    let console = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(console_filter);
+
+    let file = match app.log_file {
+        Some(ref path) => {
+            let file = File::create(path)
+                .map_err(|e| format!("Error creating log file '{}': {}", path.display(), e))?;
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .with_filter(EnvFilter::new("info,getset=debug"));
+            Some(layer)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(console)
+        .with(file)
+        .init();
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
-
     let app = cli::App::parse();
 
+    if let Err(e) = init_tracing(&app) {
+        eprintln!("{} {}", style("Error:").red().bold(), e);
+        std::process::exit(1);
+    }
+    tracing::debug!(
+        "getset {} started with {:?}",
+        env!("CARGO_PKG_VERSION"),
+        std::env::args().collect::<Vec<_>>()
+    );
+
     if let Err(e) = app.run().await {
+        tracing::debug!("getset failed: {}", e);
         eprintln!("\n{} {}", style("Error:").red().bold(), e);
 
+        if e.downcast_ref::<Interrupted>().is_some() {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
         std::process::exit(1);
     }
 }
@@ -0,0 +1,343 @@
+use crate::config::{CommandEntry, Config};
+use crate::state::{RunState, STATE_FILE};
+use color_eyre::eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the cache file stored next to the config file
+pub const CACHE_FILE: &str = ".getset-cache";
+
+/// Where fingerprints of steps with `sources` are stored, relative to the config file
+pub const FINGERPRINTS_FILE: &str = ".getset/fingerprints.json";
+
+/// Input hashes of steps from their last successful run
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    /// Input hash keyed by step title
+    entries: BTreeMap<String, String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Cache {
+    /// Load the cache stored in `base_dir`, starting empty if it is missing or unreadable
+    pub fn load(base_dir: &Path) -> Self {
+        Self::load_file(base_dir.join(CACHE_FILE))
+    }
+
+    /// Load the `sources` fingerprints stored in `base_dir`
+    pub fn load_fingerprints(base_dir: &Path) -> Self {
+        Self::load_file(base_dir.join(FINGERPRINTS_FILE))
+    }
+
+    fn load_file(path: PathBuf) -> Self {
+        let mut cache: Cache = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        cache.path = path;
+        cache
+    }
+
+    /// Whether the step last succeeded with exactly these inputs
+    pub fn is_fresh(&self, title: &str, hash: &str) -> bool {
+        self.entries.get(title).is_some_and(|cached| cached == hash)
+    }
+
+    /// Remember the inputs of a successful step and persist the cache
+    pub fn record(&mut self, title: &str, hash: String) -> Result<()> {
+        self.entries.insert(title.to_string(), hash);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| eyre!("Error creating '{}': {}", dir.display(), e))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&self.path, content)
+            .map_err(|e| eyre!("Error writing cache '{}': {}", self.path.display(), e))
+    }
+}
+
+/// Hash a step's command together with the files matched by its `inputs` globs
+///
+/// Globs are resolved relative to the step's config file. Files are hashed in
+/// sorted order so the result does not depend on directory iteration order.
+pub fn hash_inputs(cmd_entry: &CommandEntry) -> Result<String> {
+    hash_files(cmd_entry, &cmd_entry.inputs)
+}
+
+/// Hash a step's command together with the files matched by its `sources` globs
+pub fn hash_sources(cmd_entry: &CommandEntry) -> Result<String> {
+    hash_files(cmd_entry, &cmd_entry.sources)
+}
+
+/// Hash a step's command together with the files matched by its watched globs
+pub fn hash_watched(cmd_entry: &CommandEntry) -> Result<String> {
+    hash_files(cmd_entry, cmd_entry.watched())
+}
+
+/// Whether every `outputs` glob of the step matches at least one existing path
+pub fn outputs_exist(cmd_entry: &CommandEntry) -> Result<bool> {
+    Ok(missing_output(cmd_entry)?.is_none())
+}
+
+/// The first `outputs` glob of the step that matches no existing path
+fn missing_output(cmd_entry: &CommandEntry) -> Result<Option<&str>> {
+    for pattern in &cmd_entry.outputs {
+        if expand_glob(&cmd_entry.base_dir, pattern)?.next().is_none() {
+            return Ok(Some(pattern));
+        }
+    }
+    Ok(None)
+}
+
+/// Existing paths matching `pattern`, resolved relative to `base_dir`
+fn expand_glob(base_dir: &Path, pattern: &str) -> Result<impl Iterator<Item = PathBuf>> {
+    let full_pattern = base_dir.join(pattern);
+    let paths = glob::glob(&full_pattern.to_string_lossy())
+        .map_err(|e| eyre!("Invalid input glob '{}': {}", pattern, e))?;
+    Ok(paths.filter_map(|path| path.ok()))
+}
+
+fn hash_files(cmd_entry: &CommandEntry, patterns: &[String]) -> Result<String> {
+    let base_dir = &cmd_entry.base_dir;
+    let mut files = Vec::new();
+    for pattern in patterns {
+        files.extend(expand_glob(base_dir, pattern)?.filter(|path| path.is_file()));
+    }
+    files.sort();
+    files.dedup();
+
+    let mut hasher = Sha256::new();
+    hasher.update(cmd_entry.display_command().as_bytes());
+    for file in files {
+        let content = fs::read(&file)
+            .map_err(|e| eyre!("Error reading input '{}': {}", file.display(), e))?;
+        let relative = file.strip_prefix(base_dir).unwrap_or(&file);
+
+        hasher.update([0]);
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(&content);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Whether the next `getset up` would skip a step, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepStatus {
+    pub title: String,
+    pub skipped: bool,
+    pub reason: String,
+}
+
+/// Explain, step by step, what the stored fingerprints and run state mean for the next run
+pub fn status(config: &Config) -> Result<Vec<StepStatus>> {
+    let fingerprints = Cache::load_fingerprints(&config.base_dir);
+    let inputs = Cache::load(&config.base_dir);
+    let state = RunState::load(&config.base_dir);
+
+    let mut statuses = Vec::new();
+    for cmd_entry in &config.commands {
+        let (skipped, mut reason) = if !cmd_entry.sources.is_empty() {
+            let hash = hash_sources(cmd_entry)?;
+            match fingerprints.entries.get(&cmd_entry.title) {
+                None => (false, "runs: no successful run recorded".to_string()),
+                Some(recorded) if *recorded != hash => (
+                    false,
+                    "runs: sources or command changed since the last successful run".to_string(),
+                ),
+                Some(_) => match missing_output(cmd_entry)? {
+                    Some(pattern) => (false, format!("runs: output '{}' is missing", pattern)),
+                    None => (
+                        true,
+                        "skipped: sources unchanged and outputs present".to_string(),
+                    ),
+                },
+            }
+        } else if !cmd_entry.inputs.is_empty() {
+            let fresh = inputs.is_fresh(&cmd_entry.title, &hash_inputs(cmd_entry)?);
+            if fresh {
+                (
+                    false,
+                    "runs: inputs unchanged, so skipped with --incremental".to_string(),
+                )
+            } else {
+                (
+                    false,
+                    "runs: inputs changed since the last successful run".to_string(),
+                )
+            }
+        } else {
+            (false, "runs: no sources to fingerprint".to_string())
+        };
+
+        if state.completed.contains(&cmd_entry.title) {
+            reason.push_str("; completed before the last failure, so skipped with --resume");
+        }
+        statuses.push(StepStatus {
+            title: cmd_entry.title.clone(),
+            skipped,
+            reason,
+        });
+    }
+    Ok(statuses)
+}
+
+/// Remove the stored fingerprints, input hashes and run state in `base_dir`
+///
+/// Returns the files that existed and were removed.
+pub fn clear(base_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for file in [FINGERPRINTS_FILE, CACHE_FILE, STATE_FILE] {
+        let path = base_dir.join(file);
+        match fs::remove_file(&path) {
+            Ok(()) => removed.push(path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(eyre!("Error removing '{}': {}", path.display(), e)),
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("getset-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entry_with_inputs(dir: &Path, inputs: &[&str]) -> CommandEntry {
+        CommandEntry {
+            title: "Install".to_string(),
+            command: Some("true".to_string()),
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            base_dir: dir.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hash_changes_with_input_content() {
+        let dir = temp_dir("content");
+        fs::write(dir.join("a.lock"), "v1").unwrap();
+        let cmd = entry_with_inputs(&dir, &["*.lock"]);
+
+        let first = hash_inputs(&cmd).unwrap();
+        assert_eq!(first, hash_inputs(&cmd).unwrap());
+
+        fs::write(dir.join("a.lock"), "v2").unwrap();
+        assert_ne!(first, hash_inputs(&cmd).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = temp_dir("round-trip");
+
+        let mut cache = Cache::load(&dir);
+        assert!(!cache.is_fresh("Install", "abc"));
+
+        cache.record("Install", "abc".to_string()).unwrap();
+
+        let reloaded = Cache::load(&dir);
+        assert!(reloaded.is_fresh("Install", "abc"));
+        assert!(!reloaded.is_fresh("Install", "def"));
+        assert!(!reloaded.is_fresh("Other", "abc"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_outputs_must_all_exist() {
+        let dir = temp_dir("outputs");
+        let mut cmd = entry_with_inputs(&dir, &[]);
+        cmd.outputs = vec!["vendor/bundle".to_string(), "*.log".to_string()];
+
+        fs::create_dir_all(dir.join("vendor/bundle")).unwrap();
+        assert!(!outputs_exist(&cmd).unwrap());
+
+        fs::write(dir.join("install.log"), "").unwrap();
+        assert!(outputs_exist(&cmd).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprints_are_stored_under_getset_dir() {
+        let dir = temp_dir("fingerprints");
+
+        let mut fingerprints = Cache::load_fingerprints(&dir);
+        fingerprints.record("Install", "abc".to_string()).unwrap();
+
+        assert!(dir.join(FINGERPRINTS_FILE).exists());
+        assert!(Cache::load_fingerprints(&dir).is_fresh("Install", "abc"));
+        assert!(!Cache::load(&dir).is_fresh("Install", "abc"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_status_explains_each_step_and_clear_resets_it() {
+        let dir = temp_dir("status");
+        fs::write(dir.join("deps.lock"), "v1").unwrap();
+        let mut install = entry_with_inputs(&dir, &[]);
+        install.sources = vec!["deps.lock".to_string()];
+        let config = Config {
+            commands: vec![
+                install.clone(),
+                CommandEntry {
+                    title: "Migrate".to_string(),
+                    command: Some("true".to_string()),
+                    ..Default::default()
+                },
+            ],
+            base_dir: dir.clone(),
+            ..Default::default()
+        };
+
+        let statuses = status(&config).unwrap();
+        assert!(!statuses[0].skipped);
+        assert_eq!(statuses[0].reason, "runs: no successful run recorded");
+        assert_eq!(statuses[1].reason, "runs: no sources to fingerprint");
+
+        let mut fingerprints = Cache::load_fingerprints(&dir);
+        fingerprints
+            .record("Install", hash_sources(&install).unwrap())
+            .unwrap();
+        assert!(status(&config).unwrap()[0].skipped);
+
+        assert_eq!(clear(&dir).unwrap(), [dir.join(FINGERPRINTS_FILE)]);
+        assert!(!status(&config).unwrap()[0].skipped);
+        assert!(clear(&dir).unwrap().is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_glob_errors() {
+        let dir = temp_dir("invalid-glob");
+        let cmd = entry_with_inputs(&dir, &["[unclosed"]);
+
+        let result = hash_inputs(&cmd);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Invalid input glob")
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
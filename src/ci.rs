@@ -0,0 +1,213 @@
+use crate::config::CommandEntry;
+use crate::redact;
+use clap::ValueEnum;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Whether getset behaves as in CI, set once at startup by [`init`]
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Variables set by CI systems: the common `CI`, then GitHub Actions, GitLab,
+/// Buildkite, CircleCI, Travis, Jenkins, TeamCity, Azure Pipelines, Bitbucket,
+/// Drone and CodeBuild
+const CI_VARS: [&str; 12] = [
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "JENKINS_URL",
+    "TEAMCITY_VERSION",
+    "TF_BUILD",
+    "BITBUCKET_BUILD_NUMBER",
+    "DRONE",
+    "CODEBUILD_BUILD_ID",
+];
+
+/// CI system whose log markup getset writes around each step
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CiFormat {
+    /// GitHub Actions workflow commands: collapsible groups and error annotations
+    Github,
+    /// GitLab CI collapsible sections
+    Gitlab,
+}
+
+/// The CI system getset is running under, if it has log markup getset supports
+pub fn detect() -> Option<CiFormat> {
+    let is_set = |var: &str| env::var(var).as_deref() == Ok("true");
+    if is_set("GITHUB_ACTIONS") {
+        Some(CiFormat::Github)
+    } else if is_set("GITLAB_CI") {
+        Some(CiFormat::Gitlab)
+    } else {
+        None
+    }
+}
+
+/// Whether the environment looks like a CI job
+pub fn detected() -> bool {
+    CI_VARS.iter().any(|var| {
+        env::var(var).is_ok_and(|value| !value.is_empty() && value != "false" && value != "0")
+    })
+}
+
+/// Turn CI mode on or off for the rest of the run, as `--ci` or `--no-ci` say or else as detected
+///
+/// In CI mode getset never waits for answers: prompts without a default or an
+/// environment variable fail, as do `confirm` steps without `--yes`. Steps get
+/// no PTY, and their output lines are timestamped. Returns whether it is on.
+pub fn init(choice: Option<bool>) -> bool {
+    let active = choice.unwrap_or_else(detected);
+    ACTIVE.store(active, Ordering::Relaxed);
+    active
+}
+
+/// Whether CI mode is on
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Whether getset may ask the user questions: on a terminal, outside CI mode
+pub fn interactive() -> bool {
+    !active() && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Line opening a collapsible section of the log for a step, headed by `label`
+pub fn start_section(format: CiFormat, cmd_entry: &CommandEntry, label: &str) -> String {
+    match format {
+        CiFormat::Github => format!("::group::{}", escape_data(label)),
+        CiFormat::Gitlab => format!(
+            "\x1b[0Ksection_start:{}:{}[collapsed=true]\r\x1b[0K{}",
+            unix_time(),
+            section_name(cmd_entry),
+            label
+        ),
+    }
+}
+
+/// Line closing the step's section
+pub fn end_section(format: CiFormat, cmd_entry: &CommandEntry) -> String {
+    match format {
+        CiFormat::Github => "::endgroup::".to_string(),
+        CiFormat::Gitlab => format!(
+            "\x1b[0Ksection_end:{}:{}\r\x1b[0K",
+            unix_time(),
+            section_name(cmd_entry)
+        ),
+    }
+}
+
+/// GitLab section name for a step, which may only use letters, digits, `_`, `.` and `-`
+fn section_name(cmd_entry: &CommandEntry) -> String {
+    let name = cmd_entry.id.as_deref().unwrap_or(&cmd_entry.title);
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("getset_{}", name)
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// Annotation marking a failed step in the CI's summary of the job, if it has one
+///
+/// Failures allowed by `allow_failure` are reported as warnings.
+pub fn failure_annotation(format: CiFormat, cmd_entry: &CommandEntry) -> Option<String> {
+    match format {
+        CiFormat::Github => {
+            let level = if cmd_entry.allow_failure {
+                "warning"
+            } else {
+                "error"
+            };
+            Some(format!(
+                "::{} title={}::{}",
+                level,
+                escape_property(&format!("Step failed: {}", cmd_entry.title)),
+                escape_data(&format!(
+                    "'{}' failed running: {}",
+                    cmd_entry.title,
+                    redact::redact(&cmd_entry.display_command())
+                ))
+            ))
+        }
+        CiFormat::Gitlab => None,
+    }
+}
+
+/// Escape the message of a GitHub workflow command
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value of a GitHub workflow command
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_groups_escape_step_labels() {
+        let cmd = CommandEntry {
+            title: "Build".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            start_section(CiFormat::Github, &cmd, "[1/2] Build 100%"),
+            "::group::[1/2] Build 100%25"
+        );
+        assert_eq!(end_section(CiFormat::Github, &cmd), "::endgroup::");
+    }
+
+    #[test]
+    fn test_gitlab_sections_are_named_after_the_step() {
+        let cmd = CommandEntry {
+            title: "Install Node packages".to_string(),
+            ..Default::default()
+        };
+
+        let start = start_section(CiFormat::Gitlab, &cmd, "[2/5] Install Node packages");
+        assert!(start.starts_with("\x1b[0Ksection_start:"));
+        assert!(start.ends_with(
+            ":getset_install_node_packages[collapsed=true]\r\x1b[0K[2/5] Install Node packages"
+        ));
+        assert!(
+            end_section(CiFormat::Gitlab, &cmd).ends_with(":getset_install_node_packages\r\x1b[0K")
+        );
+        assert_eq!(failure_annotation(CiFormat::Gitlab, &cmd), None);
+    }
+
+    #[test]
+    fn test_github_annotation_names_step_and_command() {
+        let cmd = CommandEntry {
+            title: "Migrate: db, cache".to_string(),
+            command: Some("rails db:migrate\nrails cache:clear".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            failure_annotation(CiFormat::Github, &cmd).unwrap(),
+            "::error title=Step failed%3A Migrate%3A db%2C cache::'Migrate: db, cache' failed running: rails db:migrate%0Arails cache:clear"
+        );
+    }
+}
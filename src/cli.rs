@@ -1,12 +1,36 @@
-use crate::config::{CommandEntry, Config};
-use crate::platformx::{self, PlatformXClient};
-use crate::runner;
-use clap::{Parser, Subcommand};
-use color_eyre::Section;
+use crate::cache;
+use crate::ci::{self, CiFormat};
+use crate::config::{self, CommandEntry, Config, ServiceConfig};
+use crate::doctor;
+use crate::events::{self, Emitter, LogFormat, OutputFormat};
+use crate::export::{self, ExportFormat};
+use crate::glyphs::{self, ColorChoice};
+use crate::graph::{self, GraphFormat};
+use crate::hooks;
+use crate::import;
+use crate::interrupt;
+use crate::logs::LOGS_DIR;
+use crate::orchestrator::{self, RunOptions, RunSummary, StepResult};
+use crate::picker;
+use crate::platformx;
+use crate::prompts;
+use crate::redact;
+use crate::report::{self, GITHUB_STEP_SUMMARY, HTML_REPORT_FILE, ReportFormat};
+use crate::runs::{self, Change, RunRecord};
+use crate::services;
+use crate::state::{RunState, STATE_FILE};
+use crate::telemetry::Telemetry;
+use crate::timings::{self, Regression, Timings};
+use crate::tui;
+use crate::watch;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand};
 use color_eyre::eyre::{Result, eyre};
 use console::style;
-use std::path::PathBuf;
-use std::time::Instant;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "getset")]
@@ -14,165 +38,1934 @@ use std::time::Instant;
 pub struct App {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Also write a timestamped debug log of the run to this file, e.g. for bug reports
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// When to color output; `never` (or setting NO_COLOR) also replaces symbols and emoji with ASCII
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Behave as in CI: no prompts or PTYs, plain output and timestamped lines (default when a CI environment is detected)
+    #[arg(long, global = true, conflicts_with = "no_ci")]
+    pub ci: bool,
+
+    /// Behave as on a terminal even when a CI environment is detected
+    #[arg(long, global = true)]
+    pub no_ci: bool,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Run commands from a TOML file
     Up(UpCommand),
+    /// Run the teardown steps from a TOML file
+    Down(DownCommand),
+    /// Run exactly one step, chosen by its id
+    Run(RunCommand),
+    /// Choose steps from a fuzzy-searchable list, then run them
+    Pick(PickCommand),
+    /// Run steps on a full-screen dashboard with live statuses and output
+    Tui(TuiCommand),
+    /// Run the long-running services of a Procfile or [[services]] section together
+    Start(StartCommand),
+    /// List the steps (with ids and tags) in a TOML file without running them
+    List(ListCommand),
+    /// Write a starter getset.toml
+    Init(InitCommand),
+    /// Check TOML files for errors without running anything
+    Validate(ValidateCommand),
+    /// Check the prerequisites declared in a TOML file's [doctor] section
+    Doctor(DoctorCommand),
+    /// Print a shell completion script
+    Completions(CompletionsCommand),
+    /// Draw the step dependency graph as a tree, DOT or Mermaid diagram
+    Graph(GraphCommand),
+    /// List recorded runs, or compare step durations between two of them
+    Report(ReportCommand),
+    /// Inspect or reset the stored step fingerprints and run state
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Write a getset.toml from another tool's task definitions
+    #[command(subcommand)]
+    Import(ImportCommand),
+    /// Write the steps as a justfile or Makefile
+    Export(ExportCommand),
+    /// Install or remove git hooks that run steps after pulls and checkouts
+    #[command(subcommand)]
+    Hooks(HooksCommand),
 }
 
 #[derive(Parser)]
 pub struct UpCommand {
-    /// Path to the TOML file containing commands (defaults to getset.toml)
+    /// Paths (or URLs pinned with #sha256=<hash>) of TOML files containing commands, run in order (defaults to getset.toml)
     #[arg(default_value = "getset.toml")]
-    pub file: PathBuf,
+    pub file: Vec<PathBuf>,
 
-    /// Show verbose logging
+    /// Also set up every project below the config's directory that has its own getset.toml
+    #[arg(long, short = 'r')]
+    pub recursive: bool,
+
+    /// Show more detail: -v commands, -vv env, cwd and skip conditions, -vvv runner internals
+    #[arg(long, short = 'v', action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Hide step output unless the step fails
+    #[arg(long, short = 'q')]
+    pub quiet: bool,
+
+    /// Show only stderr from the output of failed steps
     #[arg(long)]
-    pub verbose: bool,
+    pub stderr_only: bool,
+
+    /// Directory for per-run step logs (defaults to .getset/logs next to the config)
+    #[arg(long, value_name = "DIR")]
+    pub log_dir: Option<PathBuf>,
+
+    /// Do not write step logs
+    #[arg(long, conflicts_with = "log_dir")]
+    pub no_logs: bool,
 
     /// Show profiling report at the end
     #[arg(long)]
     pub report: bool,
 
+    /// Format of the report; implies --report
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub report_format: Option<ReportFormat>,
+
+    /// Where to write the report instead of printing it (HTML defaults to .getset/report.html)
+    #[arg(long, value_name = "PATH")]
+    pub report_file: Option<PathBuf>,
+
     /// Run only steps matching this substring (case-insensitive)
     #[arg(long)]
     pub step: Option<String>,
+
+    /// Match --step against the exact step title instead of a substring
+    #[arg(long, requires = "step")]
+    pub step_exact: bool,
+
+    /// Run only steps with this tag (repeat to match any of several)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Start at this step (id or title), skipping the steps before it
+    #[arg(long, value_name = "STEP")]
+    pub from: Option<String>,
+
+    /// Stop after this step (id or title), skipping the steps after it
+    #[arg(long, value_name = "STEP")]
+    pub until: Option<String>,
+
+    /// Skip the steps that completed before the last run failed
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Maximum number of `parallel` steps to run at once
+    #[arg(long, short = 'j', value_name = "N", default_value_t = default_jobs())]
+    pub jobs: usize,
+
+    /// Skip steps whose `inputs` are unchanged since their last successful run
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// Prompt for which steps to run when --step matches several (terminals only)
+    #[arg(long)]
+    pub pick: bool,
+
+    /// Keep running the remaining steps after a step fails, then summarize failures
+    #[arg(long, visible_alias = "keep-going")]
+    pub continue_on_error: bool,
+
+    /// With --continue-on-error, stop once N steps have failed (0 = never bail)
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        requires = "continue_on_error"
+    )]
+    pub bail_after: u32,
+
+    /// Run steps marked `confirm` without asking, e.g. in CI
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Before each step, choose whether to run it, skip it, see its command or abort
+    #[arg(long, short = 'i', conflicts_with_all = ["yes", "watch"])]
+    pub interactive: bool,
+
+    /// Send no PlatformX events, even if the config has a [platformx] section (also GETSET_NO_TELEMETRY=1 or DO_NOT_TRACK=1); Slack, webhook and metrics sinks still send
+    #[arg(long)]
+    pub no_telemetry: bool,
+
+    /// Re-run the selected steps N times, stopping at the first failed iteration (0 = until failure)
+    #[arg(long, value_name = "N")]
+    pub repeat: Option<u32>,
+
+    /// Print the ordered steps that would run without executing them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Run only this project's steps, without first setting up the projects it `requires`
+    #[arg(long)]
+    pub skip_requires: bool,
+
+    /// Run the [[teardown]] steps if the run is interrupted with Ctrl-C
+    #[arg(long)]
+    pub teardown_on_interrupt: bool,
+
+    /// After the run, keep re-running steps whose `sources` or `watch` files change
+    #[arg(long, conflicts_with = "repeat")]
+    pub watch: bool,
+
+    /// Format for getset's own lifecycle output
+    #[arg(long, value_enum, default_value_t = LogFormat::Pretty)]
+    pub log_format: LogFormat,
+
+    /// CI log markup to write around each step (detected from the environment by default)
+    #[arg(long, value_enum, value_name = "CI")]
+    pub ci_format: Option<CiFormat>,
+
+    /// Write newline-delimited JSON events, including step output, to stdout instead of styled output
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human, conflicts_with = "log_format")]
+    pub output: OutputFormat,
+}
+
+#[derive(Parser)]
+pub struct DownCommand {
+    /// Paths to TOML files containing teardown steps, run in order (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+
+    /// Show more detail: -v commands, -vv env, cwd and skip conditions, -vvv runner internals
+    #[arg(long, short = 'v', action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Show profiling report at the end
+    #[arg(long)]
+    pub report: bool,
+
+    /// Run only teardown steps matching this substring (case-insensitive)
+    #[arg(long)]
+    pub step: Option<String>,
+
+    /// Match --step against the exact step title instead of a substring
+    #[arg(long, requires = "step")]
+    pub step_exact: bool,
+
+    /// Run steps marked `confirm` without asking
+    #[arg(long, short = 'y')]
+    pub yes: bool,
 }
 
-#[derive(Debug)]
-struct CommandResult {
-    title: String,
-    duration: std::time::Duration,
+#[derive(Parser)]
+pub struct PickCommand {
+    /// Paths to TOML files containing commands (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+
+    /// Show more detail: -v commands, -vv env, cwd and skip conditions, -vvv runner internals
+    #[arg(long, short = 'v', action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Show profiling report at the end
+    #[arg(long)]
+    pub report: bool,
+
+    /// Run steps marked `confirm` without asking
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+}
+
+#[derive(Parser)]
+pub struct TuiCommand {
+    /// Paths to TOML files containing commands (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+
+    /// Run only commands matching this substring (case-insensitive)
+    #[arg(long)]
+    pub step: Option<String>,
+
+    /// Run only steps with this tag (repeat to match any of several)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Run steps marked `confirm` without asking
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+}
+
+#[derive(Parser)]
+pub struct StartCommand {
+    /// Procfile, or TOML file with [[services]] (defaults to getset.toml if it has services, else Procfile)
+    pub file: Option<PathBuf>,
+
+    /// Start only this service (repeat to start several)
+    #[arg(long, value_name = "NAME")]
+    pub only: Vec<String>,
+}
+
+#[derive(Parser)]
+pub struct RunCommand {
+    /// Id of the step to run (the steps it needs are not run)
+    pub id: String,
+
+    /// Paths to TOML files containing commands (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+
+    /// Show more detail: -v commands, -vv env, cwd and skip conditions, -vvv runner internals
+    #[arg(long, short = 'v', action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Show profiling report at the end
+    #[arg(long)]
+    pub report: bool,
+
+    /// Run the step without asking, if it is marked `confirm`
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+}
+
+#[derive(Parser)]
+pub struct ListCommand {
+    /// Paths to TOML files containing commands (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+
+    /// Also show each step's command
+    #[arg(long)]
+    pub verbose: bool,
+}
+
+#[derive(Parser)]
+pub struct InitCommand {
+    /// Path of the TOML file to create
+    #[arg(default_value = "getset.toml")]
+    pub file: PathBuf,
+
+    /// Overwrite the file if it already exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Parser)]
+pub struct ValidateCommand {
+    /// Paths to TOML files to check together (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct DoctorCommand {
+    /// Paths to TOML files declaring prerequisites (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct CompletionsCommand {
+    /// Shell to generate completions for
+    #[arg(value_enum)]
+    pub shell: clap_complete::Shell,
+}
+
+#[derive(Parser)]
+pub struct GraphCommand {
+    /// Paths to TOML files containing commands (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+
+    /// Diagram format
+    #[arg(long, value_enum, default_value_t = GraphFormat::Tree)]
+    pub format: GraphFormat,
+}
+
+#[derive(Parser)]
+pub struct ReportCommand {
+    /// Path to the TOML file whose runs to report on (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: PathBuf,
+
+    /// Compare step durations against this earlier run
+    #[arg(long, value_name = "RUN")]
+    pub compare: Option<String>,
+
+    /// Run to compare (defaults to the latest)
+    #[arg(long, value_name = "RUN", requires = "compare")]
+    pub run: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Show whether the next run would skip each step, and why
+    Status {
+        /// Paths to TOML files containing commands (defaults to getset.toml)
+        #[arg(default_value = "getset.toml")]
+        file: Vec<PathBuf>,
+    },
+    /// Forget the stored fingerprints, input hashes and run state
+    Clear {
+        /// Paths to TOML files containing commands (defaults to getset.toml)
+        #[arg(default_value = "getset.toml")]
+        file: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum HooksCommand {
+    /// Write the git hooks listed in [hooks], each running `getset up` with its tags
+    Install {
+        /// Path to the TOML file the hooks run (defaults to getset.toml)
+        #[arg(default_value = "getset.toml")]
+        file: PathBuf,
+
+        /// Replace hooks that getset did not write
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove the git hooks written by `getset hooks install`
+    Uninstall {
+        /// Path to a TOML file in the repository (defaults to getset.toml)
+        #[arg(default_value = "getset.toml")]
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportCommand {
+    /// Turn the targets of a Makefile into steps, with prerequisites as `needs`
+    Makefile {
+        /// Makefile to read
+        #[arg(default_value = "Makefile")]
+        path: PathBuf,
+
+        /// Import only this target and the targets it depends on (repeatable; defaults to all)
+        #[arg(long, value_name = "TARGET")]
+        target: Vec<String>,
+
+        #[command(flatten)]
+        output: ImportOutput,
+    },
+    /// Turn the scripts of a package.json into steps that `npm run` them
+    Npm {
+        /// package.json to read
+        #[arg(default_value = "package.json")]
+        path: PathBuf,
+
+        /// Import only scripts whose names start with this, e.g. `setup:`
+        #[arg(long)]
+        prefix: Option<String>,
+
+        #[command(flatten)]
+        output: ImportOutput,
+    },
+}
+
+#[derive(Parser)]
+pub struct ExportCommand {
+    /// Paths to TOML files containing commands (defaults to getset.toml)
+    #[arg(default_value = "getset.toml")]
+    pub file: Vec<PathBuf>,
+
+    /// File format to write
+    #[arg(long, value_enum)]
+    pub format: ExportFormat,
+
+    /// Write to this file instead of stdout
+    #[arg(long, short = 'o')]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct ImportOutput {
+    /// Path of the TOML file to write
+    #[arg(long, short = 'o', default_value = "getset.toml")]
+    pub output: PathBuf,
+
+    /// Overwrite the file if it already exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Starter configuration written by `getset init`
+const INIT_TEMPLATE: &str = include_str!("init_template.toml");
+
+/// Default for `--jobs`: the number of available CPUs
+fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
 }
 
 impl App {
+    /// The `-v` level of the command, for deciding how much to log
+    pub fn verbosity(&self) -> u8 {
+        match self.command {
+            Commands::Up(ref cmd) => cmd.verbose,
+            Commands::Down(ref cmd) => cmd.verbose,
+            Commands::Run(ref cmd) => cmd.verbose,
+            Commands::Pick(ref cmd) => cmd.verbose,
+            _ => 0,
+        }
+    }
+
     pub async fn run(self) -> Result<()> {
+        let ci_mode = ci::init(match (self.ci, self.no_ci) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        });
+        // CI logs get plain output unless colors are asked for
+        glyphs::init(if ci_mode && self.color == ColorChoice::Auto {
+            ColorChoice::Never
+        } else {
+            self.color
+        });
+
         match self.command {
             Commands::Up(cmd) => cmd.run().await,
+            Commands::Down(cmd) => cmd.run(),
+            Commands::Run(cmd) => cmd.run(),
+            Commands::Pick(cmd) => cmd.run(),
+            Commands::Tui(cmd) => cmd.run(),
+            Commands::Start(cmd) => cmd.run(),
+            Commands::List(cmd) => cmd.run(),
+            Commands::Init(cmd) => cmd.run(),
+            Commands::Validate(cmd) => cmd.run(),
+            Commands::Doctor(cmd) => cmd.run(),
+            Commands::Graph(cmd) => cmd.run(),
+            Commands::Report(cmd) => cmd.run(),
+            Commands::Cache(cmd) => cmd.run(),
+            Commands::Hooks(cmd) => cmd.run(),
+            Commands::Import(cmd) => cmd.run(),
+            Commands::Export(cmd) => cmd.run(),
+            Commands::Completions(cmd) => {
+                cmd.run();
+                Ok(())
+            }
         }
     }
 }
 
 impl UpCommand {
-    pub async fn run(self) -> Result<()> {
-        let config = Config::from_file(&self.file)?;
+    pub async fn run(mut self) -> Result<()> {
+        if self.output == OutputFormat::Json {
+            self.log_format = LogFormat::Stream;
+        }
+        if self.interactive && !ci::interactive() {
+            return Err(eyre!("--interactive needs a terminal to ask on"));
+        }
 
+        let mut config = if self.recursive {
+            Config::from_files_recursive(&self.file)?
+        } else {
+            Config::from_files(&self.file)?
+        };
+        let mut state = RunState::load(&config.base_dir);
+        let mut timings = Timings::load(&config.base_dir);
         // Get default metadata for telemetry
-        let default_metadata = platformx::get_globals();
+        let default_metadata = platformx::get_globals(&config.base_dir);
 
-        // Initialize PlatformX client if configured
-        let platformx_client = config
-            .platformx
-            .as_ref()
-            .map(|px_config| PlatformXClient::new(px_config.clone(), default_metadata.clone()));
+        let options = RunOptions {
+            verbose: self.verbose,
+            step: self.step.clone(),
+            step_exact: self.step_exact,
+            tags: self.tags.clone(),
+            continue_on_error: self.continue_on_error,
+            yes: self.yes,
+            interactive: self.interactive,
+            // Only a single run has someone watching to answer
+            recover: !self.watch && self.repeat.is_none(),
+            prompter: None,
+            detached: false,
+            bail_after: self.bail_after,
+            jobs: self.jobs,
+            incremental: self.incremental,
+            pick: self.pick,
+            picked: None,
+            log_format: self.log_format,
+            quiet: self.quiet,
+            stderr_only: self.stderr_only,
+            log_dir: if self.no_logs {
+                None
+            } else {
+                Some(
+                    self.log_dir
+                        .clone()
+                        .unwrap_or_else(|| config.base_dir.join(LOGS_DIR)),
+                )
+            },
+            id: None,
+            from: self.from.clone(),
+            until: self.until.clone(),
+            completed: if self.resume {
+                state.completed.clone()
+            } else {
+                Vec::new()
+            },
+            typical: timings.typical_all(),
+            ci: self
+                .ci_format
+                .or_else(ci::detect)
+                .filter(|_| self.log_format == LogFormat::Pretty),
+            timestamps: ci::active(),
+            uuid: Some(default_metadata.run_id.clone()),
+            executor: None,
+            event_handler: None,
+        };
+
+        if self.dry_run {
+            if !self.skip_requires {
+                for (path, required) in config.required_configs(&self.file)? {
+                    println!(
+                        "{} Required project {} is set up first",
+                        style("Info:").cyan().bold(),
+                        style(path.display()).bold()
+                    );
+                    print_plan(&orchestrator::select_commands(
+                        &required,
+                        &RunOptions::default(),
+                    )?);
+                }
+            }
+            let commands = orchestrator::select_commands(&config, &options)?;
+            print_plan(&commands);
+            return Ok(());
+        }
+
+        let answers = prompts::resolve(&config.prompts, &config.base_dir)?;
+        config.export_env(&answers);
+        interrupt::install_handler();
+
+        if !self.skip_requires {
+            set_up_requirements(&config, &self.file, &options)?;
+        }
+
+        if self.watch {
+            let summary = orchestrator::run(&config, options.clone())?;
+            if !summary.success() {
+                println!("\n{}", style(failure_message(&summary)).red());
+            }
+            return watch::watch(&config, &options);
+        }
+
+        let platformx = !self.no_telemetry && !platformx::opted_out();
+        let telemetry = Telemetry::from_config(&config, &default_metadata, platformx)?;
 
         let timer = Instant::now();
+        telemetry.start().await;
 
-        if let Some(ref client) = platformx_client {
-            // ignore errors to avoid failing due to tracking
-            let _ = client.send_start().await;
-        }
-
-        // Filter commands based on --step argument if provided
-        let commands_to_run: Vec<&CommandEntry> = if let Some(ref step_filter) = self.step {
-            let matches: Vec<&CommandEntry> = config
-                .commands
-                .iter()
-                .filter(|cmd| {
-                    cmd.title
-                        .to_lowercase()
-                        .contains(&step_filter.to_lowercase())
-                })
-                .collect();
-
-            if matches.is_empty() {
-                return Err(eyre!(
-                    "{} No steps found matching '{}'",
-                    style("Error:").red().bold(),
-                    step_filter
-                ));
+        let mut iteration = 0;
+        let summary = loop {
+            iteration += 1;
+
+            if self.repeat.is_some() && self.log_format == LogFormat::Pretty {
+                println!(
+                    "{}",
+                    style(format!(
+                        "{} iteration {} {}",
+                        glyphs::RULE,
+                        iteration,
+                        glyphs::RULE
+                    ))
+                    .bold()
+                    .dim()
+                );
+            }
+
+            // Each iteration is a run of its own, with its own logs and record
+            let options = RunOptions {
+                uuid: (iteration == 1).then(|| default_metadata.run_id.clone()),
+                ..options.clone()
+            };
+            let summary = orchestrator::run(&config, options)?;
+            if let Err(e) = RunRecord::from_summary(&summary).save(&config.base_dir) {
+                tracing::warn!("{}", e);
+            }
+            let finished = match self.repeat {
+                None => true,
+                Some(0) => false,
+                Some(n) => iteration >= n,
+            };
+
+            if !summary.success() || finished {
+                break summary;
+            }
+        };
+
+        let regressions = timings.regressions(&summary);
+        timings.record(&summary);
+        if let Err(e) = timings.save() {
+            tracing::warn!("{}", e);
+        }
+
+        if !summary.success() {
+            if self.log_format == LogFormat::Pretty {
+                print_projects(&summary);
+            }
+            if let Some(ref dir) = summary.log_dir
+                && self.log_format == LogFormat::Pretty
+            {
+                println!(
+                    "\n{} Step logs saved to {}",
+                    style("Info:").cyan().bold(),
+                    dir.display()
+                );
             }
 
-            if matches.len() > 1 {
+            // A continue-on-error run that reached every step has nothing left to resume
+            let stopped_early = !self.continue_on_error || summary.stopped_early();
+            if stopped_early && self.log_format == LogFormat::Pretty {
                 println!(
-                    "{} Found {} steps matching '{}':",
+                    "\n{} Completed {} steps before stopping",
                     style("Info:").cyan().bold(),
-                    matches.len(),
-                    step_filter
+                    summary.progress()
                 );
-                for (i, cmd) in matches.iter().enumerate() {
-                    println!("  {}. {}", i + 1, style(&cmd.title).cyan());
-                }
-                println!();
             }
 
-            matches
-        } else {
-            config.commands.iter().collect()
-        };
+            state.update(&summary);
+            if let Err(e) = state.save() {
+                tracing::warn!("{}", e);
+            } else if stopped_early && self.log_format == LogFormat::Pretty {
+                println!(
+                    "\n{} Progress saved to {}; run `getset up --resume` to skip the {} completed steps",
+                    style("Info:").cyan().bold(),
+                    STATE_FILE,
+                    state.completed.len()
+                );
+            }
 
-        let mut results = Vec::new();
+            let elapsed = timer.elapsed();
+            let interruption = summary.interruption();
+            let mut error_msg = match interruption {
+                Some(ref interrupted) => interrupted.to_string(),
+                None => failure_message(&summary),
+            };
+            if self.repeat.is_some() {
+                error_msg = format!("Iteration {} failed: {}", iteration, error_msg);
+            }
 
-        for cmd_entry in commands_to_run.iter() {
-            match runner::run_command(cmd_entry, self.verbose) {
-                Ok(duration) => {
-                    results.push(CommandResult {
-                        title: cmd_entry.title.clone(),
-                        duration,
-                    });
-                }
-                Err(e) => {
-                    let elapsed = timer.elapsed();
+            self.show_report(&config.base_dir, &summary, elapsed, &regressions);
+            if self.log_format == LogFormat::Stream {
+                events::run_finished(
+                    Emitter::new(self.log_format, &summary.uuid),
+                    elapsed,
+                    Some(&error_msg),
+                );
+            }
 
-                    if let Some(ref client) = platformx_client {
-                        let error_msg = e.clone();
-                        // ignore errors to avoid failing due to tracking
-                        let _ = client.send_error(elapsed, error_msg).await;
-                    }
+            telemetry.finish(&summary, elapsed, Some(&error_msg)).await;
 
-                    return Err(eyre!("A command failed").with_note(|| e));
+            let Some(interrupted) = interruption else {
+                return Err(eyre!("{}{}", error_msg, output_tails(&summary)));
+            };
+            if self.teardown_on_interrupt && !config.teardown.is_empty() {
+                interrupt::reset();
+                if self.log_format == LogFormat::Pretty {
+                    println!("\n{} Running teardown steps", style("Info:").cyan().bold());
                 }
+                let teardown = RunOptions {
+                    verbose: self.verbose,
+                    log_format: self.log_format,
+                    ci: options.ci,
+                    timestamps: options.timestamps,
+                    ..Default::default()
+                };
+                orchestrator::run(&config.into_teardown(), teardown)?;
             }
+            return Err(interrupted.into());
         }
 
-        let elapsed = timer.elapsed();
+        // A filtered run leaves the progress of the earlier full run to resume
+        let full_run = self.step.is_none()
+            && self.tags.is_empty()
+            && self.from.is_none()
+            && self.until.is_none();
+        if full_run && let Err(e) = state.clear() {
+            tracing::warn!("{}", e);
+        }
 
-        println!(
-            "\n🎯 All set! {}",
-            style(format!("({:.2}s)", elapsed.as_secs_f64())).dim()
-        );
+        let elapsed = timer.elapsed();
 
-        if self.report {
-            print_report(&results, elapsed);
+        match self.log_format {
+            LogFormat::Pretty => {
+                println!(
+                    "\n{}All set! {} {}",
+                    glyphs::DONE,
+                    style(format!("{} steps", summary.progress())).dim(),
+                    style(format!("({:.2}s)", elapsed.as_secs_f64())).dim()
+                );
+                print_projects(&summary);
+                print_allowed_failures(&summary);
+            }
+            LogFormat::Json => events::run_complete(self.log_format, &summary.uuid, elapsed),
+            LogFormat::Stream => {
+                events::run_finished(Emitter::new(self.log_format, &summary.uuid), elapsed, None)
+            }
         }
 
-        if let Some(ref client) = platformx_client {
-            // ignore errors to avoid failing due to tracking
-            let _ = client.send_complete(elapsed).await;
+        self.show_report(&config.base_dir, &summary, elapsed, &regressions);
+        if self.report && self.repeat.is_some() {
+            let _ = writeln!(
+                self.report_output(),
+                "{} {} in {}",
+                style(glyphs::REPEAT).dim(),
+                style(format!("{} iterations", iteration)).bold(),
+                style(format!("{:.2}s", elapsed.as_secs_f64())).dim()
+            );
         }
 
+        telemetry.finish(&summary, elapsed, None).await;
+
         Ok(())
     }
 }
 
-fn print_report(results: &[CommandResult], total: std::time::Duration) {
-    println!("\n{}", style("📊 Report").bold());
+impl UpCommand {
+    /// Print the report of the run, or write it to a file, if one was requested
+    ///
+    /// In GitHub Actions, a Markdown report is also added to the job's step summary.
+    /// Where the report is printed: stderr when streaming JSON, so stdout stays all events
+    fn report_output(&self) -> Box<dyn Write> {
+        if self.log_format == LogFormat::Stream {
+            Box::new(io::stderr())
+        } else {
+            Box::new(io::stdout())
+        }
+    }
+
+    fn show_report(
+        &self,
+        base_dir: &Path,
+        summary: &RunSummary,
+        elapsed: Duration,
+        regressions: &[Regression],
+    ) {
+        let step_summary = env::var_os(GITHUB_STEP_SUMMARY).filter(|path| !path.is_empty());
+        if let Some(ref path) = step_summary
+            && let Err(e) = append_report(
+                Path::new(path),
+                &report::render_markdown(summary, regressions),
+            )
+        {
+            tracing::warn!("{}", e);
+        }
 
-    for result in results {
-        println!(
-            "{} {} {}",
-            style("├──▶").dim(),
-            style(format!("{:.2}s", result.duration.as_secs_f64())).dim(),
-            &result.title,
-        );
+        let mut out = self.report_output();
+        match self.report_format {
+            None if !self.report => {}
+            None | Some(ReportFormat::Text) => {
+                print_report(&mut out, &summary.steps, elapsed, regressions)
+            }
+            Some(ReportFormat::Html) => {
+                let path = self
+                    .report_file
+                    .clone()
+                    .unwrap_or_else(|| base_dir.join(HTML_REPORT_FILE));
+                self.save_report(&path, &report::render_html(summary, regressions));
+            }
+            Some(ReportFormat::Markdown) => {
+                let markdown = report::render_markdown(summary, regressions);
+                match (&self.report_file, step_summary) {
+                    (Some(path), _) => self.save_report(path, &markdown),
+                    (None, None) => {
+                        let _ = write!(out, "\n{}", markdown);
+                    }
+                    // Already added to the step summary
+                    (None, Some(_)) => {}
+                }
+            }
+            Some(ReportFormat::Gantt) => {
+                let gantt = report::render_gantt(summary);
+                match self.report_file {
+                    Some(ref path) => self.save_report(path, &gantt),
+                    None => {
+                        let _ = write!(out, "\n{}", gantt);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write a report to `path` and say where it went
+    fn save_report(&self, path: &Path, content: &str) {
+        if let Err(e) = write_report(path, content) {
+            tracing::warn!("{}", e);
+        } else if self.log_format == LogFormat::Pretty {
+            println!(
+                "\n{} Report written to {}",
+                style("Info:").cyan().bold(),
+                path.display()
+            );
+        }
     }
+}
+
+/// Write a report to `path`, creating its directory if needed
+fn write_report(path: &Path, content: &str) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| eyre!("Error creating '{}': {}", dir.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| eyre!("Error writing '{}': {}", path.display(), e))
+}
 
+/// Add a report to the end of `path`, such as GitHub's step summary file
+fn append_report(path: &Path, content: &str) -> Result<()> {
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| eyre!("Error writing '{}': {}", path.display(), e))
+}
+
+/// Print the steps a run would execute, in order
+fn print_plan(commands: &[&CommandEntry]) {
     println!(
-        "{} {} {}",
-        style("└─▶").dim(),
-        style(format!("{:.2}s", total.as_secs_f64())).dim().bold(),
-        style("Total").bold(),
+        "{} {} steps would run:",
+        style("Plan:").cyan().bold(),
+        commands.len()
     );
+    for (i, cmd_entry) in commands.iter().enumerate() {
+        let mut notes = Vec::new();
+        if cmd_entry.parallel {
+            notes.push("parallel".to_string());
+        }
+        if cmd_entry.allow_failure {
+            notes.push("failure allowed".to_string());
+        }
+        if cmd_entry.confirm {
+            notes.push("asks first".to_string());
+        }
+        if let Some(dir) = cmd_entry.working_dir() {
+            notes.push(format!("in {}", dir.display()));
+        }
+
+        let title = style(&cmd_entry.title).bold();
+        if notes.is_empty() {
+            println!("  {}. {}", i + 1, title);
+        } else {
+            println!(
+                "  {}. {} {}",
+                i + 1,
+                title,
+                style(format!("({})", notes.join(", "))).dim()
+            );
+        }
+        println!(
+            "     {} {}",
+            style("$").dim(),
+            redact::redact(&cmd_entry.display_command())
+        );
+    }
+}
+
+impl DownCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(&self.file)?.into_teardown();
+        if config.commands.is_empty() {
+            println!(
+                "{} No [[teardown]] steps to run",
+                style("Info:").cyan().bold()
+            );
+            return Ok(());
+        }
+
+        let options = RunOptions {
+            verbose: self.verbose,
+            step: self.step.clone(),
+            step_exact: self.step_exact,
+            yes: self.yes,
+            ci: ci::detect(),
+            timestamps: ci::active(),
+            ..Default::default()
+        };
+        let summary = run_and_report(&config, options, self.report)?;
+
+        println!(
+            "\n{}All torn down! {}",
+            glyphs::TORN_DOWN,
+            style(format!("({:.2}s)", summary.duration.as_secs_f64())).dim()
+        );
+        Ok(())
+    }
+}
+
+impl RunCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(&self.file)?;
+
+        let options = RunOptions {
+            verbose: self.verbose,
+            id: Some(self.id.clone()),
+            yes: self.yes,
+            ci: ci::detect(),
+            timestamps: ci::active(),
+            ..Default::default()
+        };
+        let summary = run_and_report(&config, options, self.report)?;
+
+        println!(
+            "\n{}Done! {}",
+            glyphs::DONE,
+            style(format!("({:.2}s)", summary.duration.as_secs_f64())).dim()
+        );
+        Ok(())
+    }
+}
+
+impl PickCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(&self.file)?;
+        let titles: Vec<String> = config
+            .commands
+            .iter()
+            .map(|cmd| cmd.title.clone())
+            .collect();
+
+        let picked = picker::pick(&titles)?;
+        if picked.is_empty() {
+            println!("{} No steps picked", style("Info:").cyan().bold());
+            return Ok(());
+        }
+
+        let options = RunOptions {
+            verbose: self.verbose,
+            picked: Some(picked),
+            yes: self.yes,
+            ci: ci::detect(),
+            timestamps: ci::active(),
+            ..Default::default()
+        };
+        let summary = run_and_report(&config, options, self.report)?;
+
+        println!(
+            "\n{}Done! {}",
+            glyphs::DONE,
+            style(format!("({:.2}s)", summary.duration.as_secs_f64())).dim()
+        );
+        Ok(())
+    }
+}
+
+impl TuiCommand {
+    pub fn run(self) -> Result<()> {
+        let mut config = Config::from_files(&self.file)?;
+        let mut state = RunState::load(&config.base_dir);
+        let mut timings = Timings::load(&config.base_dir);
+        let options = RunOptions {
+            step: self.step.clone(),
+            tags: self.tags.clone(),
+            yes: self.yes,
+            jobs: default_jobs(),
+            log_dir: Some(config.base_dir.join(LOGS_DIR)),
+            typical: timings.typical_all(),
+            ..Default::default()
+        };
+
+        let answers = prompts::resolve(&config.prompts, &config.base_dir)?;
+        config.export_env(&answers);
+        let summary = tui::run(&config, options)?;
+
+        if let Err(e) = RunRecord::from_summary(&summary).save(&config.base_dir) {
+            tracing::warn!("{}", e);
+        }
+        timings.record(&summary);
+        if let Err(e) = timings.save() {
+            tracing::warn!("{}", e);
+        }
+
+        if summary.success() {
+            // A filtered run leaves the progress of the earlier full run to resume
+            if self.step.is_none()
+                && self.tags.is_empty()
+                && let Err(e) = state.clear()
+            {
+                tracing::warn!("{}", e);
+            }
+            return Ok(());
+        }
+
+        state.update(&summary);
+        if let Err(e) = state.save() {
+            tracing::warn!("{}", e);
+        }
+        match summary.interruption() {
+            Some(interrupted) => Err(interrupted.into()),
+            None => Err(eyre!("{}", failure_message(&summary))),
+        }
+    }
+}
+
+impl StartCommand {
+    pub fn run(self) -> Result<()> {
+        let mut services = match self.file {
+            Some(ref path) => read_services(path)?,
+            None => {
+                let config = Path::new("getset.toml");
+                let services = if config.is_file() {
+                    read_services(config)?
+                } else {
+                    Vec::new()
+                };
+                if services.is_empty() {
+                    read_services(Path::new("Procfile"))?
+                } else {
+                    services
+                }
+            }
+        };
+
+        if let Some(unknown) = self
+            .only
+            .iter()
+            .find(|name| !services.iter().any(|service| service.name == **name))
+        {
+            return Err(eyre!("No service named '{}'", unknown));
+        }
+        if !self.only.is_empty() {
+            services.retain(|service| self.only.contains(&service.name));
+        }
+        services::start(&services)
+    }
+}
+
+/// The services declared in a TOML config's `[[services]]`, or in a Procfile
+fn read_services(path: &Path) -> Result<Vec<ServiceConfig>> {
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        return Ok(Config::from_file(path)?.services);
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| eyre!("Error reading file '{}': {}", path.display(), e))?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let services = services::parse_procfile(&content, base_dir)?;
+    config::validate_services(&services)?;
+    Ok(services)
+}
+
+/// Set up the projects `config`, read from `files`, requires, in order, failing at the first that fails
+///
+/// Each project runs all of its steps with its own state and fingerprints,
+/// so one already set up only re-runs what changed.
+fn set_up_requirements(config: &Config, files: &[PathBuf], options: &RunOptions) -> Result<()> {
+    for (path, mut required) in config.required_configs(files)? {
+        if options.log_format == LogFormat::Pretty {
+            println!(
+                "{} Setting up required project {}",
+                style("Info:").cyan().bold(),
+                style(path.display()).bold()
+            );
+        }
+        let answers = prompts::resolve(&required.prompts, &required.base_dir)?;
+        required.export_env(&answers);
+
+        let log_dir = options
+            .log_dir
+            .as_ref()
+            .map(|_| required.base_dir.join(LOGS_DIR));
+        let summary = orchestrator::run(
+            &required,
+            RunOptions {
+                verbose: options.verbose,
+                yes: options.yes,
+                jobs: options.jobs,
+                incremental: options.incremental,
+                log_format: options.log_format,
+                quiet: options.quiet,
+                stderr_only: options.stderr_only,
+                log_dir,
+                ci: options.ci,
+                timestamps: options.timestamps,
+                uuid: options.uuid.clone(),
+                executor: options.executor.clone(),
+                ..Default::default()
+            },
+        )?;
+        if !summary.success() {
+            return Err(eyre!(
+                "Required project '{}' failed: {}",
+                path.display(),
+                failure_message(&summary)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Run the selected steps, printing the report if requested and failing if a step failed
+fn run_and_report(config: &Config, options: RunOptions, report: bool) -> Result<RunSummary> {
+    let summary = orchestrator::run(config, options)?;
+
+    if report {
+        print_report(&mut io::stdout(), &summary.steps, summary.duration, &[]);
+    }
+    if !summary.success() {
+        return Err(eyre!(
+            "{}{}",
+            failure_message(&summary),
+            output_tails(&summary)
+        ));
+    }
+    Ok(summary)
+}
+
+impl ListCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(&self.file)?;
+
+        for (i, cmd_entry) in config.commands.iter().enumerate() {
+            let mut line = format!("{:>3}. {}", i + 1, style(&cmd_entry.title).bold());
+            if let Some(ref id) = cmd_entry.id {
+                line.push_str(&format!(" {}", style(format!("({})", id)).dim()));
+            }
+            if !cmd_entry.tags.is_empty() {
+                line.push_str(&format!(
+                    " {}",
+                    style(format!("[{}]", cmd_entry.tags.join(", "))).cyan()
+                ));
+            }
+            println!("{}", line);
+            if self.verbose {
+                println!(
+                    "     {} {}",
+                    style("$").dim(),
+                    redact::redact(&cmd_entry.display_command())
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl InitCommand {
+    pub fn run(self) -> Result<()> {
+        if self.file.exists() && !self.force {
+            return Err(eyre!(
+                "'{}' already exists; use --force to overwrite it",
+                self.file.display()
+            ));
+        }
+
+        std::fs::write(&self.file, INIT_TEMPLATE)
+            .map_err(|e| eyre!("Error writing file '{}': {}", self.file.display(), e))?;
+
+        println!(
+            "{} Created {}; edit it, then run {}",
+            style(glyphs::SUCCESS).green().bold(),
+            style(self.file.display()).bold(),
+            style("getset up").cyan()
+        );
+        Ok(())
+    }
+}
+
+impl ValidateCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(&self.file)?;
+
+        let files: Vec<String> = self
+            .file
+            .iter()
+            .map(|file| file.display().to_string())
+            .collect();
+        println!(
+            "{} {} is valid ({} steps)",
+            style(glyphs::SUCCESS).green().bold(),
+            files.join(", "),
+            config.commands.len()
+        );
+        Ok(())
+    }
+}
+
+impl DoctorCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(&self.file)?;
+        let Some(doctor) = config.doctor else {
+            println!(
+                "{} No [doctor] checks declared",
+                style("Info:").cyan().bold()
+            );
+            return Ok(());
+        };
+
+        let checks = doctor::run_checks(&doctor);
+        for check in &checks {
+            match &check.outcome {
+                Ok(detail) => println!(
+                    "{} {} {}",
+                    style(glyphs::SUCCESS).green().bold(),
+                    check.label,
+                    style(format!("({})", detail)).dim()
+                ),
+                Err(problem) => println!(
+                    "{} {} {}",
+                    style(glyphs::FAILURE).red().bold(),
+                    style(&check.label).bold(),
+                    style(problem).red()
+                ),
+            }
+        }
+
+        let failed = checks.iter().filter(|check| !check.passed()).count();
+        if failed > 0 {
+            return Err(eyre!("{} of {} doctor checks failed", failed, checks.len()));
+        }
+
+        println!("\n{}All {} checks passed", glyphs::HEALTHY, checks.len());
+        Ok(())
+    }
+}
+
+impl GraphCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(&self.file)?;
+        print!("{}", graph::render(&config.commands, self.format)?);
+        Ok(())
+    }
+}
+
+impl ReportCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(std::slice::from_ref(&self.file))?;
+        let ids = runs::list(&config.base_dir)?;
+
+        let Some(ref earlier) = self.compare else {
+            if ids.is_empty() {
+                println!("{} No recorded runs yet", style("Info:").cyan().bold());
+            }
+            for id in ids.iter().rev() {
+                let record = RunRecord::load(&config.base_dir, id)?;
+                let marker = if record.success {
+                    style(glyphs::SUCCESS).green().bold()
+                } else {
+                    style(glyphs::FAILURE).red().bold()
+                };
+                let finished = chrono::DateTime::parse_from_rfc3339(&record.finished_at)
+                    .map(|at| {
+                        at.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M:%S ")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                println!(
+                    "{} {} {}{}",
+                    marker,
+                    style(&record.id).bold(),
+                    style(finished).dim(),
+                    style(format!("({:.2}s)", record.duration_secs)).dim()
+                );
+            }
+            return Ok(());
+        };
+
+        let current = match self.run.as_ref().or(ids.last()) {
+            Some(id) => RunRecord::load(&config.base_dir, id)?,
+            None => return Err(eyre!("No recorded runs to compare")),
+        };
+        let earlier = RunRecord::load(&config.base_dir, earlier)?;
+
+        println!(
+            "Comparing run {} against {}\n",
+            style(&current.id).bold(),
+            style(&earlier.id).bold()
+        );
+        let width = current
+            .steps
+            .iter()
+            .chain(&earlier.steps)
+            .map(|step| step.title.chars().count())
+            .max()
+            .unwrap_or(0);
+        for step in runs::compare(&earlier, &current) {
+            let secs = |duration: Option<Duration>| {
+                duration.map_or("-".to_string(), |d| format!("{:.2}s", d.as_secs_f64()))
+            };
+            let line = format!(
+                "{:<width$}  {:>9} {} {:<9}",
+                step.title,
+                secs(step.before),
+                glyphs::ARROW,
+                secs(step.after),
+                width = width
+            );
+            let change = match (step.before, step.after) {
+                (Some(before), Some(after)) if after >= before => {
+                    format!("+{:.2}s", (after - before).as_secs_f64())
+                }
+                (Some(before), Some(after)) => {
+                    format!("-{:.2}s", (before - after).as_secs_f64())
+                }
+                _ => String::new(),
+            };
+            match step.change {
+                Change::Slower => println!("{}  {}", line, style(change).red().bold()),
+                Change::Faster => println!("{}  {}", line, style(change).green().bold()),
+                Change::Unchanged => println!("{}  {}", line, style(change).dim()),
+                Change::New => println!("{}  {}", line, style("new").cyan()),
+                Change::NotRun => println!("{}  {}", line, style("not run").dim()),
+            }
+        }
+        println!(
+            "\nTotal {:.2}s {} {:.2}s",
+            earlier.duration_secs,
+            glyphs::ARROW,
+            current.duration_secs
+        );
+        Ok(())
+    }
+}
+
+impl CacheCommand {
+    pub fn run(self) -> Result<()> {
+        match self {
+            CacheCommand::Status { file } => {
+                let config = Config::from_files(&file)?;
+                for step in cache::status(&config)? {
+                    let marker = if step.skipped {
+                        style(glyphs::SKIPPED).dim()
+                    } else {
+                        style(glyphs::ARROW).cyan()
+                    };
+                    println!(
+                        "{} {} {}",
+                        marker,
+                        style(&step.title).bold(),
+                        style(format!("({})", step.reason)).dim()
+                    );
+                }
+            }
+            CacheCommand::Clear { file } => {
+                let config = Config::from_files(&file)?;
+                let removed = cache::clear(&config.base_dir)?;
+                if removed.is_empty() {
+                    println!("{} Nothing to clear", style("Info:").cyan().bold());
+                }
+                for path in removed {
+                    println!(
+                        "{} Removed {}",
+                        style(glyphs::SUCCESS).green().bold(),
+                        path.display()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HooksCommand {
+    pub fn run(self) -> Result<()> {
+        match self {
+            HooksCommand::Install { file, force } => {
+                let config = Config::from_file(&file)?;
+                let tags = config
+                    .hooks
+                    .as_ref()
+                    .map(|hooks| hooks.tags.join(", "))
+                    .filter(|tags| !tags.is_empty());
+                for path in hooks::install(&config, &file, force)? {
+                    println!(
+                        "{} Installed {}",
+                        style(glyphs::SUCCESS).green().bold(),
+                        path.display()
+                    );
+                }
+                let steps = match tags {
+                    Some(tags) => format!("steps tagged {}", tags),
+                    None => "every step".to_string(),
+                };
+                println!(
+                    "{} The hooks run {} of {}; remove them with {}",
+                    style("Info:").cyan().bold(),
+                    steps,
+                    file.display(),
+                    style("getset hooks uninstall").cyan()
+                );
+            }
+            HooksCommand::Uninstall { file } => {
+                let base_dir = file.parent().unwrap_or(Path::new(""));
+                let removed = hooks::uninstall(base_dir)?;
+                if removed.is_empty() {
+                    println!("{} No getset hooks installed", style("Info:").cyan().bold());
+                }
+                for path in removed {
+                    println!(
+                        "{} Removed {}",
+                        style(glyphs::SUCCESS).green().bold(),
+                        path.display()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ImportCommand {
+    pub fn run(self) -> Result<()> {
+        match self {
+            ImportCommand::Makefile {
+                path,
+                target,
+                output,
+            } => {
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| eyre!("Error reading file '{}': {}", path.display(), e))?;
+                output.write(import::makefile(&content, &target), &path)
+            }
+            ImportCommand::Npm {
+                path,
+                prefix,
+                output,
+            } => {
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| eyre!("Error reading file '{}': {}", path.display(), e))?;
+                output.write(import::npm(&content, prefix.as_deref())?, &path)
+            }
+        }
+    }
+}
+
+impl ImportOutput {
+    /// Write the imported steps as a config, after checking that it loads
+    fn write(&self, imported: import::Imported, source: &Path) -> Result<()> {
+        if self.output.exists() && !self.force {
+            return Err(eyre!(
+                "'{}' already exists; use --force to overwrite it",
+                self.output.display()
+            ));
+        }
+        if imported.steps.is_empty() {
+            return Err(eyre!("Found no steps to import in '{}'", source.display()));
+        }
+
+        for warning in &imported.warnings {
+            println!("{} {}", style(glyphs::WARNING).yellow().bold(), warning);
+        }
+        let steps = match imported.steps.len() {
+            1 => "1 step".to_string(),
+            n => format!("{} steps", n),
+        };
+        let base_dir = self.output.parent().unwrap_or(Path::new(""));
+        let toml = imported.into_config(base_dir).to_toml()?;
+        toml.parse::<Config>()
+            .map_err(|e| eyre!("Imported steps are not a valid config: {}", e))?;
+        fs::write(&self.output, toml)
+            .map_err(|e| eyre!("Error writing file '{}': {}", self.output.display(), e))?;
+
+        println!(
+            "{} Created {} with {} from {}; review it, then run {}",
+            style(glyphs::SUCCESS).green().bold(),
+            style(self.output.display()).bold(),
+            steps,
+            source.display(),
+            style("getset up").cyan()
+        );
+        Ok(())
+    }
+}
+
+impl ExportCommand {
+    pub fn run(self) -> Result<()> {
+        let config = Config::from_files(&self.file)?;
+        let source = self
+            .file
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut exported = export::render(&config, self.format, &source)?;
+        exported
+            .warnings
+            .extend(export::reference_warnings(&self.file)?);
+
+        for warning in &exported.warnings {
+            eprintln!("{} {}", style(glyphs::WARNING).yellow().bold(), warning);
+        }
+        match self.output {
+            Some(ref path) => fs::write(path, &exported.text)
+                .map_err(|e| eyre!("Error writing file '{}': {}", path.display(), e))?,
+            None => print!("{}", exported.text),
+        }
+        Ok(())
+    }
+}
+
+impl CompletionsCommand {
+    pub fn run(self) {
+        clap_complete::generate(
+            self.shell,
+            &mut App::command(),
+            "getset",
+            &mut std::io::stdout(),
+        );
+    }
+}
+
+/// List how the steps of each workspace member went, if the run had any
+fn print_projects(summary: &RunSummary) {
+    let projects = orchestrator::by_project(&summary.steps);
+    if projects.iter().all(|(project, _)| project.is_none()) {
+        return;
+    }
+    for (project, steps) in projects {
+        let name = project.unwrap_or("(root)");
+        let counts = report_counts(steps.iter().copied());
+        if steps.iter().any(|step| step.is_failure()) {
+            println!(
+                "  {} {} {}",
+                style(glyphs::FAILURE).red(),
+                style(name).red().bold(),
+                style(counts).dim()
+            );
+        } else {
+            println!(
+                "  {} {} {}",
+                style(glyphs::SUCCESS).green(),
+                style(name).bold(),
+                style(counts).dim()
+            );
+        }
+    }
+}
+
+/// Warn about steps that failed without failing the run
+fn print_allowed_failures(summary: &RunSummary) {
+    let allowed: Vec<&StepResult> = summary.allowed_failures().collect();
+    if allowed.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} {} allowed to fail:",
+        style(glyphs::WARNING).yellow().bold(),
+        if allowed.len() == 1 {
+            "1 step was".to_string()
+        } else {
+            format!("{} steps were", allowed.len())
+        }
+    );
+    for step in allowed {
+        println!(
+            "  - {}",
+            style(step.error.as_deref().unwrap_or(&step.title)).yellow()
+        );
+    }
+}
+
+/// Describe the failed steps of a run
+fn failure_message(summary: &RunSummary) -> String {
+    let errors: Vec<&str> = summary
+        .failures()
+        .map(|step| step.error.as_deref().unwrap_or("Step failed"))
+        .collect();
+
+    match errors.as_slice() {
+        [error] => error.to_string(),
+        errors => format!(
+            "{} steps failed:\n  - {}",
+            errors.len(),
+            errors.join("\n  - ")
+        ),
+    }
+}
+
+/// The last lines of output of each failed step, to show under the run's error
+fn output_tails(summary: &RunSummary) -> String {
+    summary
+        .failures()
+        .filter(|step| !step.output_tail.is_empty())
+        .map(|step| {
+            format!(
+                "\n\nLast lines of output from '{}':\n  {}",
+                step.title,
+                step.output_tail.join("\n  ")
+            )
+        })
+        .collect()
+}
+
+/// Width of the bar chart column in the report
+const REPORT_BAR_WIDTH: usize = 20;
+
+/// Unstyled cells of one report row
+struct ReportRow {
+    duration: String,
+    title: String,
+    bar: String,
+}
+
+/// Bar of `width` cells, filled in proportion to `share` of the total
+fn report_bar(share: f64, width: usize) -> String {
+    let filled = (share.clamp(0.0, 1.0) * width as f64).round() as usize;
+    format!(
+        "{}{}",
+        glyphs::BAR_FILLED.as_str().repeat(filled),
+        glyphs::BAR_EMPTY.as_str().repeat(width - filled)
+    )
+}
+
+/// Build the report rows for each step, with bars relative to `total`
+fn report_rows<'a>(
+    results: impl IntoIterator<Item = &'a StepResult>,
+    total: Duration,
+) -> Vec<ReportRow> {
+    let total_secs = total.as_secs_f64();
+
+    results
+        .into_iter()
+        .map(|result| {
+            let share = if total_secs > 0.0 {
+                result.duration.as_secs_f64() / total_secs
+            } else {
+                0.0
+            };
+            let title = short_title(result);
+            let title = match result.skip_reason {
+                Some(ref reason) => format!("{} ({} {})", title, glyphs::SKIPPED, reason),
+                None if result.satisfied => {
+                    format!("{} ({} already satisfied)", title, glyphs::SUCCESS)
+                }
+                None => title.to_string(),
+            };
+
+            ReportRow {
+                duration: format!("{:.2}s", result.duration.as_secs_f64()),
+                title,
+                bar: report_bar(share, REPORT_BAR_WIDTH),
+            }
+        })
+        .collect()
+}
+
+/// A step's title without the workspace member before it, as it is shown under its member
+fn short_title(result: &StepResult) -> &str {
+    result
+        .project
+        .as_deref()
+        .and_then(|project| result.title.strip_prefix(project))
+        .and_then(|title| title.strip_prefix(": "))
+        .unwrap_or(&result.title)
+}
+
+/// Pad `text` with spaces to `width` terminal columns
+fn pad_to_width(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(console::measure_text_width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+/// Print the report of the steps to `out`
+fn print_report(
+    out: &mut dyn Write,
+    results: &[StepResult],
+    total: Duration,
+    regressions: &[Regression],
+) {
+    let _ = writeln!(
+        out,
+        "\n{}",
+        style(format!("{}Report", glyphs::REPORT)).bold()
+    );
+
+    let rows = report_rows(results, total);
+    let total_duration = format!("{:.2}s", total.as_secs_f64());
+
+    let duration_width = rows
+        .iter()
+        .map(|row| row.duration.len())
+        .chain([total_duration.len()])
+        .max()
+        .unwrap_or_default();
+    let title_width = rows
+        .iter()
+        .map(|row| console::measure_text_width(&row.title))
+        .max()
+        .unwrap_or_default();
+
+    // Steps of workspace members are listed under the member's name
+    for (project, steps) in orchestrator::by_project(results) {
+        if let Some(project) = project {
+            let _ = writeln!(
+                out,
+                "{} {}",
+                style(glyphs::REPORT_BRANCH).dim(),
+                style(project).bold()
+            );
+        }
+        let rows = report_rows(steps.iter().copied(), total);
+        for (row, result) in rows.iter().zip(steps) {
+            let title = pad_to_width(&row.title, title_width);
+            let title = if result.skip_reason.is_some() || result.satisfied {
+                style(title).dim()
+            } else if result.success {
+                style(title)
+            } else if result.allowed_failure {
+                style(title).yellow()
+            } else {
+                style(title).red()
+            };
+
+            let _ = writeln!(
+                out,
+                "{} {} {} {}",
+                style(glyphs::REPORT_BRANCH).dim(),
+                style(format!("{:>width$}", row.duration, width = duration_width)).dim(),
+                title,
+                style(&row.bar).cyan(),
+            );
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "{} {} {} {}",
+        style(glyphs::REPORT_LAST_BRANCH).dim(),
+        style(format!(
+            "{:>width$}",
+            total_duration,
+            width = duration_width
+        ))
+        .dim()
+        .bold(),
+        style("Total").bold(),
+        style(report_counts(results)).dim(),
+    );
+
+    for regression in regressions {
+        let _ = writeln!(
+            out,
+            "{} {}",
+            style(glyphs::WARNING).yellow().bold(),
+            style(regression_message(regression)).yellow()
+        );
+    }
+}
+
+/// Warning for a step that took much longer than usual
+fn regression_message(regression: &Regression) -> String {
+    format!(
+        "{} took {:.1}x longer than usual ({:.2}s, usually {})",
+        regression.title,
+        regression.ratio(),
+        regression.duration.as_secs_f64(),
+        timings::estimate(regression.average)
+    )
+}
+
+/// Summary of how many steps were executed, already satisfied and skipped
+fn report_counts<'a>(results: impl IntoIterator<Item = &'a StepResult>) -> String {
+    let results: Vec<&StepResult> = results.into_iter().collect();
+    let satisfied = results.iter().filter(|r| r.satisfied).count();
+    let skipped = results.iter().filter(|r| r.skip_reason.is_some()).count();
+    let executed = results.len() - satisfied - skipped;
+
+    let mut counts = vec![format!("{} executed", executed)];
+    if satisfied > 0 {
+        counts.push(format!("{} already satisfied", satisfied));
+    }
+    if skipped > 0 {
+        counts.push(format!("{} skipped", skipped));
+    }
+    format!("({})", counts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(title: &str, secs: u64) -> StepResult {
+        StepResult {
+            title: title.to_string(),
+            duration: Duration::from_secs(secs),
+            success: true,
+            error: None,
+            output_tail: Vec::new(),
+            skip_reason: None,
+            allowed_failure: false,
+            satisfied: false,
+            started: Duration::ZERO,
+            project: None,
+        }
+    }
+
+    fn filled(bar: &str) -> usize {
+        bar.chars().filter(|&c| c == '█').count()
+    }
+
+    #[test]
+    fn test_report_bars_are_proportional_to_duration() {
+        let results = [step("Quick", 1), step("Slowest", 3), step("Medium", 2)];
+
+        let rows = report_rows(&results, Duration::from_secs(6));
+
+        let lengths: Vec<usize> = rows.iter().map(|row| filled(&row.bar)).collect();
+        assert_eq!(lengths, [3, 10, 7]);
+        let slowest = lengths
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, len)| **len)
+            .unwrap();
+        assert_eq!(rows[slowest.0].title, "Slowest");
+        assert!(
+            rows.iter()
+                .all(|row| row.bar.chars().count() == REPORT_BAR_WIDTH)
+        );
+    }
+
+    #[test]
+    fn test_report_bar_handles_zero_total() {
+        let rows = report_rows(&[step("Instant", 0)], Duration::ZERO);
+        assert_eq!(filled(&rows[0].bar), 0);
+    }
+
+    #[test]
+    fn test_regression_message_names_step_and_ratio() {
+        let regression = Regression {
+            title: "Seed database".to_string(),
+            duration: Duration::from_secs(45),
+            average: Duration::from_secs(10),
+        };
+
+        assert_eq!(
+            regression_message(&regression),
+            "Seed database took 4.5x longer than usual (45.00s, usually ~10s)"
+        );
+    }
+
+    #[test]
+    fn test_report_distinguishes_satisfied_steps() {
+        let mut installed = step("Installed", 0);
+        installed.satisfied = true;
+        let mut skipped = step("Skipped", 0);
+        skipped.skip_reason = Some("cached".to_string());
+        let results = [step("Ran", 1), installed, skipped];
+
+        let rows = report_rows(&results, Duration::from_secs(1));
+
+        assert_eq!(rows[1].title, "Installed (✔︎ already satisfied)");
+        assert_eq!(
+            report_counts(&results),
+            "(1 executed, 1 already satisfied, 1 skipped)"
+        );
+        assert_eq!(report_counts(&results[..1]), "(1 executed)");
+    }
+
+    #[test]
+    fn test_init_template_is_valid_config() {
+        let config: Config = INIT_TEMPLATE.parse().expect("template should parse");
+        assert_eq!(config.commands.len(), 3);
+        assert!(config.platformx.is_none());
+    }
+
+    #[test]
+    fn test_pad_to_width_uses_display_width() {
+        assert_eq!(pad_to_width("ab", 4), "ab  ");
+        assert_eq!(pad_to_width("⊝ x", 5), "⊝ x  ");
+        assert_eq!(pad_to_width("longer", 3), "longer");
+    }
 }
@@ -1,156 +1,2670 @@
+use crate::graph;
+use crate::redact;
+use crate::remote;
+use crate::workspaces::{self, Member};
 use color_eyre::eyre::{Report, Result, eyre};
-use serde::Deserialize;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-#[derive(Debug, Deserialize)]
+/// A getset configuration, as loaded from a `getset.toml` and the files it includes
+#[derive(Debug, Deserialize, Serialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Other config files (or globs), relative to this one, whose steps run first
+    ///
+    /// A URL pinned to its content, like `https://host/setup.toml#sha256=<hash>`,
+    /// is downloaded (or read from `.getset/remote`) and runs in this file's directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Configs of other projects, relative to this one, set up before this one's steps
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<PathBuf>,
+    /// Ids or titles of steps to remove, typically from a `getset.local.toml` override
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+    /// `.env` files, relative to this file, whose variables every step receives
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dotenv: Vec<PathBuf>,
+    /// Values substituted for `${name}` in step commands, `cwd` and `env`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub commands: Vec<CommandEntry>,
+    /// Steps run by `getset down` to undo the setup
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teardown: Vec<CommandEntry>,
+    /// Long-running processes started together by `getset start`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<ServiceConfig>,
     pub platformx: Option<PlatformXConfig>,
+    /// Where to announce that a run started, succeeded or failed
+    pub notifications: Option<NotificationsConfig>,
+    /// Where to report step and run metrics
+    pub metrics: Option<MetricsConfig>,
+    /// Prerequisites checked by `getset doctor`
+    pub doctor: Option<DoctorConfig>,
+    /// Values asked for at the start of `getset up` and exported to every step
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prompts: Vec<Prompt>,
+    /// Projects in subdirectories whose own configs are set up after this one
+    pub workspaces: Option<WorkspacesConfig>,
+    /// Git hooks written by `getset hooks install`
+    pub hooks: Option<HooksConfig>,
+    /// Directory that relative paths in the config are resolved against
+    #[serde(skip)]
+    pub base_dir: PathBuf,
 }
 
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct PlatformXConfig {
-    pub secret_key: String,
+    /// Secret key as written, which may refer to vars and environment variables;
+    /// see [`PlatformXConfig::resolve_secret_key`]
+    pub secret_key: Option<String>,
+    /// Environment variable to read the secret key from, instead of `secret_key`
+    pub secret_key_env: Option<String>,
     pub event_namespace: Option<String>,
+    /// Endpoint events are sent to, e.g. a proxy (defaults to the PlatformX API)
+    pub api_url: Option<String>,
+    /// Longest an event may take to deliver, retries included (defaults to 5)
+    pub timeout_secs: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
+impl PlatformXConfig {
+    /// The secret key, expanded from `secret_key` or read from `secret_key_env`
+    ///
+    /// Only resolved once events are about to be sent, so a run that sends none,
+    /// e.g. with `--no-telemetry`, doesn't need the key to be set.
+    pub fn resolve_secret_key(&self, vars: &BTreeMap<String, String>) -> Result<String> {
+        let secret_key = match (&self.secret_key, &self.secret_key_env) {
+            (Some(key), None) => expand_vars(key, vars).map_err(|name| {
+                eyre!(
+                    "Invalid config: [platformx] uses unset environment variable '{}'",
+                    name
+                )
+            })?,
+            (None, Some(name)) => std::env::var(name).map_err(|_| {
+                eyre!(
+                    "Invalid config: [platformx] secret_key_env names environment variable '{}', which is not set",
+                    name
+                )
+            })?,
+            _ => {
+                return Err(eyre!(
+                    "Invalid config: [platformx] needs exactly one of secret_key or secret_key_env"
+                ));
+            }
+        };
+        redact::register(&secret_key);
+        Ok(secret_key)
+    }
+}
+
+// Written by hand so a key read from `secret_key_env` is never written out
+impl Serialize for PlatformXConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let secret_key = self
+            .secret_key
+            .as_ref()
+            .filter(|_| self.secret_key_env.is_none());
+        let fields = [
+            ("secret_key", secret_key),
+            ("secret_key_env", self.secret_key_env.as_ref()),
+            ("event_namespace", self.event_namespace.as_ref()),
+            ("api_url", self.api_url.as_ref()),
+        ];
+
+        let mut state = serializer.serialize_struct("PlatformXConfig", 5)?;
+        for (name, value) in fields {
+            if let Some(value) = value {
+                state.serialize_field(name, value)?;
+            }
+        }
+        if let Some(timeout_secs) = self.timeout_secs {
+            state.serialize_field("timeout_secs", &timeout_secs)?;
+        }
+        state.end()
+    }
+}
+
+/// Projects of a monorepo, each with its own `getset.toml`, set up along with the root config
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspacesConfig {
+    /// Directories (or globs) relative to this file, set up in the order listed
+    pub members: Vec<String>,
+    /// Members that must be set up before a member, keyed by its name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub needs: BTreeMap<String, Vec<String>>,
+}
+
+/// Where `getset up` announces that a run started, succeeded or failed
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationsConfig {
+    pub slack: Option<SlackConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhook: Vec<WebhookConfig>,
+}
+
+/// A Slack incoming webhook to post run notifications to
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SlackConfig {
+    pub webhook_url: String,
+}
+
+/// Run lifecycle events a webhook can be sent for
+pub const WEBHOOK_EVENTS: [&str; 3] = ["start", "success", "failure"];
+
+/// An HTTP endpoint to send run notifications to, e.g. PagerDuty or Discord
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default = "default_webhook_method")]
+    pub method: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// JSON body, with `{{name}}` placeholders for the event's fields
+    ///
+    /// Defaults to an object of every field.
+    pub body: Option<String>,
+    /// Events to send the webhook for, from [`WEBHOOK_EVENTS`] (defaults to all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+}
+
+fn default_webhook_method() -> String {
+    "POST".to_string()
+}
+
+/// Git hooks `getset hooks install` can write
+pub const GIT_HOOKS: [&str; 3] = ["post-checkout", "post-merge", "post-rewrite"];
+
+/// Which git hooks run `getset up`, and which of the steps they run
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Hooks to install, from [`GIT_HOOKS`] (defaults to post-merge and post-checkout)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    /// Run only steps with at least one of these tags (defaults to every step)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl HooksConfig {
+    /// The hooks to install, defaulting to those run after a pull or a branch switch
+    pub fn events(&self) -> Vec<&str> {
+        if self.events.is_empty() {
+            vec!["post-merge", "post-checkout"]
+        } else {
+            self.events.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+/// Where `getset up` reports step and run metrics at the end of each run
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub prometheus: Option<PrometheusConfig>,
+    pub statsd: Option<StatsdConfig>,
+    pub local: Option<LocalMetricsConfig>,
+}
+
+/// A Prometheus Pushgateway to push the metrics of each run to
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PrometheusConfig {
+    pub pushgateway_url: String,
+    /// Job label the metrics are grouped under (defaults to `getset`)
+    #[serde(default = "default_prometheus_job")]
+    pub job: String,
+}
+
+fn default_prometheus_job() -> String {
+    "getset".to_string()
+}
+
+/// A StatsD or DogStatsD agent to send the metrics of each run to over UDP
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StatsdConfig {
+    /// Address of the agent, e.g. `127.0.0.1:8125`
+    pub addr: String,
+    /// Prefix of every metric name (defaults to `getset`)
+    #[serde(default = "default_statsd_prefix")]
+    pub prefix: String,
+}
+
+fn default_statsd_prefix() -> String {
+    "getset".to_string()
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A local file that the events of each run are appended to as JSON lines
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LocalMetricsConfig {
+    /// File to append to, relative to the config file (defaults to
+    /// `events.jsonl` in getset's data directory)
+    pub path: Option<PathBuf>,
+}
+
+/// Value asked for once per run and exported to every step as an environment variable
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Prompt {
+    /// Environment variable the answer is exported as
+    pub name: String,
+    /// Question shown to the user (defaults to `name`)
+    pub message: Option<String>,
+    /// Answer used when the user enters nothing or there is no terminal
+    pub default: Option<String>,
+    /// Hide the input and never cache the answer
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub secret: bool,
+    /// Remember the answer for later runs
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cache: bool,
+}
+
+/// Prerequisites that `getset doctor` verifies without running any steps
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DoctorConfig {
+    /// Programs that must be found on `PATH`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub binaries: Vec<String>,
+    /// Environment variables that must be set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+    /// Minimum versions keyed by program, read from `<program> --version`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub versions: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct CommandEntry {
+    /// Stable identifier for `getset run` and `needs`
+    pub id: Option<String>,
     pub title: String,
+    /// Shell script executed through `sh -c`
+    pub command: Option<String>,
+    /// Program and arguments executed directly, without a shell
+    pub argv: Option<Vec<String>>,
+    /// Shell used to run `command` instead of `sh -c`
+    pub shell: Option<Shell>,
+    /// Directory to run this step in, relative to the config file
+    pub cwd: Option<PathBuf>,
+    /// Environment variables set for this step
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Globs of files whose contents decide whether `--incremental` can skip the step
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<String>,
+    /// Globs of files whose fingerprint lets every run skip the step while unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    /// Globs of files the step produces; the step re-runs if any is missing
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+    /// Globs that re-run the step when they change under `up --watch` (defaults to `sources`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watch: Vec<String>,
+    /// Labels for selecting groups of steps with `--tag`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Ids or titles of steps that must run before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub needs: Vec<String>,
+    /// Operating systems this step runs on (e.g. `macos`, `linux`); empty = all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    /// CPU architectures this step runs on (e.g. `aarch64`, `x86_64`); empty = all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arch: Vec<String>,
+    /// Shell condition that skips this step when it exits successfully
+    pub skip_if: Option<String>,
+    /// Shell condition that must exit successfully for this step to run
+    pub only_if: Option<String>,
+    /// Shell check that, when it succeeds, marks the step as already satisfied
+    pub check: Option<String>,
+    /// Values to expand this step over; one step runs per combination, with
+    /// `${matrix.<key>}` substituted into its title, command and other text fields
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub matrix: BTreeMap<String, Vec<String>>,
+    /// Let the run continue (with a warning) if this step fails
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub allow_failure: bool,
+    /// Run concurrently with adjacent `parallel` steps
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub parallel: bool,
+    /// Capture the step's output and show it only if the step fails
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub capture: bool,
+    /// Ask before running this step, e.g. because it drops data
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub confirm: bool,
+    /// Directory of the config file that declared this step
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+    /// Where the step is declared, for error messages
+    #[serde(skip)]
+    pub location: Option<Location>,
+    /// Workspace member the step belongs to, to group it by in the summary and report
+    #[serde(skip)]
+    pub project: Option<String>,
+}
+
+/// A long-running process started by `getset start`, such as a web server or worker
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceConfig {
+    /// Name shown before each line of the service's output
+    pub name: String,
+    /// Shell script that starts the service, run through `sh -c`
     pub command: String,
+    /// Directory to run the service in, relative to the config file (defaults to its directory)
+    pub cwd: Option<PathBuf>,
+    /// Environment variables set for the service
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Directory of the config file (or Procfile) that declared this service
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+impl ServiceConfig {
+    /// Directory the service runs in, unless it is the current one
+    pub fn working_dir(&self) -> Option<PathBuf> {
+        let dir = match self.cwd {
+            Some(ref cwd) => self.base_dir.join(cwd),
+            None => self.base_dir.clone(),
+        };
+        (!dir.as_os_str().is_empty()).then_some(dir)
+    }
+
+    /// Expand `${name}` and `${env:NAME}` references in the command, `cwd` and `env`
+    fn expand_vars(&mut self, vars: &BTreeMap<String, String>) -> Result<()> {
+        let unset = |name| {
+            eyre!(
+                "Invalid config: service '{}' uses unset environment variable '{}'",
+                self.name,
+                name
+            )
+        };
+        let command = expand_vars(&self.command, vars).map_err(unset)?;
+        let cwd = match self.cwd {
+            Some(ref cwd) => Some(PathBuf::from(
+                expand_vars(&cwd.to_string_lossy(), vars).map_err(unset)?,
+            )),
+            None => None,
+        };
+        let mut env = BTreeMap::new();
+        for (key, value) in &self.env {
+            env.insert(key.clone(), expand_vars(value, vars).map_err(unset)?);
+        }
+        self.command = command;
+        self.cwd = cwd;
+        self.env = env;
+        Ok(())
+    }
+}
+
+/// Position of a step's `[[commands]]` header in its config file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Location {
+    /// Config file, if the step was read from one
+    pub file: Option<PathBuf>,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file.display(), self.line, self.column),
+            None => write!(f, "line {}, column {}", self.line, self.column),
+        }
+    }
+}
+
+impl Location {
+    /// Location of byte `offset` within `content`
+    fn at(content: &str, offset: usize) -> Self {
+        let before = &content[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Location {
+            file: None,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// A config's `base_dir` as an absolute path, e.g. to identify the project
+///
+/// A config named without a directory, such as `getset.toml`, has an empty
+/// `base_dir`, which stands for the current directory.
+pub(crate) fn absolute_dir(base_dir: &Path) -> PathBuf {
+    let dir = if base_dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base_dir
+    };
+    dir.canonicalize()
+        .or_else(|_| std::path::absolute(dir))
+        .unwrap_or_else(|_| dir.to_path_buf())
 }
 
-impl Config {
-    /// Load and parse a TOML configuration file
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path_ref = path.as_ref();
-        let toml_content = fs::read_to_string(path_ref)
-            .map_err(|e| eyre!("Error reading file '{}': {}", path_ref.display(), e))?;
-        toml_content.parse()
+/// Path of the local override for a config file (`getset.toml` -> `getset.local.toml`)
+fn local_override_path(path: &Path) -> PathBuf {
+    path.with_extension("local.toml")
+}
+
+/// Replace `${name}` with the value of `name` from `vars`, and `${env:NAME}`
+/// and `${secret:NAME}` with the environment variable `NAME`
+///
+/// References to names that are not in `vars` are left untouched so shell
+/// variables like `${HOME}` keep working, and `$${name}` produces a literal
+/// `${name}`. An unset `${env:NAME}` or `${secret:NAME}` is an error naming
+/// the variable. Values read with `${secret:NAME}`, and `${env:NAME}` values
+/// whose name looks like a credential (see [`is_secret_name`]), are registered
+/// as secrets, to be redacted from output and telemetry.
+fn expand_vars(text: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            out.push_str("${");
+            rest = after;
+            continue;
+        };
+
+        let name = &after[..end];
+        let value = match name.split_once(':') {
+            Some(("env", env_name)) => {
+                let value = std::env::var(env_name).map_err(|_| env_name.to_string())?;
+                if is_secret_name(env_name) {
+                    redact::register(&value);
+                }
+                Some(value)
+            }
+            Some(("secret", env_name)) => {
+                let value = std::env::var(env_name).map_err(|_| env_name.to_string())?;
+                redact::register(&value);
+                Some(value)
+            }
+            _ => vars.get(name).cloned(),
+        };
+        match value {
+            Some(value) => {
+                out.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str("${");
+                rest = after;
+            }
+        }
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Words marking an environment variable as holding a credential
+const SECRET_NAME_WORDS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "KEY",
+    "CREDENTIAL",
+    "AUTH",
+];
+
+/// Whether an environment variable's name suggests it holds a credential, like
+/// `GITHUB_TOKEN` or `AWS_SECRET_ACCESS_KEY`
+///
+/// Other `${env:}` values, like `HOME` or `PATH`, are not redacted, since
+/// masking them would mangle ordinary output.
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_NAME_WORDS.iter().any(|word| name.contains(word))
+}
+
+/// Spans of each `[[commands]]` and `[[teardown]]` table, parsed alongside the config
+#[derive(Deserialize)]
+struct CommandSpans {
+    #[serde(default)]
+    commands: Vec<toml::Spanned<toml::Table>>,
+    #[serde(default)]
+    teardown: Vec<toml::Spanned<toml::Table>>,
+}
+
+/// Shell that runs a step's `command` script
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Shell {
+    /// Shell program, run as `<program> -c <script>` (or `/C` and `-Command`
+    /// for `cmd` and PowerShell)
+    Program(String),
+    /// Shell program and flags, followed by the script (e.g. `["zsh", "-lc"]`)
+    Argv(Vec<String>),
+}
+
+impl Default for Shell {
+    /// `sh`, or `cmd` on Windows
+    fn default() -> Self {
+        if cfg!(windows) {
+            Shell::Program("cmd".to_string())
+        } else {
+            Shell::Program("sh".to_string())
+        }
+    }
+}
+
+impl Shell {
+    /// Program and the flags that precede the script
+    pub fn argv(&self) -> Vec<String> {
+        match self {
+            Shell::Program(program) => vec![program.clone(), script_flag(program).to_string()],
+            Shell::Argv(argv) => argv.clone(),
+        }
+    }
+
+    /// Whether the shell is POSIX-like, so it understands `-x` to trace execution
+    pub fn is_posix(&self) -> bool {
+        let program = match self {
+            Shell::Program(program) => program,
+            Shell::Argv(argv) => argv.first().map_or("", String::as_str),
+        };
+        script_flag(program) == "-c"
+    }
+}
+
+/// Flag that makes `program` run the script given as the next argument
+fn script_flag(program: &str) -> &'static str {
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(program)
+        .to_ascii_lowercase();
+    match name.as_str() {
+        "cmd" => "/C",
+        "powershell" | "pwsh" => "-Command",
+        _ => "-c",
+    }
+}
+
+impl CommandEntry {
+    /// A step titled `title` that runs the shell script `command`
+    pub fn new(title: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            command: Some(command.into()),
+            ..Default::default()
+        }
+    }
+
+    /// A step titled `title` that runs a program and its arguments directly, without a shell
+    pub fn program(
+        title: impl Into<String>,
+        argv: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            argv: Some(argv.into_iter().map(Into::into).collect()),
+            ..Default::default()
+        }
+    }
+
+    /// Give the step an id, for `getset run` and `needs`
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Run the step in `cwd`, relative to the config's directory
+    pub fn with_cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Set an environment variable for the step
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Add a tag for selecting the step with `--tag`
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Run the step after `step`, given by id or title
+    pub fn with_need(mut self, step: impl Into<String>) -> Self {
+        self.needs.push(step.into());
+        self
+    }
+
+    /// Skip the step as already satisfied whenever the shell check `check` succeeds
+    pub fn with_check(mut self, check: impl Into<String>) -> Self {
+        self.check = Some(check.into());
+        self
+    }
+
+    /// Whether `name` is this step's id or title
+    pub fn is_named(&self, name: &str) -> bool {
+        self.id.as_deref() == Some(name) || self.title == name
+    }
+
+    /// Whether `other` overrides this step, matching by id if both have one
+    fn is_same_step(&self, other: &CommandEntry) -> bool {
+        match (&self.id, &other.id) {
+            (Some(id), Some(other_id)) => id == other_id,
+            _ => self.title == other.title,
+        }
+    }
+
+    /// Configuration error about this step, pointing at where it is declared
+    pub(crate) fn invalid(&self, problem: impl fmt::Display) -> Report {
+        match &self.location {
+            Some(location) => eyre!(
+                "Invalid config: step '{}' {} (at {})",
+                self.title,
+                problem,
+                location
+            ),
+            None => eyre!("Invalid config: step '{}' {}", self.title, problem),
+        }
+    }
+
+    /// Directory this step runs in, if it sets `cwd`
+    pub fn working_dir(&self) -> Option<PathBuf> {
+        let cwd = self.cwd.as_ref()?;
+        if cwd == Path::new(".") && !self.base_dir.as_os_str().is_empty() {
+            return Some(self.base_dir.clone());
+        }
+        Some(self.base_dir.join(cwd))
+    }
+
+    /// Why this step should be skipped on the current platform, if it should
+    pub fn platform_mismatch(&self) -> Option<String> {
+        self.platform_mismatch_on(std::env::consts::OS, std::env::consts::ARCH)
+    }
+
+    /// Why this step should be skipped on the given OS and architecture, if it should
+    fn platform_mismatch_on(&self, os: &str, arch: &str) -> Option<String> {
+        if !self.os.is_empty() && !self.os.iter().any(|o| o == os) {
+            return Some(format!("{} only", self.os.join("/")));
+        }
+        if !self.arch.is_empty() && !self.arch.iter().any(|a| a == arch) {
+            return Some(format!("{} only", self.arch.join("/")));
+        }
+        None
+    }
+
+    /// Copies of this step for each combination of its `matrix` values
+    fn matrix_steps(&self) -> Result<Vec<CommandEntry>> {
+        let mut combinations: Vec<Vec<(&str, &str)>> = vec![Vec::new()];
+        for (key, values) in &self.matrix {
+            if values.is_empty() {
+                return Err(self.invalid(format!("has an empty matrix '{}'", key)));
+            }
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((key.as_str(), value.as_str()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+
+        Ok(combinations
+            .into_iter()
+            .map(|combination| {
+                let substitute = |text: &str| {
+                    combination
+                        .iter()
+                        .fold(text.to_string(), |text, (key, value)| {
+                            text.replace(&format!("${{matrix.{}}}", key), value)
+                        })
+                };
+
+                let mut step = self.clone();
+                step.matrix = BTreeMap::new();
+                step.title = substitute(&self.title);
+                if step.title == self.title {
+                    let values: Vec<&str> = combination.iter().map(|(_, value)| *value).collect();
+                    step.title = format!("{} ({})", self.title, values.join(", "));
+                }
+                step.id = self.id.as_ref().map(|id| {
+                    let expanded = substitute(id);
+                    if &expanded == id {
+                        let values: Vec<&str> =
+                            combination.iter().map(|(_, value)| *value).collect();
+                        format!("{}-{}", id, values.join("-"))
+                    } else {
+                        expanded
+                    }
+                });
+                step.command = self.command.as_deref().map(substitute);
+                step.argv = self
+                    .argv
+                    .as_ref()
+                    .map(|argv| argv.iter().map(|arg| substitute(arg)).collect());
+                step.cwd = self
+                    .cwd
+                    .as_ref()
+                    .map(|cwd| PathBuf::from(substitute(&cwd.to_string_lossy())));
+                step.env = self
+                    .env
+                    .iter()
+                    .map(|(key, value)| (key.clone(), substitute(value)))
+                    .collect();
+                for condition in [&mut step.check, &mut step.skip_if, &mut step.only_if] {
+                    *condition = condition.as_deref().map(substitute);
+                }
+                for texts in [
+                    &mut step.inputs,
+                    &mut step.sources,
+                    &mut step.outputs,
+                    &mut step.watch,
+                    &mut step.tags,
+                    &mut step.needs,
+                ] {
+                    *texts = texts.iter().map(|text| substitute(text)).collect();
+                }
+                step
+            })
+            .collect())
+    }
+
+    /// Substitute variables into the step's command, argv, `cwd` and `env` values
+    fn expand_vars(&mut self, vars: &BTreeMap<String, String>) -> Result<()> {
+        self.expand_fields(vars)
+            .map_err(|name| self.invalid(format!("uses unset environment variable '{}'", name)))
+    }
+
+    /// Expand each field, returning the name of the first unset environment variable
+    fn expand_fields(&mut self, vars: &BTreeMap<String, String>) -> Result<(), String> {
+        if let Some(ref mut command) = self.command {
+            *command = expand_vars(command, vars)?;
+        }
+        for arg in self.argv.iter_mut().flatten() {
+            *arg = expand_vars(arg, vars)?;
+        }
+        if let Some(ref mut cwd) = self.cwd {
+            *cwd = PathBuf::from(expand_vars(&cwd.to_string_lossy(), vars)?);
+        }
+        for value in self.env.values_mut() {
+            *value = expand_vars(value, vars)?;
+        }
+        Ok(())
+    }
+
+    /// Globs that `up --watch` checks for changes to this step
+    pub fn watched(&self) -> &[String] {
+        if self.watch.is_empty() {
+            &self.sources
+        } else {
+            &self.watch
+        }
+    }
+
+    /// Human-readable form of what this step executes
+    pub fn display_command(&self) -> String {
+        match (&self.command, &self.argv) {
+            (Some(command), _) => command.clone(),
+            (None, Some(argv)) => argv
+                .iter()
+                .map(|arg| {
+                    if arg.is_empty() || arg.contains(char::is_whitespace) {
+                        format!("'{}'", arg)
+                    } else {
+                        arg.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load and parse a TOML configuration file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Config::load(&[path], false)
+    }
+
+    /// An empty configuration, to build up in code, whose relative paths resolve against `base_dir`
+    ///
+    /// ```
+    /// use getset::Config;
+    /// use getset::config::CommandEntry;
+    ///
+    /// let config = Config::new(".")
+    ///     .with_step(CommandEntry::new("Install gems", "bundle install").with_id("gems"))
+    ///     .with_step(CommandEntry::new("Migrate", "bin/rails db:migrate").with_need("gems"))
+    ///     .build()?;
+    /// assert_eq!(config.commands.len(), 2);
+    /// # Ok::<(), color_eyre::Report>(())
+    /// ```
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a step, to run after the steps added before it
+    pub fn with_step(mut self, mut step: CommandEntry) -> Self {
+        step.base_dir = self.base_dir.clone();
+        self.commands.push(step);
+        self
+    }
+
+    /// Add a step for `getset down`, to run after the teardown steps added before it
+    pub fn with_teardown(mut self, mut step: CommandEntry) -> Self {
+        step.base_dir = self.base_dir.clone();
+        self.teardown.push(step);
+        self
+    }
+
+    /// Set a value substituted for `${name}` in steps
+    pub fn with_var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Expand and validate a configuration built in code, as loading a file does
+    pub fn build(mut self) -> Result<Self> {
+        self.expand()?;
+        self.expand_matrices()?;
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Read a single config file exactly as written, to change it and write it back
+    ///
+    /// Unlike [`Config::from_file`], includes, local overrides and `dotenv`
+    /// files are not read, and `${...}` references and matrices are left as
+    /// they are, so [`Config::to_toml`] reproduces the file's settings.
+    pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let toml_content = fs::read_to_string(path)
+            .map_err(|e| eyre!("Error reading file '{}': {}", path.display(), e))?;
+        let mut config = Config::parse(&toml_content)
+            .map_err(|e| eyre!("Error parsing TOML in '{}': {}", path.display(), e))?;
+        config.locate(path.parent().unwrap_or(Path::new("")), path);
+        Ok(config)
+    }
+
+    /// Resolve paths against `base_dir`, and point step locations at `file`
+    fn locate(&mut self, base_dir: &Path, file: &Path) {
+        self.base_dir = base_dir.to_path_buf();
+        for cmd in self.commands.iter_mut().chain(&mut self.teardown) {
+            cmd.base_dir = self.base_dir.clone();
+            if let Some(ref mut location) = cmd.location {
+                location.file = Some(file.to_path_buf());
+            }
+        }
+        for service in &mut self.services {
+            service.base_dir = self.base_dir.clone();
+        }
+    }
+
+    /// The configuration as TOML, e.g. to write a built or edited config to a file
+    ///
+    /// Settings left at their defaults are omitted, and comments are not kept.
+    /// A config loaded with [`Config::from_file`] has its references already
+    /// expanded, so write one read with [`Config::parse_file`] instead.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| eyre!("Error writing config as TOML: {}", e))
+    }
+
+    /// Read and parse a file and its includes without validating them
+    ///
+    /// A `<name>.local.toml` file next to `path` is applied as an override.
+    /// A pinned URL is read as a remote config running in the current directory.
+    /// The steps of `[workspaces]` members, and with `recursive` of every
+    /// project below the file's directory, are added after the file's own.
+    /// The file's own matrices and `disable` list are left for the caller to
+    /// apply once every file is merged.
+    fn read(path: &Path, recursive: bool) -> Result<Self> {
+        let mut config = Config::read_project(path)?;
+
+        let mut members = match config.workspaces {
+            Some(ref workspaces) => workspaces::resolve(&config.base_dir, &workspaces.members)?,
+            None => Vec::new(),
+        };
+        if recursive {
+            for found in workspaces::discover(&config.base_dir)? {
+                if !members.iter().any(|member| member.name == found.name) {
+                    members.push(found);
+                }
+            }
+        }
+        if !members.is_empty() {
+            let needs = config
+                .workspaces
+                .as_ref()
+                .map(|workspaces| workspaces.needs.clone())
+                .unwrap_or_default();
+            config.add_members(members, &needs)?;
+        }
+        Ok(config)
+    }
+
+    /// Read and parse a file with its includes and local override, leaving out its workspaces
+    fn read_project(path: &Path) -> Result<Self> {
+        let mut config = Config::read_included(path, Path::new(""), &mut Vec::new())?;
+        tracing::debug!(
+            "Read '{}' with {} steps and {} teardown steps",
+            path.display(),
+            config.commands.len(),
+            config.teardown.len()
+        );
+
+        let local_path = local_override_path(path);
+        if !remote::is_remote(&path.to_string_lossy()) && local_path.is_file() {
+            tracing::debug!("Applying local override '{}'", local_path.display());
+            let local = Config::read_included(&local_path, Path::new(""), &mut Vec::new())?;
+            config.apply_override(local, &local_path);
+        }
+        Ok(config)
+    }
+
+    /// Append the steps of workspace members, namespaced by member
+    ///
+    /// A member listed in `needs` has each of its steps that needs no other
+    /// step wait for every step of the members it needs.
+    fn add_members(
+        &mut self,
+        members: Vec<Member>,
+        needs: &BTreeMap<String, Vec<String>>,
+    ) -> Result<()> {
+        for (name, needed) in needs {
+            for need in std::iter::once(name).chain(needed) {
+                if !members.iter().any(|member| &member.name == need) {
+                    return Err(eyre!(
+                        "Invalid config: [workspaces] needs unknown member '{}'",
+                        need
+                    ));
+                }
+            }
+        }
+
+        let mut loaded = Vec::new();
+        for member in members {
+            let path = member.config_path();
+            let mut config = Config::read_project(&path)?;
+            config.expand_matrices()?;
+            config.disable_steps();
+            config.run_in_own_dir();
+            workspaces::namespace(&mut config, &member.name);
+            tracing::debug!(
+                "Adding workspace '{}' with {} steps",
+                member.name,
+                config.commands.len()
+            );
+            loaded.push((member.name, config, path));
+        }
+
+        for index in 0..loaded.len() {
+            let Some(needed) = needs.get(&loaded[index].0) else {
+                continue;
+            };
+            let titles: Vec<String> = loaded
+                .iter()
+                .filter(|(name, _, _)| needed.contains(name))
+                .flat_map(|(_, config, _)| config.commands.iter().map(|step| step.title.clone()))
+                .collect();
+            for step in &mut loaded[index].1.commands {
+                if step.needs.is_empty() {
+                    step.needs.extend(titles.iter().cloned());
+                }
+            }
+        }
+
+        for (_, config, path) in loaded {
+            self.merge(config, &path);
+        }
+        Ok(())
+    }
+
+    /// Merge a local override: steps replace same-titled steps or are appended
+    fn apply_override(&mut self, local: Config, path: &Path) {
+        for (steps, overrides) in [
+            (&mut self.commands, local.commands),
+            (&mut self.teardown, local.teardown),
+        ] {
+            for step in overrides {
+                match steps.iter_mut().find(|s| s.is_same_step(&step)) {
+                    Some(existing) => *existing = step,
+                    None => steps.push(step),
+                }
+            }
+        }
+        self.disable.extend(local.disable);
+
+        let sections = Config {
+            doctor: local.doctor,
+            platformx: local.platformx,
+            notifications: local.notifications,
+            metrics: local.metrics,
+            ..Default::default()
+        };
+        self.merge(sections, path);
+    }
+
+    /// Remove the steps listed in `disable`, along with `needs` on them, emptying the list
+    fn disable_steps(&mut self) {
+        let disable = std::mem::take(&mut self.disable);
+        for name in &disable {
+            let known = self
+                .commands
+                .iter()
+                .chain(&self.teardown)
+                .any(|step| step.is_named(name));
+            if !known {
+                tracing::warn!("Cannot disable unknown step '{}'", name);
+            }
+        }
+
+        for steps in [&mut self.commands, &mut self.teardown] {
+            let removed: Vec<String> = steps
+                .iter()
+                .filter(|step| disable.iter().any(|name| step.is_named(name)))
+                .flat_map(|step| step.id.iter().chain([&step.title]).cloned())
+                .collect();
+            steps.retain(|step| !disable.iter().any(|name| step.is_named(name)));
+            for step in steps.iter_mut() {
+                step.needs.retain(|need| !removed.contains(need));
+            }
+        }
+    }
+
+    /// Read a file, merging in its `include`s; `chain` holds the files including it
+    ///
+    /// Included files are merged in the order listed, with glob matches sorted
+    /// by path, and their steps run before the including file's own steps.
+    /// A remote `path` runs in `base_dir`, the directory of the file including it.
+    fn read_included(path: &Path, base_dir: &Path, chain: &mut Vec<PathBuf>) -> Result<Self> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if chain.contains(&canonical) {
+            return Err(eyre!(
+                "Invalid config: '{}' includes itself",
+                path.display()
+            ));
+        }
+
+        let location = path.to_string_lossy();
+        let mut config = if remote::is_remote(&location) {
+            Config::read_remote(&location, base_dir)?
+        } else {
+            Config::read_file(path)?
+        };
+        if config.include.is_empty() {
+            return Ok(config);
+        }
+
+        let included = config.include_paths()?;
+        let commands = std::mem::take(&mut config.commands);
+        let teardown = std::mem::take(&mut config.teardown);
+
+        let base_dir = config.base_dir.clone();
+        chain.push(canonical);
+        for include in included {
+            let other = Config::read_included(&include, &base_dir, chain)?;
+            config.merge(other, &include);
+        }
+        chain.pop();
+
+        config.commands.extend(commands);
+        config.teardown.extend(teardown);
+        Ok(config)
+    }
+
+    /// Files named by `include`, resolved against the config file's directory
+    fn include_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for pattern in &self.include {
+            if remote::is_remote(pattern) {
+                paths.push(PathBuf::from(pattern));
+                continue;
+            }
+            let joined = self.base_dir.join(pattern);
+            if !pattern.contains(['*', '?', '[']) {
+                paths.push(joined);
+                continue;
+            }
+
+            let mut matches = glob::glob(&joined.to_string_lossy())
+                .map_err(|e| eyre!("Invalid config: bad include pattern '{}': {}", pattern, e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| eyre!("Error reading include '{}': {}", pattern, e))?;
+            matches.sort();
+            paths.extend(matches);
+        }
+        Ok(paths)
+    }
+
+    /// Read and parse a single file without validating it, resolving paths against its directory
+    fn read_file(path: &Path) -> Result<Self> {
+        Config::parse_file(path)?.loaded()
+    }
+
+    /// Fetch and parse a pinned remote config without validating it, resolving paths against `base_dir`
+    fn read_remote(location: &str, base_dir: &Path) -> Result<Self> {
+        let content = remote::fetch(location, base_dir)?;
+        let mut config = Config::parse(&content)
+            .map_err(|e| eyre!("Error parsing TOML in '{}': {}", location, e))?;
+        config.locate(base_dir, Path::new(location));
+        config.loaded()
+    }
+
+    /// Apply the `dotenv` files and expand `${...}` references of a config just parsed
+    fn loaded(mut self) -> Result<Self> {
+        for path in &mut self.requires {
+            *path = self.base_dir.join(&*path);
+        }
+        let dotenv = self.load_dotenv()?;
+        self.export_env(&dotenv);
+        self.expand()?;
+        Ok(self)
+    }
+
+    /// Run the steps that set no `cwd` in this config's directory instead of the current one
+    ///
+    /// For projects set up from another project's directory, like workspace
+    /// members and required projects.
+    pub(crate) fn run_in_own_dir(&mut self) {
+        for step in self.commands.iter_mut().chain(&mut self.teardown) {
+            step.cwd.get_or_insert_with(|| PathBuf::from("."));
+        }
+    }
+
+    /// Variables from the `dotenv` files, later files taking precedence
+    ///
+    /// Missing files are skipped so optional files like `.env.local` can be listed.
+    pub(crate) fn load_dotenv(&self) -> Result<BTreeMap<String, String>> {
+        let mut vars = BTreeMap::new();
+        for file in &self.dotenv {
+            let path = self.base_dir.join(file);
+            if !path.is_file() {
+                tracing::debug!("Skipping missing dotenv file '{}'", path.display());
+                continue;
+            }
+
+            let entries = dotenvy::from_path_iter(&path)
+                .map_err(|e| eyre!("Error reading dotenv file '{}': {}", path.display(), e))?;
+            for entry in entries {
+                let (key, value) = entry
+                    .map_err(|e| eyre!("Error parsing dotenv file '{}': {}", path.display(), e))?;
+                vars.insert(key, value);
+            }
+        }
+        Ok(vars)
+    }
+
+    /// Parse TOML content without validating it, recording where each step is declared
+    fn parse(toml_content: &str) -> Result<Self, toml::de::Error> {
+        let mut config: Config = toml::from_str(toml_content)?;
+
+        if let Ok(spans) = toml::from_str::<CommandSpans>(toml_content) {
+            let steps = config.commands.iter_mut().zip(spans.commands);
+            let teardown = config.teardown.iter_mut().zip(spans.teardown);
+            for (cmd, span) in steps.chain(teardown) {
+                cmd.location = Some(Location::at(toml_content, span.span().start));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Expand `${name}` and `${env:NAME}` references in steps and the
+    /// `[platformx]`, `[notifications]` and `[metrics]` sections
+    ///
+    /// `${matrix.<key>}` references are left for [`Config::expand_matrices`].
+    fn expand(&mut self) -> Result<()> {
+        for cmd in self.commands.iter_mut().chain(&mut self.teardown) {
+            cmd.expand_vars(&self.vars)?;
+        }
+        for service in &mut self.services {
+            service.expand_vars(&self.vars)?;
+        }
+
+        if let Some(ref mut platformx) = self.platformx {
+            let unset = |name| {
+                eyre!(
+                    "Invalid config: [platformx] uses unset environment variable '{}'",
+                    name
+                )
+            };
+            // The key itself is resolved only when telemetry is sent
+            match (&platformx.secret_key, &platformx.secret_key_env) {
+                (Some(_), Some(_)) => {
+                    return Err(eyre!(
+                        "Invalid config: [platformx] sets both secret_key and secret_key_env"
+                    ));
+                }
+                (None, None) => {
+                    return Err(eyre!(
+                        "Invalid config: [platformx] needs a secret_key or secret_key_env"
+                    ));
+                }
+                _ => {}
+            }
+            if let Some(ref mut namespace) = platformx.event_namespace {
+                *namespace = expand_vars(namespace, &self.vars).map_err(unset)?;
+            }
+            if let Some(ref mut api_url) = platformx.api_url {
+                *api_url = expand_vars(api_url, &self.vars).map_err(unset)?;
+            }
+        }
+        if let Some(ref mut notifications) = self.notifications {
+            let unset = |name| {
+                eyre!(
+                    "Invalid config: [notifications] uses unset environment variable '{}'",
+                    name
+                )
+            };
+            // Webhook URLs and headers carry the credentials to post with
+            if let Some(ref mut slack) = notifications.slack {
+                slack.webhook_url = expand_vars(&slack.webhook_url, &self.vars).map_err(unset)?;
+                redact::register(&slack.webhook_url);
+            }
+            for webhook in &mut notifications.webhook {
+                webhook.url = expand_vars(&webhook.url, &self.vars).map_err(unset)?;
+                redact::register(&webhook.url);
+                for value in webhook.headers.values_mut() {
+                    *value = expand_vars(value, &self.vars).map_err(unset)?;
+                    redact::register(value);
+                }
+            }
+        }
+        if let Some(ref mut metrics) = self.metrics {
+            let unset = |name| {
+                eyre!(
+                    "Invalid config: [metrics] uses unset environment variable '{}'",
+                    name
+                )
+            };
+            if let Some(ref mut prometheus) = metrics.prometheus {
+                prometheus.pushgateway_url =
+                    expand_vars(&prometheus.pushgateway_url, &self.vars).map_err(unset)?;
+            }
+            if let Some(ref mut statsd) = metrics.statsd {
+                statsd.addr = expand_vars(&statsd.addr, &self.vars).map_err(unset)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load several configuration files and merge them into one
+    ///
+    /// Commands and teardown steps are concatenated in argument order. The
+    /// `[platformx]`, `[notifications]` and `[metrics]` sections come from the
+    /// first file that declares each; later declarations are ignored with a warning.
+    /// Relative paths stay relative to the file that declared them, and steps
+    /// may depend on steps from other files. `[doctor]` checks from every file
+    /// are combined.
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        Config::load(paths, false)
+    }
+
+    /// Load and merge `paths` like [`Config::from_files`], adding every project
+    /// below each file's directory that has a `getset.toml` as a workspace member
+    pub fn from_files_recursive<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        Config::load(paths, true)
+    }
+
+    fn load<P: AsRef<Path>>(paths: &[P], recursive: bool) -> Result<Self> {
+        let mut merged: Option<Config> = None;
+
+        for path in paths {
+            let config = Config::read(path.as_ref(), recursive)?;
+
+            match merged {
+                None => merged = Some(config),
+                Some(ref mut merged) => merged.merge(config, path.as_ref()),
+            }
+        }
+
+        let mut merged = merged.ok_or_else(|| eyre!("No configuration files given"))?;
+        merged.expand_matrices()?;
+        merged.disable_steps();
+        merged.validate()?;
+        tracing::debug!(
+            "Resolved config in '{}' to steps: {:?}",
+            merged.base_dir.display(),
+            merged
+                .commands
+                .iter()
+                .map(|cmd| &cmd.title)
+                .collect::<Vec<_>>()
+        );
+        Ok(merged)
+    }
+
+    /// The configs named by `requires`, and those they require, in the order to set them up
+    ///
+    /// `paths` are the files this config was read from, so a project requiring
+    /// one of them is reported as a cycle. Each project comes after the
+    /// projects it requires, and is listed once however many configs require it.
+    pub fn required_configs<P: AsRef<Path>>(&self, paths: &[P]) -> Result<Vec<(PathBuf, Config)>> {
+        let mut chain: Vec<PathBuf> = paths
+            .iter()
+            .filter_map(|path| path.as_ref().canonicalize().ok())
+            .collect();
+        let roots = chain.len();
+        let mut required = Vec::new();
+        self.collect_required(&mut chain, roots, &mut Vec::new(), &mut required)?;
+        Ok(required)
+    }
+
+    /// Add the projects this config requires to `required`
+    ///
+    /// `chain` holds the canonical paths of the configs requiring it, starting
+    /// with the `roots` files of the config being set up.
+    fn collect_required(
+        &self,
+        chain: &mut Vec<PathBuf>,
+        roots: usize,
+        seen: &mut Vec<PathBuf>,
+        required: &mut Vec<(PathBuf, Config)>,
+    ) -> Result<()> {
+        for path in &self.requires {
+            let canonical = path
+                .canonicalize()
+                .map_err(|e| eyre!("Error reading file '{}': {}", path.display(), e))?;
+            if let Some(start) = chain.iter().position(|known| *known == canonical) {
+                // The other root files don't require each other, so leave them out
+                let cycle: Vec<String> = chain
+                    .iter()
+                    .enumerate()
+                    .skip(start)
+                    .filter(|&(index, _)| index == start || index >= roots)
+                    .map(|(_, known)| known.display().to_string())
+                    .chain([canonical.display().to_string()])
+                    .collect();
+                return Err(eyre!(
+                    "Invalid config: projects require each other: {}",
+                    cycle.join(" -> ")
+                ));
+            }
+            if seen.contains(&canonical) {
+                continue;
+            }
+
+            // Its steps run in its own directory, not the requiring project's
+            let mut config = Config::from_file(path)?;
+            config.run_in_own_dir();
+            chain.push(canonical.clone());
+            config.collect_required(chain, roots, seen, required)?;
+            chain.pop();
+            seen.push(canonical);
+            required.push((path.clone(), config));
+        }
+        Ok(())
+    }
+
+    /// Replace each step that has a `matrix` with one step per combination
+    ///
+    /// `needs` and `disable` entries naming a matrix step's original title or
+    /// id are redirected to every expanded step, whichever file they are in.
+    fn expand_matrices(&mut self) -> Result<()> {
+        let mut expanded_names: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for steps in [&mut self.commands, &mut self.teardown] {
+            if steps.iter().all(|step| step.matrix.is_empty()) {
+                continue;
+            }
+
+            let mut expanded = Vec::with_capacity(steps.len());
+            for step in std::mem::take(steps) {
+                if step.matrix.is_empty() {
+                    expanded.push(step);
+                    continue;
+                }
+
+                let copies = step.matrix_steps()?;
+                expanded_names.insert(
+                    step.title.clone(),
+                    copies.iter().map(|copy| copy.title.clone()).collect(),
+                );
+                if let Some(id) = step.id {
+                    expanded_names
+                        .insert(id, copies.iter().flat_map(|copy| copy.id.clone()).collect());
+                }
+                expanded.extend(copies);
+            }
+            *steps = expanded;
+        }
+        if expanded_names.is_empty() {
+            return Ok(());
+        }
+
+        let redirect = |names: &[String]| -> Vec<String> {
+            names
+                .iter()
+                .flat_map(|name| match expanded_names.get(name) {
+                    Some(names) => names.clone(),
+                    None => vec![name.clone()],
+                })
+                .collect()
+        };
+        for step in self.commands.iter_mut().chain(&mut self.teardown) {
+            step.needs = redirect(&step.needs);
+        }
+        self.disable = redirect(&self.disable);
+        Ok(())
+    }
+
+    /// Append the steps of `other`, read from `path`, and combine its sections
+    fn merge(&mut self, other: Config, path: &Path) {
+        self.requires.extend(other.requires);
+        self.disable.extend(other.disable);
+        self.commands.extend(other.commands);
+        self.teardown.extend(other.teardown);
+        self.services.extend(other.services);
+        self.prompts.extend(other.prompts);
+
+        match (&mut self.doctor, other.doctor) {
+            (None, doctor) => self.doctor = doctor,
+            (Some(merged), Some(doctor)) => {
+                merged.binaries.extend(doctor.binaries);
+                merged.env.extend(doctor.env);
+                merged.versions.extend(doctor.versions);
+            }
+            (Some(_), None) => {}
+        }
+
+        match (&self.platformx, other.platformx) {
+            (None, platformx) => self.platformx = platformx,
+            (Some(_), Some(_)) => tracing::warn!(
+                "Ignoring [platformx] in '{}'; using the first declared section",
+                path.display()
+            ),
+            (Some(_), None) => {}
+        }
+
+        match (&self.notifications, other.notifications) {
+            (None, notifications) => self.notifications = notifications,
+            (Some(_), Some(_)) => tracing::warn!(
+                "Ignoring [notifications] in '{}'; using the first declared section",
+                path.display()
+            ),
+            (Some(_), None) => {}
+        }
+
+        match (&self.metrics, other.metrics) {
+            (None, metrics) => self.metrics = metrics,
+            (Some(_), Some(_)) => tracing::warn!(
+                "Ignoring [metrics] in '{}'; using the first declared section",
+                path.display()
+            ),
+            (Some(_), None) => {}
+        }
+
+        match (&self.hooks, other.hooks) {
+            (None, hooks) => self.hooks = hooks,
+            (Some(_), Some(_)) => tracing::warn!(
+                "Ignoring [hooks] in '{}'; using the first declared section",
+                path.display()
+            ),
+            (Some(_), None) => {}
+        }
+    }
+
+    /// Check invariants that the TOML schema alone cannot express
+    fn validate(&self) -> Result<()> {
+        validate_steps(&self.commands)?;
+        validate_steps(&self.teardown)?;
+        validate_services(&self.services)?;
+
+        let webhooks = self.notifications.iter().flat_map(|n| &n.webhook);
+        for webhook in webhooks {
+            if let Some(event) = webhook
+                .events
+                .iter()
+                .find(|event| !WEBHOOK_EVENTS.contains(&event.as_str()))
+            {
+                return Err(eyre!(
+                    "Invalid config: webhook '{}' has unknown event '{}' (expected one of: {})",
+                    webhook.url,
+                    event,
+                    WEBHOOK_EVENTS.join(", ")
+                ));
+            }
+        }
+
+        let mut hooks = self.hooks.iter().flat_map(|hooks| &hooks.events);
+        if let Some(event) = hooks.find(|event| !GIT_HOOKS.contains(&event.as_str())) {
+            return Err(eyre!(
+                "Invalid config: [hooks] has unknown event '{}' (expected one of: {})",
+                event,
+                GIT_HOOKS.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Export `vars` to every step and service, without overriding their own `env`
+    pub fn export_env(&mut self, vars: &BTreeMap<String, String>) {
+        let envs = self
+            .commands
+            .iter_mut()
+            .chain(&mut self.teardown)
+            .map(|cmd| &mut cmd.env);
+        for env in envs.chain(self.services.iter_mut().map(|service| &mut service.env)) {
+            for (key, value) in vars {
+                env.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+
+    /// The teardown steps, as a configuration `getset down` can run
+    pub fn into_teardown(self) -> Config {
+        Config {
+            commands: self.teardown,
+            teardown: Vec::new(),
+            ..self
+        }
+    }
+}
+
+/// Check that services have distinct names and a command
+pub fn validate_services(services: &[ServiceConfig]) -> Result<()> {
+    let mut seen = HashSet::new();
+    for service in services {
+        if service.name.trim().is_empty() || service.name.contains(char::is_whitespace) {
+            return Err(eyre!(
+                "Invalid config: service '{}' needs a name without spaces",
+                service.name
+            ));
+        }
+        if !seen.insert(&service.name) {
+            return Err(eyre!(
+                "Invalid config: service '{}' is declared more than once",
+                service.name
+            ));
+        }
+        if service.command.trim().is_empty() {
+            return Err(eyre!(
+                "Invalid config: service '{}' has an empty 'command'",
+                service.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check the steps of one section, including their `needs` graph
+fn validate_steps(steps: &[CommandEntry]) -> Result<()> {
+    let mut seen: HashMap<&str, &CommandEntry> = HashMap::new();
+    let mut seen_ids: HashMap<&str, &CommandEntry> = HashMap::new();
+
+    for cmd in steps {
+        if let Some(ref id) = cmd.id {
+            if id.is_empty() || id.contains(char::is_whitespace) {
+                return Err(cmd.invalid(format!("has an invalid id '{}'", id)));
+            }
+            if let Some(first) = seen_ids.insert(id, cmd) {
+                return Err(cmd.invalid(format!(
+                    "has the same id '{}' as step '{}'",
+                    id, first.title
+                )));
+            }
+        }
+
+        if cmd.title.trim().is_empty() {
+            return Err(cmd.invalid("has an empty 'title'"));
+        }
+        if let Some(first) = seen.insert(&cmd.title, cmd) {
+            return Err(match &first.location {
+                Some(location) => {
+                    cmd.invalid(format!("has the same title as the step at {}", location))
+                }
+                None => cmd.invalid("has the same title as another step"),
+            });
+        }
+
+        match (&cmd.command, &cmd.argv) {
+            (Some(command), None) if command.trim().is_empty() => {
+                return Err(cmd.invalid("has an empty 'command'"));
+            }
+            (Some(_), None) => {}
+            (None, Some(argv)) if !argv.is_empty() => {}
+            (None, Some(_)) => return Err(cmd.invalid("has an empty 'argv'")),
+            _ => {
+                return Err(cmd.invalid("must set exactly one of 'command' or 'argv'"));
+            }
+        }
+
+        match &cmd.shell {
+            Some(_) if cmd.argv.is_some() => {
+                return Err(cmd.invalid("sets 'shell', which only applies to 'command'"));
+            }
+            Some(Shell::Program(program)) if program.is_empty() => {
+                return Err(cmd.invalid("has an empty 'shell'"));
+            }
+            Some(Shell::Argv(argv)) if argv.is_empty() => {
+                return Err(cmd.invalid("has an empty 'shell'"));
+            }
+            _ => {}
+        }
+
+        if !cmd.outputs.is_empty() && cmd.sources.is_empty() {
+            return Err(cmd.invalid("sets 'outputs' without 'sources'"));
+        }
+    }
+    graph::validate(steps)
+}
+
+impl FromStr for Config {
+    type Err = Report;
+    /// Parse a TOML configuration from a string
+    fn from_str(toml_content: &str) -> Result<Self, Self::Err> {
+        let mut config =
+            Config::parse(toml_content).map_err(|e| eyre!("Error parsing TOML: {}", e))?;
+        config.expand()?;
+        config.expand_matrices()?;
+        config.disable_steps();
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_load_from_file() {
+        let config = Config::from_file("tests/fixtures/valid_config.toml")
+            .expect("Failed to load config from file");
+
+        assert_eq!(config.commands.len(), 2);
+        assert_eq!(config.base_dir, Path::new("tests/fixtures"));
+        assert_eq!(config.commands[0].title, "Test Command 1");
+        assert_eq!(config.commands[0].command.as_deref(), Some("echo test1"));
+        assert_eq!(config.commands[1].title, "Test Command 2");
+        assert_eq!(config.commands[1].command.as_deref(), Some("echo test2"));
+    }
+
+    #[test]
+    fn test_load_from_multiple_files() {
+        let config = Config::from_files(&[
+            "tests/fixtures/valid_config.toml",
+            "tests/fixtures/verbose-test.toml",
+        ])
+        .expect("Failed to load configs");
+
+        let titles: Vec<&str> = config.commands.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Test Command 1", "Test Command 2", "Simple echo"]);
+        assert_eq!(config.base_dir, Path::new("tests/fixtures"));
+    }
+
+    #[test]
+    fn test_multiple_files_keep_first_platformx() {
+        let config = Config::from_files(&[
+            "tests/fixtures/valid_config.toml",
+            "tests/fixtures/platformx-first.toml",
+            "tests/fixtures/platformx-second.toml",
+        ])
+        .expect("Failed to load configs");
+
+        assert_eq!(config.commands.len(), 4);
+        let platformx = config
+            .platformx
+            .expect("platformx from first declaring file");
+        assert_eq!(platformx.secret_key.as_deref(), Some("first_key"));
+    }
+
+    #[test]
+    fn test_multiple_files_missing_file_errors() {
+        let result = Config::from_files(&[
+            "tests/fixtures/valid_config.toml",
+            "tests/fixtures/nonexistent.toml",
+        ]);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Error reading file")
+        );
+    }
+
+    #[test]
+    fn test_load_from_string() {
+        let toml_str = r#"
+[[commands]]
+title = "String Command"
+command = "echo from string"
+"#;
+
+        let config: Config = toml_str
+            .parse()
+            .expect("Failed to parse config from string");
+
+        assert_eq!(config.commands.len(), 1);
+        assert_eq!(config.commands[0].title, "String Command");
+        assert_eq!(
+            config.commands[0].command.as_deref(),
+            Some("echo from string")
+        );
+    }
+
+    #[test]
+    fn test_valid_parse() {
+        let toml_str = r#"
+[[commands]]
+title = "Valid Command 1"
+command = "ls -la"
+
+[[commands]]
+title = "Valid Command 2"
+command = "pwd"
+"#;
+
+        let config: Config = toml_str.parse().expect("Failed to parse valid TOML");
+
+        assert_eq!(config.commands.len(), 2);
+        assert_eq!(config.commands[0].title, "Valid Command 1");
+        assert_eq!(config.commands[0].command.as_deref(), Some("ls -la"));
+        assert_eq!(config.commands[1].title, "Valid Command 2");
+        assert_eq!(config.commands[1].command.as_deref(), Some("pwd"));
+    }
+
+    #[test]
+    fn test_invalid_parse_missing_field() {
+        let invalid_toml = r#"
+[[commands]]
+title = "Missing command field"
+"#;
+
+        let result: Result<Config, _> = invalid_toml.parse();
+        assert!(result.is_err(), "Should fail when command field is missing");
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("must set exactly one of 'command' or 'argv'")
+        );
+    }
+
+    #[test]
+    fn test_invalid_parse_malformed_toml() {
+        let malformed_toml = r#"
+[[commands]
+title = "Missing closing bracket"
+command = "echo test"
+"#;
+
+        let result: Result<Config, _> = malformed_toml.parse();
+        assert!(result.is_err(), "Should fail with malformed TOML");
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Error parsing TOML")
+        );
+    }
+
+    #[test]
+    fn test_load_from_nonexistent_file() {
+        let result = Config::from_file("tests/fixtures/nonexistent.toml");
+        assert!(result.is_err(), "Should fail when file doesn't exist");
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Error reading file")
+        );
+    }
+
+    #[test]
+    fn test_load_from_invalid_file() {
+        let result = Config::from_file("tests/fixtures/invalid_config.toml");
+        assert!(result.is_err(), "Should fail with invalid config file");
+        assert!(result.unwrap_err().to_string().contains("Invalid config"));
+    }
+
+    #[test]
+    fn test_argv_command() {
+        let toml_str = r#"
+[[commands]]
+title = "Run container"
+argv = ["docker", "run", "--rm", "img"]
+"#;
+
+        let config: Config = toml_str.parse().expect("Failed to parse argv command");
+
+        assert!(config.commands[0].command.is_none());
+        assert_eq!(
+            config.commands[0].argv,
+            Some(vec![
+                "docker".to_string(),
+                "run".to_string(),
+                "--rm".to_string(),
+                "img".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_command_and_argv_are_mutually_exclusive() {
+        let toml_str = r#"
+[[commands]]
+title = "Both"
+command = "echo hi"
+argv = ["echo", "hi"]
+"#;
+
+        let result: Result<Config, _> = toml_str.parse();
+        assert!(
+            result.is_err(),
+            "Should fail when both command and argv are set"
+        );
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("step 'Both' must set exactly one of 'command' or 'argv'")
+        );
+    }
+
+    #[test]
+    fn test_empty_argv_is_rejected() {
+        let toml_str = r#"
+[[commands]]
+title = "Empty"
+argv = []
+"#;
+
+        let result: Result<Config, _> = toml_str.parse();
+        assert!(result.is_err(), "Should fail when argv is empty");
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("step 'Empty' has an empty 'argv'")
+        );
+    }
+
+    #[test]
+    fn test_parallel_defaults_to_false() {
+        let toml_str = r#"
+[[commands]]
+title = "Sequential"
+command = "true"
+
+[[commands]]
+title = "Concurrent"
+command = "true"
+parallel = true
+"#;
+
+        let config: Config = toml_str.parse().expect("Failed to parse parallel flag");
+
+        assert!(!config.commands[0].parallel);
+        assert!(config.commands[1].parallel);
+    }
+
+    #[test]
+    fn test_needs_cycle_is_rejected() {
+        let toml_str = r#"
+[[commands]]
+title = "Migrate"
+command = "true"
+needs = ["Seed"]
+
+[[commands]]
+title = "Seed"
+command = "true"
+needs = ["Migrate"]
+"#;
+
+        let result: Result<Config, _> = toml_str.parse();
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("dependency cycle among steps 'Migrate', 'Seed'")
+        );
+    }
+
+    #[test]
+    fn test_step_env_table() {
+        let toml_str = r#"
+[[commands]]
+title = "Migrate"
+command = "bin/rails db:migrate"
+env = { RAILS_ENV = "development", VERBOSE = "1" }
+"#;
+
+        let config: Config = toml_str.parse().expect("Failed to parse env table");
+
+        let env = &config.commands[0].env;
+        assert_eq!(env.len(), 2);
+        assert_eq!(env["RAILS_ENV"], "development");
+        assert_eq!(env["VERBOSE"], "1");
+    }
+
+    #[test]
+    fn test_cwd_is_relative_to_config_file() {
+        let config = Config::from_file("tests/fixtures/cwd-test.toml").expect("Failed to load");
+
+        assert_eq!(
+            config.commands[0].working_dir(),
+            Some(PathBuf::from("tests/fixtures/cwd-test"))
+        );
+        assert_eq!(config.commands[1].working_dir(), None);
+    }
+
+    #[test]
+    fn test_shell_as_program_or_argv() {
+        let toml_str = r#"
+[[commands]]
+title = "Bash"
+command = "echo ${BASH_VERSION}"
+shell = "bash"
+
+[[commands]]
+title = "Zsh"
+command = "echo hi"
+shell = ["zsh", "-lc"]
+
+[[commands]]
+title = "Default"
+command = "echo hi"
+"#;
+
+        let config: Config = toml_str.parse().expect("Failed to parse shells");
+
+        assert_eq!(
+            config.commands[0].shell.as_ref().unwrap().argv(),
+            ["bash", "-c"]
+        );
+        assert_eq!(
+            config.commands[1].shell.as_ref().unwrap().argv(),
+            ["zsh", "-lc"]
+        );
+        assert!(config.commands[2].shell.is_none());
+        if cfg!(windows) {
+            assert_eq!(Shell::default().argv(), ["cmd", "/C"]);
+        } else {
+            assert_eq!(Shell::default().argv(), ["sh", "-c"]);
+        }
+    }
+
+    #[test]
+    fn test_windows_shells_use_their_own_script_flag() {
+        let argv = |program: &str| Shell::Program(program.to_string()).argv();
+
+        assert_eq!(argv("cmd"), ["cmd", "/C"]);
+        assert_eq!(argv("CMD.EXE"), ["CMD.EXE", "/C"]);
+        assert_eq!(argv("pwsh"), ["pwsh", "-Command"]);
+        assert_eq!(argv("powershell.exe"), ["powershell.exe", "-Command"]);
+        assert!(!Shell::Program("cmd".to_string()).is_posix());
+        assert!(Shell::Argv(vec!["bash".to_string(), "-lc".to_string()]).is_posix());
+    }
+
+    #[test]
+    fn test_shell_with_argv_is_rejected() {
+        let toml_str = r#"
+[[commands]]
+title = "Both"
+argv = ["echo", "hi"]
+shell = "bash"
+"#;
+
+        let result: Result<Config, _> = toml_str.parse();
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("step 'Both' sets 'shell', which only applies to 'command'")
+        );
+    }
+
+    #[test]
+    fn test_platform_mismatch() {
+        let toml_str = r#"
+            [[commands]]
+            title = "Homebrew"
+            command = "brew bundle"
+            os = ["macos"]
+            arch = ["aarch64"]
+        "#;
+        let config = Config::from_str(toml_str).expect("Failed to parse config");
+        let cmd = &config.commands[0];
+
+        assert_eq!(cmd.platform_mismatch_on("macos", "aarch64"), None);
+        assert_eq!(
+            cmd.platform_mismatch_on("linux", "aarch64").as_deref(),
+            Some("macos only")
+        );
+        assert_eq!(
+            cmd.platform_mismatch_on("macos", "x86_64").as_deref(),
+            Some("aarch64 only")
+        );
+        assert_eq!(
+            CommandEntry::default().platform_mismatch_on("linux", "x86_64"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_duplicate_titles_are_rejected_with_locations() {
+        let toml_str = "[[commands]]\ntitle = \"Build\"\ncommand = \"make\"\n\n[[commands]]\ntitle = \"Build\"\ncommand = \"make all\"\n";
+
+        let err = Config::from_str(toml_str).unwrap_err().to_string();
+        assert!(err.contains(
+            "step 'Build' has the same title as the step at line 1, column 1 (at line 5, column 1)"
+        ));
+    }
+
+    #[test]
+    fn test_empty_command_is_rejected() {
+        let toml_str = r#"
+            [[commands]]
+            title = "Blank"
+            command = "  "
+        "#;
+
+        let err = Config::from_str(toml_str).unwrap_err().to_string();
+        assert!(err.contains("step 'Blank' has an empty 'command'"));
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let toml_str =
+            "[[commands]]\ntitle = \"Typo\"\ncommand = \"true\"\nneeds_step = [\"Other\"]\n";
+
+        let err = Config::from_str(toml_str).unwrap_err().to_string();
+        assert!(err.contains("line 4, column 1"));
+        assert!(err.contains("unknown field `needs_step`"));
     }
-}
 
-impl FromStr for Config {
-    type Err = Report;
-    /// Parse a TOML configuration from a string
-    fn from_str(toml_content: &str) -> Result<Self, Self::Err> {
-        toml::from_str(toml_content).map_err(|e| eyre!("Error parsing TOML: {}", e))
+    #[test]
+    fn test_errors_point_at_the_file_and_line() {
+        let err = Config::from_file("tests/fixtures/invalid_config.toml")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("(at tests/fixtures/invalid_config.toml:1:1)"));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_location_at_offset() {
+        let content = "a = 1\n  [[commands]]\n";
+        let location = Location::at(content, content.find('[').unwrap());
+        assert_eq!((location.line, location.column), (2, 3));
+        assert_eq!(location.to_string(), "line 2, column 3");
+    }
 
     #[test]
-    fn test_load_from_file() {
-        let config = Config::from_file("tests/fixtures/valid_config.toml")
-            .expect("Failed to load config from file");
+    fn test_teardown_section() {
+        let toml_str = r#"
+            [[commands]]
+            title = "Start database"
+            command = "docker compose up -d db"
 
-        assert_eq!(config.commands.len(), 2);
-        assert_eq!(config.commands[0].title, "Test Command 1");
-        assert_eq!(config.commands[0].command, "echo test1");
-        assert_eq!(config.commands[1].title, "Test Command 2");
-        assert_eq!(config.commands[1].command, "echo test2");
+            [[teardown]]
+            title = "Stop database"
+            command = "docker compose down"
+
+            [[teardown]]
+            title = "Remove volumes"
+            command = "docker volume prune -f"
+            needs = ["Stop database"]
+        "#;
+
+        let config = Config::from_str(toml_str).expect("Failed to parse config");
+        assert_eq!(config.commands.len(), 1);
+        assert_eq!(config.teardown.len(), 2);
+        assert_eq!(config.teardown[1].location.as_ref().unwrap().line, 10);
+
+        let teardown = config.into_teardown();
+        let titles: Vec<&str> = teardown.commands.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Stop database", "Remove volumes"]);
+        assert!(teardown.teardown.is_empty());
     }
 
     #[test]
-    fn test_load_from_string() {
+    fn test_teardown_needs_resolve_within_teardown() {
         let toml_str = r#"
-[[commands]]
-title = "String Command"
-command = "echo from string"
-"#;
+            [[commands]]
+            title = "Start database"
+            command = "true"
 
-        let config: Config = toml_str
-            .parse()
-            .expect("Failed to parse config from string");
+            [[teardown]]
+            title = "Stop database"
+            command = "true"
+            needs = ["Start database"]
+        "#;
 
-        assert_eq!(config.commands.len(), 1);
-        assert_eq!(config.commands[0].title, "String Command");
-        assert_eq!(config.commands[0].command, "echo from string");
+        let err = Config::from_str(toml_str).unwrap_err().to_string();
+        assert!(err.contains("step 'Stop database' needs unknown step 'Start database'"));
     }
 
     #[test]
-    fn test_valid_parse() {
+    fn test_vars_are_interpolated() {
         let toml_str = r#"
-[[commands]]
-title = "Valid Command 1"
-command = "ls -la"
+            [vars]
+            ruby = "3.3.0"
+            app = "services/app"
 
-[[commands]]
-title = "Valid Command 2"
-command = "pwd"
-"#;
+            [[commands]]
+            title = "Install Ruby"
+            command = "rbenv install ${ruby} && echo ${HOME}"
+            cwd = "${app}"
+            env = { RUBY_VERSION = "${ruby}", LITERAL = "$${ruby}" }
+        "#;
 
-        let config: Config = toml_str.parse().expect("Failed to parse valid TOML");
+        let config = Config::from_str(toml_str).expect("Failed to parse config");
+        let cmd = &config.commands[0];
+        assert_eq!(
+            cmd.command.as_deref(),
+            Some("rbenv install 3.3.0 && echo ${HOME}")
+        );
+        assert_eq!(cmd.cwd, Some(PathBuf::from("services/app")));
+        assert_eq!(cmd.env["RUBY_VERSION"], "3.3.0");
+        assert_eq!(cmd.env["LITERAL"], "${ruby}");
+    }
 
-        assert_eq!(config.commands.len(), 2);
-        assert_eq!(config.commands[0].title, "Valid Command 1");
-        assert_eq!(config.commands[0].command, "ls -la");
-        assert_eq!(config.commands[1].title, "Valid Command 2");
-        assert_eq!(config.commands[1].command, "pwd");
+    #[test]
+    fn test_expand_vars_edge_cases() {
+        let vars = BTreeMap::from([("a".to_string(), "1".to_string())]);
+
+        assert_eq!(expand_vars("${a}${a}", &vars).unwrap(), "11");
+        assert_eq!(expand_vars("${unclosed", &vars).unwrap(), "${unclosed");
+        assert_eq!(expand_vars("$$ ${b} $${a}", &vars).unwrap(), "$$ ${b} ${a}");
     }
 
     #[test]
-    fn test_invalid_parse_missing_field() {
-        let invalid_toml = r#"
-[[commands]]
-title = "Missing command field"
-"#;
+    fn test_env_references_are_expanded() {
+        let path = std::env::var("PATH").unwrap();
+        let vars = BTreeMap::new();
 
-        let result: Result<Config, _> = invalid_toml.parse();
-        assert!(result.is_err(), "Should fail when command field is missing");
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("Error parsing TOML")
+        assert_eq!(
+            expand_vars("PATH=${env:PATH}", &vars).unwrap(),
+            format!("PATH={}", path)
+        );
+        assert_eq!(
+            expand_vars("${env:GETSET_UNSET_VAR}", &vars),
+            Err("GETSET_UNSET_VAR".to_string())
+        );
+        assert_eq!(
+            expand_vars("${secret:GETSET_UNSET_VAR}", &vars),
+            Err("GETSET_UNSET_VAR".to_string())
         );
     }
 
     #[test]
-    fn test_invalid_parse_malformed_toml() {
-        let malformed_toml = r#"
-[[commands]
-title = "Missing closing bracket"
-command = "echo test"
-"#;
+    fn test_env_references_to_credentials_are_redacted() {
+        assert!(is_secret_name("GITHUB_TOKEN"));
+        assert!(is_secret_name("aws_secret_access_key"));
+        assert!(is_secret_name("DB_PASSWORD"));
+        assert!(!is_secret_name("HOME"));
+        assert!(!is_secret_name("PATH"));
 
-        let result: Result<Config, _> = malformed_toml.parse();
-        assert!(result.is_err(), "Should fail with malformed TOML");
-        assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("Error parsing TOML")
+        // SAFETY: the variable is unique to this test
+        unsafe { std::env::set_var("GETSET_TEST_API_TOKEN", "tok-3xp4nd3d") };
+        let expanded = expand_vars("--token ${env:GETSET_TEST_API_TOKEN}", &BTreeMap::new());
+        assert_eq!(expanded.unwrap(), "--token tok-3xp4nd3d");
+        assert_eq!(redact::redact("token tok-3xp4nd3d"), "token [REDACTED]");
+    }
+
+    #[test]
+    fn test_unset_env_reference_is_rejected() {
+        let toml_str = r#"
+            [[commands]]
+            title = "Deploy"
+            command = "deploy --token ${env:GETSET_UNSET_TOKEN}"
+        "#;
+
+        let err = Config::from_str(toml_str).unwrap_err().to_string();
+        assert!(err.contains(
+            "step 'Deploy' uses unset environment variable 'GETSET_UNSET_TOKEN' (at line 2, column 13)"
+        ));
+
+        // Only once telemetry is about to be sent
+        let toml_str = r#"
+            commands = []
+
+            [platformx]
+            secret_key = "${env:GETSET_UNSET_SECRET}"
+        "#;
+
+        let config = Config::from_str(toml_str).expect("the key is resolved later");
+        let err = config
+            .platformx
+            .unwrap()
+            .resolve_secret_key(&config.vars)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("[platformx] uses unset environment variable 'GETSET_UNSET_SECRET'"));
+    }
+
+    #[test]
+    fn test_include_merges_files_in_order() {
+        let config = Config::from_file("tests/fixtures/include/getset.toml")
+            .expect("Failed to load config with includes");
+
+        let titles: Vec<&str> = config.commands.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["Base step", "Tool A step", "Tool B step", "Main step"]
         );
+        assert_eq!(
+            config.commands[1].base_dir,
+            Path::new("tests/fixtures/include/tools")
+        );
+        assert_eq!(config.base_dir, Path::new("tests/fixtures/include"));
     }
 
     #[test]
-    fn test_load_from_nonexistent_file() {
-        let result = Config::from_file("tests/fixtures/nonexistent.toml");
-        assert!(result.is_err(), "Should fail when file doesn't exist");
+    fn test_include_fetches_pinned_remote_configs() {
+        let shared = "[[commands]]\ntitle = \"Shared step\"\ncommand = \"true\"\n";
+        let mut server = mockito::Server::new();
+        server.mock("GET", "/setup.toml").with_body(shared).create();
+        let dir =
+            std::env::temp_dir().join(format!("getset-remote-include-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let hash = format!("{:x}", Sha256::digest(shared));
+        fs::write(
+            dir.join("getset.toml"),
+            format!(
+                "include = [\"{}/setup.toml#sha256={}\"]\n\n[[commands]]\ntitle = \"Own step\"\ncommand = \"true\"\n",
+                server.url(),
+                hash
+            ),
+        )
+        .unwrap();
+
+        let config = Config::from_file(dir.join("getset.toml")).expect("Failed to load config");
+        let titles: Vec<&str> = config.commands.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Shared step", "Own step"]);
+        assert_eq!(config.commands[0].base_dir, dir);
         assert!(
-            result
-                .unwrap_err()
-                .to_string()
-                .contains("Error reading file")
+            dir.join(remote::REMOTE_DIR)
+                .join(format!("{}.toml", hash))
+                .is_file()
         );
+
+        let _ = fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn test_load_from_invalid_file() {
-        let result = Config::from_file("tests/fixtures/invalid_config.toml");
-        assert!(result.is_err(), "Should fail with invalid config file");
-        assert!(
-            result
-                .unwrap_err()
+    fn test_workspaces_add_namespaced_member_steps() {
+        let dir =
+            std::env::temp_dir().join(format!("getset-workspaces-config-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (member, title) in [
+            ("web", "Install packages"),
+            ("services/api", "Install gems"),
+        ] {
+            fs::create_dir_all(dir.join(member)).unwrap();
+            fs::write(
+                dir.join(member).join("getset.toml"),
+                format!(
+                    "[[commands]]\nid = \"deps\"\ntitle = \"{}\"\ncommand = \"true\"\n",
+                    title
+                ),
+            )
+            .unwrap();
+        }
+        fs::write(
+            dir.join("getset.toml"),
+            r#"
+            [workspaces]
+            members = ["web"]
+            needs = { web = ["services/api"] }
+
+            [[commands]]
+            title = "Root step"
+            command = "true"
+            "#,
+        )
+        .unwrap();
+
+        let err = Config::from_file(dir.join("getset.toml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("[workspaces] needs unknown member 'services/api'"));
+
+        let config = Config::from_files_recursive(&[dir.join("getset.toml")])
+            .expect("Failed to load workspaces");
+        let steps: Vec<(&str, &[String])> = config
+            .commands
+            .iter()
+            .map(|step| (step.title.as_str(), step.needs.as_slice()))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("Root step", &[][..]),
+                (
+                    "web: Install packages",
+                    &["services/api: Install gems".to_string()][..]
+                ),
+                ("services/api: Install gems", &[][..]),
+            ]
+        );
+        assert_eq!(config.commands[2].id.as_deref(), Some("services/api/deps"));
+        assert_eq!(config.commands[2].base_dir, dir.join("services/api"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_required_configs_come_before_the_projects_requiring_them() {
+        let dir = std::env::temp_dir().join(format!("getset-requires-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (project, requires) in [
+            ("app", r#"["../api/getset.toml", "../infra/getset.toml"]"#),
+            ("api", r#"["../infra/getset.toml"]"#),
+            ("infra", "[]"),
+        ] {
+            fs::create_dir_all(dir.join(project)).unwrap();
+            fs::write(
+                dir.join(project).join("getset.toml"),
+                format!(
+                    "requires = {}\n\n[[commands]]\ntitle = \"Set up {}\"\ncommand = \"true\"\n",
+                    requires, project
+                ),
+            )
+            .unwrap();
+        }
+
+        let app = dir.join("app/getset.toml");
+        let config = Config::from_file(&app).expect("Failed to load config");
+        let required: Vec<String> = config
+            .required_configs(&[&app])
+            .unwrap()
+            .into_iter()
+            .map(|(_, config)| config.commands[0].title.clone())
+            .collect();
+        assert_eq!(required, ["Set up infra", "Set up api"]);
+
+        fs::write(
+            dir.join("infra/getset.toml"),
+            "requires = [\"../app/getset.toml\"]\n",
+        )
+        .unwrap();
+        let err = config.required_configs(&[&app]).unwrap_err().to_string();
+        let canonical = |project: &str| {
+            dir.join(project)
+                .join("getset.toml")
+                .canonicalize()
+                .unwrap()
+                .display()
                 .to_string()
-                .contains("Error parsing TOML")
+        };
+        assert!(err.contains(&format!(
+            "projects require each other: {} -> {} -> {} -> {}",
+            canonical("app"),
+            canonical("api"),
+            canonical("infra"),
+            canonical("app")
+        )));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_include_cycle_is_rejected() {
+        let err = Config::from_file("tests/fixtures/include/cycle-a.toml")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("cycle-a.toml' includes itself"));
+    }
+
+    #[test]
+    fn test_local_override_replaces_adds_and_disables_steps() {
+        let config = Config::from_file("tests/fixtures/local-override/getset.toml")
+            .expect("Failed to load config with local override");
+
+        let steps: Vec<(&str, &str)> = config
+            .commands
+            .iter()
+            .map(|c| (c.title.as_str(), c.command.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("Install tools", "echo 'my tools'"),
+                ("Migrate", "echo 'migrating'"),
+                ("Personal step", "echo 'just me'"),
+            ]
         );
+        assert!(config.commands[1].needs.is_empty());
+    }
+
+    #[test]
+    fn test_local_override_path() {
+        assert_eq!(
+            local_override_path(Path::new("dir/getset.toml")),
+            Path::new("dir/getset.local.toml")
+        );
+    }
+
+    #[test]
+    fn test_dotenv_files_fill_in_step_env() {
+        let config = Config::from_file("tests/fixtures/dotenv/getset.toml")
+            .expect("Failed to load config with dotenv");
+        let env = &config.commands[0].env;
+
+        assert_eq!(env["SHARED"], "from .env");
+        assert_eq!(env["OVERRIDDEN"], "from .env.local");
+        assert_eq!(env["STEP_WINS"], "from step");
+    }
+
+    #[test]
+    fn test_matrix_expands_steps() {
+        let toml_str = r#"
+            [[commands]]
+            title = "Install ${matrix.service} (${matrix.env})"
+            command = "bin/setup ${matrix.service} --env ${matrix.env}"
+            matrix = { service = ["api", "web"], env = ["dev", "test"] }
+
+            [[commands]]
+            title = "Seed"
+            command = "bin/seed"
+            needs = ["Install ${matrix.service} (${matrix.env})"]
+            matrix = { shard = ["1", "2"] }
+        "#;
+
+        let config = Config::from_str(toml_str).expect("Failed to parse config");
+        let titles: Vec<&str> = config.commands.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "Install api (dev)",
+                "Install web (dev)",
+                "Install api (test)",
+                "Install web (test)",
+                "Seed (1)",
+                "Seed (2)",
+            ]
+        );
+        assert_eq!(
+            config.commands[1].command.as_deref(),
+            Some("bin/setup web --env dev")
+        );
+        assert_eq!(config.commands[4].needs.len(), 4);
+    }
+
+    #[test]
+    fn test_matrix_substitutes_every_text_field() {
+        let toml_str = r#"
+            [[commands]]
+            title = "Build ${matrix.app}"
+            command = "true"
+            matrix = { app = ["api"] }
+
+            [[commands]]
+            title = "Test ${matrix.app}"
+            command = "true"
+            check = "test -f ${matrix.app}/ok"
+            skip_if = "test -f ${matrix.app}/skip"
+            only_if = "test -d ${matrix.app}"
+            sources = ["${matrix.app}/**/*.rb"]
+            outputs = ["${matrix.app}/out"]
+            tags = ["${matrix.app}"]
+            needs = ["Build ${matrix.app}"]
+            matrix = { app = ["api"] }
+        "#;
+
+        let config = Config::from_str(toml_str).expect("Failed to parse config");
+
+        let step = &config.commands[1];
+        assert_eq!(step.check.as_deref(), Some("test -f api/ok"));
+        assert_eq!(step.skip_if.as_deref(), Some("test -f api/skip"));
+        assert_eq!(step.only_if.as_deref(), Some("test -d api"));
+        assert_eq!(step.sources, ["api/**/*.rb"]);
+        assert_eq!(step.outputs, ["api/out"]);
+        assert_eq!(step.tags, ["api"]);
+        assert_eq!(step.needs, ["Build api"]);
+    }
+
+    #[test]
+    fn test_matrix_expands_after_includes_and_overrides_merge() {
+        let dir = std::env::temp_dir().join(format!("getset-matrix-merge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("shared.toml"),
+            "[[commands]]\ntitle = \"Install\"\ncommand = \"true\"\nmatrix = { app = [\"api\", \"web\", \"docs\"] }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("getset.toml"),
+            "include = [\"shared.toml\"]\n\n[[commands]]\ntitle = \"Deploy\"\ncommand = \"true\"\nneeds = [\"Install\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("getset.local.toml"),
+            "disable = [\"Install (docs)\"]\n",
+        )
+        .unwrap();
+
+        let config = Config::from_file(dir.join("getset.toml"));
+
+        let _ = fs::remove_dir_all(&dir);
+        let config = config.expect("Failed to load config");
+        let titles: Vec<&str> = config.commands.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Install (api)", "Install (web)", "Deploy"]);
+        assert_eq!(config.commands[2].needs, ["Install (api)", "Install (web)"]);
+    }
+
+    #[test]
+    fn test_disabling_a_matrix_step_removes_every_copy() {
+        let toml_str = r#"
+            disable = ["Install"]
+
+            [[commands]]
+            title = "Install"
+            command = "true"
+            matrix = { app = ["api", "web"] }
+
+            [[commands]]
+            title = "Deploy"
+            command = "true"
+            needs = ["Install"]
+        "#;
+
+        let config = Config::from_str(toml_str).expect("Failed to parse config");
+
+        assert_eq!(config.commands.len(), 1);
+        assert!(config.commands[0].needs.is_empty());
+    }
+
+    #[test]
+    fn test_empty_matrix_is_rejected() {
+        let toml_str = r#"
+            [[commands]]
+            title = "Install"
+            command = "true"
+            matrix = { service = [] }
+        "#;
+
+        let err = Config::from_str(toml_str).unwrap_err().to_string();
+        assert!(err.contains("step 'Install' has an empty matrix 'service'"));
+    }
+
+    #[test]
+    fn test_ids_must_be_unique() {
+        let toml_str = r#"
+            [[commands]]
+            id = "db"
+            title = "Start database"
+            command = "true"
+
+            [[commands]]
+            id = "db"
+            title = "Drop database"
+            command = "true"
+        "#;
+
+        let err = Config::from_str(toml_str).unwrap_err().to_string();
+        assert!(err.contains("step 'Drop database' has the same id 'db' as step 'Start database'"));
+    }
+
+    #[test]
+    fn test_outputs_require_sources() {
+        let toml_str = r#"
+            [[commands]]
+            title = "Install gems"
+            command = "bundle install"
+            outputs = ["vendor/bundle"]
+        "#;
+
+        let err = Config::from_str(toml_str).unwrap_err().to_string();
+        assert!(err.contains("step 'Install gems' sets 'outputs' without 'sources'"));
+    }
+
+    #[test]
+    fn test_display_command_quotes_argv() {
+        let cmd = CommandEntry {
+            title: "Greet".to_string(),
+            argv: Some(vec!["echo".to_string(), "hello world".to_string()]),
+            ..Default::default()
+        };
+
+        assert_eq!(cmd.display_command(), "echo 'hello world'");
     }
 
     #[test]
@@ -170,7 +2684,7 @@ secret_key = "test_secret_key_123"
 
         assert!(config.platformx.is_some());
         let platformx = config.platformx.unwrap();
-        assert_eq!(platformx.secret_key, "test_secret_key_123");
+        assert_eq!(platformx.secret_key.as_deref(), Some("test_secret_key_123"));
         assert!(platformx.event_namespace.is_none());
     }
 
@@ -184,6 +2698,8 @@ command = "echo test"
 [platformx]
 secret_key = "test_secret_key_123"
 event_namespace = "my_namespace"
+api_url = "https://dx-proxy.example.com/events.track"
+timeout_secs = 2
 "#;
 
         let config: Config = toml_str
@@ -192,8 +2708,13 @@ event_namespace = "my_namespace"
 
         assert!(config.platformx.is_some());
         let platformx = config.platformx.unwrap();
-        assert_eq!(platformx.secret_key, "test_secret_key_123");
+        assert_eq!(platformx.secret_key.as_deref(), Some("test_secret_key_123"));
         assert_eq!(platformx.event_namespace, Some("my_namespace".to_string()));
+        assert_eq!(
+            platformx.api_url.as_deref(),
+            Some("https://dx-proxy.example.com/events.track")
+        );
+        assert_eq!(platformx.timeout_secs, Some(2));
     }
 
     #[test]
@@ -229,8 +2750,55 @@ event_namespace = "my_namespace"
             result
                 .unwrap_err()
                 .to_string()
-                .contains("Error parsing TOML")
+                .contains("[platformx] needs a secret_key or secret_key_env")
+        );
+    }
+
+    #[test]
+    fn test_platformx_secret_key_env() {
+        let config = Config::from_str(
+            r#"
+            [platformx]
+            secret_key_env = "PATH"
+            "#,
+        )
+        .expect("Failed to parse config with secret_key_env");
+
+        let platformx = config.platformx.expect("platformx");
+        assert_eq!(platformx.secret_key, None);
+        assert_eq!(
+            platformx.resolve_secret_key(&config.vars).ok(),
+            std::env::var("PATH").ok()
         );
+
+        // An unset variable only matters once telemetry is about to be sent
+        let config = Config::from_str(
+            r#"
+            [platformx]
+            secret_key_env = "GETSET_UNSET_DX_SECRET"
+            "#,
+        )
+        .expect("the key is resolved later");
+        let err = config
+            .platformx
+            .unwrap()
+            .resolve_secret_key(&config.vars)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(
+            "secret_key_env names environment variable 'GETSET_UNSET_DX_SECRET', which is not set"
+        ));
+
+        let err = Config::from_str(
+            r#"
+            [platformx]
+            secret_key = "abc"
+            secret_key_env = "PATH"
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("sets both secret_key and secret_key_env"));
     }
 
     #[test]
@@ -250,18 +2818,249 @@ secret_key = ""
 
         assert!(config.platformx.is_some());
         let platformx = config.platformx.unwrap();
-        assert_eq!(platformx.secret_key, "");
+        assert_eq!(platformx.secret_key.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_notifications_slack_expands_vars() {
+        let toml_str = r#"
+[vars]
+team = "T1"
+
+[[commands]]
+title = "Test"
+command = "echo test"
+
+[notifications.slack]
+webhook_url = "https://hooks.slack.test/${team}"
+"#;
+
+        let config = Config::from_str(toml_str).expect("Failed to parse config with slack");
+
+        let slack = config.notifications.and_then(|n| n.slack).expect("slack");
+        assert_eq!(slack.webhook_url, "https://hooks.slack.test/T1");
+
+        let err = Config::from_str(
+            r#"
+            [notifications.slack]
+            webhook_url = "${env:GETSET_UNSET_SLACK_HOOK}"
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.contains(
+                "[notifications] uses unset environment variable 'GETSET_UNSET_SLACK_HOOK'"
+            )
+        );
+    }
+
+    #[test]
+    fn test_notifications_webhooks() {
+        let toml_str = r#"
+[vars]
+token = "abc123"
+
+[[notifications.webhook]]
+url = "https://alerts.test/v1"
+headers = { Authorization = "Bearer ${token}" }
+events = ["failure"]
+
+[[notifications.webhook]]
+url = "https://chat.test/hook"
+method = "PUT"
+body = '{"content": "{{event}}"}'
+"#;
+
+        let config = Config::from_str(toml_str).expect("Failed to parse config with webhooks");
+
+        let webhooks = config.notifications.map(|n| n.webhook).unwrap_or_default();
+        assert_eq!(webhooks.len(), 2);
+        assert_eq!(webhooks[0].method, "POST");
+        assert_eq!(webhooks[0].headers["Authorization"], "Bearer abc123");
+        assert_eq!(webhooks[0].events, ["failure"]);
+        assert_eq!(webhooks[1].method, "PUT");
+        assert_eq!(
+            webhooks[1].body.as_deref(),
+            Some(r#"{"content": "{{event}}"}"#)
+        );
+        assert!(webhooks[1].events.is_empty());
+
+        let err = Config::from_str(
+            r#"
+            [[notifications.webhook]]
+            url = "https://alerts.test/v1"
+            events = ["finish"]
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("unknown event 'finish'"));
+    }
+
+    #[test]
+    fn test_metrics_prometheus() {
+        let config = Config::from_str(
+            r#"
+            [vars]
+            host = "push.test"
+
+            [metrics.prometheus]
+            pushgateway_url = "https://${host}:9091"
+            "#,
+        )
+        .expect("Failed to parse config with metrics");
+
+        let prometheus = config
+            .metrics
+            .and_then(|m| m.prometheus)
+            .expect("prometheus");
+        assert_eq!(prometheus.pushgateway_url, "https://push.test:9091");
+        assert_eq!(prometheus.job, "getset");
+    }
+
+    #[test]
+    fn test_metrics_statsd() {
+        let config = Config::from_str(
+            r#"
+            [metrics.statsd]
+            addr = "127.0.0.1:8125"
+            prefix = "dev.setup"
+            "#,
+        )
+        .expect("Failed to parse config with statsd");
+
+        let statsd = config.metrics.and_then(|m| m.statsd).expect("statsd");
+        assert_eq!(statsd.addr, "127.0.0.1:8125");
+        assert_eq!(statsd.prefix, "dev.setup");
+    }
+
+    #[test]
+    fn test_metrics_local() {
+        let config = Config::from_str("[metrics.local]").expect("Failed to parse config");
+        let local = config.metrics.and_then(|m| m.local).expect("local");
+        assert!(local.path.is_none());
     }
 
     #[test]
     fn test_platformx_clone() {
         let platformx = PlatformXConfig {
-            secret_key: "test_key".to_string(),
+            secret_key: Some("test_key".to_string()),
+            secret_key_env: None,
             event_namespace: Some("test_namespace".to_string()),
+            api_url: None,
+            timeout_secs: None,
         };
 
         let cloned = platformx.clone();
         assert_eq!(platformx.secret_key, cloned.secret_key);
         assert_eq!(platformx.event_namespace, cloned.event_namespace);
     }
+
+    #[test]
+    fn test_services_expand_vars_and_need_distinct_names() {
+        let config = Config::from_str(
+            r#"
+            [vars]
+            port = "3000"
+
+            [[services]]
+            name = "web"
+            command = "bin/rails server -p ${port}"
+            env = { RAILS_ENV = "development" }
+
+            [[services]]
+            name = "worker"
+            command = "bundle exec sidekiq"
+            cwd = "jobs"
+            "#,
+        )
+        .expect("Failed to parse config");
+
+        assert_eq!(config.services.len(), 2);
+        assert_eq!(config.services[0].command, "bin/rails server -p 3000");
+        assert_eq!(config.services[0].env["RAILS_ENV"], "development");
+        assert_eq!(
+            config.services[1].working_dir(),
+            Some(PathBuf::from("jobs"))
+        );
+
+        let err = Config::from_str(
+            "[[services]]\nname = \"web\"\ncommand = \"a\"\n[[services]]\nname = \"web\"\ncommand = \"b\"\n",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("service 'web' is declared more than once"));
+    }
+
+    #[test]
+    fn test_built_config_round_trips_through_toml() {
+        let config = Config::new(".")
+            .with_var("ruby", "3.3")
+            .with_step(
+                CommandEntry::new("Install Ruby", "rbenv install ${ruby}")
+                    .with_id("ruby")
+                    .with_check("rbenv versions | grep ${ruby}")
+                    .with_tag("tools"),
+            )
+            .with_step(
+                CommandEntry::program("Install gems", ["bundle", "install"])
+                    .with_need("ruby")
+                    .with_cwd("app")
+                    .with_env("BUNDLE_JOBS", "4"),
+            )
+            .with_teardown(CommandEntry::new("Drop database", "bin/rails db:drop"));
+
+        let toml = config.to_toml().expect("Failed to write config");
+        assert!(!toml.contains("allow_failure"));
+        assert!(!toml.contains("matrix"));
+
+        let parsed = Config::from_str(&toml).expect("Failed to parse written config");
+        assert_eq!(parsed.commands.len(), 2);
+        assert_eq!(parsed.commands[0].id.as_deref(), Some("ruby"));
+        assert_eq!(
+            parsed.commands[0].command.as_deref(),
+            Some("rbenv install 3.3")
+        );
+        assert_eq!(parsed.commands[0].tags, ["tools"]);
+        assert_eq!(
+            parsed.commands[1].argv,
+            Some(vec!["bundle".to_string(), "install".to_string()])
+        );
+        assert_eq!(parsed.commands[1].needs, ["ruby"]);
+        assert_eq!(parsed.commands[1].cwd, Some(PathBuf::from("app")));
+        assert_eq!(parsed.commands[1].env["BUNDLE_JOBS"], "4");
+        assert_eq!(parsed.teardown[0].title, "Drop database");
+
+        let built = config.build().expect("Failed to build config");
+        assert_eq!(
+            built.commands[0].command.as_deref(),
+            Some("rbenv install 3.3")
+        );
+    }
+
+    #[test]
+    fn test_build_validates_the_config() {
+        let result = Config::new(".")
+            .with_step(CommandEntry::new("Migrate", "bin/rails db:migrate").with_need("missing"))
+            .build();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_to_toml_leaves_out_a_secret_key_read_from_the_environment() {
+        let mut config = Config::new(".");
+        config.platformx = Some(PlatformXConfig {
+            secret_key: Some("from-the-env".to_string()),
+            secret_key_env: Some("PLATFORMX_KEY".to_string()),
+            event_namespace: Some("shop".to_string()),
+            api_url: None,
+            timeout_secs: Some(10),
+        });
+
+        let toml = config.to_toml().expect("Failed to write config");
+        assert!(!toml.contains("from-the-env"));
+        assert!(toml.contains("secret_key_env = \"PLATFORMX_KEY\""));
+        assert!(toml.contains("timeout_secs = 10"));
+    }
 }
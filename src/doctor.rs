@@ -0,0 +1,169 @@
+use crate::config::DoctorConfig;
+use std::cmp::Ordering;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Outcome of one prerequisite check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    /// What was checked (e.g. `git`, `$DATABASE_URL`, `node >= 18`)
+    pub label: String,
+    /// Detail on success, or why the check failed
+    pub outcome: Result<String, String>,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// Run every check declared in the `[doctor]` section, in declaration order
+pub fn run_checks(doctor: &DoctorConfig) -> Vec<Check> {
+    let binaries = doctor.binaries.iter().map(|binary| Check {
+        label: binary.clone(),
+        outcome: find_in_path(binary)
+            .map(|path| path.display().to_string())
+            .ok_or_else(|| "not found on PATH".to_string()),
+    });
+
+    let env_vars = doctor.env.iter().map(|name| Check {
+        label: format!("${}", name),
+        outcome: match env::var_os(name) {
+            Some(value) if !value.is_empty() => Ok("set".to_string()),
+            _ => Err("not set".to_string()),
+        },
+    });
+
+    let versions = doctor.versions.iter().map(|(program, minimum)| Check {
+        label: format!("{} >= {}", program, minimum),
+        outcome: check_version(program, minimum),
+    });
+
+    binaries.chain(env_vars).chain(versions).collect()
+}
+
+/// Locate an executable the way a shell would, by searching `PATH`
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        let path = Path::new(program);
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Compare the version reported by `<program> --version` against `minimum`
+fn check_version(program: &str, minimum: &str) -> Result<String, String> {
+    let required =
+        parse_version(minimum).ok_or_else(|| format!("'{}' is not a version number", minimum))?;
+
+    let output = Command::new(program)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!("could not run '{} --version': {}", program, e))?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let installed = parse_version(&text)
+        .ok_or_else(|| format!("no version number in '{} --version' output", program))?;
+    let installed_text = join_version(&installed);
+
+    if compare_versions(&installed, &required) == Ordering::Less {
+        Err(format!("found {}", installed_text))
+    } else {
+        Ok(format!("found {}", installed_text))
+    }
+}
+
+/// First dotted version number in `text` (e.g. `v18.17.1` -> `[18, 17, 1]`)
+fn parse_version(text: &str) -> Option<Vec<u64>> {
+    text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map(|token| token.trim_matches('.'))
+        .find(|token| token.starts_with(|c: char| c.is_ascii_digit()))
+        .map(|token| {
+            token
+                .split('.')
+                .map_while(|part| part.parse().ok())
+                .collect()
+        })
+}
+
+fn join_version(version: &[u64]) -> String {
+    version
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Compare versions component-wise, treating missing components as zero
+fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    let len = a.len().max(b.len());
+    let component = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| component(a, i).cmp(&component(b, i)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("v18.17.1"), Some(vec![18, 17, 1]));
+        assert_eq!(
+            parse_version("git version 2.43.0 (Apple Git-115)"),
+            Some(vec![2, 43, 0])
+        );
+        assert_eq!(parse_version("ruby 3.2"), Some(vec![3, 2]));
+        assert_eq!(parse_version("no digits here"), None);
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions(&[18, 0], &[18]), Ordering::Equal);
+        assert_eq!(compare_versions(&[18, 17, 1], &[18, 2]), Ordering::Greater);
+        assert_eq!(compare_versions(&[3, 1], &[3, 2]), Ordering::Less);
+        assert_eq!(compare_versions(&[10], &[9, 9]), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_run_checks_reports_each_prerequisite() {
+        let doctor = DoctorConfig {
+            binaries: vec!["sh".to_string(), "getset-missing-binary".to_string()],
+            env: vec!["PATH".to_string(), "GETSET_MISSING_VAR".to_string()],
+            versions: [("sh".to_string(), "not-a-version".to_string())].into(),
+        };
+
+        let checks = run_checks(&doctor);
+
+        let summary: Vec<(&str, bool)> = checks
+            .iter()
+            .map(|check| (check.label.as_str(), check.passed()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("sh", true),
+                ("getset-missing-binary", false),
+                ("$PATH", true),
+                ("$GETSET_MISSING_VAR", false),
+                ("sh >= not-a-version", false),
+            ]
+        );
+        assert_eq!(checks[1].outcome, Err("not found on PATH".to_string()));
+        assert_eq!(
+            checks[4].outcome,
+            Err("'not-a-version' is not a version number".to_string())
+        );
+    }
+}
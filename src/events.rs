@@ -0,0 +1,324 @@
+use chrono::Utc;
+use clap::ValueEnum;
+use serde_json::{Value, json};
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+/// Something that happened during a run, as told to an [`EventHandler`]
+///
+/// The styled console output of `getset up` is rendered from these events too.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A step is about to run
+    StepStarted { title: String },
+    /// A step printed something; whole lines, with any invalid UTF-8 replaced
+    OutputChunk {
+        title: String,
+        stream: OutputStream,
+        text: String,
+    },
+    /// A step was run, skipped or found already satisfied
+    StepFinished {
+        title: String,
+        duration: Duration,
+        /// Exit code of the step's command, if it ran and exited normally
+        exit_code: Option<i32>,
+        outcome: StepOutcome,
+    },
+    /// The run is over; `error` says why a failed run stopped
+    RunFinished {
+        duration: Duration,
+        error: Option<String>,
+    },
+}
+
+/// Which of a step's output streams an [`Event::OutputChunk`] came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// How a step ended, for [`Event::StepFinished`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StepOutcome {
+    Succeeded,
+    /// The command failed
+    Failed,
+    /// The command was stopped by Ctrl-C or a cancelled run
+    Interrupted,
+    /// The step was not run
+    Skipped {
+        reason: String,
+    },
+    /// The step's `check` succeeded, so its command was not run
+    Satisfied,
+}
+
+impl Event {
+    /// The event as a JSON line of `--log-format json` and `--output json`
+    pub fn record(&self) -> Value {
+        match self {
+            Event::StepStarted { title } => event_record("step_started", json!({ "title": title })),
+            Event::OutputChunk {
+                title,
+                stream,
+                text,
+            } => event_record(
+                "output_chunk",
+                json!({
+                    "title": title,
+                    "stream": match stream {
+                        OutputStream::Stdout => "stdout",
+                        OutputStream::Stderr => "stderr",
+                    },
+                    "text": text,
+                }),
+            ),
+            Event::StepFinished {
+                title,
+                duration,
+                exit_code,
+                outcome,
+            } => match outcome {
+                StepOutcome::Succeeded | StepOutcome::Failed | StepOutcome::Interrupted => {
+                    event_record(
+                        if *outcome == StepOutcome::Succeeded {
+                            "step_finished"
+                        } else {
+                            "step_failed"
+                        },
+                        json!({
+                            "title": title,
+                            "duration_secs": duration.as_secs_f64(),
+                            "exit_code": exit_code,
+                        }),
+                    )
+                }
+                StepOutcome::Skipped { reason } => {
+                    event_record("step_skipped", json!({ "title": title, "reason": reason }))
+                }
+                StepOutcome::Satisfied => event_record(
+                    "step_satisfied",
+                    json!({ "title": title, "duration_secs": duration.as_secs_f64() }),
+                ),
+            },
+            Event::RunFinished { duration, error } => event_record(
+                "run_finished",
+                json!({
+                    "duration_secs": duration.as_secs_f64(),
+                    "success": error.is_none(),
+                    "error": error,
+                }),
+            ),
+        }
+    }
+}
+
+/// Told about every [`Event`] of a run it is given to, e.g. by a tool embedding getset
+///
+/// Events of concurrent steps arrive from their own threads, so handlers should
+/// return quickly; a closure taking `&Event` is a handler too.
+pub trait EventHandler: Send + Sync {
+    fn handle(&self, event: &Event);
+}
+
+impl fmt::Debug for dyn EventHandler + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventHandler")
+    }
+}
+
+impl<F: Fn(&Event) + Send + Sync> EventHandler for F {
+    fn handle(&self, event: &Event) {
+        self(event)
+    }
+}
+
+impl EventHandler for Sender<Event> {
+    fn handle(&self, event: &Event) {
+        let _ = self.send(event.clone());
+    }
+}
+
+/// How getset reports its own lifecycle events
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Styled, human-readable output
+    #[default]
+    Pretty,
+    /// One JSON object per lifecycle event on stderr
+    Json,
+    /// One JSON object per event on stdout, including step output, instead of any
+    /// styled output; selected with `--output json`
+    #[value(skip)]
+    Stream,
+}
+
+impl LogFormat {
+    /// Whether lifecycle events are written as JSON lines
+    pub fn is_json(self) -> bool {
+        matches!(self, LogFormat::Json | LogFormat::Stream)
+    }
+}
+
+/// What `getset up` writes to stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Styled output for people, with the commands' own output passed through
+    #[default]
+    Human,
+    /// Newline-delimited JSON events, with the commands' output as `output_chunk` events
+    Json,
+}
+
+/// Build a lifecycle event record
+fn event_record(event: &str, fields: Value) -> Value {
+    let mut record = json!({
+        "event": event,
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+
+    if let (Some(record), Value::Object(fields)) = (record.as_object_mut(), fields) {
+        record.extend(fields);
+    }
+
+    record
+}
+
+/// Where the events of one run go
+#[derive(Debug, Clone, Copy)]
+pub struct Emitter<'a> {
+    pub log_format: LogFormat,
+    /// Id of the run, put on each JSON line
+    pub run_id: &'a str,
+    /// Told about each event instead of it being written as a JSON line
+    pub handler: Option<&'a dyn EventHandler>,
+}
+
+impl<'a> Emitter<'a> {
+    /// Events of the run `run_id` written as JSON lines if `log_format` is JSON, with no handler
+    pub fn new(log_format: LogFormat, run_id: &'a str) -> Self {
+        Self {
+            log_format,
+            run_id,
+            handler: None,
+        }
+    }
+}
+
+/// Tell the run's handler about an event, or write it as a JSON line if the log format is JSON
+///
+/// Lines go to stdout when streaming and stderr otherwise, each with the
+/// `run_id` of its run; with the pretty format and no handler, events are
+/// only rendered by the runner.
+pub fn emit(to: Emitter<'_>, event: &Event) {
+    if let Some(handler) = to.handler {
+        handler.handle(event);
+    } else if to.log_format.is_json() {
+        write_record(to.log_format, to.run_id, event.record());
+    }
+}
+
+fn write_record(log_format: LogFormat, run_id: &str, mut record: Value) {
+    if let Some(record) = record.as_object_mut() {
+        record.insert("run_id".to_string(), json!(run_id));
+    }
+    if log_format == LogFormat::Stream {
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "{}", record);
+        let _ = stdout.flush();
+    } else {
+        eprintln!("{}", record);
+    }
+}
+
+/// Emit a piece of a step's output, as text with any invalid UTF-8 replaced
+pub fn output_chunk(to: Emitter<'_>, title: &str, to_stderr: bool, bytes: &[u8]) {
+    emit(
+        to,
+        &Event::OutputChunk {
+            title: title.to_string(),
+            stream: if to_stderr {
+                OutputStream::Stderr
+            } else {
+                OutputStream::Stdout
+            },
+            text: String::from_utf8_lossy(bytes).into_owned(),
+        },
+    );
+}
+
+/// Write the `--log-format json` line for a run where every step succeeded
+///
+/// Handlers are told about the end of a run with [`Event::RunFinished`] instead.
+pub fn run_complete(log_format: LogFormat, run_id: &str, elapsed: Duration) {
+    write_record(
+        log_format,
+        run_id,
+        event_record(
+            "run_complete",
+            json!({ "duration_secs": elapsed.as_secs_f64() }),
+        ),
+    );
+}
+
+/// Emit the last event of a run; `error` says why a failed run stopped
+pub fn run_finished(to: Emitter<'_>, elapsed: Duration, error: Option<&str>) {
+    emit(
+        to,
+        &Event::RunFinished {
+            duration: elapsed,
+            error: error.map(str::to_string),
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_record_includes_event_and_timestamp() {
+        let record = event_record("step_started", json!({ "title": "Build" }));
+
+        assert_eq!(record["event"], "step_started");
+        assert_eq!(record["title"], "Build");
+        let timestamp = record["timestamp"].as_str().expect("timestamp is a string");
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+    }
+
+    #[test]
+    fn test_step_events_keep_their_json_names() {
+        let finished = |outcome| Event::StepFinished {
+            title: "Build".to_string(),
+            duration: Duration::from_millis(1500),
+            exit_code: Some(1),
+            outcome,
+        };
+
+        let failed = finished(StepOutcome::Failed).record();
+        assert_eq!(failed["event"], "step_failed");
+        assert_eq!(failed["duration_secs"], 1.5);
+        assert_eq!(failed["exit_code"], 1);
+        let skipped = finished(StepOutcome::Skipped {
+            reason: "cached".to_string(),
+        })
+        .record();
+        assert_eq!(skipped["event"], "step_skipped");
+        assert_eq!(skipped["reason"], "cached");
+        assert_eq!(
+            finished(StepOutcome::Satisfied).record()["event"],
+            "step_satisfied"
+        );
+    }
+
+    #[test]
+    fn test_default_log_format_is_pretty() {
+        assert_eq!(LogFormat::default(), LogFormat::Pretty);
+    }
+}
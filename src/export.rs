@@ -0,0 +1,423 @@
+use crate::config::{CommandEntry, Config};
+use crate::graph;
+use crate::import::shell_quote;
+use crate::redact;
+use clap::ValueEnum;
+use color_eyre::eyre::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+/// File format written by `getset export`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// justfile, for `just`
+    Just,
+    /// Makefile, for GNU make
+    Make,
+}
+
+/// A configuration written out for another tool
+#[derive(Debug, Default)]
+pub struct Exported {
+    pub text: String,
+    /// What the other tool will do differently from getset
+    pub warnings: Vec<String>,
+}
+
+/// A recipe (or target) running one step, after the recipes for the steps it needs
+struct Recipe {
+    name: String,
+    title: String,
+    needs: Vec<String>,
+    script: String,
+}
+
+/// Write the steps of `config` as recipes of a justfile or targets of a Makefile
+///
+/// Each step becomes a recipe named after its id (or title) that depends on
+/// the recipes of the steps it needs, and runs its command as one script, as
+/// getset does, after changing to its `cwd`, exporting its `env` and checking
+/// its `check`, `skip_if` and `only_if`. A `setup` recipe, the default, runs
+/// every step in order, and a `teardown` recipe the teardown steps.
+///
+/// Variables from `dotenv` files are left out, and known secrets are redacted.
+/// Platform filters and non-default shells are reported in
+/// [`Exported::warnings`], since the recipes ignore them.
+pub fn render(config: &Config, format: ExportFormat, source: &str) -> Result<Exported> {
+    let mut exported = Exported::default();
+    let dotenv = config.load_dotenv()?;
+    let mut taken = BTreeSet::new();
+    let steps = recipes(&config.commands, "", &dotenv, &mut taken, &mut exported)?;
+    let teardown = recipes(
+        &config.teardown,
+        "teardown-",
+        &dotenv,
+        &mut taken,
+        &mut exported,
+    )?;
+
+    let mut aggregates = vec![("setup", "Run every step", &steps)];
+    if !teardown.is_empty() {
+        aggregates.push(("teardown", "Run every teardown step", &teardown));
+    }
+    let aggregates: Vec<Recipe> = aggregates
+        .into_iter()
+        .map(|(name, title, recipes)| Recipe {
+            name: unique_name(name, &mut taken),
+            title: title.to_string(),
+            needs: recipes.iter().map(|recipe| recipe.name.clone()).collect(),
+            script: String::new(),
+        })
+        .collect();
+    let all: Vec<&Recipe> = aggregates.iter().chain(&steps).chain(&teardown).collect();
+
+    let out = &mut exported.text;
+    let _ = writeln!(
+        out,
+        "# Generated by `getset export` from {}; edit that instead",
+        source
+    );
+    if format == ExportFormat::Make {
+        let names: Vec<&str> = all.iter().map(|recipe| recipe.name.as_str()).collect();
+        let _ = writeln!(out, "\n.ONESHELL:\n.PHONY: {}", names.join(" "));
+    }
+    for recipe in all {
+        let _ = write!(out, "\n# {}\n{}:", recipe.title, recipe.name);
+        for need in &recipe.needs {
+            let _ = write!(out, " {}", need);
+        }
+        out.push('\n');
+        match format {
+            ExportFormat::Just => write_just_body(out, &recipe.script),
+            ExportFormat::Make => {
+                for line in recipe.script.lines() {
+                    let _ = writeln!(out, "\t{}", line.replace('$', "$$"));
+                }
+            }
+        }
+    }
+
+    let redacted = redact::redact(&exported.text);
+    if redacted != exported.text {
+        exported
+            .warnings
+            .push("Replaced known secrets with [REDACTED]".to_string());
+        exported.text = redacted;
+    }
+    Ok(exported)
+}
+
+/// The body of a just recipe: a plain line, or a shebang script for several lines
+fn write_just_body(out: &mut String, script: &str) {
+    // `{{` starts an interpolation in just; `{{{{` is a literal `{{`
+    let script = script.replace("{{", "{{{{");
+    if script.is_empty() {
+        return;
+    }
+    if !script.contains('\n') {
+        let _ = writeln!(out, "    {}", script);
+        return;
+    }
+    out.push_str("    #!/usr/bin/env sh\n");
+    for line in script.lines() {
+        if line.is_empty() {
+            out.push('\n');
+        } else {
+            let _ = writeln!(out, "    {}", line);
+        }
+    }
+}
+
+/// Recipes for `steps` in the order they run, named with `prefix` and unlike any in `taken`
+fn recipes(
+    steps: &[CommandEntry],
+    prefix: &str,
+    dotenv: &BTreeMap<String, String>,
+    taken: &mut BTreeSet<String>,
+    exported: &mut Exported,
+) -> Result<Vec<Recipe>> {
+    let deps = graph::dependencies(steps)?;
+    let all: Vec<usize> = (0..steps.len()).collect();
+    let order = graph::execution_order(steps, &all)?;
+
+    let mut names = vec![String::new(); steps.len()];
+    for &index in &order {
+        let step = &steps[index];
+        let name = slug(step.id.as_deref().unwrap_or(&step.title));
+        names[index] = unique_name(&format!("{}{}", prefix, name), taken);
+    }
+
+    Ok(order
+        .into_iter()
+        .map(|index| Recipe {
+            name: names[index].clone(),
+            title: steps[index].title.clone(),
+            needs: deps[index].iter().map(|&dep| names[dep].clone()).collect(),
+            script: script(&steps[index], dotenv, &mut exported.warnings),
+        })
+        .collect())
+}
+
+/// `name`, or `name-2`, `name-3`… if it is already taken, marked as taken
+fn unique_name(name: &str, taken: &mut BTreeSet<String>) -> String {
+    let mut unique = name.to_string();
+    let mut n = 1;
+    while taken.contains(&unique) {
+        n += 1;
+        unique = format!("{}-{}", name, n);
+    }
+    taken.insert(unique.clone());
+    unique
+}
+
+/// A recipe name for a step: lowercase words joined by `-`, starting with a letter
+fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        slug.to_string()
+    } else {
+        format!("step-{}", slug).trim_end_matches('-').to_string()
+    }
+}
+
+/// The shell script a step's recipe runs
+fn script(
+    step: &CommandEntry,
+    dotenv: &BTreeMap<String, String>,
+    warnings: &mut Vec<String>,
+) -> String {
+    if !step.os.is_empty() || !step.arch.is_empty() {
+        warnings.push(format!(
+            "Step '{}' is limited to some platforms, but its recipe runs on all of them",
+            step.title
+        ));
+    }
+    if step.shell.is_some() {
+        warnings.push(format!(
+            "Step '{}' sets a shell, but its recipe runs with sh",
+            step.title
+        ));
+    }
+
+    let mut lines = Vec::new();
+    if let Some(ref cwd) = step.cwd {
+        lines.push(format!("cd {}", shell_quote(&cwd.to_string_lossy())));
+    }
+    for (key, value) in &step.env {
+        if dotenv.get(key) != Some(value) {
+            lines.push(format!("export {}={}", key, shell_quote(value)));
+        }
+    }
+    let conditions = [
+        (&step.check, ""),
+        (&step.skip_if, ""),
+        (&step.only_if, "! "),
+    ];
+    for (condition, negate) in conditions {
+        if let Some(condition) = condition {
+            // A `}` on its own line also closes a condition ending in a comment or newline
+            let end = if condition.contains('\n') { "\n" } else { "; " };
+            lines.push(format!(
+                "if {}{{ {}{}}} >/dev/null 2>&1; then exit 0; fi",
+                negate,
+                condition.trim_end(),
+                end
+            ));
+        }
+    }
+
+    let command = step.display_command();
+    for prefix in ['-', '@', '+'] {
+        if command
+            .lines()
+            .any(|line| line.trim_start().starts_with(prefix))
+        {
+            warnings.push(format!(
+                "Step '{}' has a line starting with '{}', which make and just read as a recipe prefix instead of running it",
+                step.title, prefix
+            ));
+        }
+    }
+    if step.allow_failure {
+        lines.push(format!("(\n{}\n) || true", command));
+    } else {
+        lines.push(command);
+    }
+    lines.join("\n")
+}
+
+/// Warnings for the `${env:NAME}` references in the steps of the config files at `paths`
+///
+/// The config is exported as loaded, so each reference is written out as its
+/// value on this machine rather than read from the environment of whoever
+/// runs the recipe.
+pub fn reference_warnings<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    for path in paths {
+        let config = Config::parse_file(path)?;
+        for step in config.commands.iter().chain(&config.teardown) {
+            let cwd = step.cwd.as_ref().map(|cwd| cwd.to_string_lossy());
+            let values = [&step.command, &step.check, &step.skip_if, &step.only_if]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .chain(step.argv.iter().flatten().map(String::as_str))
+                .chain(step.env.values().map(String::as_str))
+                .chain(cwd.as_deref());
+            let names: BTreeSet<&str> = values.flat_map(env_references).collect();
+            for name in names {
+                warnings.push(format!(
+                    "Step '{}' reads ${{env:{}}}, which is written as its value on this machine",
+                    step.title, name
+                ));
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+/// Names of the environment variables `text` reads with `${env:NAME}`, leaving out escaped `$${env:NAME}`
+fn env_references(text: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${env:") {
+        let escaped = rest[..start].ends_with('$');
+        rest = &rest[start + "${env:".len()..];
+        if let Some(end) = rest.find('}')
+            && !escaped
+        {
+            names.push(&rest[..end]);
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        r#"
+[[commands]]
+title = "Install gems"
+command = "bundle install"
+cwd = "app"
+env = { BUNDLE_JOBS = "4" }
+
+[[commands]]
+id = "db"
+title = "Set up database"
+command = """
+bin/rails db:setup
+echo "done in $PWD {{ok}}""""
+needs = ["Install gems"]
+check = "test -f db/dev.sqlite3"
+
+[[teardown]]
+title = "Drop database"
+command = "bin/rails db:drop"
+allow_failure = true
+"#
+        .parse()
+        .expect("Failed to parse config")
+    }
+
+    #[test]
+    fn test_export_just() {
+        let exported = render(&config(), ExportFormat::Just, "getset.toml").unwrap();
+
+        assert_eq!(
+            exported.text,
+            "\
+# Generated by `getset export` from getset.toml; edit that instead
+
+# Run every step
+setup: install-gems db
+
+# Run every teardown step
+teardown: teardown-drop-database
+
+# Install gems
+install-gems:
+    #!/usr/bin/env sh
+    cd app
+    export BUNDLE_JOBS=4
+    bundle install
+
+# Set up database
+db: install-gems
+    #!/usr/bin/env sh
+    if { test -f db/dev.sqlite3; } >/dev/null 2>&1; then exit 0; fi
+    bin/rails db:setup
+    echo \"done in $PWD {{{{ok}}\"
+
+# Drop database
+teardown-drop-database:
+    #!/usr/bin/env sh
+    (
+    bin/rails db:drop
+    ) || true
+"
+        );
+        assert!(exported.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_export_make_escapes_dollars() {
+        let exported = render(&config(), ExportFormat::Make, "getset.toml").unwrap();
+
+        assert!(exported.text.contains(
+            ".ONESHELL:\n.PHONY: setup teardown install-gems db teardown-drop-database\n"
+        ));
+        assert!(
+            exported
+                .text
+                .contains("\n# Set up database\ndb: install-gems\n")
+        );
+        assert!(exported.text.contains("\techo \"done in $$PWD {{ok}}\"\n"));
+    }
+
+    #[test]
+    fn test_export_warns_about_recipe_prefixes() {
+        let config: Config = r#"
+[[commands]]
+title = "Clean"
+command = "-rm -rf tmp"
+"#
+        .parse()
+        .expect("Failed to parse config");
+
+        let exported = render(&config, ExportFormat::Make, "getset.toml").unwrap();
+
+        assert_eq!(
+            exported.warnings,
+            [
+                "Step 'Clean' has a line starting with '-', which make and just read as a recipe prefix instead of running it"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_references_are_found_unless_escaped() {
+        assert_eq!(
+            env_references("cd ${env:HOME} && echo ${env:USER} $${env:SHELL} ${name}"),
+            ["HOME", "USER"]
+        );
+    }
+
+    #[test]
+    fn test_slug_makes_recipe_names() {
+        assert_eq!(slug("Install Ruby (3.3)"), "install-ruby-3-3");
+        assert_eq!(slug("setup:db"), "setup-db");
+        assert_eq!(slug("2fa keys"), "step-2fa-keys");
+    }
+}
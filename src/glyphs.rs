@@ -0,0 +1,112 @@
+use clap::ValueEnum;
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether output is plain, using ASCII in place of symbols and emoji
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// When to color output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color output to terminals, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    /// Always color output, even when it is piped
+    Always,
+    /// Never color output, and use ASCII in place of symbols and emoji
+    Never,
+}
+
+/// Apply the color choice to all of getset's output
+///
+/// Output is plain when colors are turned off with `never` or the `NO_COLOR`
+/// environment variable, since logs that can't show colors often can't show
+/// symbols either.
+pub fn init(choice: ColorChoice) {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let colors = match choice {
+        ColorChoice::Always => Some(true),
+        ColorChoice::Never => Some(false),
+        ColorChoice::Auto if no_color => Some(false),
+        ColorChoice::Auto => None,
+    };
+
+    if let Some(colors) = colors {
+        console::set_colors_enabled(colors);
+        console::set_colors_enabled_stderr(colors);
+    }
+    PLAIN.store(colors == Some(false), Ordering::Relaxed);
+}
+
+/// Whether output is plain ASCII
+pub fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// A symbol with an ASCII stand-in for plain output
+#[derive(Clone, Copy, Debug)]
+pub struct Glyph {
+    fancy: &'static str,
+    ascii: &'static str,
+}
+
+impl Glyph {
+    pub fn as_str(self) -> &'static str {
+        if plain() { self.ascii } else { self.fancy }
+    }
+}
+
+impl fmt::Display for Glyph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+const fn glyph(fancy: &'static str, ascii: &'static str) -> Glyph {
+    Glyph { fancy, ascii }
+}
+
+pub const SUCCESS: Glyph = glyph("✔︎", "+");
+pub const FAILURE: Glyph = glyph("✘", "x");
+pub const WARNING: Glyph = glyph("⚠", "!");
+pub const SKIPPED: Glyph = glyph("⊝", "-");
+pub const ARROW: Glyph = glyph("→", "->");
+pub const ELLIPSIS: Glyph = glyph("…", "...");
+pub const RULE: Glyph = glyph("──", "--");
+pub const BAR_FILLED: Glyph = glyph("█", "#");
+pub const BAR_EMPTY: Glyph = glyph("░", ".");
+pub const BRANCH: Glyph = glyph("├─▶ ", "|-> ");
+pub const LAST_BRANCH: Glyph = glyph("└─▶ ", "`-> ");
+pub const TRUNK: Glyph = glyph("│   ", "|   ");
+pub const REPORT_BRANCH: Glyph = glyph("├──▶", "|-->");
+pub const REPORT_LAST_BRANCH: Glyph = glyph("└──▶", "`-->");
+
+// Emoji include their trailing space, so plain output doesn't start with one
+pub const DONE: Glyph = glyph("🎯 ", "");
+pub const TORN_DOWN: Glyph = glyph("🧹 ", "");
+pub const HEALTHY: Glyph = glyph("🩺 ", "");
+pub const REPORT: Glyph = glyph("📊 ", "");
+pub const REPEAT: Glyph = glyph("🔁", "=");
+pub const WATCHING: Glyph = glyph("👀", ">");
+
+/// Frames of the spinner animation
+pub fn spinner_frames() -> &'static [&'static str] {
+    if plain() {
+        &["|", "/", "-", "\\"]
+    } else {
+        &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyphs_are_fancy_by_default() {
+        assert_eq!(SUCCESS.to_string(), "✔︎");
+        assert_eq!(format!("{:<3}|", SKIPPED), "⊝  |");
+        assert_eq!(spinner_frames().len(), 10);
+    }
+}
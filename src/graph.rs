@@ -0,0 +1,304 @@
+use crate::config::CommandEntry;
+use crate::glyphs;
+use clap::ValueEnum;
+use color_eyre::eyre::{Result, eyre};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// How `getset graph` draws the execution plan
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    /// Indented tree of steps under the steps they need
+    #[default]
+    Tree,
+    /// Graphviz DOT
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+}
+
+/// Indices of the steps each step needs, resolved from `needs` ids or titles
+pub(crate) fn dependencies(commands: &[CommandEntry]) -> Result<Vec<Vec<usize>>> {
+    commands
+        .iter()
+        .map(|cmd| {
+            let mut deps = Vec::new();
+            for need in &cmd.needs {
+                let before = deps.len();
+                deps.extend(
+                    commands
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, other)| other.is_named(need))
+                        .map(|(i, _)| i),
+                );
+
+                if deps.len() == before {
+                    return Err(cmd.invalid(format!("needs unknown step '{}'", need)));
+                }
+            }
+            Ok(deps)
+        })
+        .collect()
+}
+
+/// Order `selected` steps, plus everything they transitively need, so each
+/// step runs after its dependencies
+///
+/// Among steps that are ready to run, config order is preserved, so configs
+/// without `needs` run exactly in the order they are written.
+pub fn execution_order(commands: &[CommandEntry], selected: &[usize]) -> Result<Vec<usize>> {
+    let deps = dependencies(commands)?;
+
+    let mut included = BTreeSet::new();
+    let mut stack: Vec<usize> = selected.to_vec();
+    while let Some(index) = stack.pop() {
+        if included.insert(index) {
+            stack.extend(&deps[index]);
+        }
+    }
+
+    let mut order = Vec::with_capacity(included.len());
+    let mut remaining = included;
+    while !remaining.is_empty() {
+        let ready = remaining
+            .iter()
+            .copied()
+            .find(|&index| deps[index].iter().all(|dep| !remaining.contains(dep)));
+
+        let Some(ready) = ready else {
+            let titles: Vec<String> = remaining
+                .iter()
+                .map(|&index| format!("'{}'", commands[index].title))
+                .collect();
+            return Err(eyre!(
+                "Invalid config: dependency cycle among steps {}",
+                titles.join(", ")
+            ));
+        };
+
+        remaining.remove(&ready);
+        order.push(ready);
+    }
+
+    Ok(order)
+}
+
+/// Check that every `needs` reference exists and that there are no cycles
+pub fn validate(commands: &[CommandEntry]) -> Result<()> {
+    let all: Vec<usize> = (0..commands.len()).collect();
+    execution_order(commands, &all).map(|_| ())
+}
+
+/// Draw the steps and their `needs` edges, listing steps in execution order
+pub fn render(commands: &[CommandEntry], format: GraphFormat) -> Result<String> {
+    let deps = dependencies(commands)?;
+    let all: Vec<usize> = (0..commands.len()).collect();
+    let order = execution_order(commands, &all)?;
+
+    Ok(match format {
+        GraphFormat::Tree => render_tree(commands, &deps, &order),
+        GraphFormat::Dot => render_dot(commands, &deps, &order),
+        GraphFormat::Mermaid => render_mermaid(commands, &deps, &order),
+    })
+}
+
+/// Steps without `needs` at the top level, each followed by the steps that need it
+///
+/// A step needed by several others appears under each of them; repeats are
+/// marked with `…` instead of repeating their subtree.
+fn render_tree(commands: &[CommandEntry], deps: &[Vec<usize>], order: &[usize]) -> String {
+    fn walk(
+        index: usize,
+        prefix: &str,
+        children_of: &[Vec<usize>],
+        commands: &[CommandEntry],
+        printed: &mut BTreeSet<usize>,
+        out: &mut String,
+    ) {
+        let children = &children_of[index];
+        for (i, &child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let branch = if last {
+                glyphs::LAST_BRANCH
+            } else {
+                glyphs::BRANCH
+            };
+            let title = &commands[child].title;
+
+            if !printed.insert(child) {
+                let _ = writeln!(out, "{}{}{} {}", prefix, branch, title, glyphs::ELLIPSIS);
+                continue;
+            }
+            let _ = writeln!(out, "{}{}{}", prefix, branch, title);
+            let prefix = if last {
+                format!("{}    ", prefix)
+            } else {
+                format!("{}{}", prefix, glyphs::TRUNK)
+            };
+            walk(child, &prefix, children_of, commands, printed, out);
+        }
+    }
+
+    let mut children_of = vec![Vec::new(); commands.len()];
+    for &index in order {
+        for &dep in &deps[index] {
+            children_of[dep].push(index);
+        }
+    }
+
+    let mut out = String::new();
+    let mut printed = BTreeSet::new();
+    for &root in order.iter().filter(|&&index| deps[index].is_empty()) {
+        printed.insert(root);
+        let _ = writeln!(out, "{}", commands[root].title);
+        walk(root, "", &children_of, commands, &mut printed, &mut out);
+    }
+    out
+}
+
+fn render_dot(commands: &[CommandEntry], deps: &[Vec<usize>], order: &[usize]) -> String {
+    let quote = |title: &str| format!("\"{}\"", title.replace('\\', "\\\\").replace('"', "\\\""));
+
+    let mut out = String::from("digraph getset {\n    rankdir=LR;\n");
+    for &index in order {
+        let _ = writeln!(out, "    {};", quote(&commands[index].title));
+    }
+    for &index in order {
+        for &dep in &deps[index] {
+            let _ = writeln!(
+                out,
+                "    {} -> {};",
+                quote(&commands[dep].title),
+                quote(&commands[index].title)
+            );
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn render_mermaid(commands: &[CommandEntry], deps: &[Vec<usize>], order: &[usize]) -> String {
+    let mut out = String::from("flowchart TD\n");
+    for &index in order {
+        let title = commands[index].title.replace('"', "#quot;");
+        let _ = writeln!(out, "    step{}[\"{}\"]", index, title);
+    }
+    for &index in order {
+        for &dep in &deps[index] {
+            let _ = writeln!(out, "    step{} --> step{}", dep, index);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(title: &str, needs: &[&str]) -> CommandEntry {
+        CommandEntry {
+            title: title.to_string(),
+            command: Some("true".to_string()),
+            needs: needs.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_order_without_needs_is_config_order() {
+        let commands = [step("A", &[]), step("B", &[]), step("C", &[])];
+
+        assert_eq!(execution_order(&commands, &[0, 1, 2]).unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_dependencies_run_first() {
+        let commands = [
+            step("Migrate", &["Start database"]),
+            step("Start database", &[]),
+            step("Seed", &["Migrate"]),
+        ];
+
+        assert_eq!(execution_order(&commands, &[0, 1, 2]).unwrap(), [1, 0, 2]);
+    }
+
+    #[test]
+    fn test_selection_pulls_in_dependencies() {
+        let commands = [
+            step("Start database", &[]),
+            step("Unrelated", &[]),
+            step("Migrate", &["Start database"]),
+            step("Seed", &["Migrate"]),
+        ];
+
+        assert_eq!(execution_order(&commands, &[3]).unwrap(), [0, 2, 3]);
+    }
+
+    #[test]
+    fn test_needs_resolve_ids() {
+        let mut database = step("Start database", &[]);
+        database.id = Some("db".to_string());
+        let commands = [step("Migrate", &["db"]), database];
+
+        assert_eq!(execution_order(&commands, &[0]).unwrap(), [1, 0]);
+    }
+
+    #[test]
+    fn test_cycle_is_rejected() {
+        let commands = [step("A", &["B"]), step("B", &["A"]), step("C", &[])];
+
+        let err = validate(&commands).unwrap_err().to_string();
+        assert!(err.contains("dependency cycle among steps 'A', 'B'"));
+    }
+
+    fn diamond() -> [CommandEntry; 4] {
+        [
+            step("Start database", &[]),
+            step("Migrate", &["Start database"]),
+            step("Seed", &["Migrate", "Install \"gems\""]),
+            step("Install \"gems\"", &[]),
+        ]
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let tree = render(&diamond(), GraphFormat::Tree).unwrap();
+
+        assert_eq!(
+            tree,
+            "Start database\n\
+             └─▶ Migrate\n\
+             \x20   └─▶ Seed\n\
+             Install \"gems\"\n\
+             └─▶ Seed …\n"
+        );
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let dot = render(&diamond(), GraphFormat::Dot).unwrap();
+
+        assert!(dot.starts_with("digraph getset {"));
+        assert!(dot.contains("    \"Start database\" -> \"Migrate\";\n"));
+        assert!(dot.contains("    \"Install \\\"gems\\\"\" -> \"Seed\";\n"));
+    }
+
+    #[test]
+    fn test_render_mermaid() {
+        let mermaid = render(&diamond(), GraphFormat::Mermaid).unwrap();
+
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("    step3[\"Install #quot;gems#quot;\"]\n"));
+        assert!(mermaid.contains("    step1 --> step2\n"));
+        assert!(mermaid.contains("    step3 --> step2\n"));
+    }
+
+    #[test]
+    fn test_unknown_need_is_rejected() {
+        let commands = [step("A", &["Missing"])];
+
+        let err = validate(&commands).unwrap_err().to_string();
+        assert!(err.contains("step 'A' needs unknown step 'Missing'"));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::config::{Config, GIT_HOOKS};
+use crate::import::shell_quote;
+use color_eyre::eyre::{Result, eyre};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// First comment of every hook getset writes, so it can be replaced or removed later
+const MARKER: &str = "# Installed by `getset hooks install`";
+
+/// Write a git hook for each of the config's `[hooks] events` that runs `getset up` on `config_path`
+///
+/// Hooks are written to the hooks directory of the repository containing the
+/// config, honoring `core.hooksPath`. Hooks that getset did not write are
+/// only replaced with `force`. Returns the paths of the hooks written.
+pub fn install(config: &Config, config_path: &Path, force: bool) -> Result<Vec<PathBuf>> {
+    let hooks = config.hooks.clone().unwrap_or_default();
+    let dir = hooks_dir(&config.base_dir)?;
+    let top = git(&config.base_dir, &["rev-parse", "--show-toplevel"])?;
+    let config_path = relative_to(config_path, Path::new(&top))?;
+
+    let paths: Vec<PathBuf> = hooks.events().iter().map(|event| dir.join(event)).collect();
+    if let Some(path) = paths
+        .iter()
+        .find(|path| !force && path.exists() && !is_ours(path))
+    {
+        return Err(eyre!(
+            "'{}' already exists; use --force to overwrite it",
+            path.display()
+        ));
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| eyre!("Error creating '{}': {}", dir.display(), e))?;
+    for (event, path) in hooks.events().into_iter().zip(&paths) {
+        fs::write(path, script(event, &config_path, &hooks.tags))
+            .map_err(|e| eyre!("Error writing file '{}': {}", path.display(), e))?;
+        make_executable(path)?;
+    }
+    Ok(paths)
+}
+
+/// Remove the hooks getset wrote from the repository containing `base_dir`
+///
+/// Returns the paths of the hooks removed; other hooks are left alone.
+pub fn uninstall(base_dir: &Path) -> Result<Vec<PathBuf>> {
+    let dir = hooks_dir(base_dir)?;
+    let mut removed = Vec::new();
+    for event in GIT_HOOKS {
+        let path = dir.join(event);
+        if is_ours(&path) {
+            fs::remove_file(&path)
+                .map_err(|e| eyre!("Error removing '{}': {}", path.display(), e))?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+/// The hook script, which skips the runs its hook's arguments rule out
+///
+/// It does nothing if getset is not installed, so teammates without it can
+/// still pull and switch branches.
+fn script(event: &str, config_path: &str, tags: &[String]) -> String {
+    let mut command = format!("getset up {}", shell_quote(config_path));
+    for tag in tags {
+        command.push_str(&format!(" --tag {}", shell_quote(tag)));
+    }
+    let guard = match event {
+        // Called with the old and new HEAD, then 1 for a branch checkout or 0 for files
+        "post-checkout" => "[ \"$3\" = 1 ] || exit 0\n",
+        // Called with `amend` or `rebase`
+        "post-rewrite" => "[ \"$1\" = rebase ] || exit 0\n",
+        _ => "",
+    };
+    format!(
+        "#!/bin/sh\n{}; remove with `getset hooks uninstall`\n{}command -v getset >/dev/null 2>&1 || exit 0\nexec {}\n",
+        MARKER, guard, command
+    )
+}
+
+/// Whether the file at `path` is a hook getset wrote
+fn is_ours(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|content| content.contains(MARKER))
+}
+
+/// `path` relative to the repository's top level, where git runs hooks
+fn relative_to(path: &Path, top: &Path) -> Result<String> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| eyre!("Error reading file '{}': {}", path.display(), e))?;
+    let top = top.canonicalize().unwrap_or_else(|_| top.to_path_buf());
+    let relative = canonical.strip_prefix(&top).map_err(|_| {
+        eyre!(
+            "'{}' is not inside the git repository at '{}'",
+            path.display(),
+            top.display()
+        )
+    })?;
+    Ok(relative.to_string_lossy().into_owned())
+}
+
+/// The hooks directory of the repository containing `dir`
+fn hooks_dir(dir: &Path) -> Result<PathBuf> {
+    Ok(working_dir(dir).join(git(dir, &["rev-parse", "--git-path", "hooks"])?))
+}
+
+/// Trimmed output of a git command run in `dir`
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(working_dir(dir))
+        .output()
+        .map_err(|e| eyre!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "'{}' is not in a git repository: {}",
+            working_dir(dir).display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// `dir`, or `.` for a config in the current directory
+fn working_dir(dir: &Path) -> &Path {
+    if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| eyre!("Error making '{}' executable: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_runs_tagged_steps_on_branch_checkouts() {
+        assert_eq!(
+            script(
+                "post-checkout",
+                "web app/getset.toml",
+                &["deps".to_string()]
+            ),
+            "#!/bin/sh
+# Installed by `getset hooks install`; remove with `getset hooks uninstall`
+[ \"$3\" = 1 ] || exit 0
+command -v getset >/dev/null 2>&1 || exit 0
+exec getset up 'web app/getset.toml' --tag deps
+"
+        );
+        assert!(
+            script("post-merge", "getset.toml", &[]).ends_with("\nexec getset up getset.toml\n")
+        );
+    }
+}
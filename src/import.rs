@@ -0,0 +1,645 @@
+use crate::config::{CommandEntry, Config};
+use color_eyre::eyre::{Result, eyre};
+use serde::Deserialize;
+use serde::de::{MapAccess, Visitor};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Steps and variables translated from another tool's task definitions
+#[derive(Debug, Default)]
+pub struct Imported {
+    /// Values substituted for `${name}` in the steps
+    pub vars: BTreeMap<String, String>,
+    pub steps: Vec<CommandEntry>,
+    /// What could not be translated and may need editing by hand
+    pub warnings: Vec<String>,
+}
+
+impl Imported {
+    /// A configuration holding the imported steps, to write as a `getset.toml` in `base_dir`
+    pub fn into_config(self, base_dir: impl Into<PathBuf>) -> Config {
+        let mut config = Config::new(base_dir);
+        config.vars = self.vars;
+        self.steps
+            .into_iter()
+            .fold(config, |config, step| config.with_step(step))
+    }
+}
+
+/// Directives whose effect is not translated; their lines are skipped
+const DIRECTIVES: &[&str] = &[
+    "define", "endef", "ifeq", "ifneq", "ifdef", "ifndef", "else", "endif", "include", "-include",
+    "sinclude", "vpath", "unexport", "undefine",
+];
+
+/// A rule of a Makefile, merged across every line that names its target
+#[derive(Debug, Default)]
+struct Target {
+    name: String,
+    description: Option<String>,
+    prerequisites: Vec<String>,
+    recipe: Vec<String>,
+}
+
+/// Turn the targets of a Makefile into steps, one per target with a recipe
+///
+/// Each step's id is the target name, and its title the `## description` after
+/// the rule or the comment above it, if any. Recipe lines are joined with `&&`
+/// so the step stops at the first failing line, as make does, and lines
+/// prefixed with `-` may fail. Prerequisites that are targets become `needs`,
+/// looking through targets without a recipe to the targets they depend on.
+///
+/// Simple variables become `vars`, and `$$` the shell's `$`. Only `targets`
+/// and what they depend on are imported, or every target if it is empty.
+/// Conditionals, includes, pattern rules and make functions are not
+/// translated, and are reported in [`Imported::warnings`].
+pub fn makefile(content: &str, targets: &[String]) -> Imported {
+    let mut imported = Imported::default();
+    let mut rules: Vec<Target> = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut current: Vec<usize> = Vec::new();
+    let mut in_define = false;
+
+    for line in logical_lines(content) {
+        if in_define {
+            in_define = line.trim() != "endef";
+            continue;
+        }
+        if let Some(recipe) = line.strip_prefix('\t') {
+            let recipe = recipe.trim();
+            if !recipe.is_empty() && !recipe.starts_with('#') {
+                for &index in &current {
+                    rules[index].recipe.push(recipe.to_string());
+                }
+            }
+            continue;
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            comment = None;
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+        current.clear();
+        let comment_above = comment.take();
+
+        let keyword = line.split_whitespace().next().unwrap_or_default();
+        if DIRECTIVES.contains(&keyword) {
+            in_define = keyword == "define";
+            if !matches!(keyword, "endef" | "else" | "endif") {
+                imported
+                    .warnings
+                    .push(format!("Skipped '{}', which is not translated", line));
+            }
+            continue;
+        }
+
+        if let Some((name, op, value)) = assignment(line) {
+            let value = translate(value, &imported.vars, None).0;
+            match op {
+                "+=" => {
+                    let joined = match imported.vars.get(name) {
+                        Some(existing) if !existing.is_empty() => format!("{} {}", existing, value),
+                        _ => value,
+                    };
+                    imported.vars.insert(name.to_string(), joined);
+                }
+                "?=" => {
+                    imported.vars.entry(name.to_string()).or_insert(value);
+                }
+                "!=" => imported.warnings.push(format!(
+                    "Skipped variable '{}', which is set from a shell command",
+                    name
+                )),
+                _ => {
+                    imported.vars.insert(name.to_string(), value);
+                }
+            }
+            continue;
+        }
+
+        let Some((names, rest)) = line.split_once(':') else {
+            imported
+                .warnings
+                .push(format!("Skipped '{}', which is not a rule", line));
+            continue;
+        };
+        let rest = rest.strip_prefix(':').unwrap_or(rest);
+        let (rest, inline_recipe) = match rest.split_once(';') {
+            Some((rest, recipe)) => (rest, Some(recipe.trim())),
+            None => (rest, None),
+        };
+        let (prerequisites, description) = match rest.split_once('#') {
+            Some((prerequisites, text)) => (
+                prerequisites,
+                Some(text.trim_start_matches('#').trim().to_string()),
+            ),
+            None => (rest, comment_above),
+        };
+
+        for name in names.split_whitespace() {
+            if name.starts_with('.') || name.contains(['%', '$']) {
+                continue;
+            }
+            let index = match rules.iter().position(|rule| rule.name == name) {
+                Some(index) => index,
+                None => {
+                    rules.push(Target {
+                        name: name.to_string(),
+                        ..Default::default()
+                    });
+                    rules.len() - 1
+                }
+            };
+            let rule = &mut rules[index];
+            rule.prerequisites.extend(
+                prerequisites
+                    .split_whitespace()
+                    .filter(|prerequisite| *prerequisite != "|")
+                    .map(str::to_string),
+            );
+            if let Some(ref description) = description
+                && !description.is_empty()
+            {
+                rule.description = Some(description.clone());
+            }
+            if let Some(recipe) = inline_recipe.filter(|recipe| !recipe.is_empty()) {
+                rule.recipe.push(recipe.to_string());
+            }
+            current.push(index);
+        }
+    }
+
+    let wanted = wanted_targets(&rules, targets, &mut imported.warnings);
+    for rule in rules.iter().filter(|rule| !rule.recipe.is_empty()) {
+        if !wanted.contains(rule.name.as_str()) {
+            continue;
+        }
+        let mut needs = Vec::new();
+        for prerequisite in &rule.prerequisites {
+            step_prerequisites(&rules, prerequisite, &mut BTreeSet::new(), &mut needs);
+        }
+
+        let mut untranslated = false;
+        let lines: Vec<String> = rule
+            .recipe
+            .iter()
+            .map(|line| {
+                let ignore_errors = line.trim_start_matches(['@', '+']).starts_with('-');
+                let line = line.trim_start_matches(['@', '-', '+']).trim_start();
+                let (line, complete) = translate(line, &imported.vars, Some(&rule.name));
+                untranslated |= !complete;
+                // Grouped, so the `|| true` does not swallow failures earlier in the `&&` chain
+                if ignore_errors {
+                    format!("{{ {} || true; }}", line)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        if untranslated {
+            imported.warnings.push(format!(
+                "Target '{}' uses make syntax that is not translated; check its command",
+                rule.name
+            ));
+        }
+
+        let title = rule
+            .description
+            .clone()
+            .unwrap_or_else(|| rule.name.clone());
+        let mut step = CommandEntry::new(title, lines.join(" &&\n")).with_id(&rule.name);
+        step.needs = needs;
+        imported.steps.push(step);
+    }
+    imported
+}
+
+/// Lines of a Makefile, with `\` continuations joined by a space as make does
+fn logical_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut pending = String::new();
+    for line in content.lines() {
+        let line = if pending.is_empty() {
+            line
+        } else {
+            line.trim_start()
+        };
+        match line.strip_suffix('\\') {
+            Some(start) => {
+                pending.push_str(start.trim_end());
+                pending.push(' ');
+            }
+            None => {
+                pending.push_str(line);
+                lines.push(std::mem::take(&mut pending));
+            }
+        }
+    }
+    if !pending.is_empty() {
+        lines.push(pending);
+    }
+    lines
+}
+
+/// Name, operator and value of a variable assignment such as `RUBY ?= 3.3`
+fn assignment(line: &str) -> Option<(&str, &str, &str)> {
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let line = line.strip_prefix("override ").unwrap_or(line);
+    let eq = line.find('=')?;
+    let (before, value) = (&line[..eq], line[eq + 1..].trim());
+    let name = before.trim_end_matches([':', '?', '+', '!']);
+    let op = &line[name.len()..=eq];
+    let name = name.trim();
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ':') {
+        return None;
+    }
+    Some((name, op.trim(), value))
+}
+
+/// Names of the targets to import: `targets` and everything they depend on, or all
+fn wanted_targets<'a>(
+    rules: &'a [Target],
+    targets: &'a [String],
+    warnings: &mut Vec<String>,
+) -> BTreeSet<&'a str> {
+    if targets.is_empty() {
+        return rules.iter().map(|rule| rule.name.as_str()).collect();
+    }
+
+    let mut wanted = BTreeSet::new();
+    let mut pending: Vec<&str> = targets.iter().map(String::as_str).collect();
+    while let Some(name) = pending.pop() {
+        if !wanted.insert(name) {
+            continue;
+        }
+        match rules.iter().find(|rule| rule.name == name) {
+            Some(rule) => pending.extend(rule.prerequisites.iter().map(String::as_str)),
+            None if targets.iter().any(|target| target == name) => {
+                warnings.push(format!("No target '{}' in the Makefile", name));
+            }
+            None => {}
+        }
+    }
+    wanted
+}
+
+/// Add the steps standing for `prerequisite` to `needs`
+///
+/// A target with a recipe is a step itself; one without stands for the steps
+/// of its own prerequisites. Prerequisites that are files are left out.
+fn step_prerequisites<'a>(
+    rules: &'a [Target],
+    prerequisite: &'a str,
+    visited: &mut BTreeSet<&'a str>,
+    needs: &mut Vec<String>,
+) {
+    if !visited.insert(prerequisite) {
+        return;
+    }
+    let Some(rule) = rules.iter().find(|rule| rule.name == prerequisite) else {
+        return;
+    };
+    if !rule.recipe.is_empty() {
+        if !needs.contains(&rule.name) {
+            needs.push(rule.name.clone());
+        }
+        return;
+    }
+    for prerequisite in &rule.prerequisites {
+        step_prerequisites(rules, prerequisite, visited, needs);
+    }
+}
+
+/// Rewrite make's `$` references in `text` for getset, returning whether all were understood
+///
+/// In a recipe (`target` set), known variables become `${name}` references and
+/// other names the shell's environment variables; in a variable's value, known
+/// variables are replaced by their value. `$@` is the target's name, and
+/// `$(MAKE)` is `make`. Anything else, such as `$<` or `$(shell ...)`, is kept.
+fn translate(text: &str, vars: &BTreeMap<String, String>, target: Option<&str>) -> (String, bool) {
+    let mut out = String::with_capacity(text.len());
+    let mut complete = true;
+    let mut rest = text;
+
+    while let Some(at) = rest.find('$') {
+        out.push_str(&rest[..at]);
+        let after = &rest[at + 1..];
+
+        if let Some(shell) = after.strip_prefix('$') {
+            // getset would expand `${`, so keep the shell's `${NAME}` escaped
+            out.push_str(if target.is_some() && shell.starts_with('{') {
+                "$$"
+            } else {
+                "$"
+            });
+            rest = shell;
+            continue;
+        }
+        if let Some(shell) = after.strip_prefix('@')
+            && let Some(target) = target
+        {
+            out.push_str(target);
+            rest = shell;
+            continue;
+        }
+
+        let close = match after.chars().next() {
+            Some('(') => ')',
+            Some('{') => '}',
+            _ => {
+                complete = false;
+                out.push('$');
+                rest = after;
+                continue;
+            }
+        };
+        let Some(end) = after.find(close) else {
+            complete = false;
+            out.push('$');
+            rest = after;
+            continue;
+        };
+
+        let name = &after[1..end];
+        let reference = &after[..=end];
+        rest = &after[end + 1..];
+        if name == "MAKE" {
+            out.push_str("make");
+        } else if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ',') {
+            complete = false;
+            out.push('$');
+            out.push_str(reference);
+        } else if let Some(value) = vars.get(name) {
+            match target {
+                Some(_) => out.push_str(&format!("${{{}}}", name)),
+                None => out.push_str(value),
+            }
+        } else {
+            // make falls back to the environment for variables it doesn't define
+            out.push_str(if target.is_some() { "$${" } else { "${" });
+            out.push_str(name);
+            out.push('}');
+        }
+    }
+    out.push_str(rest);
+    (out, complete)
+}
+
+/// The parts of a `package.json` that steps are imported from
+#[derive(Debug, Deserialize)]
+struct PackageJson {
+    #[serde(default)]
+    scripts: Scripts,
+}
+
+/// The `scripts` of a `package.json`, in the order they are written
+#[derive(Debug, Default)]
+struct Scripts(Vec<(String, String)>);
+
+impl<'de> Deserialize<'de> for Scripts {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ScriptsVisitor;
+
+        impl<'de> Visitor<'de> for ScriptsVisitor {
+            type Value = Scripts;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of script names to commands")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Scripts, A::Error> {
+                let mut scripts = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    scripts.push(entry);
+                }
+                Ok(Scripts(scripts))
+            }
+        }
+
+        deserializer.deserialize_map(ScriptsVisitor)
+    }
+}
+
+/// Turn the `scripts` of a `package.json` into steps that `npm run` each one
+///
+/// Steps are titled and identified by the script's name, in the order the
+/// scripts are written. With a `prefix` such as `setup:`, only scripts whose
+/// names start with it are imported. `pre` and `post` scripts are left out, as
+/// `npm run` already runs them around the script they belong to.
+pub fn npm(content: &str, prefix: Option<&str>) -> Result<Imported> {
+    let package: PackageJson =
+        serde_json::from_str(content).map_err(|e| eyre!("Error parsing package.json: {}", e))?;
+    let scripts = package.scripts.0;
+    let is_hook = |name: &str| {
+        ["pre", "post"].iter().any(|hook| {
+            name.strip_prefix(hook)
+                .is_some_and(|script| scripts.iter().any(|(other, _)| other == script))
+        })
+    };
+
+    let mut imported = Imported::default();
+    for (name, _) in &scripts {
+        if prefix.is_some_and(|prefix| !name.starts_with(prefix)) || is_hook(name) {
+            continue;
+        }
+        let mut step = CommandEntry::new(name, format!("npm run {}", shell_quote(name)));
+        if !name.is_empty() && !name.contains(char::is_whitespace) {
+            step = step.with_id(name);
+        }
+        imported.steps.push(step);
+    }
+    Ok(imported)
+}
+
+/// `word` quoted for a POSIX shell, unless it needs no quoting
+pub(crate) fn shell_quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_.:/@+=,".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAKEFILE: &str = "\
+RUBY_VERSION ?= 3.3
+BUNDLE = bundle
+BUNDLE_FLAGS := --jobs 4 \\
+\t--retry 3
+BUNDLE_FLAGS += --quiet
+
+.PHONY: setup deps db clean
+
+setup: deps db ## Set everything up
+
+# Install Ruby
+ruby:
+\t@rbenv install --skip-existing $(RUBY_VERSION)
+
+deps: ruby Gemfile.lock
+\t$(BUNDLE) install $(BUNDLE_FLAGS)
+\t-yarn install
+
+db: deps
+\tbin/rails db:setup
+\techo \"done in $$PWD as $${USER} for $@\"
+
+clean:
+\trm -rf tmp
+";
+
+    #[test]
+    fn test_makefile_targets_become_steps() {
+        let imported = makefile(MAKEFILE, &[]);
+
+        let ids: Vec<&str> = imported
+            .steps
+            .iter()
+            .map(|step| step.id.as_deref().unwrap())
+            .collect();
+        assert_eq!(ids, ["ruby", "deps", "db", "clean"]);
+        assert_eq!(imported.steps[0].title, "Install Ruby");
+        assert_eq!(imported.steps[1].title, "deps");
+        assert_eq!(
+            imported.steps[0].command.as_deref(),
+            Some("rbenv install --skip-existing ${RUBY_VERSION}")
+        );
+        assert_eq!(
+            imported.steps[1].command.as_deref(),
+            Some("${BUNDLE} install ${BUNDLE_FLAGS} &&\n{ yarn install || true; }")
+        );
+        assert_eq!(
+            imported.steps[2].command.as_deref(),
+            Some("bin/rails db:setup &&\necho \"done in $PWD as $${USER} for db\"")
+        );
+        assert_eq!(imported.steps[1].needs, ["ruby"]);
+        assert_eq!(imported.steps[2].needs, ["deps"]);
+        assert_eq!(imported.vars["RUBY_VERSION"], "3.3");
+        assert_eq!(imported.vars["BUNDLE_FLAGS"], "--jobs 4 --retry 3 --quiet");
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+    }
+
+    #[test]
+    fn test_makefile_ignored_errors_do_not_hide_earlier_failures() {
+        let imported = makefile("setup:\n\tfalse\n\t-rm -f x\n\techo SHOULD_NOT_RUN\n", &[]);
+
+        let command = imported.steps[0].command.clone().unwrap();
+        assert_eq!(
+            command,
+            "false &&\n{ rm -f x || true; } &&\necho SHOULD_NOT_RUN"
+        );
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .current_dir(std::env::temp_dir())
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+
+    #[test]
+    fn test_makefile_imports_only_the_chosen_targets_and_their_prerequisites() {
+        let imported = makefile(MAKEFILE, &["setup".to_string(), "lint".to_string()]);
+
+        let titles: Vec<&str> = imported.steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Install Ruby", "deps", "db"]);
+        assert_eq!(imported.warnings, ["No target 'lint' in the Makefile"]);
+    }
+
+    #[test]
+    fn test_makefile_reports_what_it_cannot_translate() {
+        let imported = makefile(
+            "ifeq ($(CI),true)\nFLAGS = --ci\nendif\n%.o: %.c\n\tcc -c $<\nbuild: main.o\n\tcc -o app $(wildcard *.o)\n",
+            &[],
+        );
+
+        assert_eq!(imported.steps.len(), 1);
+        assert_eq!(
+            imported.steps[0].command.as_deref(),
+            Some("cc -o app $(wildcard *.o)")
+        );
+        assert_eq!(
+            imported.warnings,
+            [
+                "Skipped 'ifeq ($(CI),true)', which is not translated",
+                "Target 'build' uses make syntax that is not translated; check its command",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imported_config_is_valid() {
+        let toml = makefile(MAKEFILE, &[])
+            .into_config(".")
+            .to_toml()
+            .expect("Failed to write config");
+
+        let config: Config = toml.parse().expect("Imported config should load");
+        assert_eq!(config.commands.len(), 4);
+        assert_eq!(
+            config.commands[0].command.as_deref(),
+            Some("rbenv install --skip-existing 3.3")
+        );
+        assert!(
+            config.commands[2]
+                .command
+                .as_deref()
+                .unwrap()
+                .contains("as ${USER} for db")
+        );
+    }
+
+    const PACKAGE_JSON: &str = r#"{
+        "name": "shop",
+        "scripts": {
+            "setup:deps": "yarn install",
+            "presetup:db": "docker compose up -d db",
+            "setup:db": "prisma migrate dev",
+            "setup:seed data": "node seed.js",
+            "build": "tsc",
+            "postbuild": "cp -r assets dist"
+        }
+    }"#;
+
+    #[test]
+    fn test_npm_scripts_become_steps_in_order() {
+        let imported = npm(PACKAGE_JSON, None).expect("package.json should parse");
+
+        let titles: Vec<&str> = imported.steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["setup:deps", "setup:db", "setup:seed data", "build"]
+        );
+        assert_eq!(imported.steps[0].id.as_deref(), Some("setup:deps"));
+        assert_eq!(
+            imported.steps[0].command.as_deref(),
+            Some("npm run setup:deps")
+        );
+        assert_eq!(imported.steps[2].id, None);
+        assert_eq!(
+            imported.steps[2].command.as_deref(),
+            Some("npm run 'setup:seed data'")
+        );
+    }
+
+    #[test]
+    fn test_npm_prefix_filters_scripts() {
+        let imported = npm(PACKAGE_JSON, Some("setup:")).unwrap();
+        assert_eq!(imported.steps.len(), 3);
+
+        let empty = npm(r#"{"name": "empty"}"#, Some("setup:")).unwrap();
+        assert!(empty.steps.is_empty());
+        assert!(npm("{", None).is_err());
+    }
+}
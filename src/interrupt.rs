@@ -0,0 +1,273 @@
+use std::fmt;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Once, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Exit code used when a run is stopped with Ctrl-C (128 + SIGINT, as shells do)
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// How long an interrupted command gets to exit on its own before it is killed
+const GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// How often a running command is checked for exit or interruption
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+static INSTALL: Once = Once::new();
+
+/// Number of the last SIGINT, SIGTERM or SIGHUP received, or 0
+fn received() -> &'static Arc<AtomicUsize> {
+    static RECEIVED: OnceLock<Arc<AtomicUsize>> = OnceLock::new();
+    RECEIVED.get_or_init(Default::default)
+}
+
+/// Handle SIGINT, SIGTERM and SIGHUP by flagging the run as interrupted instead of exiting
+///
+/// On Windows, Ctrl-C and Ctrl-Break are handled as SIGINT. Install this only
+/// once prompts are answered, since a pending terminal read would otherwise
+/// keep waiting for input.
+pub fn install_handler() {
+    INSTALL.call_once(|| {
+        #[cfg(unix)]
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            let flag = Arc::clone(received());
+            if let Err(e) = signal_hook::flag::register_usize(signal, flag, signal as usize) {
+                tracing::warn!("Failed to install handler for signal {}: {}", signal, e);
+            }
+        }
+        #[cfg(not(unix))]
+        if let Err(e) = ctrlc::set_handler(|| received().store(SIGINT, Ordering::SeqCst)) {
+            tracing::warn!("Failed to install Ctrl-C handler: {}", e);
+        }
+    });
+}
+
+/// Signal number recorded for Ctrl-C where there are no Unix signals
+#[cfg(not(unix))]
+const SIGINT: usize = 2;
+
+/// The signal that interrupted the run, if any, since the handler was installed or last reset
+fn signal() -> Option<i32> {
+    match received().load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal as i32),
+    }
+}
+
+/// Whether the run has been interrupted by a signal
+pub fn requested() -> bool {
+    signal().is_some()
+}
+
+/// Flag the run as interrupted, as Ctrl-C would, e.g. when the user quits the dashboard
+pub fn request() {
+    #[cfg(unix)]
+    let signal = libc::SIGINT as usize;
+    #[cfg(not(unix))]
+    let signal = SIGINT;
+    received().store(signal, Ordering::SeqCst);
+}
+
+/// Flag the run as interrupted from within the process, e.g. when an embedding application cancels it
+///
+/// Unlike with Ctrl-C, no step has been signalled by the terminal, so running
+/// commands are sent SIGTERM even when they share our process group.
+pub fn cancel() {
+    #[cfg(unix)]
+    let signal = libc::SIGTERM as usize;
+    #[cfg(not(unix))]
+    let signal = SIGINT;
+    received().store(signal, Ordering::SeqCst);
+}
+
+/// Clear the interruption, e.g. before running cleanup steps
+pub fn reset() {
+    received().store(0, Ordering::SeqCst);
+}
+
+/// Start `command` in its own process group, so signals can reach everything it spawns
+///
+/// Commands reading stdin from our terminal must stay in the terminal's
+/// foreground group instead, or they would be stopped when reading.
+pub fn isolate(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+/// Wait for `child` to exit, stopping it and its process group if the run is interrupted
+///
+/// The interrupting signal is forwarded to the child's group when it has its
+/// own (`own_group`); a child sharing our group already received Ctrl-C from
+/// the terminal, so it is only sent SIGTERM and SIGHUP. Anything still running
+/// after the grace period is killed. Returns `None` if the run was interrupted.
+pub fn wait(child: &mut Child, own_group: bool) -> std::io::Result<Option<ExitStatus>> {
+    let mut interrupted_at: Option<Instant> = None;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if interrupted_at.is_none() {
+                return Ok(Some(status));
+            }
+            // Clean up anything the command left behind in its group
+            if own_group {
+                send(child, true, KILL);
+            }
+            return Ok(None);
+        }
+
+        if let Some(signal) = signal() {
+            let since = *interrupted_at.get_or_insert_with(|| {
+                forward(child, own_group, signal);
+                Instant::now()
+            });
+            if since.elapsed() >= GRACE_PERIOD {
+                tracing::debug!(
+                    "Process {} still running after {:?}; killing it",
+                    child.id(),
+                    GRACE_PERIOD
+                );
+                send(child, own_group, KILL);
+                let _ = child.kill();
+                child.wait()?;
+                return Ok(None);
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+const KILL: i32 = libc::SIGKILL;
+#[cfg(not(unix))]
+const KILL: i32 = 9;
+
+fn forward(child: &Child, own_group: bool, signal: i32) {
+    #[cfg(unix)]
+    if !own_group && signal == libc::SIGINT {
+        tracing::debug!(
+            "Process {} shares our process group and already received SIGINT",
+            child.id()
+        );
+        return;
+    }
+    tracing::debug!(
+        "Forwarding signal {} to process {}{}",
+        signal,
+        child.id(),
+        if own_group { " and its group" } else { "" }
+    );
+    send(child, own_group, signal);
+}
+
+/// Send `signal` to the child, or to its whole process group
+///
+/// Windows has no signals to forward; killing there ends the child's whole
+/// process tree with `taskkill`.
+fn send(child: &Child, own_group: bool, signal: i32) {
+    #[cfg(unix)]
+    {
+        let pid = child.id() as libc::pid_t;
+        let target = if own_group { -pid } else { pid };
+        // SAFETY: kill has no memory-safety preconditions; a stale pid only yields ESRCH
+        unsafe {
+            libc::kill(target, signal);
+        }
+    }
+    #[cfg(not(unix))]
+    if own_group && signal == KILL {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &child.id().to_string()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status();
+    }
+}
+
+/// Error returned for a run stopped with Ctrl-C, so the process can exit with
+/// [`EXIT_INTERRUPTED`]
+#[derive(Debug)]
+pub struct Interrupted {
+    /// The step that was running, if any
+    pub step: Option<String>,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.step {
+            Some(ref step) => write!(
+                f,
+                "Interrupted during step '{}' after {:.2}s",
+                step,
+                self.elapsed.as_secs_f64()
+            ),
+            None => write!(f, "Interrupted after {:.2}s", self.elapsed.as_secs_f64()),
+        }
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_wait_returns_status_when_not_interrupted() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 4"]);
+        isolate(&mut command);
+        let mut child = command.spawn().unwrap();
+
+        let status = wait(&mut child, true).unwrap().expect("not interrupted");
+        assert_eq!(status.code(), Some(4));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_signals_reach_the_whole_process_group() {
+        use std::io::Read;
+
+        let mut command = Command::new("sh");
+        command
+            .args(["-c", "sleep 30 & echo started; wait"])
+            .stdout(std::process::Stdio::piped());
+        isolate(&mut command);
+        let mut child = command.spawn().unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        let mut started = [0; 8];
+        stdout.read_exact(&mut started).unwrap();
+
+        let pid = child.id() as libc::pid_t;
+        // SAFETY: getpgid has no memory-safety preconditions
+        assert_eq!(unsafe { libc::getpgid(pid) }, pid);
+
+        let timer = Instant::now();
+        send(&child, true, libc::SIGTERM);
+        child.wait().unwrap();
+        // The backgrounded sleep holds stdout open until it is stopped too
+        stdout.read_to_end(&mut Vec::new()).unwrap();
+        assert!(
+            timer.elapsed() < Duration::from_secs(10),
+            "the grandchild outlived the signal"
+        );
+    }
+
+    #[test]
+    fn test_interrupted_message_names_step() {
+        let err = Interrupted {
+            step: Some("Seed database".to_string()),
+            elapsed: Duration::from_millis(1500),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Interrupted during step 'Seed database' after 1.50s"
+        );
+    }
+}
@@ -1,7 +1,50 @@
+//! Set up a project by running the steps of its `getset.toml`
+//!
+//! Besides the `getset` binary, the crate can be embedded: load a [`Config`],
+//! then execute it with a [`Runner`] and [`RunOptions`], and inspect the
+//! [`RunSummary`] it returns. To follow a run as it happens, give the runner an
+//! [`EventHandler`], which is told about each [`Event`], and to run steps some
+//! other way than as processes, e.g. in tests, give it an [`Executor`]. Those
+//! types, with [`StepResult`], [`ExecOptions`], [`Failure`] and [`run`], are
+//! the stable library API; the other modules serve the binary and may change
+//! between releases. The option and result structs are `#[non_exhaustive]`,
+//! so fields can be added to them without breaking callers.
+
+pub mod cache;
+pub mod ci;
 pub mod cli;
 pub mod config;
+pub mod doctor;
+pub mod events;
+pub mod export;
+pub mod glyphs;
+pub mod graph;
+pub mod hooks;
+pub mod import;
+pub mod interrupt;
+pub mod logs;
+pub mod metrics;
+pub mod notify;
+pub mod orchestrator;
+pub mod picker;
 pub mod platformx;
+pub mod prompts;
+pub mod redact;
+pub mod remote;
+pub mod report;
 pub mod runner;
+pub mod runs;
+pub mod services;
+pub mod spinner;
+pub mod state;
+pub mod telemetry;
+pub mod timings;
+pub mod tui;
+pub mod watch;
+pub mod workspaces;
 
 pub use cli::App;
 pub use config::Config;
+pub use events::{Event, EventHandler, OutputStream, StepOutcome};
+pub use orchestrator::{RunHandle, RunOptions, RunSummary, Runner, StepResult, run};
+pub use runner::{ExecOptions, Executor, Failure};
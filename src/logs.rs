@@ -0,0 +1,30 @@
+use std::path::{Path, PathBuf};
+
+/// Where step logs are written by default, relative to the config file
+pub const LOGS_DIR: &str = ".getset/logs";
+
+/// Log file for a step, named after a filesystem-safe form of its title
+pub fn step_log_path(run_dir: &Path, title: &str) -> PathBuf {
+    run_dir.join(format!("{}.log", slug(title)))
+}
+
+/// Lowercase `title`, keeping letters and digits and joining the rest with dashes
+fn slug(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_log_path_is_filesystem_safe() {
+        let path = step_log_path(Path::new("logs"), "Install gems (bundle/ruby 3.2)");
+        assert_eq!(path, Path::new("logs/install-gems-bundle-ruby-3-2.log"));
+    }
+}
@@ -0,0 +1,537 @@
+use crate::config::{LocalMetricsConfig, PrometheusConfig, StatsdConfig};
+use crate::orchestrator::{RunSummary, StepResult};
+use crate::platformx::Globals;
+use crate::telemetry::{SinkFuture, TelemetrySink};
+use crate::timings;
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::fmt::Write;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where the events of each run are appended when `[metrics.local]` names no path
+const EVENTS_FILE: &str = "events.jsonl";
+
+/// Statuses a step's metrics are labelled with, in the order they are reported
+const STATUSES: [&str; 3] = ["success", "failure", "skipped"];
+
+/// Status label for the metrics of `step`
+pub(crate) fn step_status(step: &StepResult) -> &'static str {
+    if step.skip_reason.is_some() {
+        "skipped"
+    } else if step.success {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+/// Escape a label value for the Prometheus text format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+/// The metrics of a run, in the Prometheus text exposition format
+pub fn prometheus_text(summary: &RunSummary) -> String {
+    let mut text = String::new();
+
+    text.push_str("# HELP getset_step_duration_seconds How long each step of the run took\n");
+    text.push_str("# TYPE getset_step_duration_seconds gauge\n");
+    for step in &summary.steps {
+        let _ = writeln!(
+            text,
+            "getset_step_duration_seconds{{step=\"{}\",status=\"{}\"}} {}",
+            escape_label(&step.title),
+            step_status(step),
+            step.duration.as_secs_f64()
+        );
+    }
+
+    text.push_str("# HELP getset_steps_total Steps of the run by status\n");
+    text.push_str("# TYPE getset_steps_total gauge\n");
+    for status in STATUSES {
+        let count = summary
+            .steps
+            .iter()
+            .filter(|step| step_status(step) == status)
+            .count();
+        let _ = writeln!(
+            text,
+            "getset_steps_total{{status=\"{}\"}} {}",
+            status, count
+        );
+    }
+
+    text.push_str("# HELP getset_run_duration_seconds How long the run took\n");
+    text.push_str("# TYPE getset_run_duration_seconds gauge\n");
+    let _ = writeln!(
+        text,
+        "getset_run_duration_seconds {}",
+        summary.duration.as_secs_f64()
+    );
+
+    text.push_str("# HELP getset_run_success Whether the run succeeded\n");
+    text.push_str("# TYPE getset_run_success gauge\n");
+    let _ = writeln!(text, "getset_run_success {}", u8::from(summary.success()));
+
+    text
+}
+
+/// Push the metrics of a run to a Pushgateway, replacing those of the user's last run of the project
+pub async fn push_prometheus(
+    config: &PrometheusConfig,
+    project: &str,
+    user: &str,
+    summary: &RunSummary,
+) -> Result<(), String> {
+    let mut url = reqwest::Url::parse(&config.pushgateway_url).map_err(|e| {
+        format!(
+            "Invalid Pushgateway URL '{}': {}",
+            config.pushgateway_url, e
+        )
+    })?;
+    url.path_segments_mut()
+        .map_err(|_| format!("Invalid Pushgateway URL '{}'", config.pushgateway_url))?
+        .pop_if_empty()
+        .extend([
+            "metrics",
+            "job",
+            config.job.as_str(),
+            "project",
+            project,
+            "user",
+            user,
+        ]);
+
+    tracing::info!("Pushing metrics to '{}'", url);
+    let result = reqwest::Client::new()
+        .put(url)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(prometheus_text(summary))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to push metrics to the Pushgateway: {}", e))?;
+
+    let status = result.status();
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Pushgateway rejected the metrics with status: {}",
+            status
+        ))
+    }
+}
+
+/// Make a value safe to use as a DogStatsD tag value
+fn escape_tag(value: &str) -> String {
+    value.replace([',', '|', '#', '\n'], "_")
+}
+
+/// The metrics of a run as StatsD lines, tagged in the DogStatsD format
+pub fn statsd_lines(prefix: &str, summary: &RunSummary) -> Vec<String> {
+    let mut lines = Vec::new();
+    for step in &summary.steps {
+        let tags = format!(
+            "#step:{},status:{}",
+            escape_tag(&step.title),
+            step_status(step)
+        );
+        lines.push(format!(
+            "{}.step.duration:{}|ms|{}",
+            prefix,
+            step.duration.as_millis(),
+            tags
+        ));
+        lines.push(format!("{}.step.count:1|c|{}", prefix, tags));
+    }
+
+    let status = if summary.success() {
+        "success"
+    } else {
+        "failure"
+    };
+    lines.push(format!(
+        "{}.run.duration:{}|ms|#status:{}",
+        prefix,
+        summary.duration.as_millis(),
+        status
+    ));
+    lines.push(format!("{}.run.count:1|c|#status:{}", prefix, status));
+    lines
+}
+
+/// Send the metrics of a run to a StatsD agent, one UDP packet per metric
+pub fn send_statsd(config: &StatsdConfig, summary: &RunSummary) -> Result<(), String> {
+    let unreachable =
+        |e: std::io::Error| format!("Failed to reach StatsD at '{}': {}", config.addr, e);
+    let agent = config
+        .addr
+        .to_socket_addrs()
+        .map_err(unreachable)?
+        .next()
+        .ok_or_else(|| format!("StatsD address '{}' did not resolve", config.addr))?;
+    let local = if agent.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local)
+        .and_then(|socket| socket.connect(agent).map(|_| socket))
+        .map_err(unreachable)?;
+
+    tracing::info!("Sending metrics to StatsD at '{}'", config.addr);
+    for line in statsd_lines(&config.prefix, summary) {
+        socket
+            .send(line.as_bytes())
+            .map_err(|e| format!("Failed to send metrics to StatsD: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Pushes the metrics of each run to a Pushgateway when it finishes
+pub struct PrometheusSink {
+    config: PrometheusConfig,
+    /// Name of the project, to group the metrics by
+    project: String,
+    /// Who ran getset, from their git config, so developers don't replace each other's metrics
+    user: String,
+}
+
+impl PrometheusSink {
+    pub fn new(config: PrometheusConfig, project: String, user: String) -> Self {
+        Self {
+            config,
+            project,
+            user,
+        }
+    }
+
+    async fn push(&self, summary: &RunSummary) {
+        if let Err(e) = push_prometheus(&self.config, &self.project, &self.user, summary).await {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+impl TelemetrySink for PrometheusSink {
+    fn on_complete<'a>(&'a self, summary: &'a RunSummary, _duration: Duration) -> SinkFuture<'a> {
+        Box::pin(self.push(summary))
+    }
+
+    fn on_error<'a>(
+        &'a self,
+        summary: &'a RunSummary,
+        _duration: Duration,
+        _error: &'a str,
+    ) -> SinkFuture<'a> {
+        Box::pin(self.push(summary))
+    }
+}
+
+/// Sends the metrics of each run to a StatsD agent when it finishes
+pub struct StatsdSink {
+    config: StatsdConfig,
+}
+
+impl StatsdSink {
+    pub fn new(config: StatsdConfig) -> Self {
+        Self { config }
+    }
+
+    fn send(&self, summary: &RunSummary) {
+        if let Err(e) = send_statsd(&self.config, summary) {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+impl TelemetrySink for StatsdSink {
+    fn on_complete<'a>(&'a self, summary: &'a RunSummary, _duration: Duration) -> SinkFuture<'a> {
+        self.send(summary);
+        Box::pin(async {})
+    }
+
+    fn on_error<'a>(
+        &'a self,
+        summary: &'a RunSummary,
+        _duration: Duration,
+        _error: &'a str,
+    ) -> SinkFuture<'a> {
+        self.send(summary);
+        Box::pin(async {})
+    }
+}
+
+/// Appends the events of each run to a local JSON lines file, to analyze without a server
+pub struct LocalSink {
+    path: PathBuf,
+    globals: Globals,
+    /// Name of the project, recorded with each event
+    project: String,
+}
+
+impl LocalSink {
+    pub fn new(
+        config: &LocalMetricsConfig,
+        base_dir: &Path,
+        globals: Globals,
+        project: String,
+    ) -> Self {
+        let path = match config.path {
+            Some(ref path) => base_dir.join(path),
+            None => timings::data_dir()
+                .unwrap_or_else(|| base_dir.join(".getset"))
+                .join(EVENTS_FILE),
+        };
+        Self {
+            path,
+            globals,
+            project,
+        }
+    }
+
+    /// Append an `event` line with `fields` and the details of the project and user
+    fn append(&self, event: &str, fields: Value) {
+        let mut record = json!({
+            "event": event,
+            "timestamp": Utc::now().to_rfc3339(),
+            "project": self.project,
+            "user": self.globals.git_email,
+            "git_branch": self.globals.git_branch,
+            "git_commit": self.globals.git_commit,
+            "git_dirty": self.globals.git_dirty,
+            "run_id": self.globals.run_id,
+        });
+        if let (Some(record), Value::Object(fields)) = (record.as_object_mut(), fields) {
+            record.extend(fields);
+        }
+        if let Err(e) = append_line(&self.path, &record) {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+fn append_line(path: &Path, record: &Value) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Error creating '{}': {}", dir.display(), e))?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", record))
+        .map_err(|e| format!("Error writing '{}': {}", path.display(), e))
+}
+
+impl TelemetrySink for LocalSink {
+    fn on_start(&self) -> SinkFuture<'_> {
+        self.append("start", json!({}));
+        Box::pin(async {})
+    }
+
+    fn on_step<'a>(&'a self, step: &'a StepResult) -> SinkFuture<'a> {
+        self.append(
+            "step",
+            json!({
+                "step": step.title,
+                "status": step_status(step),
+                "duration_secs": step.duration.as_secs_f64(),
+            }),
+        );
+        Box::pin(async {})
+    }
+
+    fn on_complete<'a>(&'a self, _summary: &'a RunSummary, duration: Duration) -> SinkFuture<'a> {
+        self.append(
+            "complete",
+            json!({ "duration_secs": duration.as_secs_f64() }),
+        );
+        Box::pin(async {})
+    }
+
+    fn on_error<'a>(
+        &'a self,
+        _summary: &'a RunSummary,
+        duration: Duration,
+        error: &'a str,
+    ) -> SinkFuture<'a> {
+        self.append(
+            "error",
+            json!({
+                "duration_secs": duration.as_secs_f64(),
+                "error": error,
+            }),
+        );
+        Box::pin(async {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(title: &str, millis: u64, success: bool) -> StepResult {
+        StepResult {
+            title: title.to_string(),
+            duration: Duration::from_millis(millis),
+            success,
+            error: (!success).then(|| "Step failed with exit code 1".to_string()),
+            output_tail: Vec::new(),
+            skip_reason: None,
+            allowed_failure: false,
+            satisfied: false,
+            started: Duration::ZERO,
+            project: None,
+        }
+    }
+
+    fn summary() -> RunSummary {
+        let mut cached = step("Build", 0, true);
+        cached.skip_reason = Some("cached".to_string());
+        RunSummary {
+            steps: vec![
+                step("Install \"gems\"", 1500, true),
+                cached,
+                step("Migrate", 250, false),
+            ],
+            duration: Duration::from_secs(2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_prometheus_text() {
+        let text = prometheus_text(&summary());
+
+        assert!(text.contains(
+            "getset_step_duration_seconds{step=\"Install \\\"gems\\\"\",status=\"success\"} 1.5\n"
+        ));
+        assert!(
+            text.contains("getset_step_duration_seconds{step=\"Build\",status=\"skipped\"} 0\n")
+        );
+        assert!(
+            text.contains(
+                "getset_step_duration_seconds{step=\"Migrate\",status=\"failure\"} 0.25\n"
+            )
+        );
+        assert!(text.contains("# TYPE getset_steps_total gauge\n"));
+        assert!(text.contains("getset_steps_total{status=\"success\"} 1\n"));
+        assert!(text.contains("getset_steps_total{status=\"failure\"} 1\n"));
+        assert!(text.contains("getset_steps_total{status=\"skipped\"} 1\n"));
+        assert!(text.contains("getset_run_duration_seconds 2\n"));
+        assert!(text.contains("getset_run_success 0\n"));
+    }
+
+    #[tokio::test]
+    async fn test_push_prometheus_groups_by_job_project_and_user() {
+        let mut server = mockito::Server::new_async().await;
+        let push = server
+            .mock(
+                "PUT",
+                "/metrics/job/setup/project/shop/user/dev@example.com",
+            )
+            .match_body(mockito::Matcher::Regex(
+                "getset_run_success 0\n".to_string(),
+            ))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = PrometheusConfig {
+            pushgateway_url: format!("{}/", server.url()),
+            job: "setup".to_string(),
+        };
+        push_prometheus(&config, "shop", "dev@example.com", &summary())
+            .await
+            .unwrap();
+
+        push.assert_async().await;
+    }
+
+    #[test]
+    fn test_statsd_lines_tag_steps_and_run() {
+        let mut summary = summary();
+        summary.steps[0].title = "Install gems, tools".to_string();
+
+        assert_eq!(
+            statsd_lines("getset", &summary),
+            [
+                "getset.step.duration:1500|ms|#step:Install gems_ tools,status:success",
+                "getset.step.count:1|c|#step:Install gems_ tools,status:success",
+                "getset.step.duration:0|ms|#step:Build,status:skipped",
+                "getset.step.count:1|c|#step:Build,status:skipped",
+                "getset.step.duration:250|ms|#step:Migrate,status:failure",
+                "getset.step.count:1|c|#step:Migrate,status:failure",
+                "getset.run.duration:2000|ms|#status:failure",
+                "getset.run.count:1|c|#status:failure",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_sink_appends_json_lines() {
+        let dir = std::env::temp_dir().join(format!("getset-local-events-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let globals = Globals {
+            user_shell: "/bin/bash".to_string(),
+            github_username: "testuser".to_string(),
+            git_email: "test@example.com".to_string(),
+            git_branch: "main".to_string(),
+            git_commit: "abc1234".to_string(),
+            git_dirty: Some(false),
+            run_id: "run-1".to_string(),
+        };
+        let config = LocalMetricsConfig {
+            path: Some(PathBuf::from("history/events.jsonl")),
+        };
+        let sink = LocalSink::new(&config, &dir, globals, "shop".to_string());
+
+        let summary = summary();
+        sink.on_start().await;
+        sink.on_step(&summary.steps[2]).await;
+        sink.on_error(&summary, Duration::from_secs(2), "Migrate failed")
+            .await;
+
+        let content = fs::read_to_string(dir.join("history/events.jsonl")).unwrap();
+        let records: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["event"], "start");
+        assert_eq!(records[0]["project"], "shop");
+        assert_eq!(records[0]["git_branch"], "main");
+        assert_eq!(records[1]["step"], "Migrate");
+        assert_eq!(records[1]["status"], "failure");
+        assert_eq!(records[1]["duration_secs"], 0.25);
+        assert_eq!(records[2]["event"], "error");
+        assert_eq!(records[2]["error"], "Migrate failed");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_send_statsd_sends_a_packet_per_metric() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = StatsdConfig {
+            addr: agent.local_addr().unwrap().to_string(),
+            prefix: "dev".to_string(),
+        };
+        send_statsd(&config, &summary()).unwrap();
+
+        let mut buf = [0; 512];
+        let len = agent.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "dev.step.duration:1500|ms|#step:Install \"gems\",status:success"
+        );
+    }
+}
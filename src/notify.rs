@@ -0,0 +1,410 @@
+use crate::config::{self, NotificationsConfig, SlackConfig, WebhookConfig};
+use crate::orchestrator::RunSummary;
+use crate::platformx::Globals;
+use crate::telemetry::{SinkFuture, TelemetrySink};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Longest a notification may take to deliver, so a slow endpoint can't hold up the run
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A point in a run's lifecycle that notifications are sent for
+#[derive(Debug, Clone, Copy)]
+pub enum RunEvent<'a> {
+    Started,
+    Succeeded {
+        duration: Duration,
+    },
+    Failed {
+        duration: Duration,
+        /// Title of the first failed step, if a step failed
+        failed_step: Option<&'a str>,
+        error: &'a str,
+    },
+}
+
+impl RunEvent<'_> {
+    /// Name of the event, as listed in a webhook's `events`
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunEvent::Started => "start",
+            RunEvent::Succeeded { .. } => "success",
+            RunEvent::Failed { .. } => "failure",
+        }
+    }
+}
+
+/// Sends run notifications to every destination in the `[notifications]` section
+pub struct Notifiers {
+    slack: Option<SlackConfig>,
+    webhooks: Vec<WebhookConfig>,
+    client: reqwest::Client,
+    /// Who is running getset, from their git config
+    user: String,
+    /// Name of the project being set up
+    project: String,
+    /// Unique id of the run being announced
+    run_id: String,
+}
+
+impl Notifiers {
+    pub fn new(config: Option<&NotificationsConfig>, globals: &Globals, base_dir: &Path) -> Self {
+        Self {
+            slack: config.and_then(|config| config.slack.clone()),
+            webhooks: config
+                .map(|config| config.webhook.clone())
+                .unwrap_or_default(),
+            client: reqwest::Client::builder()
+                .timeout(TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            user: globals.git_email.clone(),
+            project: project_name(base_dir),
+            run_id: globals.run_id.clone(),
+        }
+    }
+
+    /// Send `event` everywhere it is wanted, logging failures as warnings
+    pub async fn notify(&self, event: &RunEvent<'_>) {
+        if let Some(ref slack) = self.slack
+            && let Err(e) = self.send_slack(slack, event).await
+        {
+            tracing::warn!("{}", e);
+        }
+        for webhook in &self.webhooks {
+            if !webhook.events.is_empty() && !webhook.events.iter().any(|e| e == event.name()) {
+                continue;
+            }
+            if let Err(e) = self.send_webhook(webhook, event).await {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+
+    async fn send_slack(&self, slack: &SlackConfig, event: &RunEvent<'_>) -> Result<(), String> {
+        let text = slack_message(event, &self.user, &self.project);
+        tracing::info!("Sending Slack notification: {}", text);
+        let result = self
+            .client
+            .post(&slack.webhook_url)
+            .json(&json!({ "text": text }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to send Slack notification: {}", e))?;
+
+        let status = result.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Slack webhook rejected the notification with status: {}",
+                status
+            ))
+        }
+    }
+
+    async fn send_webhook(
+        &self,
+        webhook: &WebhookConfig,
+        event: &RunEvent<'_>,
+    ) -> Result<(), String> {
+        let fields = self.fields(event);
+        let body = match webhook.body {
+            Some(ref template) => render_body(template, &fields)
+                .map_err(|e| format!("Invalid body for webhook '{}': {}", webhook.url, e))?,
+            None => json!(fields).to_string(),
+        };
+        let method = reqwest::Method::from_bytes(webhook.method.to_uppercase().as_bytes())
+            .map_err(|_| {
+                format!(
+                    "Invalid method '{}' for webhook '{}'",
+                    webhook.method, webhook.url
+                )
+            })?;
+
+        tracing::info!("Sending webhook to '{}': {}", webhook.url, body);
+        let mut request = self
+            .client
+            .request(method, &webhook.url)
+            .header("Content-Type", "application/json");
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+        let result = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to send webhook to '{}': {}", webhook.url, e))?;
+
+        let status = result.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Webhook '{}' rejected the notification with status: {}",
+                webhook.url, status
+            ))
+        }
+    }
+
+    /// Values a webhook body can refer to as `{{name}}`
+    fn fields(&self, event: &RunEvent<'_>) -> BTreeMap<&'static str, Value> {
+        let mut fields = BTreeMap::from([
+            ("event", json!(event.name())),
+            ("user", json!(self.user)),
+            ("project", json!(self.project)),
+            ("run_id", json!(self.run_id)),
+        ]);
+        match *event {
+            RunEvent::Started => {}
+            RunEvent::Succeeded { duration } => {
+                fields.insert("duration_secs", json!(duration.as_secs_f64()));
+            }
+            RunEvent::Failed {
+                duration,
+                failed_step,
+                error,
+            } => {
+                fields.insert("duration_secs", json!(duration.as_secs_f64()));
+                fields.insert("failed_step", json!(failed_step));
+                fields.insert("error", json!(error));
+            }
+        }
+        fields
+    }
+}
+
+impl TelemetrySink for Notifiers {
+    fn on_start(&self) -> SinkFuture<'_> {
+        Box::pin(async { self.notify(&RunEvent::Started).await })
+    }
+
+    fn on_complete<'a>(&'a self, _summary: &'a RunSummary, duration: Duration) -> SinkFuture<'a> {
+        Box::pin(async move { self.notify(&RunEvent::Succeeded { duration }).await })
+    }
+
+    fn on_error<'a>(
+        &'a self,
+        summary: &'a RunSummary,
+        duration: Duration,
+        error: &'a str,
+    ) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let failed_step = summary.failures().next().map(|step| step.title.as_str());
+            self.notify(&RunEvent::Failed {
+                duration,
+                failed_step,
+                error,
+            })
+            .await
+        })
+    }
+}
+
+/// The Slack message announcing `event`
+fn slack_message(event: &RunEvent<'_>, user: &str, project: &str) -> String {
+    match *event {
+        RunEvent::Started => format!(
+            ":hourglass_flowing_sand: {} started setting up *{}*",
+            user, project
+        ),
+        RunEvent::Succeeded { duration } => format!(
+            ":white_check_mark: {} finished setting up *{}* in {:.2}s",
+            user,
+            project,
+            duration.as_secs_f64()
+        ),
+        RunEvent::Failed {
+            duration,
+            failed_step,
+            error,
+        } => {
+            let at = failed_step
+                .map(|title| format!(" at *{}*", title))
+                .unwrap_or_default();
+            format!(
+                ":x: {} failed setting up *{}*{} after {:.2}s\n```{}```",
+                user,
+                project,
+                at,
+                duration.as_secs_f64(),
+                error
+            )
+        }
+    }
+}
+
+/// Fill in `{{name}}` placeholders in a JSON body template, checking the result is JSON
+///
+/// A placeholder inside a JSON string is replaced with the escaped text of the
+/// value; anywhere else it becomes the value itself, so `"{{error}}"` and
+/// `{{duration_secs}}` both work. Fields the event doesn't have are `null`, or
+/// empty inside strings.
+pub fn render_body(template: &str, fields: &BTreeMap<&str, Value>) -> Result<String, String> {
+    let mut body = String::new();
+    let mut rest = template;
+    let mut in_string = false;
+
+    while let Some(start) = rest.find("{{") {
+        let (before, after) = rest.split_at(start);
+        in_string = toggles_string(before, in_string);
+        body.push_str(before);
+
+        let end = after
+            .find("}}")
+            .ok_or_else(|| "unclosed '{{' in template".to_string())?;
+        let name = after[2..end].trim();
+        let value = fields.get(name).cloned().unwrap_or(Value::Null);
+        match (in_string, value) {
+            (true, Value::String(text)) => {
+                let quoted = Value::String(text).to_string();
+                body.push_str(&quoted[1..quoted.len() - 1]);
+            }
+            (true, Value::Null) => {}
+            (_, value) => body.push_str(&value.to_string()),
+        }
+        rest = &after[end + 2..];
+    }
+    body.push_str(rest);
+
+    serde_json::from_str::<Value>(&body).map_err(|e| e.to_string())?;
+    Ok(body)
+}
+
+/// Whether JSON text that follows `text` is inside a string, given whether `text` started in one
+fn toggles_string(text: &str, mut in_string: bool) -> bool {
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ => {}
+        }
+    }
+    in_string
+}
+
+/// Name of the directory holding the config, to say which project a run set up
+pub(crate) fn project_name(base_dir: &Path) -> String {
+    config::absolute_dir(base_dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "project".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_notifiers(config: NotificationsConfig) -> Notifiers {
+        let globals = Globals {
+            user_shell: "/bin/bash".to_string(),
+            github_username: "testuser".to_string(),
+            git_email: "test@example.com".to_string(),
+            git_branch: "main".to_string(),
+            git_commit: "abc1234".to_string(),
+            git_dirty: Some(false),
+            run_id: "run-1".to_string(),
+        };
+        let mut notifiers = Notifiers::new(Some(&config), &globals, Path::new("."));
+        notifiers.project = "shop".to_string();
+        notifiers
+    }
+
+    fn failure() -> RunEvent<'static> {
+        RunEvent::Failed {
+            duration: Duration::from_millis(1500),
+            failed_step: Some("Install gems"),
+            error: "exit code 1",
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slack_failure_names_the_failed_step() {
+        let mut server = mockito::Server::new_async().await;
+        let webhook = server
+            .mock("POST", "/hook")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "text": ":x: test@example.com failed setting up *shop* at *Install gems* after 1.50s\n```exit code 1```"
+            })))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let notifiers = create_test_notifiers(NotificationsConfig {
+            slack: Some(SlackConfig {
+                webhook_url: format!("{}/hook", server.url()),
+            }),
+            ..Default::default()
+        });
+        notifiers.notify(&failure()).await;
+
+        webhook.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_webhook_sends_templated_body_for_its_events() {
+        let mut server = mockito::Server::new_async().await;
+        let webhook = server
+            .mock("PUT", "/alerts")
+            .match_header("x-api-key", "secret")
+            .match_body(mockito::Matcher::Json(json!({
+                "summary": "getset failed for test@example.com at Install gems",
+                "seconds": 1.5,
+            })))
+            .with_status(202)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let notifiers = create_test_notifiers(NotificationsConfig {
+            webhook: vec![WebhookConfig {
+                url: format!("{}/alerts", server.url()),
+                method: "put".to_string(),
+                headers: BTreeMap::from([("X-Api-Key".to_string(), "secret".to_string())]),
+                body: Some(
+                    r#"{"summary": "getset failed for {{user}} at {{ failed_step }}", "seconds": {{duration_secs}}}"#
+                        .to_string(),
+                ),
+                events: vec!["failure".to_string()],
+            }],
+            ..Default::default()
+        });
+        notifiers.notify(&RunEvent::Started).await;
+        notifiers.notify(&failure()).await;
+
+        webhook.assert_async().await;
+    }
+
+    #[test]
+    fn test_render_body_escapes_strings_and_checks_json() {
+        let fields = BTreeMap::from([
+            ("error", json!("said \"no\"\nthen quit")),
+            ("failed_step", Value::Null),
+        ]);
+
+        assert_eq!(
+            render_body(r#"{"text": "{{error}}", "step": {{failed_step}}}"#, &fields).unwrap(),
+            r#"{"text": "said \"no\"\nthen quit", "step": null}"#
+        );
+        assert_eq!(
+            render_body(r#"{"text": "at {{failed_step}}"}"#, &fields).unwrap(),
+            r#"{"text": "at "}"#
+        );
+        assert!(render_body(r#"{"text": {{error"#, &fields).is_err());
+        assert!(render_body(r#"{"text": }"#, &fields).is_err());
+    }
+
+    #[test]
+    fn test_project_name_of_the_current_directory() {
+        let current = std::env::current_dir().unwrap();
+        let name = current.file_name().unwrap().to_string_lossy();
+
+        assert_eq!(project_name(Path::new("")), name);
+        assert_eq!(project_name(Path::new(".")), name);
+    }
+}
@@ -0,0 +1,2029 @@
+use crate::cache::{self, Cache};
+use crate::ci::{self, CiFormat};
+use crate::config::{CommandEntry, Config};
+use crate::events::{self, Emitter, EventHandler, LogFormat};
+use crate::glyphs;
+use crate::graph;
+use crate::interrupt::{self, Interrupted};
+use crate::redact;
+use crate::runner::{self, ExecOptions, Executor, Progress};
+use crate::state;
+use crate::timings;
+use color_eyre::eyre::{Result, eyre};
+use console::style;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Skip reason for steps whose `sources` and `outputs` are unchanged
+pub const UP_TO_DATE: &str = "up to date";
+
+/// Skip reason for the steps left after a run gives up with `bail_after`
+const BAILED: &str = "skipped";
+
+/// Environment variable that tells each step the [`RunSummary::uuid`] of its run
+pub const RUN_ID_VAR: &str = "GETSET_RUN_ID";
+
+/// Skip reason for steps the user chose not to run, when asked to confirm or approve them
+const DECLINED: &str = "declined";
+
+/// Skip reason for steps that need a step that failed, with `continue_on_error`
+pub const NEEDS_FAILED: &str = "needs a failed step";
+
+/// Options controlling how a configuration is executed
+///
+/// Start from [`RunOptions::default`] and set the fields you need, since new
+/// options may be added in any release.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RunOptions {
+    /// Show command text and trace shell execution
+    pub verbose: u8,
+    /// Run only steps whose title contains this substring (case-insensitive)
+    pub step: Option<String>,
+    /// Require `step` to match a title exactly instead of as a substring
+    pub step_exact: bool,
+    /// Run only steps with at least one of these tags
+    pub tags: Vec<String>,
+    /// Run exactly the step with this id, without the steps it needs
+    pub id: Option<String>,
+    /// Run these steps, by index into the config's commands, instead of filtering by `step` or tags
+    pub picked: Option<Vec<usize>>,
+    /// Start from this step (id or title), skipping the steps configured before it
+    pub from: Option<String>,
+    /// Stop after this step (id or title), skipping the steps configured after it
+    pub until: Option<String>,
+    /// Titles of steps that completed in an earlier failed run and are skipped
+    pub completed: Vec<String>,
+    /// Keep running the remaining steps after a step fails
+    pub continue_on_error: bool,
+    /// Run `confirm` steps without asking
+    pub yes: bool,
+    /// Ask before every step whether to run it, skip it or abort the run (terminals only)
+    pub interactive: bool,
+    /// Ask whether to retry, skip or abort when a step fails (terminals only)
+    pub recover: bool,
+    /// Asks the `interactive` and `recover` questions instead of the terminal
+    pub prompter: Option<Arc<dyn Prompter>>,
+    /// Run every step without a PTY or stdin, e.g. while a dashboard owns the terminal
+    pub detached: bool,
+    /// With `continue_on_error`, stop once this many steps have failed (0 = never)
+    pub bail_after: u32,
+    /// Maximum number of `parallel` steps to run at once (0 or 1 = sequential)
+    pub jobs: usize,
+    /// Skip steps whose `inputs` are unchanged since their last successful run
+    pub incremental: bool,
+    /// Prompt for which steps to run when `step` matches several (terminals only)
+    pub pick: bool,
+    /// Format for getset's own lifecycle output
+    pub log_format: LogFormat,
+    /// Capture every step's output and show it only for failed steps
+    pub quiet: bool,
+    /// Show only stderr from the output of failed steps
+    pub stderr_only: bool,
+    /// Write each step's output to a log file in a new per-run directory inside this one
+    pub log_dir: Option<PathBuf>,
+    /// Typical duration of steps by title, from previous runs, for time estimates
+    pub typical: BTreeMap<String, Duration>,
+    /// CI system to write log sections and failure annotations for
+    pub ci: Option<CiFormat>,
+    /// Put the time before each line of step output, as in CI mode
+    pub timestamps: bool,
+    /// Unique id of the run for correlating its logs and telemetry (a new one if not set)
+    pub uuid: Option<String>,
+    /// Runs the steps' commands and conditions instead of spawning processes
+    pub executor: Option<Arc<dyn Executor>>,
+    /// Told about each event of the run; step output then passes through getset
+    /// rather than going straight to a terminal
+    pub event_handler: Option<Arc<dyn EventHandler>>,
+}
+
+/// Outcome of a single executed step
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct StepResult {
+    pub title: String,
+    pub duration: Duration,
+    pub success: bool,
+    /// Description of the failure, if the step did not succeed
+    pub error: Option<String>,
+    /// Last lines of a failed step's output, to show under its error
+    pub output_tail: Vec<String>,
+    /// Why the step was skipped instead of run (e.g. `cached`)
+    pub skip_reason: Option<String>,
+    /// The step failed, but is marked `allow_failure` so the run continued
+    pub allowed_failure: bool,
+    /// The step's `check` succeeded, so its command was not run
+    pub satisfied: bool,
+    /// Time from the start of the run until the step began
+    pub started: Duration,
+    /// Workspace member the step belongs to
+    pub project: Option<String>,
+}
+
+/// Outcome of a whole run
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RunSummary {
+    /// Executed steps, in the order they ran
+    pub steps: Vec<StepResult>,
+    pub duration: Duration,
+    /// Where the step logs of this run were written
+    pub log_dir: Option<PathBuf>,
+    /// The run was stopped with Ctrl-C, or aborted at a prompt
+    pub interrupted: bool,
+    /// Unique id of the run, given to its steps as `GETSET_RUN_ID`, naming its
+    /// log directory and run record, and sent with its telemetry
+    pub uuid: String,
+    /// Number of steps selected for the run, including any not reached
+    pub total: usize,
+}
+
+impl StepResult {
+    /// Result for a step that was not run, reached `started` into the run
+    fn skipped(cmd_entry: &CommandEntry, reason: &str, started: Duration) -> Self {
+        Self {
+            title: cmd_entry.title.clone(),
+            duration: Duration::ZERO,
+            success: true,
+            error: None,
+            output_tail: Vec::new(),
+            skip_reason: Some(reason.to_string()),
+            allowed_failure: false,
+            satisfied: false,
+            started,
+            project: cmd_entry.project.clone(),
+        }
+    }
+
+    /// Result for a step whose `check` ran from `started` for `duration` and succeeded
+    fn satisfied(cmd_entry: &CommandEntry, started: Duration, duration: Duration) -> Self {
+        Self {
+            title: cmd_entry.title.clone(),
+            duration,
+            success: true,
+            error: None,
+            output_tail: Vec::new(),
+            skip_reason: None,
+            allowed_failure: false,
+            satisfied: true,
+            started,
+            project: cmd_entry.project.clone(),
+        }
+    }
+
+    /// Whether the step failed in a way that fails the run
+    pub fn is_failure(&self) -> bool {
+        !self.success && !self.allowed_failure
+    }
+
+    /// Whether steps that need this one must be skipped instead of run
+    fn blocks_dependents(&self) -> bool {
+        self.is_failure() || self.skip_reason.as_deref() == Some(NEEDS_FAILED)
+    }
+}
+
+/// Steps grouped by the workspace member they belong to, each group in the order it first ran
+///
+/// Steps of the project itself come first, under `None`.
+pub fn by_project(steps: &[StepResult]) -> Vec<(Option<&str>, Vec<&StepResult>)> {
+    let mut groups: Vec<(Option<&str>, Vec<&StepResult>)> = vec![(None, Vec::new())];
+    for step in steps {
+        let project = step.project.as_deref();
+        match groups.iter_mut().find(|(known, _)| *known == project) {
+            Some((_, group)) => group.push(step),
+            None => groups.push((project, vec![step])),
+        }
+    }
+    groups.retain(|(_, group)| !group.is_empty());
+    groups
+}
+
+impl RunSummary {
+    /// Whether every executed step succeeded or was allowed to fail
+    pub fn success(&self) -> bool {
+        !self.interrupted && !self.steps.iter().any(StepResult::is_failure)
+    }
+
+    /// The error for a run stopped with Ctrl-C, naming the step that was running
+    pub fn interruption(&self) -> Option<Interrupted> {
+        self.interrupted.then(|| Interrupted {
+            step: self.failures().last().map(|step| step.title.clone()),
+            elapsed: self.duration,
+        })
+    }
+
+    /// Steps that failed the run, in the order they ran
+    pub fn failures(&self) -> impl Iterator<Item = &StepResult> {
+        self.steps.iter().filter(|step| step.is_failure())
+    }
+
+    /// Steps that failed but were allowed to, in the order they ran
+    pub fn allowed_failures(&self) -> impl Iterator<Item = &StepResult> {
+        self.steps.iter().filter(|step| step.allowed_failure)
+    }
+
+    /// Whether the run ended before running every selected step, e.g. at a
+    /// failure, a `--bail-after` give-up or Ctrl-C
+    pub fn stopped_early(&self) -> bool {
+        self.interrupted
+            || self.steps.len() < self.total
+            || self
+                .steps
+                .iter()
+                .any(|step| step.skip_reason.as_deref() == Some(BAILED))
+    }
+
+    /// How many of the selected steps were completed, as `[done/total]`
+    ///
+    /// Steps skipped after a `--bail-after` give-up are not counted as done.
+    pub fn progress(&self) -> Progress {
+        Progress {
+            current: self
+                .steps
+                .iter()
+                .filter(|step| step.success && step.skip_reason.as_deref() != Some(BAILED))
+                .count(),
+            total: self.total,
+        }
+    }
+}
+
+/// Whether a step title matches the `step` filter
+fn title_matches(title: &str, step_filter: &str, exact: bool) -> bool {
+    if exact {
+        title == step_filter
+    } else {
+        title.to_lowercase().contains(&step_filter.to_lowercase())
+    }
+}
+
+/// Position of the step named by `--from` or `--until` in the configured order
+fn range_bound(config: &Config, flag: &str, name: &str) -> Result<usize> {
+    config
+        .commands
+        .iter()
+        .position(|cmd| cmd.is_named(name))
+        .ok_or_else(|| eyre!("No step with id or title '{}' for {}", name, flag))
+}
+
+/// The contiguous slice of the configured order selected by `from` and `until`
+fn step_range(config: &Config, options: &RunOptions) -> Result<std::ops::RangeInclusive<usize>> {
+    let start = match options.from {
+        Some(ref name) => range_bound(config, "--from", name)?,
+        None => 0,
+    };
+    let end = match options.until {
+        Some(ref name) => range_bound(config, "--until", name)?,
+        None => config.commands.len().saturating_sub(1),
+    };
+
+    if start > end {
+        return Err(eyre!(
+            "--until step '{}' comes before --from step '{}'",
+            config.commands[end].title,
+            config.commands[start].title
+        ));
+    }
+    Ok(start..=end)
+}
+
+/// Indices of the commands matching the range, `tags` and `step` filters, if provided
+fn filter_steps(config: &Config, options: &RunOptions) -> Result<Vec<usize>> {
+    let range = step_range(config, options)?;
+    let tagged: Vec<usize> = config
+        .commands
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| range.contains(index))
+        .filter(|(_, cmd)| {
+            options.tags.is_empty() || cmd.tags.iter().any(|tag| options.tags.contains(tag))
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    if tagged.is_empty() && !options.tags.is_empty() {
+        return Err(eyre!(
+            "No steps found tagged '{}'",
+            options.tags.join("', '")
+        ));
+    }
+
+    let Some(ref step_filter) = options.step else {
+        return Ok(tagged);
+    };
+
+    let matches: Vec<usize> = tagged
+        .into_iter()
+        .filter(|&index| {
+            title_matches(
+                &config.commands[index].title,
+                step_filter,
+                options.step_exact,
+            )
+        })
+        .collect();
+
+    if matches.is_empty() {
+        return Err(eyre!("No steps found matching '{}'", step_filter));
+    }
+
+    let pick = matches.len() > 1 && options.pick && ci::interactive();
+
+    if matches.len() > 1 && (pick || options.log_format == LogFormat::Pretty) {
+        println!(
+            "{} Found {} steps matching '{}':",
+            style("Info:").cyan().bold(),
+            matches.len(),
+            step_filter
+        );
+        for (i, &index) in matches.iter().enumerate() {
+            println!(
+                "  {}. {}",
+                i + 1,
+                style(&config.commands[index].title).cyan()
+            );
+        }
+        println!();
+    }
+
+    if pick {
+        return prompt_selection(matches, &mut io::stdin().lock());
+    }
+
+    Ok(matches)
+}
+
+/// Select the commands to run in order, applying the `step` filter if provided
+///
+/// Steps needed by the selected steps are included and ordered first, unless
+/// they fall outside the `from`/`until` range.
+pub fn select_commands<'a>(
+    config: &'a Config,
+    options: &RunOptions,
+) -> Result<Vec<&'a CommandEntry>> {
+    if let Some(ref id) = options.id {
+        return match config
+            .commands
+            .iter()
+            .find(|cmd| cmd.id.as_ref() == Some(id))
+        {
+            Some(cmd) => Ok(vec![cmd]),
+            None => Err(eyre!("No step with id '{}'", id)),
+        };
+    }
+
+    let selected = match options.picked {
+        Some(ref picked) => picked.clone(),
+        None => filter_steps(config, options)?,
+    };
+    let range = step_range(config, options)?;
+    let order: Vec<usize> = graph::execution_order(&config.commands, &selected)?
+        .into_iter()
+        .filter(|index| range.contains(index))
+        .collect();
+
+    if order.len() > selected.len() && options.log_format == LogFormat::Pretty {
+        let required: Vec<&str> = order
+            .iter()
+            .filter(|index| !selected.contains(index))
+            .map(|&index| config.commands[index].title.as_str())
+            .collect();
+        println!(
+            "{} Also running {} required steps: {}",
+            style("Info:").cyan().bold(),
+            required.len(),
+            required.join(", ")
+        );
+    }
+
+    Ok(order
+        .into_iter()
+        .map(|index| &config.commands[index])
+        .collect())
+}
+
+/// Ask which of the listed matches to run, re-prompting on invalid input
+fn prompt_selection<T: Copy, R: BufRead>(matches: Vec<T>, input: &mut R) -> Result<Vec<T>> {
+    loop {
+        print!("Select steps [1-{}, a for all]: ", matches.len());
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err(eyre!("No steps selected"));
+        }
+
+        match parse_selection(&line, matches.len()) {
+            Ok(indices) => {
+                println!();
+                return Ok(indices.into_iter().map(|i| matches[i]).collect());
+            }
+            Err(e) => println!("{} {}", style("Invalid selection:").yellow().bold(), e),
+        }
+    }
+}
+
+/// Ask whether to run a step marked `confirm`, failing if there is no terminal to ask on or in CI mode
+fn confirm_step(cmd_entry: &CommandEntry) -> Result<bool> {
+    if !ci::interactive() {
+        return Err(eyre!(
+            "Step '{}' needs confirmation, but there is no terminal (or this is CI); pass --yes to run it",
+            cmd_entry.title
+        ));
+    }
+    prompt_confirmation(cmd_entry, &mut io::stdin().lock())
+}
+
+/// Ask "Run this step? [y/N]" for `cmd_entry`, treating anything but yes as no
+fn prompt_confirmation<R: BufRead>(cmd_entry: &CommandEntry, input: &mut R) -> Result<bool> {
+    print!(
+        "{} {} Run this step? [y/N] ",
+        style(glyphs::WARNING).yellow().bold(),
+        style(&cmd_entry.title).bold()
+    );
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(matches!(
+        line.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Answers the questions a run asks about its steps, in place of prompts on the terminal
+pub trait Prompter: Send + Sync {
+    /// Whether to run, skip or abort before a step, with `interactive`
+    ///
+    /// This covers steps marked `confirm` too.
+    fn approve(&self, cmd_entry: &CommandEntry) -> Approval;
+
+    /// What to do about a failed step, with `recover`
+    fn recover(&self, cmd_entry: &CommandEntry) -> Recovery;
+}
+
+impl fmt::Debug for dyn Prompter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Prompter")
+    }
+}
+
+/// What to do with a step, as chosen in `--interactive` mode
+#[derive(Debug, PartialEq, Eq)]
+pub enum Approval {
+    Run,
+    Skip,
+    Abort,
+}
+
+/// Ask whether to run, skip or abort at a step, showing its command on request
+///
+/// Re-prompts on unknown input, and aborts if the input ends.
+fn prompt_approval<R: BufRead>(
+    cmd_entry: &CommandEntry,
+    exec: &ExecOptions,
+    input: &mut R,
+) -> Result<Approval> {
+    loop {
+        print!(
+            "{} {} [r]un, [s]kip, show [c]ommand, [a]bort? ",
+            style(glyphs::ARROW).cyan().bold(),
+            style(exec.label(cmd_entry)).bold()
+        );
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(Approval::Abort);
+        }
+
+        match line.trim().to_ascii_lowercase().as_str() {
+            "r" | "run" | "" => return Ok(Approval::Run),
+            "s" | "skip" => return Ok(Approval::Skip),
+            "a" | "abort" => return Ok(Approval::Abort),
+            "c" | "command" => {
+                println!(
+                    "    {} {}",
+                    style("$").dim(),
+                    redact::redact(&cmd_entry.display_command())
+                );
+            }
+            other => println!(
+                "{} '{}' is not one of r, s, c or a",
+                style("Invalid choice:").yellow().bold(),
+                other
+            ),
+        }
+    }
+}
+
+/// Parse a selection like `1,3`, `1-2` or `a` into sorted zero-based indices
+fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>, String> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("a") || input.eq_ignore_ascii_case("all") {
+        return Ok((0..count).collect());
+    }
+
+    let parse_number = |s: &str| -> Result<usize, String> {
+        match s.trim().parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => Ok(n - 1),
+            _ => Err(format!(
+                "'{}' is not a number between 1 and {}",
+                s.trim(),
+                count
+            )),
+        }
+    };
+
+    let mut indices = Vec::new();
+    for part in input.split([',', ' ']).filter(|p| !p.trim().is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse_number(start)?, parse_number(end)?);
+                if start > end {
+                    return Err(format!("'{}' is not an increasing range", part));
+                }
+                indices.extend(start..=end);
+            }
+            None => indices.push(parse_number(part)?),
+        }
+    }
+
+    if indices.is_empty() {
+        return Err("select at least one step".to_string());
+    }
+
+    indices.sort_unstable();
+    indices.dedup();
+    Ok(indices)
+}
+
+/// Why a step should be skipped before running, based on its platform and conditions
+pub fn skip_reason(cmd_entry: &CommandEntry, exec: &ExecOptions) -> Option<String> {
+    if let Some(reason) = cmd_entry.platform_mismatch() {
+        return Some(reason);
+    }
+    if let Some(ref condition) = cmd_entry.skip_if
+        && exec.executor().condition(cmd_entry, condition)
+    {
+        return Some("skip_if succeeded".to_string());
+    }
+    if let Some(ref condition) = cmd_entry.only_if
+        && !exec.executor().condition(cmd_entry, condition)
+    {
+        return Some("only_if failed".to_string());
+    }
+    None
+}
+
+/// Run a single command and record its outcome, timed from `run_start`
+fn run_step(cmd_entry: &CommandEntry, exec: &ExecOptions, run_start: Instant) -> StepResult {
+    let step_timer = Instant::now();
+    let (duration, failure) = match exec.executor().run(cmd_entry, exec) {
+        Ok(duration) => (duration, None),
+        Err(failure) => (step_timer.elapsed(), Some(failure)),
+    };
+    let (error, output_tail) = match failure {
+        Some(failure) => (Some(failure.message), failure.output_tail),
+        None => (None, Vec::new()),
+    };
+
+    StepResult {
+        title: cmd_entry.title.clone(),
+        duration,
+        success: error.is_none(),
+        allowed_failure: error.is_some() && cmd_entry.allow_failure && !interrupt::requested(),
+        error,
+        output_tail,
+        skip_reason: None,
+        satisfied: false,
+        started: step_timer.duration_since(run_start),
+        project: cmd_entry.project.clone(),
+    }
+}
+
+/// What to do after a step fails, as chosen at the failure prompt
+#[derive(Debug, PartialEq, Eq)]
+pub enum Recovery {
+    Retry,
+    Skip,
+    Shell,
+    Abort,
+}
+
+/// Run a step, asking what to do whenever it fails until it succeeds or the user moves on
+///
+/// Skipping a failed step lets the run continue as if it were allowed to fail.
+/// Returns the result and whether the user chose to abort the run.
+fn run_step_with_recovery(
+    cmd_entry: &CommandEntry,
+    exec: &ExecOptions,
+    run_start: Instant,
+    prompter: Option<&dyn Prompter>,
+) -> Result<(StepResult, bool)> {
+    loop {
+        let mut result = run_step(cmd_entry, exec, run_start);
+        if !result.is_failure() || interrupt::requested() {
+            return Ok((result, false));
+        }
+
+        loop {
+            let recovery = match prompter {
+                Some(prompter) => prompter.recover(cmd_entry),
+                None => prompt_recovery(cmd_entry, &mut io::stdin().lock())?,
+            };
+            match recovery {
+                Recovery::Retry => break,
+                Recovery::Skip => {
+                    result.allowed_failure = true;
+                    return Ok((result, false));
+                }
+                Recovery::Shell => {
+                    println!(
+                        "{} Opening a shell; exit it to choose again",
+                        style("Info:").cyan().bold()
+                    );
+                    if let Err(e) = runner::open_shell(cmd_entry) {
+                        println!("{} {}", style("Error:").red().bold(), e);
+                    }
+                }
+                Recovery::Abort => return Ok((result, true)),
+            }
+        }
+    }
+}
+
+/// Ask what to do about a failed step, re-prompting on unknown input and aborting at its end
+fn prompt_recovery<R: BufRead>(cmd_entry: &CommandEntry, input: &mut R) -> Result<Recovery> {
+    loop {
+        print!(
+            "{} {} failed: [r]etry, [s]kip, open a [S]hell, [a]bort? ",
+            style(glyphs::FAILURE).red().bold(),
+            style(&cmd_entry.title).bold()
+        );
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(Recovery::Abort);
+        }
+
+        match line.trim() {
+            "r" | "retry" => return Ok(Recovery::Retry),
+            "s" | "skip" => return Ok(Recovery::Skip),
+            "S" | "shell" => return Ok(Recovery::Shell),
+            "a" | "abort" | "" => return Ok(Recovery::Abort),
+            other => println!(
+                "{} '{}' is not one of r, s, S or a",
+                style("Invalid choice:").yellow().bold(),
+                other
+            ),
+        }
+    }
+}
+
+/// Indices of the steps in `commands` that each step needs
+///
+/// Needed steps that were not selected for the run are left out.
+fn needed_steps(commands: &[&CommandEntry]) -> Vec<Vec<usize>> {
+    commands
+        .iter()
+        .map(|cmd_entry| {
+            commands
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| cmd_entry.needs.iter().any(|need| other.is_named(need)))
+                .map(|(index, _)| index)
+                .collect()
+        })
+        .collect()
+}
+
+/// Run commands, each with its own options, concurrently with at most `jobs` running at once
+///
+/// A command starts only once the commands it `needs` (indices into `commands`)
+/// have finished, and is skipped if any of them failed. Results are returned
+/// in the same order as `commands`.
+fn run_parallel(
+    commands: &[(&CommandEntry, ExecOptions)],
+    needs: &[Vec<usize>],
+    jobs: usize,
+    run_start: Instant,
+) -> Vec<StepResult> {
+    struct Schedule {
+        started: Vec<bool>,
+        results: Vec<Option<StepResult>>,
+    }
+
+    let schedule = Mutex::new(Schedule {
+        started: vec![false; commands.len()],
+        results: vec![None; commands.len()],
+    });
+    let finished = Condvar::new();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(commands.len()) {
+            scope.spawn(|| {
+                loop {
+                    let mut state = schedule.lock().unwrap();
+                    let index = loop {
+                        let ready = (0..commands.len()).find(|&index| {
+                            !state.started[index]
+                                && needs[index].iter().all(|&dep| state.results[dep].is_some())
+                        });
+                        match ready {
+                            Some(index) => break Some(index),
+                            None if state.started.iter().all(|&started| started) => break None,
+                            None => state = finished.wait(state).unwrap(),
+                        }
+                    };
+                    let Some(index) = index else {
+                        break;
+                    };
+                    state.started[index] = true;
+
+                    let (cmd_entry, exec) = &commands[index];
+                    let needs_failed = needs[index].iter().any(|&dep| {
+                        state.results[dep]
+                            .as_ref()
+                            .is_some_and(StepResult::blocks_dependents)
+                    });
+                    if needs_failed {
+                        runner::print_command_skipped(cmd_entry, NEEDS_FAILED, exec);
+                        state.results[index] = Some(StepResult::skipped(
+                            cmd_entry,
+                            NEEDS_FAILED,
+                            run_start.elapsed(),
+                        ));
+                        finished.notify_all();
+                        continue;
+                    }
+                    drop(state);
+
+                    let exec = ExecOptions {
+                        concurrent: true,
+                        ..exec.clone()
+                    };
+                    let result = run_step(cmd_entry, &exec, run_start);
+                    schedule.lock().unwrap().results[index] = Some(result);
+                    finished.notify_all();
+                }
+            });
+        }
+    });
+
+    schedule
+        .into_inner()
+        .unwrap()
+        .results
+        .into_iter()
+        .map(|result| result.expect("every command was run"))
+        .collect()
+}
+
+/// Run the commands of a configuration
+///
+/// Commands run sequentially, except that consecutive `parallel` commands
+/// run concurrently as a group when `jobs` allows it, each once the commands
+/// it `needs` have finished. With `continue_on_error`, commands that need a
+/// failed command are skipped. Step failures are
+/// recorded in the returned summary rather than returned as errors; an `Err`
+/// means the run could not start at all.
+pub fn run(config: &Config, options: RunOptions) -> Result<RunSummary> {
+    let timer = Instant::now();
+    let uuid = options
+        .uuid
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    // Steps get the run's id, unless their own `env` sets the variable
+    let selected: Vec<CommandEntry> = select_commands(config, &options)?
+        .into_iter()
+        .map(|cmd_entry| {
+            let mut cmd_entry = cmd_entry.clone();
+            cmd_entry
+                .env
+                .entry(RUN_ID_VAR.to_string())
+                .or_insert_with(|| uuid.clone());
+            cmd_entry
+        })
+        .collect();
+    let commands: Vec<&CommandEntry> = selected.iter().collect();
+    let needs = needed_steps(&commands);
+    let mut blocked = vec![false; commands.len()];
+    let mut cache = options.incremental.then(|| Cache::load(&config.base_dir));
+    let mut fingerprints: Option<Cache> = None;
+    let log_dir = options
+        .log_dir
+        .as_deref()
+        .map(|dir| dir.join(&uuid))
+        .filter(|dir| match fs::create_dir_all(dir) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to create log directory '{}': {}", dir.display(), e);
+                false
+            }
+        });
+    let exec = ExecOptions {
+        verbose: options.verbose,
+        log_format: options.log_format,
+        concurrent: options.detached,
+        quiet: options.quiet,
+        stderr_only: options.stderr_only,
+        log_dir: log_dir.clone(),
+        ci: options.ci,
+        timestamps: options.timestamps,
+        run_id: uuid.clone(),
+        executor: options.executor.clone(),
+        event_handler: options.event_handler.clone(),
+        ..Default::default()
+    };
+
+    let mut summary = RunSummary {
+        uuid,
+        log_dir,
+        total: commands.len(),
+        ..Default::default()
+    };
+    let mut failures = 0;
+    let mut next = 0;
+
+    if options.log_format == LogFormat::Pretty {
+        print_estimate(&commands, &options.typical);
+    }
+
+    while next < commands.len() {
+        let group_len = if commands[next].parallel && options.jobs > 1 {
+            commands[next..]
+                .iter()
+                .take_while(|cmd| cmd.parallel)
+                .count()
+        } else {
+            1
+        };
+        let group = &commands[next..next + group_len];
+        let group_start = next;
+        next += group_len;
+
+        // Resolve skipped and cached steps first so only the rest are executed
+        let mut results: Vec<Option<StepResult>> = vec![None; group.len()];
+        let mut pending = Vec::new();
+        let mut aborted = false;
+        for (slot, cmd_entry) in group.iter().enumerate() {
+            let started = timer.elapsed();
+            let exec = ExecOptions {
+                typical: options.typical.get(&cmd_entry.title).copied(),
+                ..exec.at(Progress {
+                    current: group_start + slot + 1,
+                    total: commands.len(),
+                })
+            };
+            if needs[group_start + slot].iter().any(|&dep| blocked[dep]) {
+                runner::print_command_skipped(cmd_entry, NEEDS_FAILED, &exec);
+                blocked[group_start + slot] = true;
+                results[slot] = Some(StepResult::skipped(cmd_entry, NEEDS_FAILED, started));
+                continue;
+            }
+            if options.completed.contains(&cmd_entry.title) {
+                runner::print_command_skipped(cmd_entry, state::RESUMED, &exec);
+                results[slot] = Some(StepResult::skipped(cmd_entry, state::RESUMED, started));
+                continue;
+            }
+
+            if let Some(reason) = skip_reason(cmd_entry, &exec) {
+                runner::print_command_skipped(cmd_entry, &reason, &exec);
+                results[slot] = Some(StepResult::skipped(cmd_entry, &reason, started));
+                continue;
+            }
+
+            if let Some(ref check) = cmd_entry.check {
+                let check_timer = Instant::now();
+                if exec.executor().condition(cmd_entry, check) {
+                    let elapsed = check_timer.elapsed();
+                    runner::print_command_satisfied(cmd_entry, elapsed, &exec);
+                    results[slot] = Some(StepResult::satisfied(cmd_entry, started, elapsed));
+                    continue;
+                }
+            }
+
+            let source_hash = if cmd_entry.sources.is_empty() {
+                None
+            } else {
+                let hash = cache::hash_sources(cmd_entry)?;
+                let fingerprints =
+                    fingerprints.get_or_insert_with(|| Cache::load_fingerprints(&config.base_dir));
+                if fingerprints.is_fresh(&cmd_entry.title, &hash)
+                    && cache::outputs_exist(cmd_entry)?
+                {
+                    runner::print_command_skipped(cmd_entry, UP_TO_DATE, &exec);
+                    results[slot] = Some(StepResult::skipped(cmd_entry, UP_TO_DATE, started));
+                    continue;
+                }
+                Some(hash)
+            };
+
+            let input_hash = match cache {
+                Some(ref cache) if !cmd_entry.inputs.is_empty() => {
+                    let hash = cache::hash_inputs(cmd_entry)?;
+                    if cache.is_fresh(&cmd_entry.title, &hash) {
+                        runner::print_command_skipped(cmd_entry, "cached", &exec);
+                        results[slot] = Some(StepResult::skipped(cmd_entry, "cached", started));
+                        continue;
+                    }
+                    Some(hash)
+                }
+                _ => None,
+            };
+
+            if options.interactive {
+                let approval = match options.prompter {
+                    Some(ref prompter) => prompter.approve(cmd_entry),
+                    None => prompt_approval(cmd_entry, &exec, &mut io::stdin().lock())?,
+                };
+                match approval {
+                    Approval::Run => {}
+                    Approval::Skip => {
+                        runner::print_command_skipped(cmd_entry, DECLINED, &exec);
+                        results[slot] = Some(StepResult::skipped(cmd_entry, DECLINED, started));
+                        continue;
+                    }
+                    Approval::Abort => {
+                        aborted = true;
+                        break;
+                    }
+                }
+            } else if cmd_entry.confirm && !options.yes && !confirm_step(cmd_entry)? {
+                runner::print_command_skipped(cmd_entry, DECLINED, &exec);
+                results[slot] = Some(StepResult::skipped(cmd_entry, DECLINED, started));
+                continue;
+            }
+            pending.push((slot, *cmd_entry, exec, input_hash, source_hash));
+        }
+
+        // Keep what already ran, so the state and run record let `--resume` skip it
+        if aborted {
+            summary.steps.extend(results.into_iter().flatten());
+            summary.interrupted = true;
+            break;
+        }
+
+        let to_run: Vec<(&CommandEntry, ExecOptions)> = pending
+            .iter()
+            .map(|(_, cmd, exec, _, _)| (*cmd, exec.clone()))
+            .collect();
+        let outcomes = if to_run.len() > 1 {
+            let to_run_needs: Vec<Vec<usize>> = pending
+                .iter()
+                .map(|(slot, ..)| {
+                    needs[group_start + slot]
+                        .iter()
+                        .filter_map(|&dep| {
+                            pending
+                                .iter()
+                                .position(|(other, ..)| group_start + other == dep)
+                        })
+                        .collect()
+                })
+                .collect();
+            run_parallel(&to_run, &to_run_needs, options.jobs, timer)
+                .into_iter()
+                .map(|result| (result, false))
+                .collect()
+        } else {
+            let recover = options.recover
+                && !options.continue_on_error
+                && (options.prompter.is_some()
+                    || options.log_format == LogFormat::Pretty && ci::interactive());
+            to_run
+                .iter()
+                .map(|(cmd, exec)| {
+                    if recover {
+                        run_step_with_recovery(cmd, exec, timer, options.prompter.as_deref())
+                    } else {
+                        Ok((run_step(cmd, exec, timer), false))
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let mut group_failed = false;
+        for ((slot, cmd_entry, _, input_hash, source_hash), (result, abort)) in
+            pending.into_iter().zip(outcomes)
+        {
+            aborted |= abort;
+            if result.success
+                && let (Some(cache), Some(hash)) = (cache.as_mut(), input_hash)
+                && let Err(e) = cache.record(&cmd_entry.title, hash)
+            {
+                tracing::warn!("{}", e);
+            }
+            if result.success
+                && let (Some(fingerprints), Some(hash)) = (fingerprints.as_mut(), source_hash)
+                && let Err(e) = fingerprints.record(&cmd_entry.title, hash)
+            {
+                tracing::warn!("{}", e);
+            }
+
+            if result.is_failure() {
+                group_failed = true;
+                failures += 1;
+            }
+            blocked[group_start + slot] = result.blocks_dependents();
+            results[slot] = Some(result);
+        }
+        summary.steps.extend(results.into_iter().flatten());
+
+        if aborted || interrupt::requested() {
+            summary.interrupted = true;
+            break;
+        }
+        if !group_failed {
+            continue;
+        }
+        if !options.continue_on_error {
+            break;
+        }
+
+        if options.bail_after > 0 && failures >= options.bail_after {
+            print_bail(&summary, options.log_format);
+            for (offset, skipped) in commands[next..].iter().enumerate() {
+                let exec = exec.at(Progress {
+                    current: next + offset + 1,
+                    total: commands.len(),
+                });
+                runner::print_command_skipped(skipped, BAILED, &exec);
+                summary
+                    .steps
+                    .push(StepResult::skipped(skipped, BAILED, timer.elapsed()));
+            }
+            break;
+        }
+    }
+
+    summary.duration = timer.elapsed();
+    Ok(summary)
+}
+
+/// Runs a configuration with a fixed set of options, for tools that embed getset
+///
+/// ```no_run
+/// use getset::{RunOptions, Runner};
+///
+/// let mut options = RunOptions::default();
+/// options.quiet = true;
+/// let summary = Runner::from_file("getset.toml")?
+///     .with_options(options)
+///     .run()?;
+/// assert!(summary.success());
+/// # Ok::<(), color_eyre::Report>(())
+/// ```
+///
+/// Unlike `getset up`, a runner asks no `[[prompts]]` and sends no telemetry;
+/// pass answers with [`Runner::with_env`] instead.
+#[derive(Debug)]
+pub struct Runner {
+    config: Config,
+    options: RunOptions,
+}
+
+impl Runner {
+    /// Run `config` with the default options
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            options: RunOptions::default(),
+        }
+    }
+
+    /// Load a config file, with its includes and local override, to run with the default options
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Config::from_file(path)?))
+    }
+
+    /// Run with `options` instead of the defaults
+    pub fn with_options(mut self, options: RunOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Send the events of this runner's runs to `handler`
+    ///
+    /// This sets [`RunOptions::event_handler`], so call it after [`Runner::with_options`].
+    pub fn with_event_handler(mut self, handler: impl EventHandler + 'static) -> Self {
+        self.options.event_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Run the steps' commands and conditions with `executor` instead of as processes
+    ///
+    /// This sets [`RunOptions::executor`], so call it after [`Runner::with_options`].
+    pub fn with_executor(mut self, executor: impl Executor + 'static) -> Self {
+        self.options.executor = Some(Arc::new(executor));
+        self
+    }
+
+    /// Export `vars` to every step, without overriding a step's own `env`
+    pub fn with_env(mut self, vars: &BTreeMap<String, String>) -> Self {
+        self.config.export_env(vars);
+        self
+    }
+
+    /// The configuration that will run
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The options each run uses
+    pub fn options(&self) -> &RunOptions {
+        &self.options
+    }
+
+    /// Run the selected steps; see [`run`] for how failures are reported
+    ///
+    /// Each call is a separate run, with a new [`RunSummary::uuid`] unless the
+    /// options set one.
+    pub fn run(&self) -> Result<RunSummary> {
+        let summary = run(&self.config, self.options.clone())?;
+        let error = match summary.interruption() {
+            Some(interrupted) => Some(interrupted.to_string()),
+            None => summary.failures().find_map(|step| step.error.clone()),
+        };
+        let emitter = Emitter {
+            log_format: self.options.log_format,
+            run_id: &summary.uuid,
+            handler: self.options.event_handler.as_deref(),
+        };
+        events::run_finished(emitter, summary.duration, error.as_deref());
+        Ok(summary)
+    }
+
+    /// Start the run on a background thread, returning a handle that can cancel it
+    ///
+    /// Any earlier interruption is cleared first. Cancelling stops every run in
+    /// the process, so only one should be in progress at a time.
+    pub fn spawn(self) -> RunHandle {
+        interrupt::reset();
+        RunHandle {
+            thread: Some(thread::spawn(move || self.run())),
+        }
+    }
+}
+
+/// A run started with [`Runner::spawn`]
+///
+/// Dropping the handle cancels the run and waits for it to stop, so no step's
+/// process outlives it.
+#[derive(Debug)]
+pub struct RunHandle {
+    thread: Option<thread::JoinHandle<Result<RunSummary>>>,
+}
+
+impl RunHandle {
+    /// Stop the run, as Ctrl-C would
+    ///
+    /// The running step's command and everything it started are sent SIGTERM,
+    /// then killed if still running a few seconds later; remaining steps are
+    /// not started. [`RunHandle::wait`] then returns a summary marked
+    /// `interrupted`.
+    pub fn cancel(&self) {
+        interrupt::cancel();
+    }
+
+    /// Whether the run is over, so [`RunHandle::wait`] won't block
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+    }
+
+    /// Wait for the run to end, returning its summary
+    pub fn wait(mut self) -> Result<RunSummary> {
+        let thread = self.thread.take().expect("only taken by wait or drop");
+        thread
+            .join()
+            .unwrap_or_else(|_| Err(eyre!("The run panicked")))
+    }
+}
+
+impl Drop for RunHandle {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            if !thread.is_finished() {
+                self.cancel();
+            }
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Print how long the run should take, judging by previous runs of its steps
+fn print_estimate(commands: &[&CommandEntry], typical: &BTreeMap<String, Duration>) {
+    let known: Vec<Duration> = commands
+        .iter()
+        .filter_map(|cmd| typical.get(&cmd.title).copied())
+        .collect();
+    if known.is_empty() {
+        return;
+    }
+
+    let unknown = commands.len() - known.len();
+    let note = match unknown {
+        0 => String::new(),
+        1 => " plus 1 step with no previous runs".to_string(),
+        n => format!(" plus {} steps with no previous runs", n),
+    };
+    println!(
+        "{} Estimated time {}{}",
+        style("Info:").cyan().bold(),
+        timings::estimate(known.iter().sum()),
+        note
+    );
+}
+
+/// Print the failures that made a continue-on-error run give up
+fn print_bail(summary: &RunSummary, log_format: LogFormat) {
+    if log_format != LogFormat::Pretty {
+        return;
+    }
+
+    let titles: Vec<&str> = summary.failures().map(|step| step.title.as_str()).collect();
+    println!(
+        "{} Bailing after {} failures: {}",
+        style("Info:").cyan().bold(),
+        titles.len(),
+        titles.join(", ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with(commands: &[(&str, &str)]) -> Config {
+        Config {
+            commands: commands
+                .iter()
+                .map(|(title, command)| CommandEntry {
+                    title: title.to_string(),
+                    command: Some(command.to_string()),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_returns_summary_of_executed_commands() {
+        let config = config_with(&[("First", "true"), ("Second", "echo second")]);
+
+        let summary = crate::run(&config, RunOptions::default()).expect("run should start");
+
+        let titles: Vec<&str> = summary.steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["First", "Second"]);
+        assert!(summary.steps.iter().all(|s| s.success && s.error.is_none()));
+        assert!(summary.success());
+        assert!(summary.duration >= summary.steps[0].duration);
+    }
+
+    #[test]
+    fn test_run_gives_steps_its_uuid() {
+        let config = config_with(&[("Check id", "test \"$GETSET_RUN_ID\" = run-1")]);
+        let options = RunOptions {
+            uuid: Some("run-1".to_string()),
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        assert!(summary.success());
+        assert_eq!(summary.uuid, "run-1");
+        let generated = run(&config_with(&[("Any", "true")]), RunOptions::default()).unwrap();
+        assert_eq!(generated.uuid.len(), 36);
+    }
+
+    #[test]
+    fn test_run_without_steps_succeeds() {
+        let summary = run(&config_with(&[]), RunOptions::default()).expect("run should start");
+
+        assert!(summary.steps.is_empty());
+        assert!(summary.success());
+    }
+
+    #[test]
+    fn test_select_commands_errors_without_doubled_prefix() {
+        let options = RunOptions {
+            tags: vec!["missing".to_string()],
+            ..Default::default()
+        };
+
+        let error = select_commands(&config_with(&[("Any", "true")]), &options).unwrap_err();
+
+        assert_eq!(error.to_string(), "No steps found tagged 'missing'");
+    }
+
+    #[test]
+    fn test_runner_runs_config_with_its_options_and_env() {
+        let options = RunOptions {
+            step: Some("greet".to_string()),
+            quiet: true,
+            ..Default::default()
+        };
+        let runner = Runner::new(config_with(&[
+            ("Greet", "test \"$GREETING\" = hello"),
+            ("Other", "exit 1"),
+        ]))
+        .with_options(options)
+        .with_env(&BTreeMap::from([(
+            "GREETING".to_string(),
+            "hello".to_string(),
+        )]));
+
+        let summary = runner.run().expect("run should start");
+
+        assert!(summary.success());
+        assert_eq!(summary.steps.len(), 1);
+        assert_eq!(runner.config().commands.len(), 2);
+    }
+
+    /// Executor that records the steps it is asked to run, failing those titled `Broken`
+    #[derive(Default)]
+    struct Recorder {
+        ran: Mutex<Vec<String>>,
+        conditions: Mutex<Vec<String>>,
+    }
+
+    impl Executor for Recorder {
+        fn run(
+            &self,
+            cmd_entry: &CommandEntry,
+            _options: &ExecOptions,
+        ) -> Result<Duration, runner::Failure> {
+            self.ran.lock().unwrap().push(cmd_entry.title.clone());
+            if cmd_entry.title == "Broken" {
+                return Err(runner::Failure::new("Step 'Broken' failed"));
+            }
+            Ok(Duration::from_millis(5))
+        }
+
+        fn condition(&self, _cmd_entry: &CommandEntry, condition: &str) -> bool {
+            self.conditions.lock().unwrap().push(condition.to_string());
+            condition == "satisfied"
+        }
+    }
+
+    #[test]
+    fn test_run_uses_the_given_executor() {
+        let mut config = config_with(&[
+            ("Install", "rm -rf /"),
+            ("Already done", "exit 1"),
+            ("Optional", "exit 1"),
+            ("Broken", "exit 1"),
+            ("Never", "true"),
+        ]);
+        config.commands[1].check = Some("satisfied".to_string());
+        config.commands[2].only_if = Some("missing".to_string());
+        let recorder = Arc::new(Recorder::default());
+        let options = RunOptions {
+            executor: Some(recorder.clone()),
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        assert_eq!(*recorder.ran.lock().unwrap(), ["Install", "Broken"]);
+        assert_eq!(
+            *recorder.conditions.lock().unwrap(),
+            ["satisfied", "missing"]
+        );
+        assert!(summary.steps[1].satisfied);
+        assert_eq!(
+            summary.steps[2].skip_reason.as_deref(),
+            Some("only_if failed")
+        );
+        assert_eq!(
+            summary.failures().next().unwrap().error.as_deref(),
+            Some("Step 'Broken' failed")
+        );
+        assert_eq!(summary.steps[0].duration, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_event_handler_hears_only_its_own_runner() {
+        let (events, received) = std::sync::mpsc::channel();
+        let options = RunOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        let mine = Runner::new(config_with(&[("Mine", "true")]))
+            .with_options(options.clone())
+            .with_event_handler(events);
+        let other = Runner::new(config_with(&[("Other", "true")])).with_options(options);
+
+        other.run().expect("run should start");
+        mine.run().expect("run should start");
+        other.run().expect("run should start");
+        drop(mine);
+
+        let received: Vec<_> = received.iter().collect();
+        assert!(matches!(
+            &received[0],
+            events::Event::StepStarted { title } if title == "Mine"
+        ));
+        assert!(matches!(
+            received.last(),
+            Some(events::Event::RunFinished { error: None, .. })
+        ));
+        assert!(received.iter().all(|event| match event {
+            events::Event::StepStarted { title }
+            | events::Event::StepFinished { title, .. }
+            | events::Event::OutputChunk { title, .. } => title == "Mine",
+            events::Event::RunFinished { .. } => true,
+        }));
+    }
+
+    /// Prompter that declines `Declined`, aborts at `Abort` and skips failed steps
+    struct Answers;
+
+    impl Prompter for Answers {
+        fn approve(&self, cmd_entry: &CommandEntry) -> Approval {
+            match cmd_entry.title.as_str() {
+                "Declined" => Approval::Skip,
+                "Abort" => Approval::Abort,
+                _ => Approval::Run,
+            }
+        }
+
+        fn recover(&self, _cmd_entry: &CommandEntry) -> Recovery {
+            Recovery::Skip
+        }
+    }
+
+    #[test]
+    fn test_run_asks_the_given_prompter() {
+        let config = config_with(&[
+            ("Install", "true"),
+            ("Declined", "true"),
+            ("Broken", "exit 1"),
+            ("After", "true"),
+        ]);
+        let recorder = Arc::new(Recorder::default());
+        let options = RunOptions {
+            interactive: true,
+            recover: true,
+            prompter: Some(Arc::new(Answers)),
+            executor: Some(recorder.clone()),
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        assert_eq!(
+            *recorder.ran.lock().unwrap(),
+            ["Install", "Broken", "After"]
+        );
+        assert_eq!(summary.steps[1].skip_reason.as_deref(), Some(DECLINED));
+        assert!(summary.steps[2].allowed_failure);
+        assert!(summary.success());
+    }
+
+    #[test]
+    fn test_aborting_at_a_prompt_keeps_the_steps_that_ran() {
+        let config = config_with(&[("Install", "true"), ("Abort", "true"), ("Never", "true")]);
+        let recorder = Arc::new(Recorder::default());
+        let options = RunOptions {
+            interactive: true,
+            prompter: Some(Arc::new(Answers)),
+            executor: Some(recorder.clone()),
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("an aborted run still has a summary");
+
+        assert_eq!(*recorder.ran.lock().unwrap(), ["Install"]);
+        assert_eq!(summary.steps.len(), 1);
+        assert!(summary.interrupted);
+        assert!(summary.stopped_early());
+    }
+
+    #[test]
+    fn test_run_stops_at_first_failure() {
+        let config = config_with(&[("Ok", "true"), ("Broken", "exit 4"), ("Never", "true")]);
+
+        let summary = run(&config, RunOptions::default()).expect("run should start");
+
+        assert_eq!(summary.steps.len(), 2);
+        assert!(!summary.success());
+        let failure = summary.failures().next().expect("one failure");
+        assert_eq!(failure.title, "Broken");
+        assert!(failure.error.as_ref().unwrap().contains("exit code 4"));
+        assert!(summary.stopped_early());
+    }
+
+    #[test]
+    fn test_run_continues_past_allowed_failure() {
+        let mut config = config_with(&[("Optional", "exit 1"), ("After", "true")]);
+        config.commands[0].allow_failure = true;
+
+        let summary = run(&config, RunOptions::default()).expect("run should start");
+
+        assert_eq!(summary.steps.len(), 2);
+        assert!(summary.success());
+        assert_eq!(summary.failures().count(), 0);
+        let allowed: Vec<&str> = summary
+            .allowed_failures()
+            .map(|s| s.title.as_str())
+            .collect();
+        assert_eq!(allowed, ["Optional"]);
+    }
+
+    #[test]
+    fn test_run_continue_on_error_runs_everything() {
+        let config = config_with(&[("Broken", "false"), ("After", "true")]);
+        let options = RunOptions {
+            continue_on_error: true,
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        assert_eq!(summary.steps.len(), 2);
+        assert!(!summary.steps[0].success);
+        assert!(summary.steps[1].success);
+        assert_eq!(summary.failures().count(), 1);
+        assert!(!summary.stopped_early());
+    }
+
+    #[test]
+    fn test_run_summary_counts_progress_of_selected_steps() {
+        let config = config_with(&[("First", "true"), ("Broken", "false"), ("Never", "true")]);
+
+        let summary = run(&config, RunOptions::default()).expect("run should start");
+
+        assert_eq!(
+            summary.progress(),
+            Progress {
+                current: 1,
+                total: 3
+            }
+        );
+        assert_eq!(summary.progress().to_string(), "[1/3]");
+    }
+
+    #[test]
+    fn test_run_parallel_steps_concurrently() {
+        let mut config = config_with(&[
+            ("Sleep 1", "sleep 0.4"),
+            ("Sleep 2", "sleep 0.4"),
+            ("Sleep 3", "sleep 0.4"),
+            ("After", "true"),
+        ]);
+        for cmd in &mut config.commands[..3] {
+            cmd.parallel = true;
+        }
+        let options = RunOptions {
+            jobs: 3,
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        let titles: Vec<&str> = summary.steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Sleep 1", "Sleep 2", "Sleep 3", "After"]);
+        assert!(summary.success());
+        assert!(
+            summary.duration < Duration::from_millis(1000),
+            "parallel steps should overlap, took {:?}",
+            summary.duration
+        );
+    }
+
+    #[test]
+    fn test_run_parallel_group_failure_stops_run() {
+        let mut config = config_with(&[("Ok", "true"), ("Broken", "exit 3"), ("After", "true")]);
+        config.commands[0].parallel = true;
+        config.commands[1].parallel = true;
+        let options = RunOptions {
+            jobs: 2,
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        assert_eq!(
+            summary.steps.len(),
+            2,
+            "the whole group runs, nothing after"
+        );
+        assert!(summary.steps[0].success);
+        assert!(!summary.steps[1].success);
+    }
+
+    #[test]
+    fn test_run_parallel_respects_single_job() {
+        let mut config = config_with(&[("Sleep 1", "sleep 0.3"), ("Sleep 2", "sleep 0.3")]);
+        for cmd in &mut config.commands {
+            cmd.parallel = true;
+        }
+        let options = RunOptions {
+            jobs: 1,
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        assert!(summary.duration >= Duration::from_millis(600));
+    }
+
+    #[test]
+    fn test_run_parallel_waits_for_needed_steps() {
+        let dir = std::env::temp_dir().join(format!("getset-needs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let marker = dir.join("built");
+        let mut config = config_with(&[
+            ("Build", &format!("sleep 0.3 && touch {}", marker.display())),
+            ("Test", &format!("test -f {}", marker.display())),
+            ("Lint", "true"),
+        ]);
+        for cmd in &mut config.commands {
+            cmd.parallel = true;
+        }
+        config.commands[1].needs = vec!["Build".to_string()];
+        let options = RunOptions {
+            jobs: 3,
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(summary.success(), "Test ran before Build finished");
+        assert!(summary.steps[1].started >= summary.steps[0].duration);
+        assert!(summary.steps[2].started < summary.steps[0].duration);
+    }
+
+    #[test]
+    fn test_run_continue_on_error_skips_steps_needing_failed_ones() {
+        let mut config = config_with(&[
+            ("Broken", "false"),
+            ("Dependent", "true"),
+            ("Indirect", "true"),
+            ("Independent", "true"),
+        ]);
+        config.commands[1].needs = vec!["Broken".to_string()];
+        config.commands[2].needs = vec!["Dependent".to_string()];
+        let options = RunOptions {
+            continue_on_error: true,
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        let reasons: Vec<Option<&str>> = summary
+            .steps
+            .iter()
+            .map(|s| s.skip_reason.as_deref())
+            .collect();
+        assert_eq!(
+            reasons,
+            [None, Some(NEEDS_FAILED), Some(NEEDS_FAILED), None]
+        );
+        assert!(summary.steps[3].success);
+        assert_eq!(summary.failures().count(), 1);
+    }
+
+    #[test]
+    fn test_run_parallel_skips_steps_needing_failed_ones() {
+        let mut config = config_with(&[("Broken", "sleep 0.1; false"), ("Dependent", "true")]);
+        for cmd in &mut config.commands {
+            cmd.parallel = true;
+        }
+        config.commands[1].needs = vec!["Broken".to_string()];
+        let options = RunOptions {
+            jobs: 2,
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        assert!(!summary.steps[0].success);
+        assert_eq!(summary.steps[1].skip_reason.as_deref(), Some(NEEDS_FAILED));
+    }
+
+    #[test]
+    fn test_run_skips_steps_for_other_platforms() {
+        let mut config = config_with(&[("Elsewhere", "exit 1"), ("Here", "true")]);
+        config.commands[0].os = vec!["plan9".to_string()];
+        config.commands[1].os = vec![std::env::consts::OS.to_string()];
+
+        let summary = run(&config, RunOptions::default()).expect("run should start");
+
+        assert!(summary.success());
+        assert_eq!(summary.steps[0].skip_reason.as_deref(), Some("plan9 only"));
+        assert_eq!(summary.steps[1].skip_reason, None);
+    }
+
+    #[test]
+    fn test_run_skips_steps_by_condition() {
+        let mut config = config_with(&[
+            ("Already done", "exit 1"),
+            ("Not needed", "exit 1"),
+            ("Needed", "true"),
+        ]);
+        config.commands[0].skip_if = Some("true".to_string());
+        config.commands[1].only_if = Some("false".to_string());
+        config.commands[2].skip_if = Some("false".to_string());
+        config.commands[2].only_if = Some("true".to_string());
+
+        let summary = run(&config, RunOptions::default()).expect("run should start");
+
+        assert!(summary.success());
+        let reasons: Vec<Option<&str>> = summary
+            .steps
+            .iter()
+            .map(|s| s.skip_reason.as_deref())
+            .collect();
+        assert_eq!(
+            reasons,
+            [Some("skip_if succeeded"), Some("only_if failed"), None]
+        );
+    }
+
+    #[test]
+    fn test_run_skips_command_when_check_succeeds() {
+        let mut config = config_with(&[("Installed", "exit 1"), ("Missing", "true")]);
+        config.commands[0].check = Some("true".to_string());
+        config.commands[1].check = Some("false".to_string());
+
+        let summary = run(&config, RunOptions::default()).expect("run should start");
+
+        assert!(summary.success());
+        assert!(summary.steps[0].satisfied);
+        assert!(!summary.steps[1].satisfied);
+        assert_eq!(summary.steps[1].skip_reason, None);
+    }
+
+    #[test]
+    fn test_run_orders_steps_by_needs() {
+        let mut config = config_with(&[("Seed", "true"), ("Migrate", "true"), ("Other", "true")]);
+        config.commands[0].needs = vec!["Migrate".to_string()];
+        let options = RunOptions {
+            step: Some("seed".to_string()),
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        let titles: Vec<&str> = summary.steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Migrate", "Seed"]);
+    }
+
+    #[test]
+    fn test_run_bail_after_skips_remaining_steps() {
+        let config = config_with(&[
+            ("Fail 1", "false"),
+            ("Pass", "true"),
+            ("Fail 2", "false"),
+            ("Fail 3", "false"),
+            ("Later", "true"),
+        ]);
+        let options = RunOptions {
+            continue_on_error: true,
+            bail_after: 2,
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        assert_eq!(summary.failures().count(), 2);
+        let skipped: Vec<&str> = summary
+            .steps
+            .iter()
+            .filter(|s| s.skip_reason.is_some())
+            .map(|s| s.title.as_str())
+            .collect();
+        assert_eq!(skipped, ["Fail 3", "Later"]);
+    }
+
+    #[test]
+    fn test_prompt_selection_runs_only_picked_steps() {
+        let config = config_with(&[
+            ("Build frontend", "true"),
+            ("Build backend", "true"),
+            ("Build docs", "true"),
+        ]);
+        let matches: Vec<&CommandEntry> = config.commands.iter().collect();
+
+        let selected = prompt_selection(matches, &mut io::Cursor::new("1\n"))
+            .expect("selection should succeed");
+
+        let titles: Vec<&str> = selected.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Build frontend"]);
+    }
+
+    #[test]
+    fn test_prompt_selection_reprompts_then_errors_on_eof() {
+        let config = config_with(&[("One", "true"), ("Two", "true")]);
+        let matches: Vec<&CommandEntry> = config.commands.iter().collect();
+
+        let result = prompt_selection(matches, &mut io::Cursor::new("9\n"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_prompt_confirmation_defaults_to_no() {
+        let cmd = CommandEntry {
+            title: "Drop database".to_string(),
+            ..Default::default()
+        };
+
+        assert!(prompt_confirmation(&cmd, &mut io::Cursor::new("y\n")).unwrap());
+        assert!(prompt_confirmation(&cmd, &mut io::Cursor::new(" YES \n")).unwrap());
+        assert!(!prompt_confirmation(&cmd, &mut io::Cursor::new("\n")).unwrap());
+        assert!(!prompt_confirmation(&cmd, &mut io::Cursor::new("")).unwrap());
+    }
+
+    #[test]
+    fn test_prompt_approval_shows_command_until_answered() {
+        let cmd = CommandEntry {
+            title: "Seed data".to_string(),
+            command: Some("bin/seed".to_string()),
+            ..Default::default()
+        };
+        let exec = ExecOptions::default();
+        let approve = |input: &str| prompt_approval(&cmd, &exec, &mut io::Cursor::new(input));
+
+        assert_eq!(approve("c\nx\nr\n").unwrap(), Approval::Run);
+        assert_eq!(approve("\n").unwrap(), Approval::Run);
+        assert_eq!(approve("S\n").unwrap(), Approval::Skip);
+        assert_eq!(approve("abort\n").unwrap(), Approval::Abort);
+        assert_eq!(approve("").unwrap(), Approval::Abort);
+    }
+
+    #[test]
+    fn test_prompt_recovery_reads_choice() {
+        let cmd = CommandEntry {
+            title: "Install gems".to_string(),
+            ..Default::default()
+        };
+        let recover = |input: &str| prompt_recovery(&cmd, &mut io::Cursor::new(input));
+
+        assert_eq!(recover("r\n").unwrap(), Recovery::Retry);
+        assert_eq!(recover("x\ns\n").unwrap(), Recovery::Skip);
+        assert_eq!(recover("S\n").unwrap(), Recovery::Shell);
+        assert_eq!(recover("shell\n").unwrap(), Recovery::Shell);
+        assert_eq!(recover("\n").unwrap(), Recovery::Abort);
+        assert_eq!(recover("").unwrap(), Recovery::Abort);
+    }
+
+    #[test]
+    fn test_run_with_yes_runs_confirm_steps() {
+        let mut config = config_with(&[("Drop database", "true")]);
+        config.commands[0].confirm = true;
+
+        let summary = run(
+            &config,
+            RunOptions {
+                yes: true,
+                ..Default::default()
+            },
+        )
+        .expect("run should start");
+
+        assert!(summary.success());
+        assert_eq!(summary.steps[0].skip_reason, None);
+    }
+
+    #[test]
+    fn test_title_matches() {
+        assert!(title_matches("Run tests", "test", false));
+        assert!(title_matches("Run tests", "RUN", false));
+        assert!(!title_matches("Run tests", "test", true));
+        assert!(!title_matches("Run tests", "run tests", true));
+        assert!(title_matches("Run tests", "Run tests", true));
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("a", 3), Ok(vec![0, 1, 2]));
+        assert_eq!(parse_selection("3, 1", 3), Ok(vec![0, 2]));
+        assert_eq!(parse_selection("1-2 2", 3), Ok(vec![0, 1]));
+        assert!(parse_selection("0", 3).is_err());
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("3-1", 3).is_err());
+        assert!(parse_selection("", 3).is_err());
+    }
+
+    #[test]
+    fn test_run_only_tagged_steps() {
+        let mut config = config_with(&[("Database", "true"), ("Assets", "true"), ("Both", "true")]);
+        config.commands[0].tags = vec!["db".to_string()];
+        config.commands[1].tags = vec!["frontend".to_string()];
+        config.commands[2].tags = vec!["db".to_string(), "frontend".to_string()];
+        let options = RunOptions {
+            tags: vec!["db".to_string()],
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        let titles: Vec<&str> = summary.steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Database", "Both"]);
+    }
+
+    #[test]
+    fn test_run_with_unmatched_tag_errors() {
+        let config = config_with(&[("Build", "true")]);
+        let options = RunOptions {
+            tags: vec!["db".to_string()],
+            ..Default::default()
+        };
+
+        let result = run(&config, options);
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("No steps found tagged 'db'")
+        );
+    }
+
+    #[test]
+    fn test_run_by_id_runs_exactly_one_step() {
+        let mut config = config_with(&[("Start database", "true"), ("Reset database", "true")]);
+        config.commands[0].id = Some("db-start".to_string());
+        config.commands[1].id = Some("db-reset".to_string());
+        config.commands[1].needs = vec!["db-start".to_string()];
+        let options = RunOptions {
+            id: Some("db-reset".to_string()),
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        let titles: Vec<&str> = summary.steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Reset database"]);
+
+        let options = RunOptions {
+            id: Some("db".to_string()),
+            ..Default::default()
+        };
+        let err = run(&config, options).unwrap_err().to_string();
+        assert!(err.contains("No step with id 'db'"));
+    }
+
+    #[test]
+    fn test_from_and_until_run_a_slice() {
+        let mut config = config_with(&[
+            ("Install", "true"),
+            ("Migrate", "true"),
+            ("Seed", "true"),
+            ("Reset", "true"),
+        ]);
+        config.commands[2].id = Some("seed".to_string());
+        config.commands[2].needs = vec!["Install".to_string()];
+        let options = RunOptions {
+            from: Some("Migrate".to_string()),
+            until: Some("seed".to_string()),
+            ..Default::default()
+        };
+
+        let summary = run(&config, options).expect("run should start");
+
+        let titles: Vec<&str> = summary.steps.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Migrate", "Seed"]);
+
+        let options = RunOptions {
+            from: Some("Seed".to_string()),
+            until: Some("Install".to_string()),
+            ..Default::default()
+        };
+        let err = run(&config, options).unwrap_err().to_string();
+        assert!(err.contains("comes before --from step"));
+    }
+
+    #[test]
+    fn test_run_with_unmatched_step_filter_errors() {
+        let config = config_with(&[("Build", "true")]);
+        let options = RunOptions {
+            step: Some("deploy".to_string()),
+            ..Default::default()
+        };
+
+        let result = run(&config, options);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("No steps found matching 'deploy'")
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::ci;
+use crate::glyphs;
+use color_eyre::eyre::{Result, eyre};
+use console::{Key, Term, style};
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+/// Most steps listed at once; the list scrolls to keep the cursor in view
+const VISIBLE: usize = 12;
+
+/// Score how well `query` fuzzy-matches `text`, or `None` if it doesn't
+///
+/// Every character of the query must appear in the text in order, ignoring
+/// case. Matches at the start of words and runs of consecutive characters score
+/// higher, and gaps between matched characters score lower.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let query: Vec<char> = query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let bonus = |at: usize| {
+        if at == 0 || !text[at - 1].is_alphanumeric() {
+            10
+        } else {
+            0
+        }
+    };
+
+    // Best score of the query so far with its last character matched at each position
+    let mut best: Vec<Option<i64>> = (0..text.len())
+        .map(|at| Some(-(at as i64).min(5)))
+        .collect();
+    for (i, &wanted) in query.iter().enumerate() {
+        let mut scores = vec![None; text.len()];
+        for (at, &c) in text.iter().enumerate() {
+            if c != wanted {
+                continue;
+            }
+            let reached = if i == 0 {
+                best[at]
+            } else {
+                (0..at)
+                    .filter_map(|prev| {
+                        let gap = at - prev;
+                        let step = if gap == 1 { 8 } else { -(gap as i64).min(5) };
+                        Some(best[prev]? + step)
+                    })
+                    .max()
+            };
+            scores[at] = reached.map(|score| score + bonus(at));
+        }
+        best = scores;
+    }
+
+    if query.is_empty() {
+        Some(0)
+    } else {
+        best.into_iter().flatten().max()
+    }
+}
+
+/// What happened to the picker after a key press
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    /// Keep picking
+    Continue,
+    /// Run the chosen steps
+    Done,
+    /// Run nothing
+    Cancelled,
+}
+
+/// State of the step picker: the filter typed so far and the steps chosen
+struct Picker<'a> {
+    titles: &'a [String],
+    query: String,
+    /// Position of the highlighted step among the current matches
+    cursor: usize,
+    /// Indices into `titles` of the chosen steps
+    chosen: BTreeSet<usize>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(titles: &'a [String]) -> Self {
+        Self {
+            titles,
+            query: String::new(),
+            cursor: 0,
+            chosen: BTreeSet::new(),
+        }
+    }
+
+    /// Indices of the titles matching the query, best match first
+    fn matches(&self) -> Vec<usize> {
+        let mut scored: Vec<(i64, usize)> = self
+            .titles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, title)| Some((fuzzy_score(&self.query, title)?, index)))
+            .collect();
+        scored.sort_by_key(|&(score, index)| (-score, index));
+        scored.into_iter().map(|(_, index)| index).collect()
+    }
+
+    fn handle(&mut self, key: Key) -> Outcome {
+        let matches = self.matches();
+        match key {
+            Key::Enter => {
+                // With nothing chosen, Enter runs the highlighted step
+                if self.chosen.is_empty() {
+                    match matches.get(self.cursor) {
+                        Some(&index) => self.chosen.insert(index),
+                        None => return Outcome::Continue,
+                    };
+                }
+                return Outcome::Done;
+            }
+            Key::Escape | Key::CtrlC => return Outcome::Cancelled,
+            Key::Tab => {
+                if let Some(&index) = matches.get(self.cursor)
+                    && !self.chosen.remove(&index)
+                {
+                    self.chosen.insert(index);
+                }
+                self.cursor += 1;
+            }
+            Key::ArrowUp => self.cursor = self.cursor.saturating_sub(1),
+            Key::ArrowDown => self.cursor += 1,
+            Key::Backspace => {
+                self.query.pop();
+                self.cursor = 0;
+            }
+            Key::Char(c) if !c.is_control() => {
+                self.query.push(c);
+                self.cursor = 0;
+            }
+            _ => {}
+        }
+        let count = self.matches().len();
+        self.cursor = self.cursor.min(count.saturating_sub(1));
+        Outcome::Continue
+    }
+
+    /// Lines showing the query and the visible part of the matches
+    fn render(&self) -> Vec<String> {
+        let matches = self.matches();
+        let first = self.cursor.saturating_sub(VISIBLE - 1);
+
+        let mut lines = vec![format!(
+            "{} {}{}",
+            style("Pick steps:").cyan().bold(),
+            self.query,
+            style(format!(
+                "  ({} of {}, {} chosen)",
+                matches.len(),
+                self.titles.len(),
+                self.chosen.len()
+            ))
+            .dim()
+        )];
+        for (position, &index) in matches.iter().enumerate().skip(first).take(VISIBLE) {
+            let mark = if self.chosen.contains(&index) {
+                style(glyphs::SUCCESS).green().to_string()
+            } else {
+                " ".to_string()
+            };
+            let title = &self.titles[index];
+            if position == self.cursor {
+                lines.push(format!(
+                    "{} {} {}",
+                    style(glyphs::ARROW).cyan().bold(),
+                    mark,
+                    style(title).bold()
+                ));
+            } else {
+                lines.push(format!("   {} {}", mark, title));
+            }
+        }
+        lines.push(
+            style("type to filter, ↑/↓ to move, tab to choose, enter to run, esc to cancel")
+                .dim()
+                .to_string(),
+        );
+        lines
+    }
+}
+
+/// Let the user pick steps from `titles` in the terminal, returning their indices in order
+///
+/// Returns an empty list if the user cancels.
+pub fn pick(titles: &[String]) -> Result<Vec<usize>> {
+    if !ci::interactive() {
+        return Err(eyre!("getset pick needs a terminal"));
+    }
+
+    let term = Term::stdout();
+    let mut picker = Picker::new(titles);
+    let mut drawn = 0;
+    term.hide_cursor()?;
+
+    let outcome = loop {
+        term.clear_last_lines(drawn)?;
+        let lines = picker.render();
+        for line in &lines {
+            term.write_line(line)?;
+        }
+        io::stdout().flush()?;
+        drawn = lines.len();
+
+        match picker.handle(term.read_key()?) {
+            Outcome::Continue => {}
+            outcome => break outcome,
+        }
+    };
+
+    term.clear_last_lines(drawn)?;
+    term.show_cursor()?;
+    Ok(match outcome {
+        Outcome::Done => picker.chosen.into_iter().collect(),
+        _ => Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_matches_subsequences() {
+        assert!(fuzzy_score("ig", "Install gems").is_some());
+        assert!(fuzzy_score("", "Install gems").is_some());
+        assert!(fuzzy_score("gi", "Install gems").is_none());
+        assert!(fuzzy_score("db", "Seed data").is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_word_starts_and_runs() {
+        let score = |query| fuzzy_score(query, "Install Node packages").unwrap();
+        assert!(score("node") > score("ode"));
+        assert!(score("np") > score("oe"));
+    }
+
+    #[test]
+    fn test_picker_filters_chooses_and_keeps_config_order() {
+        let titles = [
+            "Install gems".to_string(),
+            "Migrate database".to_string(),
+            "Install Node packages".to_string(),
+        ];
+        let mut picker = Picker::new(&titles);
+
+        for c in "inst".chars() {
+            assert_eq!(picker.handle(Key::Char(c)), Outcome::Continue);
+        }
+        assert_eq!(picker.matches(), [0, 2]);
+
+        picker.handle(Key::ArrowDown);
+        picker.handle(Key::Tab);
+        picker.handle(Key::ArrowUp);
+        picker.handle(Key::Tab);
+        assert_eq!(picker.handle(Key::Enter), Outcome::Done);
+        assert_eq!(picker.chosen.into_iter().collect::<Vec<_>>(), [0, 2]);
+    }
+
+    #[test]
+    fn test_picker_enter_runs_highlighted_step() {
+        let titles = ["Install gems".to_string(), "Migrate database".to_string()];
+        let mut picker = Picker::new(&titles);
+
+        picker.handle(Key::Char('m'));
+        assert_eq!(picker.handle(Key::Enter), Outcome::Done);
+        assert_eq!(picker.chosen.into_iter().collect::<Vec<_>>(), [1]);
+
+        let mut picker = Picker::new(&titles);
+        picker.handle(Key::Char('z'));
+        assert_eq!(picker.handle(Key::Enter), Outcome::Continue);
+        assert_eq!(picker.handle(Key::Escape), Outcome::Cancelled);
+    }
+}
@@ -1,15 +1,62 @@
 use crate::config::PlatformXConfig;
-use chrono::Utc;
-use serde_json::json;
+use crate::metrics;
+use crate::orchestrator::{RunSummary, StepResult};
+use crate::telemetry::{SinkFuture, TelemetrySink};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::{collections::HashMap, time::Duration};
 
 const PLATFORMX_API_URL: &str = "https://api.getdx.com/events.track";
 
+/// Where events that could not be delivered wait for the next run, relative to the config file
+pub const QUEUE_FILE: &str = ".getset/platformx-queue.json";
+
+/// Most undelivered events kept; the oldest are dropped first
+const MAX_QUEUED: usize = 100;
+
+/// Maximum number of attempts for a single event
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubled for each further retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+
+/// Longest an event may take to deliver, retries included, unless `timeout_secs` is set
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why an attempt to deliver an event failed
+enum SendFailure {
+    /// Worth retrying: server errors, timeouts and connection problems
+    Transient(String),
+    /// Will not succeed on retry, e.g. a rejected secret key
+    Permanent(String),
+}
+
 pub struct PlatformXClient {
     config: PlatformXConfig,
     client: reqwest::Client,
     namespace: String,
     globals: Globals,
+    api_url: String,
+    /// Longest an event may take to deliver, retries included
+    timeout: Duration,
+    /// Where to keep events that could not be delivered, if anywhere
+    queue_path: Option<PathBuf>,
+    /// Step events collected during the run, sent along with the next run event
+    pending: Mutex<Vec<Value>>,
+    /// When the steps started running, to timestamp each step event with when it finished
+    run_started: Mutex<Option<DateTime<Utc>>>,
+}
+
+/// Where and how to deliver events
+struct Endpoint {
+    client: reqwest::Client,
+    api_url: String,
+    secret_key: String,
+    timeout: Duration,
 }
 
 impl PlatformXClient {
@@ -18,57 +65,154 @@ impl PlatformXClient {
             .event_namespace
             .clone()
             .unwrap_or_else(|| "getset".to_string());
+        let api_url = config
+            .api_url
+            .clone()
+            .unwrap_or_else(|| PLATFORMX_API_URL.to_string());
+        let timeout = config
+            .timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT);
 
         Self {
             config,
             client: reqwest::Client::new(),
             namespace,
             globals,
+            api_url,
+            timeout,
+            queue_path: None,
+            pending: Mutex::new(Vec::new()),
+            run_started: Mutex::new(None),
         }
     }
 
-    /// Send an event to PlatformX
+    /// Keep events that fail to deliver in the queue file under `base_dir`,
+    /// to send at the start of the next run
+    pub fn with_queue(mut self, base_dir: &Path) -> Self {
+        self.queue_path = Some(base_dir.join(QUEUE_FILE));
+        self
+    }
+
+    /// Send an event to PlatformX, along with any step events collected so far
     pub async fn send_event(
         &self,
         event_name: &str,
         metadata: &mut HashMap<String, serde_json::Value>,
     ) -> Result<(), String> {
-        let timestamp = Utc::now().timestamp();
+        let mut payloads = std::mem::take(&mut *self.pending.lock().unwrap());
+        payloads.push(self.payload(event_name, metadata, Utc::now()));
+        self.send_batch(payloads).await
+    }
+
+    /// Collect the event for a finished step, to send with the next run event
+    ///
+    /// The event is timestamped with when the step finished, not when it is sent.
+    pub fn record_step(&self, step: &StepResult) {
+        let mut metadata = HashMap::from([
+            ("step".to_string(), json!(step.title)),
+            ("status".to_string(), json!(metrics::step_status(step))),
+            ("duration".to_string(), json!(step.duration.as_secs_f64())),
+        ]);
+        let finished = self
+            .run_started
+            .lock()
+            .unwrap()
+            .and_then(|started| {
+                chrono::Duration::from_std(step.started + step.duration)
+                    .ok()
+                    .map(|offset| started + offset)
+            })
+            .unwrap_or_else(Utc::now);
+        let payload = self.payload(&format!("{}.step", self.namespace), &mut metadata, finished);
+        self.pending.lock().unwrap().push(payload);
+    }
+
+    /// The request body for an event that happened at `timestamp`, with the default metadata added
+    fn payload(
+        &self,
+        event_name: &str,
+        metadata: &mut HashMap<String, Value>,
+        timestamp: DateTime<Utc>,
+    ) -> Value {
+        let timestamp = timestamp.timestamp();
 
         metadata.insert("user_shell".to_string(), json!(self.globals.user_shell));
+        metadata.insert("git_branch".to_string(), json!(self.globals.git_branch));
+        metadata.insert("git_commit".to_string(), json!(self.globals.git_commit));
+        metadata.insert("git_dirty".to_string(), json!(self.globals.git_dirty));
+        metadata.insert("run_id".to_string(), json!(self.globals.run_id));
 
-        let payload = json!({
+        json!({
             "name": event_name,
             "metadata": metadata,
             "timestamp": timestamp.to_string(),
             "email": self.globals.git_email,
             "github_username": self.globals.github_username,
-        });
+        })
+    }
 
-        log::info!("Sending event to PlatformX: {}", payload);
+    /// Deliver events in a single request, queueing them if it fails transiently
+    async fn send_batch(&self, payloads: Vec<Value>) -> Result<(), String> {
+        let body = batch_body(&payloads);
+        tracing::info!("Sending {} events to PlatformX: {}", payloads.len(), body);
+        match self.endpoint().deliver(&body).await {
+            Ok(()) => Ok(()),
+            Err(SendFailure::Transient(e)) => {
+                self.enqueue(payloads);
+                Err(e)
+            }
+            Err(SendFailure::Permanent(e)) => Err(e),
+        }
+    }
 
-        let result = self
-            .client
-            .post(PLATFORMX_API_URL)
-            .header(
-                "Authorization",
-                format!("Bearer {}", self.config.secret_key),
-            )
-            .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to send PlatformX event: {}", e))?;
+    /// Where events go
+    fn endpoint(&self) -> Endpoint {
+        Endpoint {
+            client: self.client.clone(),
+            api_url: self.api_url.clone(),
+            secret_key: self.config.secret_key.clone().unwrap_or_default(),
+            timeout: self.timeout,
+        }
+    }
 
-        log::debug!("PlatformX HTTP Result: {}", result.status());
+    /// Send the events queued by earlier runs in one request, keeping them if they still can't be delivered
+    pub async fn flush_queue(&self) {
+        let Some(ref path) = self.queue_path else {
+            return;
+        };
+        let queued = load_queue(path);
+        if queued.is_empty() {
+            return;
+        }
 
-        if result.status().is_success() {
-            Ok(())
-        } else {
-            Err(format!(
-                "PlatformX API call failed with status: {}",
-                result.status()
-            ))
+        tracing::info!("Sending {} queued PlatformX events", queued.len());
+        let remaining = match self.endpoint().deliver(&batch_body(&queued)).await {
+            // Rejected events would be rejected again, so they are dropped
+            Ok(()) | Err(SendFailure::Permanent(_)) => Vec::new(),
+            Err(SendFailure::Transient(_)) => queued,
+        };
+        if let Err(e) = save_queue(path, &remaining) {
+            tracing::warn!("{}", e);
+        }
+    }
+
+    /// Add undelivered events to the queue, if there is one
+    fn enqueue(&self, payloads: Vec<Value>) {
+        let Some(ref path) = self.queue_path else {
+            return;
+        };
+        let mut queued = load_queue(path);
+        queued.extend(payloads);
+        if queued.len() > MAX_QUEUED {
+            queued.drain(..queued.len() - MAX_QUEUED);
+        }
+        match save_queue(path, &queued) {
+            Ok(()) => tracing::info!(
+                "Queued PlatformX events in '{}' to send on the next run",
+                path.display()
+            ),
+            Err(e) => tracing::warn!("{}", e),
         }
     }
 
@@ -103,55 +247,224 @@ impl PlatformXClient {
     }
 }
 
+impl Endpoint {
+    /// Deliver an event within the timeout, retrying transient failures
+    async fn deliver(&self, payload: &Value) -> Result<(), SendFailure> {
+        tokio::time::timeout(self.timeout, self.retry(payload))
+            .await
+            .unwrap_or_else(|_| {
+                let e = format!(
+                    "PlatformX event was not delivered within {}s; giving up",
+                    self.timeout.as_secs_f64()
+                );
+                tracing::warn!("{}", e);
+                Err(SendFailure::Transient(e))
+            })
+    }
+
+    /// Deliver an event, retrying transient failures
+    async fn retry(&self, payload: &Value) -> Result<(), SendFailure> {
+        let mut attempt = 1;
+        loop {
+            match self.post(payload).await {
+                Ok(()) => return Ok(()),
+                Err(SendFailure::Transient(e)) if attempt < MAX_ATTEMPTS => {
+                    let delay = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+                    tracing::warn!(
+                        "{} (attempt {}/{}), retrying in {}ms",
+                        e,
+                        attempt,
+                        MAX_ATTEMPTS,
+                        delay.as_millis()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(SendFailure::Transient(e)) => {
+                    tracing::warn!("{}; giving up after {} attempts", e, attempt);
+                    return Err(SendFailure::Transient(e));
+                }
+                Err(SendFailure::Permanent(e)) => {
+                    tracing::warn!("{}; not retrying", e);
+                    return Err(SendFailure::Permanent(e));
+                }
+            }
+        }
+    }
+
+    /// Make a single delivery attempt, classifying any failure
+    async fn post(&self, payload: &Value) -> Result<(), SendFailure> {
+        let result = self
+            .client
+            .post(&self.api_url)
+            .header("Authorization", format!("Bearer {}", self.secret_key))
+            .header("Content-Type", "application/json")
+            .json(payload)
+            .send()
+            .await
+            .map_err(|e| {
+                let message = format!("Failed to send PlatformX event: {}", e);
+                if e.is_timeout() || e.is_connect() || e.is_request() {
+                    SendFailure::Transient(message)
+                } else {
+                    SendFailure::Permanent(message)
+                }
+            })?;
+
+        let status = result.status();
+        tracing::debug!("PlatformX HTTP Result: {}", status);
+
+        if status.is_success() {
+            Ok(())
+        } else if status.is_client_error() {
+            Err(SendFailure::Permanent(format!(
+                "PlatformX API rejected the event with status: {} (check secret_key)",
+                status
+            )))
+        } else {
+            Err(SendFailure::Transient(format!(
+                "PlatformX API call failed with status: {}",
+                status
+            )))
+        }
+    }
+}
+
+// Failures are already logged by `send_event`, and tracking never fails the run
+impl TelemetrySink for PlatformXClient {
+    fn on_start(&self) -> SinkFuture<'_> {
+        Box::pin(async {
+            self.flush_queue().await;
+            let _ = self.send_start().await;
+            *self.run_started.lock().unwrap() = Some(Utc::now());
+        })
+    }
+
+    // Steps are only collected here so the run isn't held up by a request per
+    // step; they go out in one batch with the complete or error event
+    fn on_step<'a>(&'a self, step: &'a StepResult) -> SinkFuture<'a> {
+        Box::pin(async move { self.record_step(step) })
+    }
+
+    fn on_complete<'a>(&'a self, _summary: &'a RunSummary, duration: Duration) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let _ = self.send_complete(duration).await;
+        })
+    }
+
+    fn on_error<'a>(
+        &'a self,
+        _summary: &'a RunSummary,
+        duration: Duration,
+        error: &'a str,
+    ) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let _ = self.send_error(duration, error.to_string()).await;
+        })
+    }
+}
+
+/// The request body for a batch of events: the event itself if there is just one, else an array
+fn batch_body(payloads: &[Value]) -> Value {
+    match payloads {
+        [payload] => payload.clone(),
+        _ => Value::Array(payloads.to_vec()),
+    }
+}
+
+/// Events waiting in the queue file, or none if it is missing or unreadable
+fn load_queue(path: &Path) -> Vec<Value> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Replace the queued events, removing the file once none are left
+fn save_queue(path: &Path, events: &[Value]) -> Result<(), String> {
+    if events.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(format!("Error removing '{}': {}", path.display(), e))
+            }
+            _ => Ok(()),
+        };
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Error creating '{}': {}", dir.display(), e))?;
+    }
+    let content = serde_json::to_string_pretty(events).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| format!("Error writing '{}': {}", path.display(), e))
+}
+
 /// Default metadata collected from the user's environment
 #[derive(Clone, Debug)]
 pub struct Globals {
     pub user_shell: String,
     pub github_username: String,
     pub git_email: String,
+    /// Checked-out branch of the project's repository, or `HEAD` when detached
+    pub git_branch: String,
+    /// Short SHA of the checked-out commit
+    pub git_commit: String,
+    /// Whether the worktree has uncommitted changes, if it is a repository at all
+    pub git_dirty: Option<bool>,
+    /// Unique id of this invocation, sent with every event
+    pub run_id: String,
 }
 
-/// Get default metadata from the user's environment and git config
-pub fn get_globals() -> Globals {
-    let user_shell = std::env::var("SHELL").unwrap_or_else(|_| "unknown".to_string());
+/// Whether the user opted out of telemetry with `GETSET_NO_TELEMETRY=1` or `DO_NOT_TRACK=1`
+pub fn opted_out() -> bool {
+    opted_out_with(|name| std::env::var(name).ok())
+}
 
-    let github_username = std::process::Command::new("git")
-        .args(["config", "get", "github.user"])
-        .output()
-        .ok()
-        .and_then(|output| {
-            if output.status.success() {
-                String::from_utf8(output.stdout).ok()
-            } else {
-                None
-            }
-        })
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| "unknown".to_string());
+fn opted_out_with(lookup: impl Fn(&str) -> Option<String>) -> bool {
+    ["GETSET_NO_TELEMETRY", "DO_NOT_TRACK"].iter().any(|name| {
+        lookup(name).is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+    })
+}
 
-    let git_email = std::process::Command::new("git")
-        .args(["config", "get", "user.email"])
-        .output()
-        .ok()
-        .and_then(|output| {
-            if output.status.success() {
-                String::from_utf8(output.stdout).ok()
-            } else {
-                None
-            }
-        })
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .unwrap_or_else(|| "unknown".to_string());
+/// Get default metadata from the user's environment, git config and the repository in `repo_dir`
+pub fn get_globals(repo_dir: &Path) -> Globals {
+    let user_shell = std::env::var("SHELL").unwrap_or_else(|_| "unknown".to_string());
+    let unknown = || "unknown".to_string();
 
     Globals {
         user_shell,
-        github_username,
-        git_email,
+        github_username: git(repo_dir, &["config", "get", "github.user"]).unwrap_or_else(unknown),
+        git_email: git(repo_dir, &["config", "get", "user.email"]).unwrap_or_else(unknown),
+        git_branch: git(repo_dir, &["rev-parse", "--abbrev-ref", "HEAD"]).unwrap_or_else(unknown),
+        git_commit: git(repo_dir, &["rev-parse", "--short", "HEAD"]).unwrap_or_else(unknown),
+        git_dirty: git_output(repo_dir, &["status", "--porcelain"])
+            .map(|status| !status.is_empty()),
+        run_id: uuid::Uuid::new_v4().to_string(),
     }
 }
 
+/// Output of a successful git command run in `dir`, if it printed anything
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    git_output(dir, args).filter(|s| !s.is_empty())
+}
+
+/// Trimmed output of a git command run in `dir`, if it succeeded
+fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    // The directory of a config given without one, like the default `getset.toml`
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|s| s.trim().to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,13 +474,20 @@ mod tests {
             user_shell: "/bin/bash".to_string(),
             github_username: "testuser".to_string(),
             git_email: "test@example.com".to_string(),
+            git_branch: "main".to_string(),
+            git_commit: "abc1234".to_string(),
+            git_dirty: Some(true),
+            run_id: "run-1".to_string(),
         }
     }
 
     fn create_test_config(namespace: Option<String>) -> PlatformXConfig {
         PlatformXConfig {
-            secret_key: "test_secret_key".to_string(),
+            secret_key: Some("test_secret_key".to_string()),
+            secret_key_env: None,
             event_namespace: namespace,
+            api_url: None,
+            timeout_secs: None,
         }
     }
 
@@ -175,13 +495,62 @@ mod tests {
     fn test_get_globals_returns_globals() {
         // This test verifies that get_globals() returns a Globals struct
         // The actual values will depend on the environment
-        let globals = get_globals();
+        let globals = get_globals(Path::new("."));
 
         // Just verify the fields exist and are not empty
         // (they may be "unknown" if environment variables are not set)
         assert!(!globals.user_shell.is_empty());
         assert!(!globals.github_username.is_empty());
         assert!(!globals.git_email.is_empty());
+        assert!(!globals.git_branch.is_empty());
+        assert!(!globals.git_commit.is_empty());
+    }
+
+    #[test]
+    fn test_get_globals_for_the_default_config_dir() {
+        let default = get_globals(Path::new(""));
+        let current = get_globals(Path::new("."));
+
+        assert_eq!(default.git_commit, current.git_commit);
+        assert_eq!(default.git_branch, current.git_branch);
+        assert_eq!(default.git_email, current.git_email);
+    }
+
+    #[test]
+    fn test_get_globals_outside_a_repository() {
+        let dir = std::env::temp_dir().join(format!("getset-no-repo-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let globals = get_globals(&dir);
+
+        assert_eq!(globals.git_branch, "unknown");
+        assert_eq!(globals.git_commit, "unknown");
+        assert_eq!(globals.git_dirty, None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_event_includes_git_metadata() {
+        let mut server = mockito::Server::new_async().await;
+        let event = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "metadata": {
+                    "git_branch": "main",
+                    "git_commit": "abc1234",
+                    "git_dirty": true,
+                    "run_id": "run-1",
+                },
+            })))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = create_test_client(server.url());
+        client.send_start().await.unwrap();
+
+        event.assert_async().await;
     }
 
     #[test]
@@ -207,6 +576,214 @@ mod tests {
         assert_eq!(client.config.secret_key, config.secret_key);
     }
 
+    fn create_test_client(api_url: String) -> PlatformXClient {
+        let mut client = PlatformXClient::new(create_test_config(None), create_test_globals());
+        client.api_url = api_url;
+        client
+    }
+
+    #[tokio::test]
+    async fn test_send_event_retries_server_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let failure = server
+            .mock("POST", "/")
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+        let success = server
+            .mock("POST", "/")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = create_test_client(server.url());
+        let result = client.send_start().await;
+
+        assert!(
+            result.is_ok(),
+            "Should succeed after retrying: {:?}",
+            result
+        );
+        failure.assert_async().await;
+        success.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_send_event_does_not_retry_client_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let unauthorized = server
+            .mock("POST", "/")
+            .with_status(401)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = create_test_client(server.url());
+        let result = client.send_start().await;
+
+        let error = result.unwrap_err();
+        assert!(error.contains("401"));
+        assert!(error.contains("check secret_key"));
+        unauthorized.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_send_event_gives_up_after_max_attempts() {
+        let mut server = mockito::Server::new_async().await;
+        let failure = server
+            .mock("POST", "/")
+            .with_status(503)
+            .expect(MAX_ATTEMPTS as usize)
+            .create_async()
+            .await;
+
+        let client = create_test_client(server.url());
+        let result = client.send_start().await;
+
+        assert!(result.unwrap_err().contains("503"));
+        failure.assert_async().await;
+    }
+
+    #[test]
+    fn test_platformx_client_uses_configured_endpoint_and_timeout() {
+        let mut config = create_test_config(None);
+        config.api_url = Some("https://dx.example.com/events.track".to_string());
+        config.timeout_secs = Some(2);
+
+        let client = PlatformXClient::new(config, create_test_globals());
+
+        assert_eq!(client.api_url, "https://dx.example.com/events.track");
+        assert_eq!(client.timeout, Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_send_event_gives_up_at_timeout() {
+        // Accepts connections but never answers them
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = create_test_client(format!("http://{}", listener.local_addr().unwrap()));
+        client.timeout = Duration::from_millis(200);
+
+        let result = client.send_start().await;
+
+        assert!(result.unwrap_err().contains("not delivered within 0.2s"));
+    }
+
+    #[tokio::test]
+    async fn test_undelivered_events_are_queued_until_the_next_flush() {
+        let dir = std::env::temp_dir().join(format!("getset-platformx-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let queue = dir.join(QUEUE_FILE);
+
+        // Offline: connections are accepted but never answered
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = create_test_client(format!("http://{}", listener.local_addr().unwrap()))
+            .with_queue(&dir);
+        client.timeout = Duration::from_millis(100);
+        assert!(client.send_start().await.is_err());
+        assert!(
+            client
+                .send_error(Duration::from_secs(3), "boom".to_string())
+                .await
+                .is_err()
+        );
+        assert_eq!(load_queue(&queue).len(), 2);
+
+        let mut server = mockito::Server::new_async().await;
+        let flush = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(json!([
+                { "name": "getset.start" },
+                {
+                    "name": "getset.error",
+                    "metadata": { "error_message": "boom" },
+                },
+            ])))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        client.api_url = server.url();
+        client.timeout = DEFAULT_TIMEOUT;
+        client.flush_queue().await;
+
+        flush.assert_async().await;
+        assert!(!queue.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_step_events_are_sent_with_the_run_event() {
+        let mut server = mockito::Server::new_async().await;
+        let step = json!({
+            "name": "getset.step",
+            "metadata": { "status": "success", "run_id": "run-1" },
+        });
+        let batch = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(json!([
+                step,
+                step,
+                { "name": "getset.complete" },
+            ])))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = create_test_client(server.url());
+        let started = Utc::now() - chrono::Duration::seconds(60);
+        *client.run_started.lock().unwrap() = Some(started);
+        let summary = RunSummary {
+            steps: ["Install gems", "Migrate"]
+                .map(|title| StepResult {
+                    title: title.to_string(),
+                    duration: Duration::from_millis(1500),
+                    success: true,
+                    error: None,
+                    output_tail: Vec::new(),
+                    skip_reason: None,
+                    allowed_failure: false,
+                    satisfied: false,
+                    started: Duration::from_secs(10),
+                    project: None,
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        for step in &summary.steps {
+            client.on_step(step).await;
+        }
+        // Stamped with when the step finished, 11.5s into the run, not when it is sent
+        let finished = (started + chrono::Duration::milliseconds(11_500)).timestamp();
+        assert_eq!(
+            client.pending.lock().unwrap()[0]["timestamp"],
+            finished.to_string()
+        );
+        assert_eq!(client.pending.lock().unwrap().len(), 2);
+        client.on_complete(&summary, Duration::from_secs(3)).await;
+
+        batch.assert_async().await;
+        assert!(client.pending.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_opted_out_with_either_variable() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert!(opted_out_with(env(&[("GETSET_NO_TELEMETRY", "1")])));
+        assert!(opted_out_with(env(&[("DO_NOT_TRACK", "true")])));
+        assert!(!opted_out_with(env(&[("DO_NOT_TRACK", "0")])));
+        assert!(!opted_out_with(env(&[])));
+    }
+
     #[test]
     fn test_api_url_constant() {
         assert_eq!(PLATFORMX_API_URL, "https://api.getdx.com/events.track");
@@ -0,0 +1,220 @@
+use crate::ci;
+use crate::config::Prompt;
+use crate::redact;
+use color_eyre::eyre::{Result, eyre};
+use console::{Term, style};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Where remembered answers are stored, relative to the config file
+pub const PROMPTS_FILE: &str = ".getset/prompts.json";
+
+/// Answers remembered from earlier runs for prompts marked `cache = true`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PromptCache {
+    answers: BTreeMap<String, String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl PromptCache {
+    /// Load the cached answers in `base_dir`, starting empty if missing or unreadable
+    fn load(base_dir: &Path) -> Self {
+        let path = base_dir.join(PROMPTS_FILE);
+
+        let mut cache: PromptCache = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        cache.path = path;
+        cache
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| eyre!("Error creating '{}': {}", dir.display(), e))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&self.path, content)
+            .map_err(|e| eyre!("Error writing '{}': {}", self.path.display(), e))
+    }
+}
+
+/// Answer every prompt, keyed by the environment variable name it sets
+///
+/// A variable already set in the environment is used as-is, then a cached
+/// answer, then the user is asked (in terminals) or the default is used.
+pub fn resolve(prompts: &[Prompt], base_dir: &Path) -> Result<BTreeMap<String, String>> {
+    if prompts.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    let interactive = ci::interactive();
+    let mut cache = PromptCache::load(base_dir);
+    let cached_before = cache.answers.clone();
+
+    let answers = resolve_with(
+        prompts,
+        &mut cache.answers,
+        |name| std::env::var(name).ok(),
+        interactive.then_some(ask),
+    )?;
+
+    if cache.answers != cached_before {
+        cache.save()?;
+    }
+    for prompt in prompts.iter().filter(|prompt| prompt.secret) {
+        redact::register(&answers[&prompt.name]);
+    }
+    Ok(answers)
+}
+
+/// Resolve answers using `env` lookups, `cached` answers and an optional `ask` function
+fn resolve_with(
+    prompts: &[Prompt],
+    cached: &mut BTreeMap<String, String>,
+    env: impl Fn(&str) -> Option<String>,
+    ask: Option<impl Fn(&Prompt) -> io::Result<String>>,
+) -> Result<BTreeMap<String, String>> {
+    let mut answers = BTreeMap::new();
+
+    for prompt in prompts {
+        let remembered = if prompt.cache && !prompt.secret {
+            cached.get(&prompt.name).cloned()
+        } else {
+            None
+        };
+
+        let answer = match (env(&prompt.name), remembered, &ask) {
+            (Some(value), _, _) => value,
+            (None, Some(value), _) => value,
+            (None, None, Some(ask)) => {
+                let answer = ask(prompt)?;
+                match (answer.is_empty(), &prompt.default) {
+                    (true, Some(default)) => default.clone(),
+                    _ => answer,
+                }
+            }
+            (None, None, None) => prompt.default.clone().ok_or_else(|| {
+                eyre!(
+                    "Prompt '{}' needs an answer, but there is no terminal (or this is CI); set {} in the environment",
+                    prompt.name,
+                    prompt.name
+                )
+            })?,
+        };
+
+        if prompt.cache && !prompt.secret {
+            cached.insert(prompt.name.clone(), answer.clone());
+        }
+        answers.insert(prompt.name.clone(), answer);
+    }
+
+    Ok(answers)
+}
+
+/// Ask a prompt on the terminal, hiding the input of secret prompts
+fn ask(prompt: &Prompt) -> io::Result<String> {
+    let message = prompt.message.as_deref().unwrap_or(&prompt.name);
+    match (&prompt.default, prompt.secret) {
+        (Some(default), false) => print!(
+            "{} {} ",
+            style(message).bold(),
+            style(format!("[{}]", default)).dim()
+        ),
+        _ => print!("{} ", style(message).bold()),
+    }
+    io::stdout().flush()?;
+
+    let term = Term::stdout();
+    let line = if prompt.secret {
+        term.read_secure_line()?
+    } else {
+        term.read_line()?
+    };
+    Ok(line.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(name: &str, default: Option<&str>, cache: bool) -> Prompt {
+        Prompt {
+            name: name.to_string(),
+            default: default.map(str::to_string),
+            cache,
+            ..Default::default()
+        }
+    }
+
+    type Ask = fn(&Prompt) -> io::Result<String>;
+
+    #[test]
+    fn test_environment_then_cache_then_default() {
+        let prompts = [
+            prompt("FROM_ENV", Some("unused"), false),
+            prompt("FROM_CACHE", Some("unused"), true),
+            prompt("FROM_DEFAULT", Some("fallback"), false),
+        ];
+        let mut cached = BTreeMap::from([("FROM_CACHE".to_string(), "remembered".to_string())]);
+
+        let answers = resolve_with(
+            &prompts,
+            &mut cached,
+            |name| (name == "FROM_ENV").then(|| "set".to_string()),
+            None::<Ask>,
+        )
+        .unwrap();
+
+        assert_eq!(answers["FROM_ENV"], "set");
+        assert_eq!(answers["FROM_CACHE"], "remembered");
+        assert_eq!(answers["FROM_DEFAULT"], "fallback");
+    }
+
+    #[test]
+    fn test_asked_answers_are_cached_unless_secret() {
+        let mut secret = prompt("TOKEN", None, true);
+        secret.secret = true;
+        let prompts = [prompt("EMAIL", Some("me@example.com"), true), secret];
+        let mut cached = BTreeMap::new();
+
+        let answers = resolve_with(
+            &prompts,
+            &mut cached,
+            |_| None,
+            Some(|p: &Prompt| {
+                Ok(if p.name == "TOKEN" {
+                    "hunter2".to_string()
+                } else {
+                    String::new()
+                })
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(answers["EMAIL"], "me@example.com");
+        assert_eq!(answers["TOKEN"], "hunter2");
+        assert_eq!(
+            cached,
+            BTreeMap::from([("EMAIL".to_string(), "me@example.com".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_missing_answer_without_terminal_errors() {
+        let err = resolve_with(
+            &[prompt("NAME", None, false)],
+            &mut BTreeMap::new(),
+            |_| None,
+            None::<Ask>,
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("set NAME in the environment"));
+    }
+}
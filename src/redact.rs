@@ -0,0 +1,189 @@
+use std::borrow::Cow;
+use std::sync::{OnceLock, RwLock};
+
+/// Shown in place of a secret value
+pub const MASK: &str = "[REDACTED]";
+
+/// Values shorter than this are not treated as secrets, since masking them
+/// would mangle ordinary output
+const MIN_SECRET_LEN: usize = 4;
+
+/// Secret values of the run: answers to secret prompts, values read through
+/// `${secret:NAME}` or from credential-like `${env:NAME}` variables, webhook
+/// URLs and headers, and the PlatformX key
+fn secrets() -> &'static RwLock<Secrets> {
+    static SECRETS: OnceLock<RwLock<Secrets>> = OnceLock::new();
+    SECRETS.get_or_init(Default::default)
+}
+
+/// Keep `value` out of everything getset prints, logs or sends from now on
+pub fn register(value: &str) {
+    secrets().write().unwrap().add(value);
+}
+
+/// `text` with every known secret replaced by [`MASK`]
+pub fn redact(text: &str) -> String {
+    secrets().read().unwrap().redact(text)
+}
+
+/// Whether any secrets are known, so command output has to be read to be redacted
+pub fn active() -> bool {
+    !secrets().read().unwrap().0.is_empty()
+}
+
+/// Redacts one stream of command output as it is read
+///
+/// Complete lines are redacted as they arrive. The end of an unterminated
+/// line is held back for as long as it could be the start of a secret, so a
+/// secret printed across two reads is still masked, while prompts that do
+/// not end a line are shown as soon as they are read.
+#[derive(Debug, Default)]
+pub struct StreamRedactor {
+    pending: Vec<u8>,
+}
+
+impl StreamRedactor {
+    /// The redacted output that can be shown once `bytes` has been read
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(bytes);
+        let secrets = secrets().read().unwrap();
+        let line_start = self
+            .pending
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |at| at + 1);
+        let held = secrets.started_at_end(&self.pending[line_start..]);
+        let rest = self.pending.split_off(self.pending.len() - held);
+        let ready = std::mem::replace(&mut self.pending, rest);
+        secrets.redact_bytes(&ready).into_owned()
+    }
+
+    /// The redacted output still held back, once the stream has ended
+    pub fn finish(&mut self) -> Vec<u8> {
+        let rest = std::mem::take(&mut self.pending);
+        secrets().read().unwrap().redact_bytes(&rest).into_owned()
+    }
+}
+
+/// A set of secret values, longest first so a secret containing another is masked whole
+#[derive(Debug, Default)]
+struct Secrets(Vec<String>);
+
+impl Secrets {
+    fn add(&mut self, value: &str) {
+        if value.len() < MIN_SECRET_LEN || self.0.iter().any(|known| known == value) {
+            return;
+        }
+        self.0.push(value.to_string());
+        self.0.sort_by_key(|known| std::cmp::Reverse(known.len()));
+    }
+
+    fn redact(&self, text: &str) -> String {
+        // Secrets are valid UTF-8, so matches fall on character boundaries
+        String::from_utf8_lossy(&self.redact_bytes(text.as_bytes())).into_owned()
+    }
+
+    /// Length of the longest end of `bytes` that a secret starts with, but
+    /// which does not hold the whole secret
+    fn started_at_end(&self, bytes: &[u8]) -> usize {
+        self.0
+            .iter()
+            .filter_map(|secret| {
+                let secret = secret.as_bytes();
+                let longest = bytes.len().min(secret.len() - 1);
+                (1..=longest)
+                    .rev()
+                    .find(|&len| secret.starts_with(&bytes[bytes.len() - len..]))
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn redact_bytes<'a>(&self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut out = Cow::Borrowed(bytes);
+        for secret in &self.0 {
+            if let Some(replaced) = replace(&out, secret.as_bytes()) {
+                out = Cow::Owned(replaced);
+            }
+        }
+        out
+    }
+}
+
+/// `haystack` with each `needle` replaced by [`MASK`], if it contains any
+fn replace(haystack: &[u8], needle: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut rest = haystack;
+    while let Some(at) = rest
+        .windows(needle.len())
+        .position(|window| window == needle)
+    {
+        out.extend_from_slice(&rest[..at]);
+        out.extend_from_slice(MASK.as_bytes());
+        rest = &rest[at + needle.len()..];
+    }
+    if rest.len() == haystack.len() {
+        return None;
+    }
+    out.extend_from_slice(rest);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_masks_every_occurrence() {
+        let mut secrets = Secrets::default();
+        secrets.add("hunter2");
+
+        assert_eq!(
+            secrets.redact("login hunter2 && echo hunter2"),
+            "login [REDACTED] && echo [REDACTED]"
+        );
+        assert_eq!(secrets.redact("nothing to hide"), "nothing to hide");
+    }
+
+    #[test]
+    fn test_longer_secrets_are_masked_first_and_short_ones_ignored() {
+        let mut secrets = Secrets::default();
+        secrets.add("abcd");
+        secrets.add("abcdefgh");
+        secrets.add("abc");
+
+        assert_eq!(
+            secrets.redact("abcdefgh abcd abc"),
+            "[REDACTED] [REDACTED] abc"
+        );
+    }
+
+    #[test]
+    fn test_redact_bytes_keeps_other_bytes() {
+        let mut secrets = Secrets::default();
+        secrets.add("s3cr3t");
+
+        assert!(matches!(
+            secrets.redact_bytes(b"\xff clean"),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            secrets.redact_bytes(b"\xff token=s3cr3t\n").as_ref(),
+            b"\xff token=[REDACTED]\n"
+        );
+    }
+
+    #[test]
+    fn test_secret_split_across_reads_is_masked() {
+        register("getset-spl1t-s3cr3t");
+        let mut stream = StreamRedactor::default();
+
+        let mut out = stream.push(b"line one\ntoken=getset-spl1t");
+        assert_eq!(out, b"line one\ntoken=");
+        out.extend(stream.push(b"-s3cr3t done\nPassword: "));
+        assert_eq!(out, b"line one\ntoken=[REDACTED] done\nPassword: ");
+        out.extend(stream.push(b"getset-"));
+        out.extend(stream.finish());
+        assert_eq!(out, b"line one\ntoken=[REDACTED] done\nPassword: getset-");
+    }
+}
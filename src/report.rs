@@ -0,0 +1,398 @@
+use crate::logs;
+use crate::orchestrator::{self, RunSummary, StepResult};
+use crate::timings::Regression;
+use clap::ValueEnum;
+use std::fmt::Write;
+use std::fs;
+
+/// Where `--report-format html` writes the page by default, relative to the config file
+pub const HTML_REPORT_FILE: &str = ".getset/report.html";
+
+/// File GitHub Actions renders as the summary of the current job step
+pub const GITHUB_STEP_SUMMARY: &str = "GITHUB_STEP_SUMMARY";
+
+/// How `getset up` presents the report of a run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Bar chart of step durations, printed after the run
+    #[default]
+    Text,
+    /// Standalone HTML page with a timeline and each step's output, written to a file
+    Html,
+    /// Markdown table of step results, as written to GitHub's step summary
+    Markdown,
+    /// Mermaid gantt chart of when each step started and how long it took
+    Gantt,
+}
+
+/// Escape text for use in HTML content and attribute values
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Status label and CSS class of a step
+fn status(step: &StepResult) -> (String, &'static str) {
+    match step.skip_reason {
+        Some(ref reason) => (format!("skipped ({})", reason), "skipped"),
+        None if step.satisfied => ("already satisfied".to_string(), "skipped"),
+        None if step.success => ("succeeded".to_string(), "succeeded"),
+        None if step.allowed_failure => ("failed (allowed)".to_string(), "allowed"),
+        None => ("failed".to_string(), "failed"),
+    }
+}
+
+/// Output the step wrote to its log in this run, if logs were kept
+fn step_output(summary: &RunSummary, step: &StepResult) -> Option<String> {
+    let dir = summary.log_dir.as_ref()?;
+    fs::read_to_string(logs::step_log_path(dir, &step.title)).ok()
+}
+
+const STYLE: &str = "\
+body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; margin: 2rem; color: #1f2328; }
+h1 { font-size: 1.5rem; }
+.summary { color: #59636e; }
+table { border-collapse: collapse; width: 100%; }
+td { padding: 0.4rem 0.6rem; border-top: 1px solid #d1d9e0; vertical-align: top; }
+th { padding: 1rem 0.6rem 0.4rem; text-align: left; }
+td.duration { text-align: right; white-space: nowrap; font-variant-numeric: tabular-nums; }
+td.timeline { width: 40%; }
+.track { position: relative; height: 1rem; background: #f6f8fa; }
+.bar { position: absolute; height: 100%; min-width: 2px; }
+.succeeded .bar { background: #1f883d; }
+.failed .bar { background: #cf222e; }
+.allowed .bar { background: #bf8700; }
+.skipped .bar { background: #818b98; }
+.failed .status { color: #cf222e; }
+.allowed .status, .warning { color: #9a6700; }
+.skipped .status { color: #59636e; }
+pre { background: #f6f8fa; padding: 0.6rem; overflow-x: auto; max-height: 30rem; }
+";
+
+/// Render the run as a standalone HTML page
+///
+/// Each step gets a bar placed on a timeline of the whole run, its status and
+/// any error, and its output from the step log in a collapsible section.
+pub fn render_html(summary: &RunSummary, regressions: &[Regression]) -> String {
+    let total = summary.duration.as_secs_f64();
+    let percent = |secs: f64| {
+        if total > 0.0 {
+            (secs / total * 100.0).clamp(0.0, 100.0)
+        } else {
+            0.0
+        }
+    };
+    let mut out = String::new();
+    let _ = writeln!(out, "<!DOCTYPE html>");
+    let _ = writeln!(out, "<html lang=\"en\">");
+    let _ = writeln!(out, "<head>");
+    let _ = writeln!(out, "<meta charset=\"utf-8\">");
+    let _ = writeln!(out, "<title>getset report</title>");
+    let _ = writeln!(out, "<style>\n{}</style>", STYLE);
+    let _ = writeln!(out, "</head>");
+    let _ = writeln!(out, "<body>");
+    let _ = writeln!(out, "<h1>getset report</h1>");
+    let _ = writeln!(
+        out,
+        "<p class=\"summary\">{} in {:.2}s &middot; {} steps completed &middot; {} &middot; run {}</p>",
+        outcome(summary),
+        total,
+        summary.progress(),
+        chrono::Local::now().format("%Y-%m-%d %H:%M"),
+        escape(&summary.uuid)
+    );
+
+    for regression in regressions {
+        let _ = writeln!(
+            out,
+            "<p class=\"warning\">&#9888; {} took {:.1}x longer than usual</p>",
+            escape(&regression.title),
+            regression.ratio()
+        );
+    }
+
+    let _ = writeln!(out, "<table>");
+    let steps = orchestrator::by_project(&summary.steps)
+        .into_iter()
+        .flat_map(|(project, steps)| steps.into_iter().map(move |step| (project, step)));
+    let mut shown = None;
+    for (project, step) in steps {
+        // Steps of workspace members come under a heading with the member's name
+        if project.is_some() && project != shown {
+            let _ = writeln!(
+                out,
+                "<tr><th colspan=\"3\">{}</th></tr>",
+                escape(project.unwrap_or_default())
+            );
+            shown = project;
+        }
+        let (label, class) = status(step);
+        let _ = writeln!(out, "<tr class=\"{}\">", class);
+        let _ = writeln!(out, "<td>");
+        let _ = writeln!(out, "<strong>{}</strong>", escape(&step.title));
+        let _ = writeln!(out, "<div class=\"status\">{}</div>", escape(&label));
+        if let Some(ref error) = step.error {
+            let _ = writeln!(out, "<div class=\"status\">{}</div>", escape(error));
+        }
+        if let Some(output) = step_output(summary, step) {
+            let _ = writeln!(
+                out,
+                "<details><summary>Output</summary><pre>{}</pre></details>",
+                escape(&output)
+            );
+        }
+        let _ = writeln!(out, "</td>");
+        let _ = writeln!(
+            out,
+            "<td class=\"duration\">{:.2}s</td>",
+            step.duration.as_secs_f64()
+        );
+        let _ = writeln!(
+            out,
+            "<td class=\"timeline\"><div class=\"track\"><div class=\"bar\" style=\"left: {:.2}%; width: {:.2}%\"></div></div></td>",
+            percent(step.started.as_secs_f64()),
+            percent(step.duration.as_secs_f64())
+        );
+        let _ = writeln!(out, "</tr>");
+    }
+    let _ = writeln!(out, "</table>");
+    let _ = writeln!(out, "</body>");
+    let _ = writeln!(out, "</html>");
+    out
+}
+
+/// Outcome of the run, e.g. `Succeeded`
+fn outcome(summary: &RunSummary) -> &'static str {
+    if summary.interrupted {
+        "Interrupted"
+    } else if summary.success() {
+        "Succeeded"
+    } else {
+        "Failed"
+    }
+}
+
+/// Render the run as a Markdown table of steps, their status and duration
+pub fn render_markdown(summary: &RunSummary, regressions: &[Regression]) -> String {
+    let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+    let icon = if summary.success() { "✅" } else { "❌" };
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "### {} getset: {} in {:.2}s ({} steps completed)\n",
+        icon,
+        outcome(summary),
+        summary.duration.as_secs_f64(),
+        summary.progress()
+    );
+    let _ = writeln!(out, "Run `{}`\n", summary.uuid);
+    let _ = writeln!(out, "| Step | Status | Duration |");
+    let _ = writeln!(out, "| --- | --- | ---: |");
+    let steps = orchestrator::by_project(&summary.steps)
+        .into_iter()
+        .flat_map(|(project, steps)| steps.into_iter().map(move |step| (project, step)));
+    let mut shown = None;
+    for (project, step) in steps {
+        if project.is_some() && project != shown {
+            let _ = writeln!(out, "| **{}** | | |", cell(project.unwrap_or_default()));
+            shown = project;
+        }
+        let (label, class) = status(step);
+        let icon = match class {
+            "succeeded" => "✅",
+            "failed" => "❌",
+            "allowed" => "⚠️",
+            _ => "⏭️",
+        };
+        let _ = writeln!(
+            out,
+            "| {} | {} {} | {:.2}s |",
+            cell(&step.title),
+            icon,
+            cell(&label),
+            step.duration.as_secs_f64()
+        );
+    }
+
+    for step in summary.failures() {
+        if let Some(ref error) = step.error {
+            let _ = write!(out, "\n> ❌ {}\n", cell(error));
+        }
+    }
+    for regression in regressions {
+        let _ = write!(
+            out,
+            "\n> ⚠️ {} took {:.1}x longer than usual\n",
+            cell(&regression.title),
+            regression.ratio()
+        );
+    }
+    out
+}
+
+/// Render the run as a Mermaid gantt chart, with times in milliseconds from its start
+///
+/// Failed steps are marked `crit` and skipped ones are shown as milestones, so
+/// overlapping parallel steps and the longest chain of steps stand out.
+pub fn render_gantt(summary: &RunSummary) -> String {
+    // `:` ends a task name and `#` starts an entity code
+    let name = |title: &str| title.replace('#', "#35;").replace(':', "#58;");
+
+    let mut out = String::from("gantt\n");
+    let _ = writeln!(out, "    title getset run ({})", outcome(summary));
+    let _ = writeln!(out, "    dateFormat x");
+    let _ = writeln!(out, "    axisFormat %M:%S");
+    let steps = orchestrator::by_project(&summary.steps)
+        .into_iter()
+        .flat_map(|(project, steps)| steps.into_iter().map(move |step| (project, step)));
+    let mut section = None;
+    for (index, (project, step)) in steps.enumerate() {
+        // A section for the project's own steps, then one for each workspace member
+        if index == 0 || project != section {
+            let _ = writeln!(out, "    section {}", name(project.unwrap_or("Steps")));
+            section = project;
+        }
+        let start = step.started.as_millis();
+        let end = (step.started + step.duration).as_millis();
+        let tag = match status(step).1 {
+            "failed" => "crit, ",
+            "skipped" if !step.satisfied => "milestone, ",
+            "allowed" => "active, ",
+            _ => "done, ",
+        };
+        let _ = writeln!(
+            out,
+            "    {} :{}step{}, {}, {}",
+            name(&step.title),
+            tag,
+            index,
+            start,
+            end
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn step(title: &str, started: u64, secs: u64, success: bool) -> StepResult {
+        StepResult {
+            title: title.to_string(),
+            duration: Duration::from_secs(secs),
+            success,
+            error: (!success).then(|| "Step failed with exit code 1".to_string()),
+            output_tail: Vec::new(),
+            skip_reason: None,
+            allowed_failure: false,
+            satisfied: false,
+            started: Duration::from_secs(started),
+            project: None,
+        }
+    }
+
+    #[test]
+    fn test_html_report_places_steps_on_timeline() {
+        let summary = RunSummary {
+            steps: vec![
+                step("Install <deps>", 0, 5, true),
+                step("Migrate", 5, 5, false),
+            ],
+            duration: Duration::from_secs(10),
+            total: 2,
+            uuid: "run-1".to_string(),
+            ..Default::default()
+        };
+
+        let html = render_html(&summary, &[]);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Failed in 10.00s"));
+        assert!(html.contains("<strong>Install &lt;deps&gt;</strong>"));
+        assert!(html.contains("left: 50.00%; width: 50.00%"));
+        assert!(html.contains("<tr class=\"failed\">"));
+        assert!(html.contains("Step failed with exit code 1"));
+        assert!(html.contains("&middot; run run-1</p>"));
+    }
+
+    #[test]
+    fn test_markdown_report_is_a_table_of_steps() {
+        let summary = RunSummary {
+            steps: vec![step("Lint | fix", 0, 2, true), step("Test", 2, 3, false)],
+            duration: Duration::from_secs(5),
+            total: 3,
+            uuid: "run-1".to_string(),
+            ..Default::default()
+        };
+
+        let markdown = render_markdown(&summary, &[]);
+
+        assert!(markdown.starts_with("### ❌ getset: Failed in 5.00s ([1/3] steps completed)\n"));
+        assert!(markdown.contains("| Lint \\| fix | ✅ succeeded | 2.00s |\n"));
+        assert!(markdown.contains("Run `run-1`\n\n| Step | Status | Duration |\n"));
+        assert!(markdown.contains("| Test | ❌ failed | 3.00s |\n"));
+        assert!(markdown.contains("> ❌ Step failed with exit code 1"));
+    }
+
+    #[test]
+    fn test_gantt_report_uses_step_offsets() {
+        let summary = RunSummary {
+            steps: vec![
+                step("Build: web", 0, 2, true),
+                step("Build: api", 0, 3, false),
+            ],
+            duration: Duration::from_secs(3),
+            total: 2,
+            ..Default::default()
+        };
+
+        let gantt = render_gantt(&summary);
+
+        assert!(gantt.starts_with("gantt\n    title getset run (Failed)\n"));
+        assert!(gantt.contains("    Build#58; web :done, step0, 0, 2000\n"));
+        assert!(gantt.contains("    Build#58; api :crit, step1, 0, 3000\n"));
+    }
+
+    #[test]
+    fn test_reports_group_steps_by_workspace_member() {
+        let in_project = |project: &str, title: &str| StepResult {
+            project: Some(project.to_string()),
+            ..step(&format!("{}: {}", project, title), 0, 1, true)
+        };
+        let summary = RunSummary {
+            steps: vec![
+                in_project("web", "Install"),
+                step("Root", 0, 1, true),
+                in_project("api", "Migrate"),
+                in_project("web", "Build"),
+            ],
+            duration: Duration::from_secs(4),
+            total: 4,
+            ..Default::default()
+        };
+
+        let markdown = render_markdown(&summary, &[]);
+        let gantt = render_gantt(&summary);
+
+        assert!(markdown.contains(
+            "| Root | ✅ succeeded | 1.00s |\n| **web** | | |\n| web: Install | ✅ succeeded | 1.00s |\n| web: Build |"
+        ));
+        assert!(markdown.contains("| **api** | | |\n| api: Migrate |"));
+        assert!(gantt.contains("    section Steps\n    Root :done, step0,"));
+        assert!(gantt.contains("    section web\n    web#58; Install :done, step1,"));
+        assert!(render_html(&summary, &[]).contains("<tr><th colspan=\"3\">api</th></tr>"));
+    }
+}
@@ -1,150 +1,1285 @@
+use chrono::Local;
 use console::style;
 
+use crate::ci::{self, CiFormat};
 use crate::config::CommandEntry;
-use std::io::{self, IsTerminal};
-use std::process::{Command, Stdio};
+use crate::events::{self, Emitter, Event, EventHandler, LogFormat, StepOutcome};
+use crate::glyphs;
+use crate::interrupt;
+use crate::logs;
+use crate::redact::{self, StreamRedactor};
+use crate::spinner::Spinner;
+use crate::timings;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
 
+/// `-v`: show each step's command and trace shell execution
+pub const SHOW_COMMANDS: u8 = 1;
+/// `-vv`: also show each step's env, working directory and skip conditions
+pub const SHOW_DETAILS: u8 = 2;
+/// `-vvv`: also log the runner's internal decisions, such as PTY fallback and signal handling
+pub const SHOW_DECISIONS: u8 = 3;
+
+/// Settings that apply to every command in a run
+///
+/// Given to an [`Executor`] for each step; new settings may be added in any release.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ExecOptions {
+    /// How much detail to show about each step, from 0 up to [`SHOW_DECISIONS`]
+    pub verbose: u8,
+    /// Format for lifecycle output
+    pub log_format: LogFormat,
+    /// The command runs alongside others, so it gets no PTY and no stdin
+    pub concurrent: bool,
+    /// Capture output and show it only if the command fails
+    pub quiet: bool,
+    /// Show only stderr from the output of a failed command
+    pub stderr_only: bool,
+    /// Directory to write each command's output to, as `<step>.log`
+    pub log_dir: Option<PathBuf>,
+    /// Position of the step in the run, shown before its title
+    pub progress: Option<Progress>,
+    /// How long the step usually takes, from previous runs
+    pub typical: Option<Duration>,
+    /// CI system to write log sections and failure annotations for
+    pub ci: Option<CiFormat>,
+    /// Put the time before each line of output, as in CI mode
+    pub timestamps: bool,
+    /// Unique id of the run, put on each of its JSON events
+    pub run_id: String,
+    /// Told about each event of the run instead of it being written as JSON
+    pub event_handler: Option<Arc<dyn EventHandler>>,
+    /// Runs the commands and conditions of steps instead of spawning processes
+    pub executor: Option<Arc<dyn Executor>>,
+}
+
+impl ExecOptions {
+    /// What runs the step's commands: the given executor, or real processes
+    pub fn executor(&self) -> &dyn Executor {
+        self.executor.as_deref().unwrap_or(&ProcessExecutor)
+    }
+
+    /// Where the run's events go
+    pub(crate) fn emitter(&self) -> Emitter<'_> {
+        Emitter {
+            log_format: self.log_format,
+            run_id: &self.run_id,
+            handler: self.event_handler.as_deref(),
+        }
+    }
+
+    /// Whether the step's output is captured rather than shown live
+    fn captures(&self, cmd_entry: &CommandEntry) -> bool {
+        self.quiet || cmd_entry.capture
+    }
+
+    /// These options for the step at `progress` in the run
+    pub fn at(&self, progress: Progress) -> Self {
+        Self {
+            progress: Some(progress),
+            ..self.clone()
+        }
+    }
+
+    /// CI log markup for this step; concurrent steps are not sectioned, as their output interleaves
+    fn ci_sections(&self) -> Option<CiFormat> {
+        self.ci.filter(|_| !self.concurrent)
+    }
+
+    /// The step's title, preceded by its position in the run if known
+    pub(crate) fn label(&self, cmd_entry: &CommandEntry) -> String {
+        match self.progress {
+            Some(progress) => format!("{} {}", progress, cmd_entry.title),
+            None => cmd_entry.title.clone(),
+        }
+    }
+}
+
+/// Position of a step among the steps selected for a run, shown as `[3/12]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// One-based position of the step
+    pub current: usize,
+    pub total: usize,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}/{}]", self.current, self.total)
+    }
+}
+
+/// `[3/12] ` before a step's status line, or nothing outside a run
+fn progress_prefix(options: &ExecOptions) -> String {
+    match options.progress {
+        Some(progress) => format!("{} ", style(progress).dim()),
+        None => String::new(),
+    }
+}
+
+/// Runs the commands and conditions of steps
+///
+/// getset runs them as processes with [`ProcessExecutor`]. Tests and embedders
+/// can give a run their own executor, e.g. one that records each step instead
+/// of running it; skipped and satisfied steps are still reported as usual.
+pub trait Executor: Send + Sync {
+    /// Run a step's command, returning how long it took or why it failed
+    fn run(&self, cmd_entry: &CommandEntry, options: &ExecOptions) -> Result<Duration, Failure>;
+
+    /// Run a step's `skip_if`, `only_if` or `check` condition, returning whether it succeeded
+    fn condition(&self, cmd_entry: &CommandEntry, condition: &str) -> bool;
+}
+
+impl fmt::Debug for dyn Executor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Executor")
+    }
+}
+
+/// Runs each command as a process, printing its status and output
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessExecutor;
+
+impl Executor for ProcessExecutor {
+    fn run(&self, cmd_entry: &CommandEntry, options: &ExecOptions) -> Result<Duration, Failure> {
+        run_command(cmd_entry, options)
+    }
+
+    fn condition(&self, cmd_entry: &CommandEntry, condition: &str) -> bool {
+        condition_succeeds(cmd_entry, condition)
+    }
+}
+
 /// Determines if we should use PTY mode based on the current context
+///
+/// Windows has no Unix PTYs, so commands there always share our console.
+/// In CI mode commands never get one, even if the job has a terminal.
 fn should_use_pty() -> bool {
     // Check if stdout is a terminal - if so, favor PTY mode
-    io::stdout().is_terminal()
+    cfg!(unix) && io::stdout().is_terminal() && !ci::active()
+}
+
+/// Tell the run about a step's event, and show it styled for the pretty format
+fn report(cmd_entry: &CommandEntry, options: &ExecOptions, event: Event) {
+    events::emit(options.emitter(), &event);
+    if options.log_format == LogFormat::Pretty {
+        render(cmd_entry, options, &event);
+    }
+}
+
+/// Print a step's event on the console, for people
+fn render(cmd_entry: &CommandEntry, options: &ExecOptions, event: &Event) {
+    match event {
+        Event::StepStarted { .. } => render_started(cmd_entry, options),
+        Event::StepFinished {
+            duration, outcome, ..
+        } => match outcome {
+            StepOutcome::Skipped { reason } => render_skipped(cmd_entry, reason, options),
+            StepOutcome::Satisfied => render_satisfied(cmd_entry, options),
+            outcome => render_exited(cmd_entry, *duration, outcome, options),
+        },
+        _ => {}
+    }
 }
 
 /// Print command start message
-fn print_command_start(cmd_entry: &CommandEntry, verbose: bool) {
+fn print_command_start(cmd_entry: &CommandEntry, options: &ExecOptions) {
+    report(
+        cmd_entry,
+        options,
+        Event::StepStarted {
+            title: cmd_entry.title.clone(),
+        },
+    );
+}
+
+fn render_started(cmd_entry: &CommandEntry, options: &ExecOptions) {
+    if let Some(ci) = options.ci_sections() {
+        println!(
+            "{}",
+            ci::start_section(ci, cmd_entry, &options.label(cmd_entry))
+        );
+    }
+
+    // Captured steps in a terminal show a spinner instead
+    if options.captures(cmd_entry) && !options.concurrent && should_use_pty() {
+        return;
+    }
+
     println!(
-        "{} {} {}",
+        "{} {}{} {}{}",
         style("==>").bold().cyan(),
+        progress_prefix(options),
         style("Step:").bold(),
-        style(&cmd_entry.title).bold().cyan()
+        style(&cmd_entry.title).bold().cyan(),
+        typical_suffix(options)
     );
 
-    if verbose {
-        println!("{}", style(&cmd_entry.command).yellow().dim());
+    if options.verbose >= SHOW_COMMANDS {
+        println!(
+            "{}",
+            style(redact::redact(&cmd_entry.display_command()))
+                .yellow()
+                .dim()
+        );
+    }
+    if options.verbose >= SHOW_DETAILS {
+        if let Some(dir) = cmd_entry.working_dir() {
+            println!("{}", style(format!("    cwd: {}", dir.display())).dim());
+        }
+        for (key, value) in &cmd_entry.env {
+            println!(
+                "{}",
+                style(format!("    env: {}={}", key, redact::redact(value))).dim()
+            );
+        }
     }
 }
 
-/// Print command result
-fn print_command_result(cmd_entry: &CommandEntry, elapsed: Duration, success: bool) {
-    if success {
+/// ` (~2m typical)` after a step's title, if previous runs recorded its duration
+fn typical_suffix(options: &ExecOptions) -> String {
+    match options.typical {
+        Some(typical) => format!(
+            " {}",
+            style(format!("({} typical)", timings::estimate(typical))).dim()
+        ),
+        None => String::new(),
+    }
+}
+
+/// Print command result; `failure` carries the exit status of a failed command
+fn print_command_result(
+    cmd_entry: &CommandEntry,
+    elapsed: Duration,
+    failure: Option<ExitStatus>,
+    options: &ExecOptions,
+) {
+    report(
+        cmd_entry,
+        options,
+        Event::StepFinished {
+            title: cmd_entry.title.clone(),
+            duration: elapsed,
+            exit_code: failure.map_or(Some(0), |status| status.code()),
+            outcome: if failure.is_none() {
+                StepOutcome::Succeeded
+            } else {
+                StepOutcome::Failed
+            },
+        },
+    );
+}
+
+/// Print that a command was stopped by Ctrl-C
+fn print_command_interrupted(cmd_entry: &CommandEntry, elapsed: Duration, options: &ExecOptions) {
+    report(
+        cmd_entry,
+        options,
+        Event::StepFinished {
+            title: cmd_entry.title.clone(),
+            duration: elapsed,
+            exit_code: None,
+            outcome: StepOutcome::Interrupted,
+        },
+    );
+}
+
+/// Print how a command that ran ended
+fn render_exited(
+    cmd_entry: &CommandEntry,
+    elapsed: Duration,
+    outcome: &StepOutcome,
+    options: &ExecOptions,
+) {
+    if let Some(ci) = options.ci_sections() {
+        println!("{}", ci::end_section(ci, cmd_entry));
+    }
+    if let Some(annotation) = options
+        .ci
+        .filter(|_| *outcome == StepOutcome::Failed)
+        .and_then(|ci| ci::failure_annotation(ci, cmd_entry))
+    {
+        println!("{}", annotation);
+    }
+
+    let seconds = elapsed.as_secs_f64();
+    if *outcome == StepOutcome::Succeeded {
         println!(
-            "{} ✔︎ {} {}",
+            "{} {} {} {}",
             style("==>").bold().green(),
+            glyphs::SUCCESS,
             style(&cmd_entry.title).dim(),
-            style(format!("({:.2}s)", elapsed.as_secs_f64())).dim()
+            style(format!("({:.2}s)", seconds)).dim()
+        );
+    } else if *outcome == StepOutcome::Interrupted {
+        println!(
+            "{} {} {} {}",
+            style("==>").bold().red(),
+            glyphs::FAILURE,
+            style(&cmd_entry.title).bold(),
+            style(format!("(interrupted after {:.2}s)", seconds)).dim()
+        );
+    } else if cmd_entry.allow_failure {
+        println!(
+            "{} {} {} {}",
+            style("==>").bold().yellow(),
+            glyphs::WARNING,
+            style(&cmd_entry.title).bold(),
+            style(format!("({:.2}s, failure allowed)", seconds)).dim()
         );
     } else {
         println!(
-            "{} ✘ {} {}",
+            "{} {} {} {}",
             style("==>").bold().red(),
+            glyphs::FAILURE,
             style(&cmd_entry.title).bold(),
-            style(format!("({:.2}s)", elapsed.as_secs_f64())).dim()
+            style(format!("({:.2}s)", seconds)).dim()
         );
     }
 }
 
+/// Program and arguments used to launch a command
+///
+/// `argv` commands are executed directly; `command` scripts go through the
+/// step's shell (`sh -c` by default, `cmd /C` on Windows) to support multiline
+/// scripts and shell features. Verbose mode traces POSIX shells with `-x`.
+fn invocation(cmd_entry: &CommandEntry, options: &ExecOptions) -> (String, Vec<String>) {
+    if let Some(argv) = &cmd_entry.argv {
+        return (argv[0].clone(), argv[1..].to_vec());
+    }
+
+    let shell = cmd_entry.shell.clone().unwrap_or_default();
+
+    let mut args = Vec::new();
+    if options.verbose >= SHOW_COMMANDS && shell.is_posix() {
+        args.push("-x".to_string());
+    }
+    let shell = shell.argv();
+    args.extend_from_slice(&shell[1..]);
+    args.push(cmd_entry.command.clone().unwrap_or_default());
+
+    (shell[0].clone(), args)
+}
+
+/// Open an interactive shell in the step's working directory with its `env`
+///
+/// Uses `$SHELL`, falling back to `sh` (`cmd` on Windows). Ctrl-C pressed in
+/// the shell is not treated as interrupting the run.
+pub fn open_shell(cmd_entry: &CommandEntry) -> io::Result<ExitStatus> {
+    let fallback = if cfg!(windows) { "cmd" } else { "sh" };
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| fallback.to_string());
+
+    let mut command = Command::new(shell);
+    command.envs(&cmd_entry.env);
+    if let Some(dir) = cmd_entry.working_dir() {
+        command.current_dir(dir);
+    }
+
+    let status = command.status();
+    interrupt::reset();
+    status
+}
+
+/// Run a `skip_if`/`only_if` condition silently and report whether it succeeded
+///
+/// The condition runs through the step's shell with its `env` and `cwd`. A
+/// condition that cannot be started counts as failed.
+pub fn condition_succeeds(cmd_entry: &CommandEntry, condition: &str) -> bool {
+    let shell = cmd_entry.shell.clone().unwrap_or_default().argv();
+
+    let mut command = Command::new(&shell[0]);
+    command
+        .args(&shell[1..])
+        .arg(condition)
+        .envs(&cmd_entry.env);
+    if let Some(dir) = cmd_entry.working_dir() {
+        command.current_dir(dir);
+    }
+
+    match command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+    {
+        Ok(status) => status.success(),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to run condition for step '{}': {}",
+                cmd_entry.title,
+                e
+            );
+            false
+        }
+    }
+}
+
+/// Print that a command was skipped without running
+pub fn print_command_skipped(cmd_entry: &CommandEntry, reason: &str, options: &ExecOptions) {
+    report(
+        cmd_entry,
+        options,
+        Event::StepFinished {
+            title: cmd_entry.title.clone(),
+            duration: Duration::ZERO,
+            exit_code: None,
+            outcome: StepOutcome::Skipped {
+                reason: reason.to_string(),
+            },
+        },
+    );
+}
+
+fn render_skipped(cmd_entry: &CommandEntry, reason: &str, options: &ExecOptions) {
+    println!(
+        "{} {}{} {} {}",
+        style("==>").bold().dim(),
+        progress_prefix(options),
+        glyphs::SKIPPED,
+        style(&cmd_entry.title).dim(),
+        style(reason).dim()
+    );
+
+    if options.verbose >= SHOW_DETAILS {
+        for detail in skip_conditions(cmd_entry) {
+            println!("{}", style(format!("    {}", detail)).dim());
+        }
+    }
+}
+
+/// The settings of a step that can cause it to be skipped, for `-vv`
+fn skip_conditions(cmd_entry: &CommandEntry) -> Vec<String> {
+    let mut conditions = Vec::new();
+    if !cmd_entry.os.is_empty() {
+        conditions.push(format!("os: {}", cmd_entry.os.join(", ")));
+    }
+    if !cmd_entry.arch.is_empty() {
+        conditions.push(format!("arch: {}", cmd_entry.arch.join(", ")));
+    }
+    if let Some(ref condition) = cmd_entry.skip_if {
+        conditions.push(format!("skip_if: {}", condition));
+    }
+    if let Some(ref condition) = cmd_entry.only_if {
+        conditions.push(format!("only_if: {}", condition));
+    }
+    if !cmd_entry.sources.is_empty() {
+        conditions.push(format!("sources: {}", cmd_entry.sources.join(", ")));
+    }
+    if !cmd_entry.inputs.is_empty() {
+        conditions.push(format!("inputs: {}", cmd_entry.inputs.join(", ")));
+    }
+    conditions
+}
+
+/// Print that a command was not run because its `check` already succeeds
+pub fn print_command_satisfied(cmd_entry: &CommandEntry, elapsed: Duration, options: &ExecOptions) {
+    report(
+        cmd_entry,
+        options,
+        Event::StepFinished {
+            title: cmd_entry.title.clone(),
+            duration: elapsed,
+            exit_code: None,
+            outcome: StepOutcome::Satisfied,
+        },
+    );
+}
+
+fn render_satisfied(cmd_entry: &CommandEntry, options: &ExecOptions) {
+    println!(
+        "{} {}{} {} {}",
+        style("==>").bold().green(),
+        progress_prefix(options),
+        glyphs::SUCCESS,
+        style(&cmd_entry.title).dim(),
+        style("already satisfied").dim()
+    );
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Ways running a single command can fail
+#[derive(Debug)]
+enum RunError {
+    /// The pseudo-terminal could not be opened
+    #[cfg(unix)]
+    PtyOpen(BoxError),
+    /// The process could not be started
+    Spawn(BoxError),
+    /// Waiting for the process to exit failed
+    Wait(std::io::Error),
+    /// The run was interrupted with Ctrl-C while the process ran
+    Interrupted { title: String, elapsed: Duration },
+    /// The process ran but did not exit successfully
+    NonZero {
+        title: String,
+        status: ExitStatus,
+        elapsed: Duration,
+        /// Last lines of the command's live output, if it passed through getset
+        output_tail: Vec<String>,
+    },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(unix)]
+            RunError::PtyOpen(e) => write!(f, "Failed to open PTY: {}", e),
+            RunError::Spawn(e) => write!(f, "Failed to spawn command: {}", e),
+            RunError::Wait(e) => write!(f, "Failed to wait for command: {}", e),
+            RunError::Interrupted { title, elapsed } => write!(
+                f,
+                "Step '{}' was interrupted after {:.2}s",
+                title,
+                elapsed.as_secs_f64()
+            ),
+            RunError::NonZero {
+                title,
+                status,
+                elapsed,
+                ..
+            } => {
+                let reason = match status.code() {
+                    Some(code) => format!("exit code {}", code),
+                    None => "no exit code (terminated by signal)".to_string(),
+                };
+
+                write!(
+                    f,
+                    "Step '{}' failed with {} after {:.2}s",
+                    title,
+                    reason,
+                    elapsed.as_secs_f64()
+                )
+            }
+        }
+    }
+}
+
 /// Run a command using PTY for better terminal support
-fn run_with_pty(cmd_entry: &CommandEntry, verbose: bool) -> Result<(bool, Duration), String> {
+///
+/// Without a `log` or secrets to redact, the command writes straight to our
+/// terminal. Otherwise it writes to the PTY, sized like our terminal, and
+/// getset copies what it writes to the terminal and the log with secrets
+/// masked, showing a spinner as for piped output. The last lines of the
+/// copied output are returned, to show again under the error.
+#[cfg(unix)]
+fn run_with_pty(
+    cmd_entry: &CommandEntry,
+    options: &ExecOptions,
+    log: Option<File>,
+) -> Result<(ExitStatus, Duration, Vec<String>), RunError> {
     let timer = Instant::now();
-    let (_, pts) =
-        pty_process::blocking::open().map_err(|e| format!("Failed to open PTY: {}", e))?;
+    let (mut pty, pts) = pty_process::blocking::open().map_err(|e| RunError::PtyOpen(e.into()))?;
 
-    let mut args: Vec<&str> = Vec::new();
-    if verbose {
-        args.push("-x");
-    }
+    let (program, args) = invocation(cmd_entry, options);
 
-    // Execute command through shell to support multiline scripts and shell features
-    let mut child = pty_process::blocking::Command::new("sh")
+    let mut command = pty_process::blocking::Command::new(program)
         .args(args)
-        .arg("-c")
-        .arg(&cmd_entry.command)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .spawn(pts)
-        .map_err(|e| format!("Failed to spawn command: {}", e))?;
+        .envs(&cmd_entry.env)
+        .stdin(Stdio::inherit());
+    if let Some(dir) = cmd_entry.working_dir() {
+        command = command.current_dir(dir);
+    }
+    let copy = log.is_some() || redact::active();
+    if !copy {
+        command = command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+    } else if let Some((rows, cols)) = console::Term::stdout().size_checked() {
+        let _ = pty.resize(pty_process::Size::new(rows, cols));
+    }
+
+    let mut child = command.spawn(pts).map_err(|e| RunError::Spawn(e.into()))?;
+
+    let copier = copy.then(|| {
+        let mut sinks = OutputSinks {
+            capture: false,
+            tail: Tail::default(),
+            log,
+            prefix: None,
+            timestamps: false,
+            partial: Default::default(),
+            event_partial: Default::default(),
+            redactors: Default::default(),
+            spinner: (options.log_format == LogFormat::Pretty)
+                .then(|| Spinner::start(&options.label(cmd_entry))),
+            events: None,
+            run_id: String::new(),
+            event_handler: None,
+        };
+        thread::spawn(move || {
+            let mut buf = [0; 8192];
+            // Reading fails with EIO rather than returning 0 once the command exits
+            while let Ok(n) = pty.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                sinks.write(false, &buf[..n]);
+            }
+            sinks.finish();
+            drop(sinks.spinner.take());
+            sinks.tail.into_lines()
+        })
+    });
 
     // Wait for command to complete
-    let status = child
-        .wait()
-        .map_err(|e| format!("Failed to wait for command: {}", e))?;
+    // pty-process starts the command in a new session, and so its own process group
+    let status = wait(&mut child, cmd_entry, timer, true)?;
+    let tail = copier
+        .and_then(|copier| copier.join().ok())
+        .unwrap_or_default();
 
     let elapsed = timer.elapsed();
-    Ok((status.success(), elapsed))
+    Ok((status, elapsed, tail))
 }
 
 /// Run a command without PTY (for non-terminal contexts)
-fn run_without_pty(cmd_entry: &CommandEntry, verbose: bool) -> Result<(bool, Duration), String> {
+fn run_without_pty(
+    cmd_entry: &CommandEntry,
+    options: &ExecOptions,
+) -> Result<(ExitStatus, Duration), RunError> {
     let timer = Instant::now();
 
-    let mut args: Vec<&str> = Vec::new();
-    if verbose {
-        args.push("-x");
+    let (program, args) = invocation(cmd_entry, options);
+
+    let stdin = if options.concurrent {
+        Stdio::null()
+    } else {
+        Stdio::inherit()
+    };
+    let own_group = options.concurrent || !io::stdin().is_terminal();
+
+    let mut command = Command::new(program);
+    command.args(args).envs(&cmd_entry.env);
+    if let Some(dir) = cmd_entry.working_dir() {
+        command.current_dir(dir);
+    }
+    if own_group {
+        interrupt::isolate(&mut command);
     }
 
-    // Execute command through shell to support multiline scripts and shell features
-    let mut child = Command::new("sh")
-        .args(args)
-        .arg("-c")
-        .arg(&cmd_entry.command)
-        .stdin(Stdio::inherit())
+    let mut child = command
+        .stdin(stdin)
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
         .spawn()
-        .map_err(|e| format!("Failed to spawn command: {}", e))?;
+        .map_err(|e| RunError::Spawn(e.into()))?;
 
     // Wait for command to complete
-    let status = child
-        .wait()
-        .map_err(|e| format!("Failed to wait for command: {}", e))?;
+    let status = wait(&mut child, cmd_entry, timer, own_group)?;
 
     let elapsed = timer.elapsed();
-    Ok((status.success(), elapsed))
+    Ok((status, elapsed))
 }
 
-/// Run a command, automatically detecting whether to use PTY or not
-pub fn run_command(cmd_entry: &CommandEntry, verbose: bool) -> Result<Duration, String> {
-    print_command_start(cmd_entry, verbose);
-
-    let (success, elapsed) = if should_use_pty() {
-        // PTY is favored when available (in terminal contexts)
-        // But if it fails, gracefully fall back to non-PTY mode
-        match run_with_pty(cmd_entry, verbose) {
-            Ok(result) => result,
-            Err(e) if e.contains("Failed to open PTY") || e.contains("Failed to spawn command") => {
-                // PTY failed, fall back to non-PTY mode
-                run_without_pty(cmd_entry, verbose)?
+/// Destinations for the output of a piped command
+struct OutputSinks {
+    /// Output is not shown live: it is held back to show only if the command
+    /// fails, or streamed as events
+    capture: bool,
+    /// Last lines of output, shown again if the command fails
+    tail: Tail,
+    log: Option<File>,
+    /// Label put before each live line, so concurrent steps stay readable
+    prefix: Option<String>,
+    /// Put the time before each live line
+    timestamps: bool,
+    /// Incomplete last line of stdout and stderr, held until it ends when prefixing
+    partial: [Vec<u8>; 2],
+    /// Incomplete last line of stdout and stderr, held until it ends for events
+    event_partial: [Vec<u8>; 2],
+    /// Secrets are masked in stdout and stderr before they go anywhere
+    redactors: [StreamRedactor; 2],
+    /// Shown while the command runs, and hidden while live output is printed
+    spinner: Option<Spinner>,
+    /// Step title to emit each output line under as an `output_chunk` event
+    events: Option<String>,
+    /// Id of the run the `output_chunk` events belong to
+    run_id: String,
+    /// Told about the `output_chunk` events instead of them being streamed
+    event_handler: Option<Arc<dyn EventHandler>>,
+}
+
+impl OutputSinks {
+    /// Where the `output_chunk` events go
+    fn emitter(&self) -> Emitter<'_> {
+        Emitter {
+            log_format: LogFormat::Stream,
+            run_id: &self.run_id,
+            handler: self.event_handler.as_deref(),
+        }
+    }
+
+    fn write(&mut self, to_stderr: bool, bytes: &[u8]) {
+        let redacted = self.redactors[usize::from(to_stderr)].push(bytes);
+        self.write_redacted(to_stderr, &redacted);
+    }
+
+    fn write_redacted(&mut self, to_stderr: bool, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        if let Some(ref title) = self.events {
+            for line in complete_lines(&mut self.event_partial[usize::from(to_stderr)], bytes) {
+                events::output_chunk(self.emitter(), title, to_stderr, &line);
             }
-            Err(e) => return Err(e),
         }
+        if !self.capture {
+            self.show(to_stderr, bytes);
+        }
+        self.tail.push(to_stderr, bytes);
+
+        if let Some(ref mut log) = self.log
+            && let Err(e) = log.write_all(bytes)
+        {
+            tracing::warn!("Failed to write step log: {}", e);
+            self.log = None;
+        }
+    }
+
+    /// Print output live
+    fn show(&mut self, to_stderr: bool, bytes: &[u8]) {
+        if self.prefix.is_some() || self.timestamps {
+            for line in complete_lines(&mut self.partial[usize::from(to_stderr)], bytes) {
+                let line = prefixed(&self.line_prefix(), &line);
+                match self.spinner {
+                    Some(ref spinner) => spinner.hide_while(true, || write_live(to_stderr, &line)),
+                    None => write_live(to_stderr, &line),
+                }
+            }
+            return;
+        }
+        match self.spinner {
+            Some(ref spinner) => {
+                spinner.hide_while(bytes.ends_with(b"\n"), || write_live(to_stderr, bytes))
+            }
+            None => write_live(to_stderr, bytes),
+        }
+    }
+
+    /// What goes before each line printed a line at a time: the time, then the label
+    fn line_prefix(&self) -> String {
+        let time = if self.timestamps {
+            style(format!("[{}] ", Local::now().format("%H:%M:%S")))
+                .dim()
+                .to_string()
+        } else {
+            String::new()
+        };
+        format!("{}{}", time, self.prefix.as_deref().unwrap_or_default())
+    }
+
+    /// Emit and print any unterminated last lines once the command has exited
+    fn finish(&mut self) {
+        for to_stderr in [false, true] {
+            let rest = self.redactors[usize::from(to_stderr)].finish();
+            self.write_redacted(to_stderr, &rest);
+        }
+        self.tail.finish();
+        let emitter = Emitter {
+            log_format: LogFormat::Stream,
+            run_id: &self.run_id,
+            handler: self.event_handler.as_deref(),
+        };
+        for (to_stderr, partial) in [false, true].into_iter().zip(&mut self.event_partial) {
+            if let Some(ref title) = self.events
+                && !partial.is_empty()
+            {
+                events::output_chunk(emitter, title, to_stderr, partial);
+            }
+            partial.clear();
+        }
+        let line_prefix = (self.prefix.is_some() || self.timestamps).then(|| self.line_prefix());
+        for (to_stderr, partial) in [false, true].into_iter().zip(&mut self.partial) {
+            if let Some(ref prefix) = line_prefix
+                && !partial.is_empty()
+            {
+                partial.push(b'\n');
+                write_live(to_stderr, &prefixed(prefix, partial));
+            }
+            partial.clear();
+        }
+    }
+}
+
+/// How many of its last lines of output a failed step shows
+const TAIL_LINES: usize = 50;
+
+/// The last [`TAIL_LINES`] lines a command printed, and whether each went to stderr
+#[derive(Default)]
+struct Tail {
+    lines: VecDeque<(bool, String)>,
+    /// Number of earlier lines no longer kept
+    dropped: usize,
+    partial: [Vec<u8>; 2],
+    /// Keep only lines printed to stderr
+    stderr_only: bool,
+}
+
+impl Tail {
+    fn new(stderr_only: bool) -> Self {
+        Self {
+            stderr_only,
+            ..Default::default()
+        }
+    }
+
+    fn push(&mut self, to_stderr: bool, bytes: &[u8]) {
+        if self.stderr_only && !to_stderr {
+            return;
+        }
+        for line in complete_lines(&mut self.partial[usize::from(to_stderr)], bytes) {
+            self.add(to_stderr, &line);
+        }
+    }
+
+    fn add(&mut self, to_stderr: bool, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches(['\r', '\n']).to_string();
+        self.lines.push_back((to_stderr, line));
+        if self.lines.len() > TAIL_LINES {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    /// Keep any unterminated last lines once the command has exited
+    fn finish(&mut self) {
+        let partials = std::mem::take(&mut self.partial);
+        for (to_stderr, partial) in [false, true].into_iter().zip(partials) {
+            if !partial.is_empty() {
+                self.add(to_stderr, &partial);
+            }
+        }
+    }
+
+    /// The kept lines, preceded by a note of how many earlier lines were left out
+    ///
+    /// Lines from stderr are shown in red, or tagged when colors are off.
+    fn into_lines(self) -> Vec<String> {
+        let note = (self.dropped > 0).then(|| format!("... {} earlier lines", self.dropped));
+        let tag_stderr = !self.stderr_only && !console::colors_enabled();
+        let lines = self.lines.into_iter().map(|(to_stderr, line)| {
+            if !to_stderr {
+                line
+            } else if tag_stderr {
+                format!("{} {}", STDERR_TAG, line)
+            } else {
+                style(line).red().to_string()
+            }
+        });
+        note.into_iter().chain(lines).collect()
+    }
+}
+
+/// Marks stderr lines in the output of a failed step when colors are off
+const STDERR_TAG: &str = "[stderr]";
+
+/// Add `bytes` to the unterminated line in `partial`, taking out any lines it completes
+fn complete_lines(partial: &mut Vec<u8>, bytes: &[u8]) -> Vec<Vec<u8>> {
+    partial.extend_from_slice(bytes);
+    let mut lines = Vec::new();
+    while let Some(end) = partial.iter().position(|&b| b == b'\n') {
+        lines.push(partial.drain(..=end).collect());
+    }
+    lines
+}
+
+fn write_live(to_stderr: bool, bytes: &[u8]) {
+    if to_stderr {
+        let _ = io::stderr().write_all(bytes);
     } else {
-        // Fall back to non-PTY mode in non-terminal contexts
-        run_without_pty(cmd_entry, verbose)?
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(bytes);
+        let _ = stdout.flush();
+    }
+}
+
+fn prefixed(prefix: &str, line: &[u8]) -> Vec<u8> {
+    let mut out = prefix.as_bytes().to_vec();
+    out.extend_from_slice(line);
+    out
+}
+
+/// Colors cycled through for the prefixes of concurrent steps
+pub(crate) const PREFIX_COLORS: [console::Color; 6] = [
+    console::Color::Cyan,
+    console::Color::Magenta,
+    console::Color::Yellow,
+    console::Color::Green,
+    console::Color::Blue,
+    console::Color::Red,
+];
+
+/// Longest step name shown in output prefixes
+const PREFIX_WIDTH: usize = 16;
+
+/// Colored `name | ` label for a step's output lines, using its id or shortened title
+///
+/// The color is derived from the name so a step keeps its color across runs.
+fn output_prefix(cmd_entry: &CommandEntry) -> String {
+    let name = cmd_entry.id.as_deref().unwrap_or(&cmd_entry.title);
+    let short: String = if name.chars().count() > PREFIX_WIDTH {
+        let ellipsis = glyphs::ELLIPSIS.as_str();
+        let mut short: String = name
+            .chars()
+            .take(PREFIX_WIDTH - ellipsis.chars().count())
+            .collect();
+        short.push_str(ellipsis);
+        short
+    } else {
+        name.to_string()
+    };
+
+    let hash = name
+        .bytes()
+        .fold(0usize, |h, b| h.wrapping_mul(31) + usize::from(b));
+    let color = PREFIX_COLORS[hash % PREFIX_COLORS.len()];
+    format!(
+        "{} ",
+        style(format!("{:<width$} |", short, width = PREFIX_WIDTH)).fg(color)
+    )
+}
+
+/// Run a command with its stdout and stderr read through pipes
+///
+/// Output is copied to the step's log file, if any, and either shown live or
+/// captured, with its last lines printed only if the command fails. A spinner
+/// with the elapsed time is shown while a command runs alone in a terminal,
+/// hidden whenever it prints, and live lines of concurrent commands are prefixed
+/// with the step name. With `--output json`, each line is emitted as an
+/// `output_chunk` event instead, and an event handler gets each line as well.
+/// The last lines of live output are returned, to show again under the error.
+fn run_piped(
+    cmd_entry: &CommandEntry,
+    options: &ExecOptions,
+    log: Option<File>,
+) -> Result<(ExitStatus, Duration, Vec<String>), RunError> {
+    let timer = Instant::now();
+    let streaming = options.log_format == LogFormat::Stream;
+    let capture = options.captures(cmd_entry) && !streaming;
+
+    let (program, args) = invocation(cmd_entry, options);
+
+    let stdin = if capture || options.concurrent {
+        Stdio::null()
+    } else {
+        Stdio::inherit()
     };
+    let own_group = capture || options.concurrent || !io::stdin().is_terminal();
+
+    let mut command = Command::new(program);
+    command.args(args).envs(&cmd_entry.env);
+    if let Some(dir) = cmd_entry.working_dir() {
+        command.current_dir(dir);
+    }
+    if own_group {
+        interrupt::isolate(&mut command);
+    }
+
+    let mut child = command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| RunError::Spawn(e.into()))?;
+
+    let spinner = (!options.concurrent && options.log_format == LogFormat::Pretty)
+        .then(|| Spinner::start(&options.label(cmd_entry)));
+
+    let sinks = Arc::new(Mutex::new(OutputSinks {
+        capture: capture || streaming,
+        tail: Tail::new(options.stderr_only),
+        log,
+        prefix: (options.concurrent && !streaming).then(|| output_prefix(cmd_entry)),
+        timestamps: options.timestamps && !streaming,
+        partial: Default::default(),
+        event_partial: Default::default(),
+        redactors: Default::default(),
+        spinner,
+        events: (streaming || options.event_handler.is_some()).then(|| cmd_entry.title.clone()),
+        run_id: options.run_id.clone(),
+        event_handler: options.event_handler.clone(),
+    }));
+    let readers: Vec<_> = [
+        child
+            .stdout
+            .take()
+            .map(|out| (false, Box::new(out) as Box<dyn Read + Send>)),
+        child
+            .stderr
+            .take()
+            .map(|err| (true, Box::new(err) as Box<dyn Read + Send>)),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(to_stderr, mut pipe)| {
+        let sinks = Arc::clone(&sinks);
+        thread::spawn(move || {
+            let mut buf = [0; 8192];
+            while let Ok(n) = pipe.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                sinks.lock().unwrap().write(to_stderr, &buf[..n]);
+            }
+        })
+    })
+    .collect();
+
+    let status = wait(&mut child, cmd_entry, timer, own_group)?;
+    for reader in readers {
+        let _ = reader.join();
+    }
+    let mut sinks = sinks.lock().unwrap();
+    sinks.finish();
+    drop(sinks.spinner.take());
+    let tail = std::mem::take(&mut sinks.tail).into_lines();
+
+    let elapsed = timer.elapsed();
+    if !capture {
+        return Ok((status, elapsed, tail));
+    }
+    if !status.success() {
+        let mut stdout = io::stdout().lock();
+        for line in &tail {
+            let _ = writeln!(stdout, "{}", line);
+        }
+        let _ = stdout.flush();
+    }
+    Ok((status, elapsed, Vec::new()))
+}
+
+/// Open the step's log file in the run's log directory, if logging is enabled
+fn open_log(cmd_entry: &CommandEntry, options: &ExecOptions) -> Option<File> {
+    let path = logs::step_log_path(options.log_dir.as_ref()?, &cmd_entry.title);
+    match File::create(&path) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            tracing::warn!("Failed to create step log '{}': {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Wait for a spawned command, reporting an interrupted run as [`RunError::Interrupted`]
+///
+/// `own_group` says whether the command was started in its own process group.
+fn wait(
+    child: &mut Child,
+    cmd_entry: &CommandEntry,
+    timer: Instant,
+    own_group: bool,
+) -> Result<ExitStatus, RunError> {
+    interrupt::wait(child, own_group)
+        .map_err(RunError::Wait)?
+        .ok_or_else(|| RunError::Interrupted {
+            title: cmd_entry.title.clone(),
+            elapsed: timer.elapsed(),
+        })
+}
+
+/// Try the PTY runner, falling back when the PTY itself could not be used
+///
+/// Only PTY setup failures fall back; a command that ran and failed is
+/// returned as-is so it is not executed twice.
+#[cfg(unix)]
+fn with_pty_fallback<T>(
+    pty: impl FnOnce() -> Result<T, RunError>,
+    fallback: impl FnOnce() -> Result<T, RunError>,
+) -> Result<T, RunError> {
+    match pty() {
+        Err(e @ (RunError::PtyOpen(_) | RunError::Spawn(_))) => {
+            tracing::debug!("{}; falling back to non-PTY mode", e);
+            fallback()
+        }
+        result => result,
+    }
+}
 
-    print_command_result(cmd_entry, elapsed, success);
+/// Run a command in a PTY attached to our terminal, copying its output to `log`
+///
+/// Without a PTY, a logged command, or one whose output has secrets to
+/// redact, falls back to having its output piped.
+#[cfg(unix)]
+fn run_in_terminal(
+    cmd_entry: &CommandEntry,
+    options: &ExecOptions,
+    log: Option<File>,
+) -> Result<(ExitStatus, Duration, Vec<String>), RunError> {
+    let fallback_log = log.as_ref().and_then(|log| log.try_clone().ok());
+    with_pty_fallback(
+        || run_with_pty(cmd_entry, options, log),
+        || match fallback_log {
+            Some(log) => run_piped(cmd_entry, options, Some(log)),
+            None if redact::active() => run_piped(cmd_entry, options, None),
+            None => {
+                let (status, elapsed) = run_without_pty(cmd_entry, options)?;
+                Ok((status, elapsed, Vec::new()))
+            }
+        },
+    )
+}
 
-    if success {
+/// Run a command attached to our console, which needs no PTY on Windows
+///
+/// A logged command, or one whose output has secrets to redact, has its
+/// output piped instead.
+#[cfg(not(unix))]
+fn run_in_terminal(
+    cmd_entry: &CommandEntry,
+    options: &ExecOptions,
+    log: Option<File>,
+) -> Result<(ExitStatus, Duration, Vec<String>), RunError> {
+    match log {
+        Some(log) => run_piped(cmd_entry, options, Some(log)),
+        None if redact::active() => run_piped(cmd_entry, options, None),
+        None => {
+            let (status, elapsed) = run_without_pty(cmd_entry, options)?;
+            Ok((status, elapsed, Vec::new()))
+        }
+    }
+}
+
+/// Run a command, preferring PTY mode in terminal contexts
+fn execute(cmd_entry: &CommandEntry, options: &ExecOptions) -> Result<Duration, RunError> {
+    if let Some(dir) = cmd_entry.working_dir()
+        && !dir.is_dir()
+    {
+        return Err(RunError::Spawn(
+            format!("working directory '{}' does not exist", dir.display()).into(),
+        ));
+    }
+
+    let log = open_log(cmd_entry, options);
+    let (program, args) = invocation(cmd_entry, options);
+    tracing::debug!(
+        "Step '{}' runs {} {}",
+        cmd_entry.title,
+        program,
+        redact::redact(&format!("{:?}", args))
+    );
+    let terminal = should_use_pty() && !options.concurrent;
+    let piped = options.captures(cmd_entry)
+        || options.concurrent
+        || ((log.is_some() || redact::active()) && !terminal)
+        || options.log_format == LogFormat::Stream
+        || options.timestamps
+        || options.event_handler.is_some();
+    let (status, elapsed, output_tail) = if piped {
+        tracing::debug!("Reading output of '{}' through pipes", cmd_entry.title);
+        run_piped(cmd_entry, options, log)?
+    } else if terminal {
+        tracing::debug!("Running '{}' in a PTY", cmd_entry.title);
+        run_in_terminal(cmd_entry, options, log)?
+    } else {
+        // Fall back to non-PTY mode in non-terminal or concurrent contexts
+        tracing::debug!("Running '{}' attached to our stdout", cmd_entry.title);
+        let (status, elapsed) = run_without_pty(cmd_entry, options)?;
+        (status, elapsed, Vec::new())
+    };
+    tracing::debug!("Step '{}' exited with {}", cmd_entry.title, status);
+
+    if status.success() {
         Ok(elapsed)
     } else {
-        Err("Command exited with non-zero status".to_string())
+        Err(RunError::NonZero {
+            title: cmd_entry.title.clone(),
+            status,
+            elapsed,
+            output_tail,
+        })
     }
 }
 
+/// Why a command did not succeed
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Failure {
+    pub message: String,
+    /// Last lines of the command's output, to show again under the error
+    ///
+    /// Empty when the output did not pass through getset, or was already shown
+    /// because the step's output was captured.
+    pub output_tail: Vec<String>,
+}
+
+impl Failure {
+    /// A failure described by `message`, with no output to show under it
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            output_tail: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Run a command, automatically detecting whether to use PTY or not
+pub fn run_command(cmd_entry: &CommandEntry, options: &ExecOptions) -> Result<Duration, Failure> {
+    print_command_start(cmd_entry, options);
+
+    let result = execute(cmd_entry, options);
+
+    match &result {
+        Ok(elapsed) => print_command_result(cmd_entry, *elapsed, None, options),
+        Err(RunError::NonZero {
+            status, elapsed, ..
+        }) => print_command_result(cmd_entry, *elapsed, Some(*status), options),
+        Err(RunError::Interrupted { elapsed, .. }) => {
+            print_command_interrupted(cmd_entry, *elapsed, options)
+        }
+        Err(_) => {
+            if let Some(ci) = options.ci_sections() {
+                println!("{}", ci::end_section(ci, cmd_entry));
+            }
+            if let Some(annotation) = options
+                .ci
+                .and_then(|ci| ci::failure_annotation(ci, cmd_entry))
+            {
+                println!("{}", annotation);
+            }
+        }
+    }
+
+    result.map_err(|e| {
+        let message = e.to_string();
+        match e {
+            RunError::NonZero { output_tail, .. } => Failure {
+                message,
+                output_tail,
+            },
+            _ => Failure::new(message),
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Shell;
 
     #[test]
     fn test_run_command_success() {
         let cmd = CommandEntry {
             title: "Test echo".to_string(),
-            command: "echo 'test'".to_string(),
+            command: Some("echo 'test'".to_string()),
+            ..Default::default()
         };
 
-        let result = run_command(&cmd, false);
+        let result = run_command(&cmd, &ExecOptions::default());
         assert!(result.is_ok(), "Command should succeed");
     }
 
@@ -152,22 +1287,44 @@ mod tests {
     fn test_run_command_failure() {
         let cmd = CommandEntry {
             title: "Test false".to_string(),
-            command: "false".to_string(),
+            command: Some("false".to_string()),
+            ..Default::default()
         };
 
-        let result = run_command(&cmd, false);
+        let result = run_command(&cmd, &ExecOptions::default());
         assert!(result.is_err(), "Command should fail");
-        assert!(result.unwrap_err().contains("non-zero status"));
+        assert!(
+            result
+                .unwrap_err()
+                .message
+                .contains("failed with exit code 1")
+        );
+    }
+
+    #[test]
+    fn test_run_command_failure_reports_title_and_exit_code() {
+        let cmd = CommandEntry {
+            title: "Build backend".to_string(),
+            command: Some("exit 2".to_string()),
+            ..Default::default()
+        };
+
+        let err = run_command(&cmd, &ExecOptions::default())
+            .unwrap_err()
+            .message;
+        assert!(err.starts_with("Step 'Build backend' failed with exit code 2 after "));
+        assert!(err.ends_with('s'));
     }
 
     #[test]
     fn test_run_command_with_output() {
         let cmd = CommandEntry {
             title: "Test ls".to_string(),
-            command: "ls -la".to_string(),
+            command: Some("ls -la".to_string()),
+            ..Default::default()
         };
 
-        let result = run_command(&cmd, false);
+        let result = run_command(&cmd, &ExecOptions::default());
         assert!(result.is_ok(), "Command should succeed");
     }
 
@@ -175,26 +1332,387 @@ mod tests {
     fn test_run_without_pty_success() {
         let cmd = CommandEntry {
             title: "Test non-PTY echo".to_string(),
-            command: "echo 'non-pty test'".to_string(),
+            command: Some("echo 'non-pty test'".to_string()),
+            ..Default::default()
         };
 
-        let result = run_without_pty(&cmd, false);
+        let result = run_without_pty(&cmd, &ExecOptions::default());
         assert!(result.is_ok(), "Non-PTY command should succeed");
-        let (success, _) = result.unwrap();
-        assert!(success, "Command should return success");
+        let (status, _) = result.unwrap();
+        assert!(status.success(), "Command should return success");
     }
 
     #[test]
     fn test_run_without_pty_failure() {
         let cmd = CommandEntry {
             title: "Test non-PTY false".to_string(),
-            command: "exit 1".to_string(),
+            command: Some("exit 1".to_string()),
+            ..Default::default()
         };
 
-        let result = run_without_pty(&cmd, false);
+        let result = run_without_pty(&cmd, &ExecOptions::default());
         assert!(result.is_ok(), "Non-PTY command should return a result");
-        let (success, _) = result.unwrap();
-        assert!(!success, "Command should return failure");
+        let (status, _) = result.unwrap();
+        assert!(!status.success(), "Command should return failure");
+        assert_eq!(status.code(), Some(1));
+    }
+
+    #[test]
+    fn test_run_without_pty_argv_preserves_spaces() {
+        let cmd = CommandEntry {
+            title: "Test argv".to_string(),
+            argv: Some(vec![
+                "test".to_string(),
+                "hello world".to_string(),
+                "=".to_string(),
+                "hello world".to_string(),
+            ]),
+            ..Default::default()
+        };
+
+        let (status, _) =
+            run_without_pty(&cmd, &ExecOptions::default()).expect("argv command should spawn");
+        assert!(
+            status.success(),
+            "Argument with spaces should be passed as a single argv entry"
+        );
+    }
+
+    #[test]
+    fn test_run_without_pty_sets_step_env() {
+        let cmd = CommandEntry {
+            title: "Test env".to_string(),
+            command: Some(r#"test "$RAILS_ENV" = "development""#.to_string()),
+            env: [("RAILS_ENV".to_string(), "development".to_string())].into(),
+            ..Default::default()
+        };
+
+        let (status, _) = run_without_pty(&cmd, &ExecOptions::default()).unwrap();
+        assert!(
+            status.success(),
+            "Step env should be visible to the command"
+        );
+    }
+
+    #[test]
+    fn test_run_command_missing_cwd_errors() {
+        let cmd = CommandEntry {
+            title: "Test missing cwd".to_string(),
+            command: Some("true".to_string()),
+            cwd: Some("getset-test-no-such-dir".into()),
+            ..Default::default()
+        };
+
+        let err = run_command(&cmd, &ExecOptions::default())
+            .unwrap_err()
+            .message;
+        assert!(err.contains("working directory 'getset-test-no-such-dir' does not exist"));
+    }
+
+    #[test]
+    fn test_invocation_uses_step_shell() {
+        let cmd = CommandEntry {
+            title: "Test shell".to_string(),
+            command: Some("echo hi".to_string()),
+            shell: Some(Shell::Argv(vec!["bash".to_string(), "-lc".to_string()])),
+            ..Default::default()
+        };
+        let verbose = ExecOptions {
+            verbose: SHOW_COMMANDS,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            invocation(&cmd, &ExecOptions::default()),
+            (
+                "bash".to_string(),
+                vec!["-lc".to_string(), "echo hi".to_string()]
+            )
+        );
+        assert_eq!(invocation(&cmd, &verbose).1, ["-x", "-lc", "echo hi"]);
+    }
+
+    #[test]
+    fn test_run_without_pty_with_bash_shell() {
+        let cmd = CommandEntry {
+            title: "Test bash arrays".to_string(),
+            command: Some(r#"items=(a b c); test "${#items[@]}" = 3"#.to_string()),
+            shell: Some(Shell::Program("bash".to_string())),
+            ..Default::default()
+        };
+
+        let (status, _) = run_without_pty(&cmd, &ExecOptions::default()).unwrap();
+        assert!(
+            status.success(),
+            "bash arrays should work with shell = \"bash\""
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pty_open_failure_falls_back() {
+        let mut fell_back = false;
+
+        let result = with_pty_fallback(
+            || {
+                Err(RunError::PtyOpen(
+                    io::Error::other("no ptys available").into(),
+                ))
+            },
+            || {
+                fell_back = true;
+                Ok(())
+            },
+        );
+
+        assert!(result.is_ok());
+        assert!(fell_back, "PTY open failure should trigger fallback");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_command_failure_does_not_fall_back() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut fell_back = false;
+
+        let result = with_pty_fallback(
+            || Ok(ExitStatus::from_raw(2 << 8)),
+            || {
+                fell_back = true;
+                Ok(ExitStatus::from_raw(0))
+            },
+        );
+
+        assert_eq!(result.unwrap().code(), Some(2));
+        assert!(!fell_back, "A failed command must not be re-run");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_wait_failure_does_not_fall_back() {
+        let result: Result<(), RunError> = with_pty_fallback(
+            || Err(RunError::Wait(io::Error::other("interrupted"))),
+            || panic!("fallback should not run"),
+        );
+
+        assert!(matches!(result, Err(RunError::Wait(_))));
+    }
+
+    #[test]
+    fn test_spawn_failure_is_reported() {
+        let cmd = CommandEntry {
+            title: "Missing binary".to_string(),
+            argv: Some(vec!["getset-test-no-such-binary".to_string()]),
+            ..Default::default()
+        };
+
+        let err = run_command(&cmd, &ExecOptions::default())
+            .unwrap_err()
+            .message;
+        assert!(err.starts_with("Failed to spawn command:"));
+    }
+
+    #[test]
+    fn test_condition_succeeds() {
+        let cmd = CommandEntry {
+            title: "Conditional".to_string(),
+            env: [("GETSET_FLAG".to_string(), "on".to_string())].into(),
+            ..Default::default()
+        };
+
+        assert!(condition_succeeds(&cmd, "test \"$GETSET_FLAG\" = on"));
+        assert!(!condition_succeeds(&cmd, "exit 1"));
+        assert!(!condition_succeeds(
+            &CommandEntry {
+                shell: Some(Shell::Program("/nonexistent/shell".to_string())),
+                ..Default::default()
+            },
+            "true"
+        ));
+    }
+
+    #[test]
+    fn test_run_captured_reports_exit_status() {
+        let cmd = CommandEntry {
+            title: "Test captured".to_string(),
+            command: Some("echo out; echo err >&2; exit 3".to_string()),
+            capture: true,
+            ..Default::default()
+        };
+
+        let (status, _, tail) = run_piped(&cmd, &ExecOptions::default(), None).unwrap();
+        assert_eq!(status.code(), Some(3));
+        // Already shown when the step failed
+        assert!(tail.is_empty());
+
+        let err = run_command(&cmd, &ExecOptions::default()).unwrap_err();
+        assert!(err.message.contains("failed with exit code 3"));
+        assert!(err.output_tail.is_empty());
+    }
+
+    #[test]
+    fn test_live_output_tail_keeps_last_lines() {
+        let cmd = CommandEntry {
+            title: "Test tail".to_string(),
+            command: Some("seq 1 60; printf 'no newline' >&2; exit 1".to_string()),
+            ..Default::default()
+        };
+
+        let (_, _, tail) = run_piped(&cmd, &ExecOptions::default(), None).unwrap();
+        assert_eq!(tail.len(), TAIL_LINES + 1);
+        assert_eq!(tail[0], "... 11 earlier lines");
+        assert_eq!(tail[1], "12");
+        assert!(tail.last().unwrap().ends_with("no newline"));
+    }
+
+    #[test]
+    fn test_tail_marks_or_keeps_only_stderr() {
+        let mut tail = Tail::new(false);
+        tail.push(false, b"compiling\n");
+        tail.push(true, b"error: missing crate\n");
+        let lines = tail.into_lines();
+        assert_eq!(lines[0], "compiling");
+        assert_eq!(
+            console::strip_ansi_codes(&lines[1]).trim_start_matches("[stderr] "),
+            "error: missing crate"
+        );
+
+        let mut tail = Tail::new(true);
+        tail.push(false, b"compiling\n");
+        tail.push(true, b"error: missing crate");
+        tail.finish();
+        assert_eq!(tail.lines, [(true, "error: missing crate".to_string())]);
+    }
+
+    #[test]
+    fn test_run_command_writes_step_log() {
+        let dir = std::env::temp_dir().join(format!("getset-step-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cmd = CommandEntry {
+            title: "Test log".to_string(),
+            command: Some("echo to-stdout; echo to-stderr >&2".to_string()),
+            ..Default::default()
+        };
+        let options = ExecOptions {
+            log_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+
+        run_command(&cmd, &options).unwrap();
+
+        let log = std::fs::read_to_string(dir.join("test-log.log")).unwrap();
+        assert!(log.contains("to-stdout"));
+        assert!(log.contains("to-stderr"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_with_pty_copies_output_to_log() {
+        let dir = std::env::temp_dir().join(format!("getset-pty-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("pty.log");
+        let cmd = CommandEntry {
+            title: "PTY log".to_string(),
+            command: Some("test -t 1 && echo in-a-terminal".to_string()),
+            ..Default::default()
+        };
+
+        let result = run_with_pty(
+            &cmd,
+            &ExecOptions::default(),
+            Some(File::create(&path).unwrap()),
+        );
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        let Ok((status, _, tail)) = result else {
+            return; // No PTYs in this environment
+        };
+        assert!(status.success(), "the command's stdout should be the PTY");
+        assert_eq!(log, "in-a-terminal\r\n");
+        assert_eq!(tail, ["in-a-terminal"]);
+    }
+
+    #[test]
+    fn test_registered_secrets_are_redacted_from_step_log() {
+        let dir = std::env::temp_dir().join(format!("getset-redact-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        redact::register("getset-test-s3cr3t");
+        let cmd = CommandEntry {
+            title: "Leaky".to_string(),
+            command: Some("echo token=getset-test-s3cr3t; exit 1".to_string()),
+            ..Default::default()
+        };
+        let options = ExecOptions {
+            log_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+
+        let failure = run_command(&cmd, &options).unwrap_err();
+
+        let log = std::fs::read_to_string(dir.join("leaky.log")).unwrap();
+        assert_eq!(log, "token=[REDACTED]\n");
+        assert_eq!(failure.output_tail, ["token=[REDACTED]"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prefixed_output_waits_for_whole_lines() {
+        let mut sinks = OutputSinks {
+            capture: false,
+            tail: Tail::default(),
+            log: None,
+            prefix: Some("db | ".to_string()),
+            timestamps: false,
+            partial: Default::default(),
+            event_partial: Default::default(),
+            redactors: Default::default(),
+            spinner: None,
+            events: None,
+            run_id: String::new(),
+            event_handler: None,
+        };
+
+        sinks.write(false, b"one\ntw");
+        assert_eq!(sinks.partial[0], b"tw");
+        sinks.write(false, b"o\n");
+        assert!(sinks.partial[0].is_empty());
+        sinks.write(true, b"no newline");
+        sinks.finish();
+        assert!(sinks.partial[1].is_empty());
+    }
+
+    #[test]
+    fn test_output_prefix_shortens_long_titles() {
+        let cmd = CommandEntry {
+            title: "Install JavaScript dependencies".to_string(),
+            ..Default::default()
+        };
+
+        let prefix = console::strip_ansi_codes(&output_prefix(&cmd)).to_string();
+        assert_eq!(prefix, "Install JavaScr… | ");
+    }
+
+    #[test]
+    fn test_label_shows_progress() {
+        let cmd = CommandEntry {
+            title: "Install gems".to_string(),
+            ..Default::default()
+        };
+        let options = ExecOptions::default();
+
+        assert_eq!(options.label(&cmd), "Install gems");
+        assert_eq!(
+            options
+                .at(Progress {
+                    current: 3,
+                    total: 12
+                })
+                .label(&cmd),
+            "[3/12] Install gems"
+        );
     }
 
     #[test]
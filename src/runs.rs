@@ -0,0 +1,303 @@
+use crate::orchestrator::{RunSummary, StepResult};
+use color_eyre::eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where a record of each `getset up` run is stored, relative to the config file
+pub const RUNS_DIR: &str = ".getset/runs";
+
+/// How many run records are kept; older ones are removed
+const MAX_RUNS: usize = 50;
+
+/// A step changing by less than this is reported as unchanged
+const MIN_CHANGE: Duration = Duration::from_secs(1);
+
+/// A step changing by less than this fraction of its earlier duration is reported as unchanged
+const MIN_CHANGE_RATIO: f64 = 0.2;
+
+/// What a run did, kept so later runs can be compared against it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// The run's [`RunSummary::uuid`]
+    pub id: String,
+    /// When the run finished, as an RFC 3339 UTC timestamp that sorts in run order
+    #[serde(default)]
+    pub finished_at: String,
+    pub success: bool,
+    pub duration_secs: f64,
+    pub steps: Vec<StepRecord>,
+}
+
+/// How a step went in a recorded run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub title: String,
+    pub duration_secs: f64,
+    /// Whether the step's command ran, rather than being skipped or already satisfied
+    pub ran: bool,
+    pub success: bool,
+}
+
+impl RunRecord {
+    pub fn from_summary(summary: &RunSummary) -> Self {
+        Self {
+            id: summary.uuid.clone(),
+            finished_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            success: summary.success(),
+            duration_secs: summary.duration.as_secs_f64(),
+            steps: summary.steps.iter().map(StepRecord::from_result).collect(),
+        }
+    }
+
+    /// Store the record in `base_dir`, removing the oldest records beyond the limit
+    pub fn save(&self, base_dir: &Path) -> Result<()> {
+        let dir = base_dir.join(RUNS_DIR);
+        fs::create_dir_all(&dir).map_err(|e| eyre!("Error creating '{}': {}", dir.display(), e))?;
+
+        let path = record_path(base_dir, &self.id);
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content)
+            .map_err(|e| eyre!("Error writing '{}': {}", path.display(), e))?;
+
+        let ids = list(base_dir)?;
+        for id in &ids[..ids.len().saturating_sub(MAX_RUNS)] {
+            let _ = fs::remove_file(record_path(base_dir, id));
+        }
+        Ok(())
+    }
+
+    /// Load the record of the run with this id, or the only one starting with it
+    pub fn load(base_dir: &Path, id: &str) -> Result<Self> {
+        let missing = || {
+            eyre!(
+                "No recorded run '{}'; run `getset report` to list recorded runs",
+                id
+            )
+        };
+        let mut path = record_path(base_dir, id);
+        if !path.is_file() {
+            let ids = list(base_dir)?;
+            let mut matches = ids.iter().filter(|known| known.starts_with(id));
+            match (matches.next(), matches.next()) {
+                (Some(only), None) => path = record_path(base_dir, only),
+                (Some(_), Some(_)) => {
+                    return Err(eyre!("Several recorded runs start with '{}'", id));
+                }
+                _ => return Err(missing()),
+            }
+        }
+
+        let content = fs::read_to_string(&path).map_err(|_| missing())?;
+        serde_json::from_str(&content)
+            .map_err(|e| eyre!("Error reading run record '{}': {}", path.display(), e))
+    }
+
+    fn step(&self, title: &str) -> Option<&StepRecord> {
+        self.steps.iter().find(|step| step.title == title)
+    }
+}
+
+impl StepRecord {
+    fn from_result(result: &StepResult) -> Self {
+        Self {
+            title: result.title.clone(),
+            duration_secs: result.duration.as_secs_f64(),
+            ran: result.skip_reason.is_none() && !result.satisfied,
+            success: result.success,
+        }
+    }
+}
+
+fn record_path(base_dir: &Path, id: &str) -> PathBuf {
+    base_dir.join(RUNS_DIR).join(format!("{}.json", id))
+}
+
+/// Ids of the recorded runs in `base_dir`, oldest first
+///
+/// Records are ordered by when their run finished; unreadable ones come first.
+pub fn list(base_dir: &Path) -> Result<Vec<String>> {
+    let dir = base_dir.join(RUNS_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(eyre!("Error reading '{}': {}", dir.display(), e)),
+    };
+
+    let mut runs: Vec<(String, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let id = (path.extension()? == "json")
+                .then(|| path.file_stem()?.to_str().map(str::to_string))??;
+            let finished_at = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<RunRecord>(&content).ok())
+                .map(|record| record.finished_at)
+                .unwrap_or_default();
+            Some((finished_at, id))
+        })
+        .collect();
+    runs.sort();
+    Ok(runs.into_iter().map(|(_, id)| id).collect())
+}
+
+/// How a step's duration changed between two runs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Slower,
+    Faster,
+    Unchanged,
+    /// The step ran only in the current run
+    New,
+    /// The step ran only in the earlier run
+    NotRun,
+}
+
+/// One step of a comparison between two runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepComparison {
+    pub title: String,
+    /// Duration in the earlier run, if the step ran then
+    pub before: Option<Duration>,
+    /// Duration in the current run, if the step ran then
+    pub after: Option<Duration>,
+    pub change: Change,
+}
+
+/// Compare the steps that ran in `current` against the same steps in `earlier`
+///
+/// Steps are listed in the order of the current run, followed by steps that
+/// only ran in the earlier one.
+pub fn compare(earlier: &RunRecord, current: &RunRecord) -> Vec<StepComparison> {
+    let ran = |record: &RunRecord, title: &str| {
+        record
+            .step(title)
+            .filter(|step| step.ran)
+            .map(|step| Duration::from_secs_f64(step.duration_secs))
+    };
+
+    let mut titles: Vec<&str> = current.steps.iter().map(|s| s.title.as_str()).collect();
+    for step in &earlier.steps {
+        if !titles.contains(&step.title.as_str()) {
+            titles.push(&step.title);
+        }
+    }
+
+    titles
+        .into_iter()
+        .filter_map(|title| {
+            let before = ran(earlier, title);
+            let after = ran(current, title);
+            let change = match (before, after) {
+                (None, None) => return None,
+                (None, Some(_)) => Change::New,
+                (Some(_), None) => Change::NotRun,
+                (Some(before), Some(after)) => {
+                    let threshold = MIN_CHANGE.max(before.mul_f64(MIN_CHANGE_RATIO));
+                    if after >= before + threshold {
+                        Change::Slower
+                    } else if after + threshold <= before {
+                        Change::Faster
+                    } else {
+                        Change::Unchanged
+                    }
+                }
+            };
+            Some(StepComparison {
+                title: title.to_string(),
+                before,
+                after,
+                change,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, steps: &[(&str, f64, bool)]) -> RunRecord {
+        RunRecord {
+            id: id.to_string(),
+            finished_at: String::new(),
+            success: true,
+            duration_secs: steps.iter().map(|(_, secs, _)| secs).sum(),
+            steps: steps
+                .iter()
+                .map(|&(title, duration_secs, ran)| StepRecord {
+                    title: title.to_string(),
+                    duration_secs,
+                    ran,
+                    success: true,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_records_round_trip_and_list_oldest_first() {
+        let dir = std::env::temp_dir().join(format!("getset-runs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let later = RunRecord {
+            finished_at: "2024-01-02T09:00:00.000Z".to_string(),
+            ..record("b7e1", &[("Install", 3.0, true)])
+        };
+        later.save(&dir).unwrap();
+        let earlier = RunRecord {
+            finished_at: "2024-01-01T09:00:00.000Z".to_string(),
+            ..record("f00d", &[])
+        };
+        earlier.save(&dir).unwrap();
+
+        assert_eq!(list(&dir).unwrap(), ["f00d", "b7e1"]);
+        assert_eq!(RunRecord::load(&dir, &later.id).unwrap(), later);
+        assert_eq!(RunRecord::load(&dir, "f0").unwrap(), earlier);
+        assert!(RunRecord::load(&dir, "missing").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_compare_highlights_slower_and_faster_steps() {
+        let earlier = record(
+            "1",
+            &[
+                ("Install", 10.0, true),
+                ("Lint", 20.0, true),
+                ("Test", 5.0, true),
+                ("Removed", 1.0, true),
+            ],
+        );
+        let current = record(
+            "2",
+            &[
+                ("Install", 30.0, true),
+                ("Lint", 5.0, true),
+                ("Test", 5.5, true),
+                ("Added", 2.0, true),
+                ("Skipped", 0.0, false),
+            ],
+        );
+
+        let comparison = compare(&earlier, &current);
+        let changes: Vec<(&str, Change)> = comparison
+            .iter()
+            .map(|step| (step.title.as_str(), step.change.clone()))
+            .collect();
+
+        assert_eq!(
+            changes,
+            [
+                ("Install", Change::Slower),
+                ("Lint", Change::Faster),
+                ("Test", Change::Unchanged),
+                ("Added", Change::New),
+                ("Removed", Change::NotRun),
+            ]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use crate::config::{ServiceConfig, Shell};
+use crate::interrupt;
+use crate::redact;
+use crate::runner::PREFIX_COLORS;
+use color_eyre::eyre::{Result, eyre};
+use console::style;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// The services of a Procfile: one `name: command` per line, as foreman reads them
+///
+/// Blank lines and `#` comments are skipped. Services run in `base_dir`, the
+/// Procfile's directory.
+pub fn parse_procfile(content: &str, base_dir: &Path) -> Result<Vec<ServiceConfig>> {
+    let mut services = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, command) = line.split_once(':').ok_or_else(|| {
+            eyre!(
+                "Invalid Procfile: line {} is not 'name: command'",
+                index + 1
+            )
+        })?;
+        services.push(ServiceConfig {
+            name: name.trim().to_string(),
+            command: command.trim().to_string(),
+            base_dir: base_dir.to_path_buf(),
+            ..Default::default()
+        });
+    }
+    Ok(services)
+}
+
+/// Run every service until one exits or the user presses Ctrl-C, then stop them all
+///
+/// Each line a service prints is shown after its name. Services are started
+/// in their own process groups; stopping sends each group SIGTERM (SIGINT for
+/// Ctrl-C) and kills whatever is still running after a grace period. A service
+/// that exits by itself stops the others, and fails the run unless it succeeded.
+pub fn start(services: &[ServiceConfig]) -> Result<()> {
+    if services.is_empty() {
+        return Err(eyre!("No services to start"));
+    }
+    interrupt::reset();
+    interrupt::install_handler();
+
+    let width = services
+        .iter()
+        .map(|service| service.name.chars().count())
+        .max()
+        .unwrap_or_default();
+    let timer = Instant::now();
+    let first_exit: Mutex<Option<(String, ExitStatus)>> = Mutex::new(None);
+
+    let results: Vec<Result<()>> = thread::scope(|scope| {
+        let handles: Vec<_> = services
+            .iter()
+            .enumerate()
+            .map(|(index, service)| {
+                let prefix = format!(
+                    "{} ",
+                    style(format!("{:<width$} |", service.name, width = width))
+                        .fg(PREFIX_COLORS[index % PREFIX_COLORS.len()])
+                );
+                let first_exit = &first_exit;
+                scope.spawn(move || supervise(service, &prefix, first_exit))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(eyre!("Service panicked")))
+            })
+            .collect()
+    });
+    interrupt::reset();
+
+    let exited = first_exit.into_inner().unwrap();
+    if let Some((ref name, status)) = exited
+        && !status.success()
+    {
+        return Err(eyre!(
+            "Service '{}' exited with {}; stopped the other services",
+            name,
+            status
+        ));
+    }
+    if let Some(e) = results.into_iter().find_map(Result::err) {
+        return Err(e);
+    }
+
+    let reason = match exited {
+        Some((name, _)) => format!("'{}' exited", name),
+        None => "interrupted".to_string(),
+    };
+    println!(
+        "{} Stopped {} services after {:.2}s ({})",
+        style("Info:").cyan().bold(),
+        services.len(),
+        timer.elapsed().as_secs_f64(),
+        reason
+    );
+    Ok(())
+}
+
+/// Run one service until it exits or every service is being stopped
+///
+/// The first service to exit by itself is recorded in `first_exit`, and stops
+/// the others. A service that cannot be started stops them too.
+fn supervise(
+    service: &ServiceConfig,
+    prefix: &str,
+    first_exit: &Mutex<Option<(String, ExitStatus)>>,
+) -> Result<()> {
+    let shell = Shell::default().argv();
+    let mut command = Command::new(&shell[0]);
+    command
+        .args(&shell[1..])
+        .arg(&service.command)
+        .envs(&service.env);
+    if let Some(dir) = service.working_dir() {
+        command.current_dir(dir);
+    }
+    interrupt::isolate(&mut command);
+
+    let mut child = match command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            interrupt::cancel();
+            return Err(eyre!("Failed to start service '{}': {}", service.name, e));
+        }
+    };
+    print_line(prefix, &format!("started with pid {}", child.id()));
+
+    let readers: Vec<_> = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|pipe| {
+        let prefix = prefix.to_string();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(pipe);
+            let mut line = Vec::new();
+            while let Ok(n) = reader.read_until(b'\n', &mut line) {
+                if n == 0 {
+                    break;
+                }
+                let text = String::from_utf8_lossy(&line);
+                print_line(&prefix, text.trim_end_matches(['\r', '\n']));
+                line.clear();
+            }
+        })
+    })
+    .collect();
+
+    let result = interrupt::wait(&mut child, true);
+    if let Ok(Some(status)) = result {
+        let mut first_exit = first_exit.lock().unwrap();
+        if first_exit.is_none() && !interrupt::requested() {
+            *first_exit = Some((service.name.clone(), status));
+            interrupt::cancel();
+        }
+    }
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    match result {
+        Ok(Some(status)) => print_line(prefix, &format!("exited with {}", status)),
+        Ok(None) => print_line(prefix, "stopped"),
+        Err(e) => {
+            return Err(eyre!(
+                "Failed to wait for service '{}': {}",
+                service.name,
+                e
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Print a line of a service's output after its name, with known secrets redacted
+fn print_line(prefix: &str, text: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}{}", prefix, redact::redact(text));
+    let _ = stdout.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_procfile() {
+        let services = parse_procfile(
+            "# Processes\nweb: bin/rails server -p $PORT\n\nworker:   bundle exec sidekiq\n",
+            Path::new("app"),
+        )
+        .expect("Procfile should parse");
+
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].name, "web");
+        assert_eq!(services[0].command, "bin/rails server -p $PORT");
+        assert_eq!(services[1].name, "worker");
+        assert_eq!(services[1].command, "bundle exec sidekiq");
+        assert_eq!(
+            services[1].working_dir(),
+            Some(Path::new("app").to_path_buf())
+        );
+
+        let err = parse_procfile("web bin/rails server", Path::new(".")).unwrap_err();
+        assert!(err.to_string().contains("line 1 is not 'name: command'"));
+    }
+}
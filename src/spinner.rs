@@ -0,0 +1,137 @@
+use crate::ci;
+use crate::glyphs;
+use console::{Term, style};
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const TICK: Duration = Duration::from_millis(100);
+
+/// How long a step must be silent before a hidden spinner is shown again
+const QUIET: Duration = Duration::from_secs(1);
+
+/// A spinner with a ticking elapsed time, redrawn in place on stdout
+///
+/// Does nothing unless stdout is a terminal. The line is cleared when the
+/// spinner is dropped.
+pub struct Spinner {
+    stop: Arc<AtomicBool>,
+    line: Arc<Mutex<Line>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// State of the terminal line the spinner draws on
+struct Line {
+    /// Whether the spinner is currently drawn
+    shown: bool,
+    /// Whether the cursor is at the start of a line, so the spinner may draw there
+    at_line_start: bool,
+    /// When output was last printed over the spinner
+    last_output: Option<Instant>,
+}
+
+impl Spinner {
+    pub fn start(message: &str) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let line = Arc::new(Mutex::new(Line {
+            shown: false,
+            at_line_start: true,
+            last_output: None,
+        }));
+        if !io::stdout().is_terminal() || ci::active() {
+            return Self {
+                stop,
+                line,
+                handle: None,
+            };
+        }
+
+        let message = message.to_string();
+        let flag = Arc::clone(&stop);
+        let state = Arc::clone(&line);
+        let handle = thread::spawn(move || {
+            let term = Term::stdout();
+            let timer = Instant::now();
+            for frame in glyphs::spinner_frames().iter().cycle() {
+                if flag.load(Ordering::Relaxed) {
+                    break;
+                }
+                let mut line = state.lock().unwrap();
+                let quiet = line.last_output.is_none_or(|at| at.elapsed() >= QUIET);
+                if line.at_line_start && quiet {
+                    let _ = term.clear_line();
+                    let _ = term.write_str(&format!(
+                        "{} {} {}",
+                        style(frame).cyan(),
+                        style(&message).bold(),
+                        style(format!("({:.0}s)", timer.elapsed().as_secs_f64())).dim()
+                    ));
+                    line.shown = true;
+                }
+                drop(line);
+                thread::sleep(TICK);
+            }
+            if state.lock().unwrap().shown {
+                let _ = term.clear_line();
+            }
+        });
+
+        Self {
+            stop,
+            line,
+            handle: Some(handle),
+        }
+    }
+
+    /// Hide the spinner while `print` writes output, showing it again once output pauses
+    ///
+    /// `ends_line` says whether the output finishes its line; the spinner stays
+    /// hidden after a partial line, such as a prompt, to avoid drawing over it.
+    pub fn hide_while(&self, ends_line: bool, print: impl FnOnce()) {
+        let mut line = self.line.lock().unwrap();
+        if line.shown {
+            let _ = Term::stdout().clear_line();
+            line.shown = false;
+        }
+        print();
+        line.at_line_start = ends_line;
+        line.last_output = Some(Instant::now());
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hide_while_tracks_whether_output_ended_its_line() {
+        let spinner = Spinner::start("Install");
+        let mut printed = false;
+
+        spinner.hide_while(false, || printed = true);
+
+        assert!(printed);
+        let line = spinner.line.lock().unwrap();
+        assert!(!line.shown);
+        assert!(
+            !line.at_line_start,
+            "a partial line such as a prompt stays clear"
+        );
+        assert!(line.last_output.is_some());
+        drop(line);
+
+        spinner.hide_while(true, || {});
+        assert!(spinner.line.lock().unwrap().at_line_start);
+    }
+}
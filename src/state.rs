@@ -0,0 +1,115 @@
+use crate::orchestrator::RunSummary;
+use color_eyre::eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the progress of a failed run is stored, relative to the config file
+pub const STATE_FILE: &str = ".getset/state.json";
+
+/// Skip reason for steps that completed in the run being resumed
+pub const RESUMED: &str = "completed";
+
+/// Steps that completed before a run failed, so `up --resume` can skip them
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunState {
+    /// Titles of the completed steps, in the order they ran
+    pub completed: Vec<String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl RunState {
+    /// Load the state stored in `base_dir`, starting empty if missing or unreadable
+    pub fn load(base_dir: &Path) -> Self {
+        let path = base_dir.join(STATE_FILE);
+
+        let mut state: RunState = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        state.path = path;
+        state
+    }
+
+    /// Record the steps of `summary` that ran successfully or were already completed
+    pub fn update(&mut self, summary: &RunSummary) {
+        self.completed = summary
+            .steps
+            .iter()
+            .filter(|step| step.success && !step.allowed_failure)
+            .filter(|step| step.skip_reason.as_deref().is_none_or(|r| r == RESUMED))
+            .map(|step| step.title.clone())
+            .collect();
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| eyre!("Error creating '{}': {}", dir.display(), e))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&self.path, content)
+            .map_err(|e| eyre!("Error writing '{}': {}", self.path.display(), e))
+    }
+
+    /// Forget the stored progress once a run has succeeded
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(eyre!("Error removing '{}': {}", self.path.display(), e))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::StepResult;
+    use std::time::Duration;
+
+    fn step(title: &str, success: bool, skip_reason: Option<&str>) -> StepResult {
+        StepResult {
+            title: title.to_string(),
+            duration: Duration::ZERO,
+            success,
+            error: None,
+            output_tail: Vec::new(),
+            skip_reason: skip_reason.map(str::to_string),
+            allowed_failure: false,
+            satisfied: false,
+            started: Duration::ZERO,
+            project: None,
+        }
+    }
+
+    #[test]
+    fn test_state_round_trip_keeps_completed_steps() {
+        let dir = std::env::temp_dir().join(format!("getset-state-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let summary = RunSummary {
+            steps: vec![
+                step("Install", true, Some(RESUMED)),
+                step("Migrate", true, None),
+                step("Lint", true, Some("skip_if succeeded")),
+                step("Seed", false, None),
+            ],
+            ..Default::default()
+        };
+        let mut state = RunState::load(&dir);
+        state.update(&summary);
+        state.save().unwrap();
+
+        assert_eq!(RunState::load(&dir).completed, ["Install", "Migrate"]);
+
+        state.clear().unwrap();
+        assert!(RunState::load(&dir).completed.is_empty());
+        state.clear().unwrap();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use crate::config::{Config, PlatformXConfig};
+use crate::metrics::{LocalSink, PrometheusSink, StatsdSink};
+use crate::notify::{self, Notifiers};
+use crate::orchestrator::{RunSummary, StepResult};
+use crate::platformx::{Globals, PlatformXClient};
+use crate::redact;
+use color_eyre::eyre::Result;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// Future returned by the methods of a [`TelemetrySink`]
+pub type SinkFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// A backend that is told about the lifecycle of a `getset up` run
+///
+/// Sinks never fail the run: each one logs its own delivery failures as
+/// warnings. Methods a sink has no use for can be left to their defaults.
+pub trait TelemetrySink: Send + Sync {
+    /// The run is starting
+    fn on_start(&self) -> SinkFuture<'_> {
+        Box::pin(async {})
+    }
+
+    /// A step finished; called for each step in the order they ran, once the run is over
+    fn on_step<'a>(&'a self, _step: &'a StepResult) -> SinkFuture<'a> {
+        Box::pin(async {})
+    }
+
+    /// Every step succeeded, taking `duration` in total
+    fn on_complete<'a>(&'a self, _summary: &'a RunSummary, _duration: Duration) -> SinkFuture<'a> {
+        Box::pin(async {})
+    }
+
+    /// The run failed or was interrupted after `duration`, with `error` describing why
+    fn on_error<'a>(
+        &'a self,
+        _summary: &'a RunSummary,
+        _duration: Duration,
+        _error: &'a str,
+    ) -> SinkFuture<'a> {
+        Box::pin(async {})
+    }
+}
+
+/// Every telemetry sink configured for a run, told about events in turn
+#[derive(Default)]
+pub struct Telemetry {
+    sinks: Vec<Box<dyn TelemetrySink>>,
+}
+
+impl Telemetry {
+    /// The sinks declared in the `[platformx]`, `[notifications]` and `[metrics]` sections
+    ///
+    /// PlatformX is left out when `platformx` is false, e.g. because the user opted
+    /// out; otherwise its secret key must resolve.
+    pub fn from_config(config: &Config, globals: &Globals, platformx: bool) -> Result<Self> {
+        let mut telemetry = Self::default();
+        if let Some(platformx) = config.platformx.as_ref().filter(|_| platformx) {
+            let platformx = PlatformXConfig {
+                secret_key: Some(platformx.resolve_secret_key(&config.vars)?),
+                ..platformx.clone()
+            };
+            telemetry
+                .add(PlatformXClient::new(platformx, globals.clone()).with_queue(&config.base_dir));
+        }
+        if let Some(ref notifications) = config.notifications {
+            telemetry.add(Notifiers::new(
+                Some(notifications),
+                globals,
+                &config.base_dir,
+            ));
+        }
+        if let Some(ref metrics) = config.metrics {
+            if let Some(ref prometheus) = metrics.prometheus {
+                telemetry.add(PrometheusSink::new(
+                    prometheus.clone(),
+                    notify::project_name(&config.base_dir),
+                    globals.git_email.clone(),
+                ));
+            }
+            if let Some(ref statsd) = metrics.statsd {
+                telemetry.add(StatsdSink::new(statsd.clone()));
+            }
+            if let Some(ref local) = metrics.local {
+                telemetry.add(LocalSink::new(
+                    local,
+                    &config.base_dir,
+                    globals.clone(),
+                    notify::project_name(&config.base_dir),
+                ));
+            }
+        }
+        Ok(telemetry)
+    }
+
+    /// Tell `sink` about the events of the run too
+    pub fn add(&mut self, sink: impl TelemetrySink + 'static) {
+        self.sinks.push(Box::new(sink));
+    }
+
+    /// Tell every sink the run is starting
+    pub async fn start(&self) {
+        for sink in &self.sinks {
+            sink.on_start().await;
+        }
+    }
+
+    /// Report the steps of a finished run, then whether it succeeded
+    ///
+    /// Known secrets are redacted from `error` before any sink sees it.
+    pub async fn finish(&self, summary: &RunSummary, duration: Duration, error: Option<&str>) {
+        let error = error.map(redact::redact);
+        let error = error.as_deref();
+        for sink in &self.sinks {
+            for step in &summary.steps {
+                sink.on_step(step).await;
+            }
+            match error {
+                None => sink.on_complete(summary, duration).await,
+                Some(error) => sink.on_error(summary, duration, error).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Sink that records the events it receives
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl TelemetrySink for Recorder {
+        fn on_start(&self) -> SinkFuture<'_> {
+            Box::pin(async { self.0.lock().unwrap().push("start".to_string()) })
+        }
+
+        fn on_step<'a>(&'a self, step: &'a StepResult) -> SinkFuture<'a> {
+            Box::pin(async { self.0.lock().unwrap().push(format!("step {}", step.title)) })
+        }
+
+        fn on_error<'a>(
+            &'a self,
+            _summary: &'a RunSummary,
+            _duration: Duration,
+            error: &'a str,
+        ) -> SinkFuture<'a> {
+            Box::pin(async move { self.0.lock().unwrap().push(format!("error {}", error)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_telemetry_tells_every_sink() {
+        let first = Arc::new(Mutex::new(Vec::new()));
+        let second = Arc::new(Mutex::new(Vec::new()));
+        let mut telemetry = Telemetry::default();
+        telemetry.add(Recorder(first.clone()));
+        telemetry.add(Recorder(second.clone()));
+
+        let summary = RunSummary {
+            steps: vec![StepResult {
+                title: "Migrate".to_string(),
+                duration: Duration::from_secs(1),
+                success: false,
+                error: Some("Step failed with exit code 1".to_string()),
+                output_tail: Vec::new(),
+                skip_reason: None,
+                allowed_failure: false,
+                satisfied: false,
+                started: Duration::ZERO,
+                project: None,
+            }],
+            ..Default::default()
+        };
+        telemetry.start().await;
+        telemetry
+            .finish(&summary, Duration::from_secs(1), Some("Migrate failed"))
+            .await;
+
+        for events in [first, second] {
+            assert_eq!(
+                *events.lock().unwrap(),
+                ["start", "step Migrate", "error Migrate failed"]
+            );
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use crate::config;
+use crate::orchestrator::{RunSummary, StepResult};
+use color_eyre::eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where step durations are stored when there is no data directory, relative to the config file
+const FALLBACK_FILE: &str = ".getset/timings.json";
+
+/// How many recent durations are kept for each step
+const HISTORY: usize = 10;
+
+/// Fewest recorded durations a step needs before it can be reported as slower than usual
+const MIN_HISTORY: usize = 3;
+
+/// How many times its average a step must take to be reported as slower than usual
+const SLOWDOWN: f64 = 2.0;
+
+/// Smallest increase over the average worth reporting, so quick steps don't cause noise
+const MIN_SLOWDOWN: Duration = Duration::from_secs(5);
+
+/// A step that took significantly longer than its rolling average
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub title: String,
+    pub duration: Duration,
+    /// Average of the step's previously recorded durations
+    pub average: Duration,
+}
+
+impl Regression {
+    /// How many times its average the step took
+    pub fn ratio(&self) -> f64 {
+        self.duration.as_secs_f64() / self.average.as_secs_f64()
+    }
+}
+
+/// Durations of each step's recent successful runs, used to estimate run times
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Timings {
+    /// Durations in seconds keyed by step title, oldest first
+    steps: BTreeMap<String, Vec<f64>>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Timings {
+    /// Load the timings of the config in `base_dir`, starting empty if missing or unreadable
+    ///
+    /// Timings live in getset's data directory, in a file per config directory,
+    /// so they survive `git clean` and are never committed by accident.
+    pub fn load(base_dir: &Path) -> Self {
+        Self::load_file(timings_path(base_dir))
+    }
+
+    fn load_file(path: PathBuf) -> Self {
+        let mut timings: Timings = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        timings.path = path;
+        timings
+    }
+
+    /// The median of a step's recorded durations, if it has any
+    pub fn typical(&self, title: &str) -> Option<Duration> {
+        let mut durations = self.steps.get(title)?.clone();
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort_by(f64::total_cmp);
+        Some(Duration::from_secs_f64(durations[durations.len() / 2]))
+    }
+
+    /// The typical duration of every step with recorded durations
+    pub fn typical_all(&self) -> BTreeMap<String, Duration> {
+        self.steps
+            .keys()
+            .filter_map(|title| Some((title.clone(), self.typical(title)?)))
+            .collect()
+    }
+
+    /// Steps of `summary` that took much longer than their recorded average
+    ///
+    /// Call this before recording the run, so steps are compared to earlier runs only.
+    pub fn regressions(&self, summary: &RunSummary) -> Vec<Regression> {
+        summary
+            .steps
+            .iter()
+            .filter(|step| counts(step))
+            .filter_map(|step| {
+                let durations = self.steps.get(&step.title)?;
+                if durations.len() < MIN_HISTORY {
+                    return None;
+                }
+                let average =
+                    Duration::from_secs_f64(durations.iter().sum::<f64>() / durations.len() as f64);
+                let slower = step.duration.as_secs_f64() >= average.as_secs_f64() * SLOWDOWN
+                    && step.duration.saturating_sub(average) >= MIN_SLOWDOWN;
+                slower.then(|| Regression {
+                    title: step.title.clone(),
+                    duration: step.duration,
+                    average,
+                })
+            })
+            .collect()
+    }
+
+    /// Record the durations of the steps of `summary` that ran and succeeded
+    pub fn record(&mut self, summary: &RunSummary) {
+        for step in summary.steps.iter().filter(|step| counts(step)) {
+            let durations = self.steps.entry(step.title.clone()).or_default();
+            durations.push(step.duration.as_secs_f64());
+            if durations.len() > HISTORY {
+                durations.remove(0);
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| eyre!("Error creating '{}': {}", dir.display(), e))?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&self.path, content)
+            .map_err(|e| eyre!("Error writing '{}': {}", self.path.display(), e))
+    }
+}
+
+/// Whether a step's duration says how long it usually takes
+///
+/// Skipped, already satisfied and failed steps stopped early or never ran.
+fn counts(step: &StepResult) -> bool {
+    step.success && step.skip_reason.is_none() && !step.satisfied
+}
+
+/// The timings file for the config in `base_dir`
+fn timings_path(base_dir: &Path) -> PathBuf {
+    let Some(data_dir) = data_dir() else {
+        return base_dir.join(FALLBACK_FILE);
+    };
+
+    let project = config::absolute_dir(base_dir);
+    let hash = format!("{:x}", Sha256::digest(project.to_string_lossy().as_bytes()));
+    data_dir
+        .join("timings")
+        .join(format!("{}.json", &hash[..16]))
+}
+
+/// getset's data directory: `$XDG_DATA_HOME/getset`, `~/.local/share/getset`,
+/// or `%LOCALAPPDATA%\getset` on Windows
+pub(crate) fn data_dir() -> Option<PathBuf> {
+    let absolute = |var: &str| {
+        env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+    };
+
+    absolute("XDG_DATA_HOME")
+        .or_else(|| absolute("HOME").map(|home| home.join(".local").join("share")))
+        .or_else(|| absolute("LOCALAPPDATA"))
+        .map(|dir| dir.join("getset"))
+}
+
+/// Round an estimated duration for display, e.g. `~45s`, `~2m` or `~1h 5m`
+pub fn estimate(duration: Duration) -> String {
+    let secs = duration.as_secs_f64().round() as u64;
+    match secs {
+        0..60 => format!("~{}s", secs.max(1)),
+        60..3600 => format!("~{}m", (secs + 30) / 60),
+        _ => {
+            let minutes = (secs + 30) / 60;
+            format!("~{}h {}m", minutes / 60, minutes % 60)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(title: &str, secs: u64, success: bool) -> StepResult {
+        StepResult {
+            title: title.to_string(),
+            duration: Duration::from_secs(secs),
+            success,
+            error: None,
+            output_tail: Vec::new(),
+            skip_reason: None,
+            allowed_failure: false,
+            satisfied: false,
+            started: Duration::ZERO,
+            project: None,
+        }
+    }
+
+    #[test]
+    fn test_timings_round_trip_uses_median_of_successful_runs() {
+        let dir = std::env::temp_dir().join(format!("getset-timings-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("timings.json");
+
+        let mut timings = Timings::load_file(path.clone());
+        for secs in [10, 100, 20] {
+            timings.record(&RunSummary {
+                steps: vec![step("Install", secs, true), step("Seed", 500, false)],
+                ..Default::default()
+            });
+        }
+        timings.save().unwrap();
+
+        let reloaded = Timings::load_file(path);
+        assert_eq!(reloaded.typical("Install"), Some(Duration::from_secs(20)));
+        assert_eq!(reloaded.typical("Seed"), None);
+        assert_eq!(reloaded.typical_all().len(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_regressions_compare_against_rolling_average() {
+        let mut timings = Timings::default();
+        for _ in 0..MIN_HISTORY {
+            timings.record(&RunSummary {
+                steps: vec![step("Seed database", 10, true), step("Lint", 1, true)],
+                ..Default::default()
+            });
+        }
+
+        let slow = RunSummary {
+            steps: vec![step("Seed database", 45, true), step("Lint", 4, true)],
+            ..Default::default()
+        };
+        let regressions = timings.regressions(&slow);
+
+        assert_eq!(regressions.len(), 1, "Lint is slower, but by too little");
+        assert_eq!(regressions[0].title, "Seed database");
+        assert_eq!(regressions[0].ratio(), 4.5);
+        assert!(Timings::default().regressions(&slow).is_empty());
+    }
+
+    #[test]
+    fn test_timings_are_kept_per_config_directory() {
+        assert_ne!(timings_path(Path::new("a")), timings_path(Path::new("b")));
+        assert_eq!(timings_path(Path::new("")), timings_path(Path::new(".")));
+    }
+
+    #[test]
+    fn test_estimate_rounds_to_a_readable_unit() {
+        assert_eq!(estimate(Duration::from_millis(200)), "~1s");
+        assert_eq!(estimate(Duration::from_secs(45)), "~45s");
+        assert_eq!(estimate(Duration::from_secs(100)), "~2m");
+        assert_eq!(estimate(Duration::from_secs(3900)), "~1h 5m");
+    }
+}
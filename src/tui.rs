@@ -0,0 +1,616 @@
+use crate::config::{CommandEntry, Config};
+use crate::events::{self, LogFormat, OutputStream, StepOutcome};
+use crate::glyphs;
+use crate::interrupt;
+use crate::orchestrator::{self, Approval, Prompter, Recovery, RunOptions, RunSummary};
+use color_eyre::eyre::{Result, eyre};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeSet;
+use std::io::{self, IsTerminal};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the dashboard redraws while waiting for input
+const TICK: Duration = Duration::from_millis(100);
+
+/// Lines scrolled by Page Up and Page Down in the output pane
+const PAGE: usize = 10;
+
+/// Where a step is in the run, as shown on the dashboard
+#[derive(Debug, Clone, PartialEq)]
+enum Status {
+    Pending,
+    Running(Instant),
+    Succeeded(Duration),
+    Failed(Duration),
+    Skipped(String),
+}
+
+/// A step's row on the dashboard, with the output it has printed
+struct StepView {
+    title: String,
+    status: Status,
+    /// Lines of output, and whether each was printed to stderr
+    output: Vec<(bool, String)>,
+}
+
+/// What the run is waiting on the user to decide, about the step at an index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Question {
+    /// Whether to run a step marked `confirm`
+    Confirm(usize),
+    /// Whether to retry a failed step
+    Failed(usize),
+}
+
+impl Question {
+    fn index(self) -> usize {
+        match self {
+            Question::Confirm(index) | Question::Failed(index) => index,
+        }
+    }
+}
+
+/// What the user chose to do about the step the run is waiting on
+#[derive(Debug, PartialEq, Eq)]
+enum Decision {
+    /// Run the step, or run it again if it failed
+    Run,
+    Skip,
+}
+
+/// Sent while the steps run in the background
+#[derive(Debug, PartialEq, Eq)]
+enum Progress {
+    /// The run waits for a [`Decision`]
+    Asking(Question),
+    /// Every step has been run or skipped, or the run was stopped
+    Finished,
+}
+
+/// What a key press asks the dashboard's caller to do
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Decide(Decision),
+    /// Pending steps marked to be skipped changed
+    Marked,
+    Quit,
+}
+
+/// State of the dashboard
+struct Dashboard {
+    steps: Vec<StepView>,
+    /// Index of the step whose output is shown
+    selected: usize,
+    /// Lines scrolled up from the end of the output, or `None` to follow it
+    scroll: Option<usize>,
+    /// Pending steps the user marked to be skipped
+    marked: BTreeSet<usize>,
+    /// What the run is waiting on the user to decide
+    question: Option<Question>,
+    finished: bool,
+}
+
+impl Dashboard {
+    fn new(commands: &[&CommandEntry]) -> Self {
+        Self {
+            steps: commands
+                .iter()
+                .map(|cmd| StepView {
+                    title: cmd.title.clone(),
+                    status: Status::Pending,
+                    output: Vec::new(),
+                })
+                .collect(),
+            selected: 0,
+            scroll: None,
+            marked: BTreeSet::new(),
+            question: None,
+            finished: false,
+        }
+    }
+
+    /// Update a step from one of the runner's lifecycle events
+    fn apply_event(&mut self, event: &events::Event) {
+        let title = match event {
+            events::Event::StepStarted { title }
+            | events::Event::OutputChunk { title, .. }
+            | events::Event::StepFinished { title, .. } => title,
+            events::Event::RunFinished { .. } => return,
+        };
+        let Some(step) = self.steps.iter_mut().find(|step| step.title == *title) else {
+            return;
+        };
+
+        match event {
+            events::Event::StepStarted { .. } => {
+                if !step.output.is_empty() {
+                    step.output
+                        .push((false, format!("{} retrying {}", glyphs::RULE, glyphs::RULE)));
+                }
+                step.status = Status::Running(Instant::now());
+            }
+            events::Event::OutputChunk { stream, text, .. } => {
+                step.output.push((
+                    *stream == OutputStream::Stderr,
+                    text.trim_end_matches(['\r', '\n']).to_string(),
+                ));
+            }
+            events::Event::StepFinished {
+                duration, outcome, ..
+            } => {
+                step.status = match outcome {
+                    StepOutcome::Succeeded => Status::Succeeded(*duration),
+                    StepOutcome::Failed | StepOutcome::Interrupted => Status::Failed(*duration),
+                    StepOutcome::Skipped { reason } => Status::Skipped(reason.clone()),
+                    StepOutcome::Satisfied => Status::Skipped("already satisfied".to_string()),
+                }
+            }
+            events::Event::RunFinished { .. } => {}
+        }
+    }
+
+    fn apply_progress(&mut self, progress: Progress) {
+        match progress {
+            Progress::Asking(question) => {
+                self.question = Some(question);
+                self.selected = question.index();
+                self.scroll = None;
+            }
+            Progress::Finished => {
+                self.question = None;
+                self.finished = true;
+            }
+        }
+    }
+
+    fn handle_key(&mut self, code: KeyCode) -> Option<Action> {
+        let last = self.steps.len().saturating_sub(1);
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                self.scroll = None;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(last);
+                self.scroll = None;
+            }
+            KeyCode::PageUp => {
+                let lines = self.steps[self.selected].output.len();
+                self.scroll = Some((self.scroll.unwrap_or(0) + PAGE).min(lines));
+            }
+            KeyCode::PageDown => {
+                self.scroll = self
+                    .scroll
+                    .and_then(|scroll| scroll.checked_sub(PAGE))
+                    .filter(|&scroll| scroll > 0);
+            }
+            KeyCode::End => self.scroll = None,
+            KeyCode::Char('r') if self.question == Some(Question::Failed(self.selected)) => {
+                self.question = None;
+                return Some(Action::Decide(Decision::Run));
+            }
+            KeyCode::Char('y') if self.question == Some(Question::Confirm(self.selected)) => {
+                self.question = None;
+                return Some(Action::Decide(Decision::Run));
+            }
+            KeyCode::Char('s') if self.question.map(Question::index) == Some(self.selected) => {
+                self.question = None;
+                return Some(Action::Decide(Decision::Skip));
+            }
+            KeyCode::Char('s') if self.steps[self.selected].status == Status::Pending => {
+                if !self.marked.remove(&self.selected) {
+                    self.marked.insert(self.selected);
+                }
+                return Some(Action::Marked);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            _ => {}
+        }
+        None
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, output_area] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+
+        let items: Vec<ListItem> = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| ListItem::new(self.step_line(index, step)))
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Steps "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let step = &self.steps[self.selected];
+        let height = output_area.height.saturating_sub(2) as usize;
+        let end = step.output.len() - self.scroll.unwrap_or(0).min(step.output.len());
+        let lines: Vec<Line> = step.output[end.saturating_sub(height)..end]
+            .iter()
+            .map(|(to_stderr, text)| {
+                let style = if *to_stderr {
+                    Style::new().fg(Color::Red)
+                } else {
+                    Style::new()
+                };
+                Line::styled(text.as_str(), style)
+            })
+            .collect();
+        let title = match self.scroll {
+            Some(scroll) => format!(" {} (scrolled up {} lines) ", step.title, scroll),
+            None => format!(" {} ", step.title),
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            output_area,
+        );
+
+        frame.render_widget(
+            Paragraph::new(self.help()).style(Style::new().add_modifier(Modifier::DIM)),
+            footer,
+        );
+    }
+
+    fn step_line(&self, index: usize, step: &StepView) -> Line<'static> {
+        let (symbol, color, note) = match &step.status {
+            Status::Pending if self.marked.contains(&index) => (
+                glyphs::SKIPPED.as_str(),
+                Color::DarkGray,
+                "will skip".to_string(),
+            ),
+            Status::Pending => (" ", Color::DarkGray, String::new()),
+            Status::Running(started) => (
+                glyphs::ELLIPSIS.as_str(),
+                Color::Cyan,
+                format!("{:.0}s", started.elapsed().as_secs_f64()),
+            ),
+            Status::Succeeded(elapsed) => (
+                glyphs::SUCCESS.as_str(),
+                Color::Green,
+                format!("{:.2}s", elapsed.as_secs_f64()),
+            ),
+            Status::Failed(elapsed) => (
+                glyphs::FAILURE.as_str(),
+                Color::Red,
+                format!("{:.2}s", elapsed.as_secs_f64()),
+            ),
+            Status::Skipped(reason) => (glyphs::SKIPPED.as_str(), Color::DarkGray, reason.clone()),
+        };
+        Line::from(vec![
+            Span::styled(format!("{} ", symbol), Style::new().fg(color)),
+            Span::raw(step.title.clone()),
+            Span::styled(
+                format!(" {}", note),
+                Style::new().add_modifier(Modifier::DIM),
+            ),
+        ])
+    }
+
+    fn help(&self) -> String {
+        match self.question {
+            Some(Question::Confirm(index)) => {
+                return format!("Run '{}'? y run, s skip, q quit", self.steps[index].title);
+            }
+            Some(Question::Failed(index)) => {
+                return format!(
+                    "'{}' failed: r retry, s skip and continue, q quit",
+                    self.steps[index].title
+                );
+            }
+            None => {}
+        }
+        if self.finished {
+            return "Finished: ↑/↓ select, PgUp/PgDn scroll, q quit".to_string();
+        }
+        "↑/↓ select, PgUp/PgDn scroll, End follow, s skip a pending step, q stop and quit"
+            .to_string()
+    }
+}
+
+/// Run the selected steps on a full-screen dashboard
+///
+/// The steps run as in `getset up`, with their output shown for the selected
+/// step instead of printed. Steps marked `confirm` wait for the user to run or
+/// skip them, and a failed step waits for the user to retry or skip it.
+pub fn run(config: &Config, options: RunOptions) -> Result<RunSummary> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(eyre!("getset tui needs a terminal"));
+    }
+
+    let commands = orchestrator::select_commands(config, &options)?;
+    if commands.is_empty() {
+        return Err(eyre!("No steps to run"));
+    }
+    let (event_tx, event_rx) = mpsc::channel::<events::Event>();
+    let (progress_tx, progress_rx) = mpsc::channel();
+    let (decision_tx, decision_rx) = mpsc::channel();
+    let marked = Arc::new(Mutex::new(BTreeSet::new()));
+    let prompter = DashboardPrompter {
+        titles: commands.iter().map(|cmd| cmd.title.clone()).collect(),
+        marked: Arc::clone(&marked),
+        yes: options.yes,
+        progress: progress_tx.clone(),
+        decisions: Mutex::new(decision_rx),
+    };
+    // Steps run detached from the terminal, which belongs to the dashboard
+    let options = RunOptions {
+        log_format: LogFormat::Stream,
+        interactive: true,
+        recover: true,
+        prompter: Some(Arc::new(prompter)),
+        detached: true,
+        event_handler: Some(Arc::new(event_tx)),
+        ..options
+    };
+    let mut dashboard = Dashboard::new(&commands);
+
+    let summary = thread::scope(|scope| {
+        let worker = scope.spawn(move || {
+            let summary = orchestrator::run(config, options);
+            let _ = progress_tx.send(Progress::Finished);
+            summary
+        });
+
+        let mut terminal = ratatui::init();
+        let shown = show(
+            &mut terminal,
+            &mut dashboard,
+            &event_rx,
+            &progress_rx,
+            &decision_tx,
+            &marked,
+        );
+        ratatui::restore();
+
+        // Stop whatever is still running, and any wait for a decision
+        if !dashboard.finished {
+            interrupt::request();
+        }
+        drop(decision_tx);
+        let summary = worker
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        shown.and(summary)
+    });
+    while let Ok(event) = event_rx.try_recv() {
+        dashboard.apply_event(&event);
+    }
+
+    print_outcome(&dashboard);
+    summary
+}
+
+/// Draw the dashboard and handle input until the user quits
+fn show(
+    terminal: &mut DefaultTerminal,
+    dashboard: &mut Dashboard,
+    events: &Receiver<events::Event>,
+    progress: &Receiver<Progress>,
+    decisions: &Sender<Decision>,
+    marked: &Mutex<BTreeSet<usize>>,
+) -> Result<()> {
+    loop {
+        while let Ok(event) = events.try_recv() {
+            dashboard.apply_event(&event);
+        }
+        while let Ok(update) = progress.try_recv() {
+            dashboard.apply_progress(update);
+        }
+        terminal.draw(|frame| dashboard.draw(frame))?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let action =
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                Some(Action::Quit)
+            } else {
+                dashboard.handle_key(key.code)
+            };
+
+        match action {
+            Some(Action::Decide(decision)) => {
+                let _ = decisions.send(decision);
+            }
+            Some(Action::Marked) => *marked.lock().unwrap() = dashboard.marked.clone(),
+            Some(Action::Quit) => return Ok(()),
+            None => {}
+        }
+    }
+}
+
+/// Answers the run's questions by asking on the dashboard
+struct DashboardPrompter {
+    /// Titles of the steps, in the dashboard's order
+    titles: Vec<String>,
+    /// Pending steps the user marked to be skipped
+    marked: Arc<Mutex<BTreeSet<usize>>>,
+    /// Run `confirm` steps without asking
+    yes: bool,
+    progress: Sender<Progress>,
+    decisions: Mutex<Receiver<Decision>>,
+}
+
+impl DashboardPrompter {
+    fn index(&self, cmd_entry: &CommandEntry) -> Option<usize> {
+        self.titles
+            .iter()
+            .position(|title| *title == cmd_entry.title)
+    }
+
+    /// Wait for the user to answer `question`, or `None` once the dashboard has closed
+    fn ask(&self, question: Question) -> Option<Decision> {
+        self.progress.send(Progress::Asking(question)).ok()?;
+        self.decisions.lock().unwrap().recv().ok()
+    }
+}
+
+impl Prompter for DashboardPrompter {
+    fn approve(&self, cmd_entry: &CommandEntry) -> Approval {
+        let Some(index) = self.index(cmd_entry) else {
+            return Approval::Run;
+        };
+        if self.marked.lock().unwrap().contains(&index) {
+            return Approval::Skip;
+        }
+        if !cmd_entry.confirm || self.yes {
+            return Approval::Run;
+        }
+        match self.ask(Question::Confirm(index)) {
+            Some(Decision::Run) => Approval::Run,
+            Some(Decision::Skip) => Approval::Skip,
+            None => Approval::Abort,
+        }
+    }
+
+    fn recover(&self, cmd_entry: &CommandEntry) -> Recovery {
+        let Some(index) = self.index(cmd_entry) else {
+            return Recovery::Abort;
+        };
+        match self.ask(Question::Failed(index)) {
+            Some(Decision::Run) => Recovery::Retry,
+            Some(Decision::Skip) => Recovery::Skip,
+            None => Recovery::Abort,
+        }
+    }
+}
+
+/// Print how each step ended, once the dashboard has closed
+fn print_outcome(dashboard: &Dashboard) {
+    for step in &dashboard.steps {
+        let line = match &step.status {
+            Status::Pending | Status::Running(_) => {
+                format!("{} {} (not finished)", glyphs::SKIPPED, step.title)
+            }
+            Status::Succeeded(elapsed) => format!(
+                "{} {} ({:.2}s)",
+                glyphs::SUCCESS,
+                step.title,
+                elapsed.as_secs_f64()
+            ),
+            Status::Failed(elapsed) => format!(
+                "{} {} ({:.2}s)",
+                glyphs::FAILURE,
+                step.title,
+                elapsed.as_secs_f64()
+            ),
+            Status::Skipped(reason) => format!("{} {} ({})", glyphs::SKIPPED, step.title, reason),
+        };
+        println!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dashboard(titles: &[&str]) -> Dashboard {
+        let commands: Vec<CommandEntry> = titles
+            .iter()
+            .map(|title| CommandEntry {
+                title: title.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        Dashboard::new(&commands.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_events_update_step_status_and_output() {
+        let mut dashboard = dashboard(&["Install gems", "Migrate"]);
+
+        dashboard.apply_event(&events::Event::StepStarted {
+            title: "Migrate".to_string(),
+        });
+        assert!(matches!(dashboard.steps[1].status, Status::Running(_)));
+
+        dashboard.apply_event(&events::Event::OutputChunk {
+            title: "Migrate".to_string(),
+            stream: OutputStream::Stderr,
+            text: "no database\n".to_string(),
+        });
+        dashboard.apply_event(&events::Event::StepFinished {
+            title: "Migrate".to_string(),
+            duration: Duration::from_millis(1500),
+            exit_code: Some(1),
+            outcome: StepOutcome::Failed,
+        });
+
+        assert_eq!(
+            dashboard.steps[1].output,
+            [(true, "no database".to_string())]
+        );
+        assert_eq!(
+            dashboard.steps[1].status,
+            Status::Failed(Duration::from_millis(1500))
+        );
+        assert_eq!(dashboard.steps[0].status, Status::Pending);
+    }
+
+    #[test]
+    fn test_keys_mark_pending_steps_and_decide_failures() {
+        let mut dashboard = dashboard(&["Install gems", "Migrate", "Seed"]);
+
+        dashboard.handle_key(KeyCode::Down);
+        dashboard.handle_key(KeyCode::Down);
+        assert_eq!(
+            dashboard.handle_key(KeyCode::Char('s')),
+            Some(Action::Marked)
+        );
+        assert_eq!(dashboard.marked, BTreeSet::from([2]));
+        // `r` does nothing until the run waits on the selected step
+        assert_eq!(dashboard.handle_key(KeyCode::Char('r')), None);
+
+        dashboard.apply_progress(Progress::Asking(Question::Failed(1)));
+        assert_eq!(dashboard.selected, 1);
+        // `y` only answers whether to run a `confirm` step
+        assert_eq!(dashboard.handle_key(KeyCode::Char('y')), None);
+        assert_eq!(
+            dashboard.handle_key(KeyCode::Char('r')),
+            Some(Action::Decide(Decision::Run))
+        );
+        assert_eq!(dashboard.question, None);
+
+        dashboard.apply_progress(Progress::Asking(Question::Confirm(2)));
+        assert_eq!(
+            dashboard.handle_key(KeyCode::Char('s')),
+            Some(Action::Decide(Decision::Skip))
+        );
+        assert_eq!(dashboard.handle_key(KeyCode::Char('q')), Some(Action::Quit));
+    }
+
+    #[test]
+    fn test_output_scrolls_back_and_follows_again() {
+        let mut dashboard = dashboard(&["Build"]);
+        dashboard.steps[0].output = vec![(false, "line".to_string()); 25];
+
+        dashboard.handle_key(KeyCode::PageUp);
+        dashboard.handle_key(KeyCode::PageUp);
+        dashboard.handle_key(KeyCode::PageUp);
+        assert_eq!(dashboard.scroll, Some(25));
+        dashboard.handle_key(KeyCode::PageDown);
+        assert_eq!(dashboard.scroll, Some(15));
+        dashboard.handle_key(KeyCode::End);
+        assert_eq!(dashboard.scroll, None);
+    }
+}
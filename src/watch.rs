@@ -0,0 +1,155 @@
+use crate::cache;
+use crate::config::{CommandEntry, Config};
+use crate::events::LogFormat;
+use crate::glyphs;
+use crate::interrupt;
+use crate::orchestrator::{self, RunOptions};
+use color_eyre::eyre::{Result, eyre};
+use console::style;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::Duration;
+
+/// How often watched files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watch the `sources`/`watch` globs of the selected steps and re-run steps whose files change
+///
+/// Runs until interrupted with Ctrl-C. Failed re-runs are reported but do not stop watching.
+pub fn watch(config: &Config, options: &RunOptions) -> Result<()> {
+    let watched: Vec<&CommandEntry> = orchestrator::select_commands(config, options)?
+        .into_iter()
+        .filter(|cmd| !cmd.watched().is_empty())
+        .collect();
+    if watched.is_empty() {
+        return Err(eyre!(
+            "No steps declare 'sources' or 'watch' globs to watch"
+        ));
+    }
+
+    let mut hashes = snapshot(&watched)?;
+    if options.log_format == LogFormat::Pretty {
+        println!(
+            "\n{} Watching {} steps for changes (Ctrl-C to stop)",
+            style(glyphs::WATCHING).bold(),
+            watched.len()
+        );
+    }
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        if interrupt::requested() {
+            return Ok(());
+        }
+
+        let current = snapshot(&watched)?;
+        let changed: Vec<&CommandEntry> = watched
+            .iter()
+            .filter(|cmd| hashes.get(&cmd.title) != current.get(&cmd.title))
+            .copied()
+            .collect();
+        hashes = current;
+        if changed.is_empty() {
+            continue;
+        }
+
+        if options.log_format == LogFormat::Pretty {
+            let titles: Vec<&str> = changed.iter().map(|cmd| cmd.title.as_str()).collect();
+            println!(
+                "\n{} Changes detected, re-running: {}",
+                style("Info:").cyan().bold(),
+                titles.join(", ")
+            );
+        }
+
+        let rerun_options = RunOptions {
+            step: None,
+            tags: Vec::new(),
+            id: None,
+            from: None,
+            until: None,
+            completed: Vec::new(),
+            ..options.clone()
+        };
+        let summary = orchestrator::run(&rerun_config(config, &changed), rerun_options)?;
+        if summary.interrupted {
+            return Ok(());
+        }
+        if !summary.success() && options.log_format == LogFormat::Pretty {
+            println!(
+                "{} {} failed; still watching",
+                style("Info:").cyan().bold(),
+                summary.failures().count()
+            );
+        }
+    }
+}
+
+/// Hash of each watched step's files, keyed by title
+fn snapshot(watched: &[&CommandEntry]) -> Result<BTreeMap<String, String>> {
+    watched
+        .iter()
+        .map(|cmd| Ok((cmd.title.clone(), cache::hash_watched(cmd)?)))
+        .collect()
+}
+
+/// A configuration containing only the `changed` steps, keeping the needs between them
+fn rerun_config(config: &Config, changed: &[&CommandEntry]) -> Config {
+    let commands = changed
+        .iter()
+        .map(|&cmd| {
+            let mut cmd = cmd.clone();
+            cmd.needs
+                .retain(|need| changed.iter().any(|other| other.is_named(need)));
+            if !cmd.watch.is_empty() {
+                // A change to an explicit `watch` glob re-runs the step even
+                // when its `sources` fingerprint is unchanged
+                cmd.sources.clear();
+                cmd.outputs.clear();
+            }
+            cmd
+        })
+        .collect();
+
+    Config {
+        commands,
+        base_dir: config.base_dir.clone(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(title: &str, needs: &[&str]) -> CommandEntry {
+        CommandEntry {
+            title: title.to_string(),
+            command: Some("true".to_string()),
+            needs: needs.iter().map(|s| s.to_string()).collect(),
+            sources: vec!["schema.graphql".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rerun_config_keeps_only_changed_steps() {
+        let install = step("Install", &[]);
+        let mut codegen = step("Codegen", &["Install"]);
+        codegen.watch = vec!["*.graphql".to_string()];
+        let typecheck = step("Typecheck", &["Install", "Codegen"]);
+        let config = Config {
+            commands: vec![install, codegen.clone(), typecheck.clone()],
+            ..Default::default()
+        };
+
+        let rerun = rerun_config(&config, &[&codegen, &typecheck]);
+
+        let titles: Vec<&str> = rerun.commands.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["Codegen", "Typecheck"]);
+        assert!(rerun.commands[0].needs.is_empty());
+        assert!(rerun.commands[0].sources.is_empty());
+        assert_eq!(rerun.commands[1].needs, ["Codegen"]);
+        assert_eq!(rerun.commands[1].sources, ["schema.graphql"]);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::config::{CommandEntry, Config};
+use color_eyre::eyre::{Result, eyre};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the config file each workspace member has
+pub const CONFIG_FILE: &str = "getset.toml";
+
+/// Directories `--recursive` does not look into for projects
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor"];
+
+/// A project set up along with the config listing (or discovering) it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    /// Its directory relative to the root config's, with `/` separators
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl Member {
+    fn new(base_dir: &Path, dir: PathBuf) -> Self {
+        let relative = dir.strip_prefix(base_dir).unwrap_or(&dir);
+        let name = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        Self { name, dir }
+    }
+
+    /// The member's own `getset.toml`
+    pub fn config_path(&self) -> PathBuf {
+        self.dir.join(CONFIG_FILE)
+    }
+}
+
+/// The members named by `[workspaces] members`, directories (or globs) relative to `base_dir`
+///
+/// Glob matches without a `getset.toml` are skipped, but a directory named
+/// exactly must have one.
+pub fn resolve(base_dir: &Path, patterns: &[String]) -> Result<Vec<Member>> {
+    let mut members = Vec::new();
+    for pattern in patterns {
+        let joined = base_dir.join(pattern);
+        if !pattern.contains(['*', '?', '[']) {
+            if !joined.join(CONFIG_FILE).is_file() {
+                return Err(eyre!(
+                    "Invalid config: workspace member '{}' has no {}",
+                    pattern,
+                    CONFIG_FILE
+                ));
+            }
+            members.push(Member::new(base_dir, joined));
+            continue;
+        }
+
+        let mut matches = glob::glob(&joined.to_string_lossy())
+            .map_err(|e| eyre!("Invalid config: bad workspace pattern '{}': {}", pattern, e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| eyre!("Error reading workspace '{}': {}", pattern, e))?;
+        matches.sort();
+        members.extend(
+            matches
+                .into_iter()
+                .filter(|dir| dir.join(CONFIG_FILE).is_file())
+                .map(|dir| Member::new(base_dir, dir)),
+        );
+    }
+    let mut seen = HashSet::new();
+    members.retain(|member| seen.insert(member.name.clone()));
+    Ok(members)
+}
+
+/// Every directory below `base_dir` with a `getset.toml`, sorted by path
+///
+/// Hidden directories and dependency directories like `node_modules` are not searched.
+pub fn discover(base_dir: &Path) -> Result<Vec<Member>> {
+    let mut dirs = Vec::new();
+    let root = if base_dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base_dir
+    };
+    walk(root, &mut dirs)?;
+    dirs.sort();
+    Ok(dirs.into_iter().map(|dir| Member::new(root, dir)).collect())
+}
+
+fn walk(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| eyre!("Error reading directory '{}': {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            let path = entry.path();
+            if path.join(CONFIG_FILE).is_file() {
+                found.push(path.clone());
+            }
+            walk(&path, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prefix the steps and services of a member's config with its name
+///
+/// Titles become `name: title` and ids `name/id`, so steps of different
+/// members never clash, and each step records the member as its project, to
+/// group the summary and report by. `needs` are rewritten to keep pointing at
+/// the member's own steps.
+pub(crate) fn namespace(config: &mut Config, name: &str) {
+    for steps in [&mut config.commands, &mut config.teardown] {
+        let renamed: Vec<(Option<String>, String)> = steps
+            .iter()
+            .map(|step| (step.id.clone(), step.title.clone()))
+            .collect();
+        for step in steps.iter_mut() {
+            for need in &mut step.needs {
+                if renamed
+                    .iter()
+                    .any(|(id, _)| id.as_deref() == Some(need.as_str()))
+                {
+                    *need = format!("{}/{}", name, need);
+                } else if renamed.iter().any(|(_, title)| title == need) {
+                    *need = format!("{}: {}", name, need);
+                }
+            }
+            prefix(step, name);
+        }
+    }
+    for service in &mut config.services {
+        service.name = format!("{}/{}", name, service.name);
+    }
+}
+
+fn prefix(step: &mut CommandEntry, name: &str) {
+    step.title = format!("{}: {}", name, step.title);
+    step.project = Some(name.to_string());
+    if let Some(ref mut id) = step.id {
+        *id = format!("{}/{}", name, id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespace_keeps_needs_inside_the_member() {
+        let mut config = Config::new("api")
+            .with_step(CommandEntry::new("Install gems", "bundle install").with_id("gems"))
+            .with_step(CommandEntry::new("Migrate", "bin/rails db:migrate").with_need("gems"))
+            .with_step(CommandEntry::new("Seed", "bin/rails db:seed").with_need("Migrate"));
+
+        namespace(&mut config, "services/api");
+
+        let steps: Vec<(Option<&str>, &str, &[String])> = config
+            .commands
+            .iter()
+            .map(|step| {
+                (
+                    step.id.as_deref(),
+                    step.title.as_str(),
+                    step.needs.as_slice(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                (
+                    Some("services/api/gems"),
+                    "services/api: Install gems",
+                    &[][..]
+                ),
+                (
+                    None,
+                    "services/api: Migrate",
+                    &["services/api/gems".to_string()][..]
+                ),
+                (
+                    None,
+                    "services/api: Seed",
+                    &["services/api: Migrate".to_string()][..]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_discover_finds_nested_projects() {
+        let dir = std::env::temp_dir().join(format!("getset-workspaces-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for project in ["web", "services/api", "web/node_modules/pkg", ".git/x"] {
+            fs::create_dir_all(dir.join(project)).unwrap();
+            fs::write(dir.join(project).join(CONFIG_FILE), "").unwrap();
+        }
+        fs::create_dir_all(dir.join("docs")).unwrap();
+
+        let names: Vec<String> = discover(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|member| member.name)
+            .collect();
+        assert_eq!(names, ["services/api", "web"]);
+
+        let members = resolve(&dir, &["services/*".to_string(), "web".to_string()]).unwrap();
+        assert_eq!(members[0].dir, dir.join("services/api"));
+        let err = resolve(&dir, &["docs".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("member 'docs' has no getset.toml"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -2,6 +2,16 @@ use assert_cmd::prelude::*;
 use predicates::prelude::*;
 use std::path::PathBuf;
 
+/// The getset binary, keeping its data directory (timings, metrics) out of the real one
+fn getset() -> std::process::Command {
+    let mut command = std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"));
+    command.env(
+        "XDG_DATA_HOME",
+        std::env::temp_dir().join(format!("getset-test-data-{}", std::process::id())),
+    );
+    command
+}
+
 /// Helper function to get the path to test fixtures
 fn get_fixture_path(filename: &str) -> PathBuf {
     let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -13,7 +23,7 @@ fn get_fixture_path(filename: &str) -> PathBuf {
 
 #[test]
 fn test_help_output() {
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("--help")
         .assert()
         .success()
@@ -26,7 +36,7 @@ fn test_help_output() {
 
 #[test]
 fn test_up_subcommand_help() {
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("--help")
         .assert()
@@ -34,14 +44,15 @@ fn test_up_subcommand_help() {
         .stdout(predicate::str::contains("Run commands from a TOML file"))
         .stdout(predicate::str::contains("--verbose"))
         .stdout(predicate::str::contains("--report"))
-        .stdout(predicate::str::contains("--step"));
+        .stdout(predicate::str::contains("--step"))
+        .stdout(predicate::str::contains("--log-format"));
 }
 
 #[test]
 fn test_valid_file_execution() {
     let fixture = get_fixture_path("valid.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg(&fixture)
         .assert()
@@ -56,7 +67,7 @@ fn test_valid_file_execution() {
 fn test_invalid_file_error() {
     let fixture = get_fixture_path("invalid.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg(&fixture)
         .assert()
@@ -66,7 +77,7 @@ fn test_invalid_file_error() {
 
 #[test]
 fn test_nonexistent_file_error() {
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("nonexistent-file-that-does-not-exist.toml")
         .assert()
@@ -81,7 +92,7 @@ fn test_nonexistent_file_error() {
 fn test_verbose_flag_shows_command_text() {
     let fixture = get_fixture_path("verbose-test.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("--verbose")
         .arg(&fixture)
@@ -95,7 +106,7 @@ fn test_verbose_flag_shows_command_text() {
 fn test_verbose_flag_without_verbose() {
     let fixture = get_fixture_path("verbose-test.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg(&fixture)
         .assert()
@@ -108,7 +119,7 @@ fn test_verbose_flag_without_verbose() {
 fn test_report_flag() {
     let fixture = get_fixture_path("valid.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("--report")
         .arg(&fixture)
@@ -122,7 +133,7 @@ fn test_default_config_file() {
     // Change to the fixtures directory so getset.toml is found
     let fixtures_dir = get_fixture_path(".");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .current_dir(fixtures_dir)
         .assert()
@@ -136,7 +147,7 @@ fn test_default_config_file() {
 fn test_step_flag_single_match() {
     let fixture = get_fixture_path("step-test.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("--step")
         .arg("production")
@@ -155,7 +166,7 @@ fn test_step_flag_single_match() {
 fn test_step_flag_multiple_matches() {
     let fixture = get_fixture_path("step-test.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("--step")
         .arg("build")
@@ -178,7 +189,7 @@ fn test_step_flag_multiple_matches() {
 fn test_step_flag_no_matches() {
     let fixture = get_fixture_path("step-test.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("--step")
         .arg("nonexistent")
@@ -194,7 +205,7 @@ fn test_step_flag_no_matches() {
 fn test_step_flag_case_insensitive() {
     let fixture = get_fixture_path("step-test.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("--step")
         .arg("PRODUCTION")
@@ -209,7 +220,7 @@ fn test_step_flag_case_insensitive() {
 fn test_step_flag_partial_match() {
     let fixture = get_fixture_path("step-test.toml");
 
-    std::process::Command::new(assert_cmd::cargo::cargo_bin!("getset"))
+    getset()
         .arg("up")
         .arg("--step")
         .arg("front")
@@ -221,3 +232,1624 @@ fn test_step_flag_partial_match() {
         .stdout(predicate::str::contains("Build backend").not())
         .stdout(predicate::str::contains("All set!"));
 }
+
+#[test]
+fn test_log_format_json_emits_lifecycle_events() {
+    let fixture = get_fixture_path("valid_config.toml");
+
+    let output = getset()
+        .arg("up")
+        .arg("--log-format")
+        .arg("json")
+        .arg(&fixture)
+        .output()
+        .expect("Failed to run getset");
+
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr is UTF-8");
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each stderr line is JSON"))
+        .collect();
+
+    let names: Vec<&str> = events
+        .iter()
+        .map(|event| event["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "step_started",
+            "step_finished",
+            "step_started",
+            "step_finished",
+            "run_complete"
+        ]
+    );
+    assert_eq!(events[0]["title"], "Test Command 1");
+    assert_eq!(events[1]["exit_code"], 0);
+    assert_eq!(events[3]["title"], "Test Command 2");
+    assert!(events[4]["duration_secs"].is_number());
+    let run_id = events[0]["run_id"].as_str().expect("events name their run");
+    assert!(!run_id.is_empty());
+    assert!(events.iter().all(|event| event["run_id"] == run_id));
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is UTF-8");
+    assert!(!stdout.contains("All set!"));
+}
+
+#[test]
+fn test_output_json_prints_the_report_to_stderr() {
+    let fixture = get_fixture_path("valid_config.toml");
+
+    let output = getset()
+        .arg("up")
+        .arg("--output")
+        .arg("json")
+        .arg("--report")
+        .arg(&fixture)
+        .output()
+        .expect("Failed to run getset");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is UTF-8");
+    for line in stdout.lines() {
+        serde_json::from_str::<serde_json::Value>(line).expect("each stdout line is JSON");
+    }
+
+    let stderr = String::from_utf8(output.stderr).expect("stderr is UTF-8");
+    assert!(stderr.contains("Report"));
+    assert!(stderr.contains("Total"));
+}
+
+#[test]
+fn test_output_json_streams_events_and_step_output() {
+    let fixture = get_fixture_path("failing-steps.toml");
+
+    let output = getset()
+        .arg("up")
+        .arg("--output")
+        .arg("json")
+        .arg(&fixture)
+        .output()
+        .expect("Failed to run getset");
+
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is UTF-8");
+    let events: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each stdout line is JSON"))
+        .collect();
+
+    let chunk = events
+        .iter()
+        .find(|event| event["event"] == "output_chunk")
+        .expect("step output is streamed");
+    assert_eq!(chunk["stream"], "stdout");
+    assert!(chunk["text"].as_str().unwrap().contains("pass 1"));
+
+    let last = events.last().unwrap();
+    assert_eq!(last["event"], "run_finished");
+    assert_eq!(last["success"], false);
+    assert!(
+        last["error"]
+            .as_str()
+            .unwrap()
+            .contains("Step 'Failing step 1' failed with exit code 3")
+    );
+}
+
+#[test]
+fn test_failure_stops_run() {
+    let fixture = get_fixture_path("failing-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("pass 1"))
+        .stdout(predicate::str::contains("pass 2").not())
+        .stderr(predicate::str::contains(
+            "Step 'Failing step 1' failed with exit code 3",
+        ));
+}
+
+#[test]
+fn test_continue_on_error_runs_remaining_steps() {
+    let fixture = get_fixture_path("failing-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg("--continue-on-error")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("pass 3"))
+        .stdout(predicate::str::contains("All set!").not())
+        .stdout(predicate::str::contains("before stopping").not())
+        .stdout(predicate::str::contains("--resume").not())
+        .stderr(predicate::str::contains("3 steps failed"))
+        .stderr(predicate::str::contains("exit code 5"));
+}
+
+#[test]
+fn test_pick_flag_without_terminal_runs_all_matches() {
+    let fixture = get_fixture_path("step-test.toml");
+
+    assert_cmd::Command::from_std(getset())
+        .arg("up")
+        .arg("--step")
+        .arg("build")
+        .arg("--pick")
+        .arg(&fixture)
+        .write_stdin("1\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Select steps").not())
+        .stdout(predicate::str::contains("Building frontend..."))
+        .stdout(predicate::str::contains("Building backend..."))
+        .stdout(predicate::str::contains("Building docs..."));
+}
+
+#[test]
+fn test_repeat_stops_at_failing_iteration() {
+    let fixture = get_fixture_path("repeat-test.toml");
+    let counter = std::env::temp_dir().join(format!("getset-repeat-{}", std::process::id()));
+    let _ = std::fs::remove_file(&counter);
+
+    getset()
+        .arg("up")
+        .arg("--no-ci")
+        .arg("--repeat")
+        .arg("0")
+        .arg(&fixture)
+        .env("GETSET_TEST_COUNTER", &counter)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("── iteration 3 ──"))
+        .stdout(predicate::str::contains("── iteration 4 ──").not())
+        .stderr(predicate::str::contains("Iteration 3 failed"))
+        .stderr(predicate::str::contains("Fail on third run"));
+
+    let runs = std::fs::read_to_string(&counter).expect("counter file written");
+    assert_eq!(runs.trim(), "3");
+    let _ = std::fs::remove_file(&counter);
+}
+
+#[test]
+fn test_repeat_runs_requested_iterations() {
+    let fixture = get_fixture_path("valid.toml");
+
+    getset()
+        .arg("up")
+        .arg("--no-ci")
+        .arg("--repeat")
+        .arg("2")
+        .arg("--report")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("── iteration 2 ──"))
+        .stdout(predicate::str::contains("── iteration 3 ──").not())
+        .stdout(predicate::str::contains("2 iterations"));
+}
+
+#[test]
+fn test_each_repeat_iteration_is_recorded_as_its_own_run() {
+    let dir = std::env::temp_dir().join(format!("getset-repeat-runs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("getset.toml"),
+        "[[commands]]\ntitle = \"Quick\"\ncommand = \"true\"\n",
+    )
+    .unwrap();
+
+    getset()
+        .args(["up", "--no-ci", "--repeat", "3"])
+        .current_dir(&dir)
+        .assert()
+        .success();
+
+    let count = |sub: &str| std::fs::read_dir(dir.join(sub)).unwrap().count();
+    assert_eq!(count(".getset/runs"), 3);
+    assert_eq!(count(".getset/logs"), 3);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_incremental_skips_unchanged_inputs() {
+    let dir = std::env::temp_dir().join(format!("getset-incremental-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("deps.lock"), "v1").unwrap();
+    std::fs::write(
+        dir.join("getset.toml"),
+        r#"
+[[commands]]
+title = "Install deps"
+command = "echo 'Installing deps'"
+inputs = ["*.lock"]
+
+[[commands]]
+title = "Always runs"
+command = "echo 'No inputs'"
+"#,
+    )
+    .unwrap();
+
+    let run = || {
+        getset()
+            .arg("up")
+            .arg("--no-ci")
+            .arg("--incremental")
+            .current_dir(&dir)
+            .assert()
+            .success()
+    };
+
+    run()
+        .stdout(predicate::str::contains("Installing deps"))
+        .stdout(predicate::str::contains("cached").not());
+    assert!(dir.join(".getset-cache").exists());
+
+    run()
+        .stdout(predicate::str::contains("Installing deps").not())
+        .stdout(predicate::str::contains("⊝ Install deps"))
+        .stdout(predicate::str::contains("No inputs"));
+
+    std::fs::write(dir.join("deps.lock"), "v2").unwrap();
+    run()
+        .stdout(predicate::str::contains("Installing deps"))
+        .stdout(predicate::str::contains("cached").not());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_step_exact_selects_only_named_step() {
+    let fixture = get_fixture_path("step-test.toml");
+
+    getset()
+        .arg("up")
+        .arg("--step")
+        .arg("Build backend")
+        .arg("--step-exact")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Building backend..."))
+        .stdout(predicate::str::contains("Found").not())
+        .stdout(predicate::str::contains("Build frontend").not())
+        .stdout(predicate::str::contains("Build documentation").not());
+}
+
+#[test]
+fn test_step_exact_rejects_partial_match() {
+    let fixture = get_fixture_path("step-test.toml");
+
+    getset()
+        .arg("up")
+        .arg("--step")
+        .arg("production")
+        .arg("--step-exact")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Deploying...").not())
+        .stderr(predicate::str::contains(
+            "No steps found matching 'production'",
+        ));
+}
+
+#[test]
+fn test_multiple_files_run_in_order() {
+    let first = get_fixture_path("valid.toml");
+    let second = get_fixture_path("verbose-test.toml");
+
+    let output = getset()
+        .arg("up")
+        .arg(&first)
+        .arg(&second)
+        .output()
+        .expect("Failed to run getset");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("stdout is UTF-8");
+    let position = |needle: &str| stdout.find(needle).expect(needle);
+    assert!(position("Echo test 1") < position("Echo test 3"));
+    assert!(position("Echo test 3") < position("Hello from verbose test"));
+    assert!(stdout.contains("All set!"));
+}
+
+#[test]
+fn test_multiple_files_step_filter_applies_across_files() {
+    let first = get_fixture_path("step-test.toml");
+    let second = get_fixture_path("verbose-test.toml");
+
+    getset()
+        .arg("up")
+        .arg("--step")
+        .arg("echo")
+        .arg(&first)
+        .arg(&second)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello from verbose test"))
+        .stdout(predicate::str::contains("Building frontend...").not());
+}
+
+#[test]
+fn test_bail_after_stops_continue_on_error_run() {
+    let fixture = get_fixture_path("failing-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg("--no-ci")
+        .arg("--continue-on-error")
+        .arg("--bail-after")
+        .arg("2")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("pass 2"))
+        .stdout(predicate::str::contains(
+            "Bailing after 2 failures: Failing step 1, Failing step 2",
+        ))
+        .stdout(predicate::str::contains("⊝ Failing step 3"))
+        .stdout(predicate::str::contains("⊝ Passing step 3"))
+        .stdout(predicate::str::contains("pass 3").not())
+        .stdout(predicate::str::contains("steps before stopping"))
+        .stderr(predicate::str::contains("2 steps failed"))
+        .stderr(predicate::str::contains("exit code 5").not());
+}
+
+#[test]
+fn test_cwd_resolves_relative_to_config_file() {
+    let fixture = get_fixture_path("cwd-test.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .current_dir(std::env::temp_dir())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("marker from cwd-test"));
+}
+
+#[test]
+fn test_allow_failure_continues_with_warning() {
+    let fixture = get_fixture_path("allow-failure.toml");
+
+    getset()
+        .arg("up")
+        .arg("--no-ci")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("⚠ Warm caches"))
+        .stdout(predicate::str::contains("After optional step"))
+        .stdout(predicate::str::contains("All set!"))
+        .stdout(predicate::str::contains("1 step was allowed to fail"))
+        .stdout(predicate::str::contains(
+            "Step 'Warm caches' failed with exit code 1",
+        ));
+}
+
+#[test]
+fn test_keep_going_reports_every_failure() {
+    let fixture = get_fixture_path("failing-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg("--keep-going")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("pass 3"))
+        .stderr(predicate::str::contains("3 steps failed"))
+        .stderr(predicate::str::contains("Failing step 1"))
+        .stderr(predicate::str::contains("exit code 5"));
+}
+
+#[test]
+fn test_dry_run_prints_plan_without_running() {
+    let fixture = get_fixture_path("failing-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg("--dry-run")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("steps would run"))
+        .stdout(predicate::str::contains("1. Passing step 1"))
+        .stdout(predicate::str::contains("$ echo 'pass 1'"))
+        .stdout(predicate::str::contains("==>").not())
+        .stdout(predicate::str::contains("All set!").not());
+}
+
+#[test]
+fn test_tag_runs_only_tagged_steps() {
+    let fixture = get_fixture_path("tagged-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg("--tag")
+        .arg("db")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("db ready"))
+        .stdout(predicate::str::contains("seeded"))
+        .stdout(predicate::str::contains("assets built").not());
+}
+
+#[test]
+fn test_steps_for_other_platforms_are_skipped() {
+    let fixture = get_fixture_path("platform-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg("--no-ci")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("⊝ Other platform step plan9 only"))
+        .stdout(predicate::str::contains("should not run").not())
+        .stdout(predicate::str::contains("runs everywhere"));
+}
+
+#[test]
+fn test_skip_if_and_only_if_conditions() {
+    let fixture = get_fixture_path("conditional-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .env_remove("MISSING_VAR")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Skipped by skip_if skip_if succeeded",
+        ))
+        .stdout(predicate::str::contains(
+            "Skipped by only_if only_if failed",
+        ))
+        .stdout(predicate::str::contains("skip_if ran").not())
+        .stdout(predicate::str::contains("only_if ran").not())
+        .stdout(predicate::str::contains("condition passed"));
+}
+
+#[test]
+fn test_check_marks_step_already_satisfied() {
+    let fixture = get_fixture_path("check-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg("--no-ci")
+        .arg("--report")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "✔︎ Already installed already satisfied",
+        ))
+        .stdout(predicate::str::contains("installing again").not())
+        .stdout(predicate::str::contains("installing now"))
+        .stdout(predicate::str::contains(
+            "(1 executed, 1 already satisfied)",
+        ));
+}
+
+#[test]
+fn test_list_prints_steps_without_running() {
+    let fixture = get_fixture_path("tagged-steps.toml");
+
+    getset()
+        .arg("list")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1. Set up database [db]"))
+        .stdout(predicate::str::contains("3. Seed data [db, seed]"))
+        .stdout(predicate::str::contains("echo").not())
+        .stdout(predicate::str::contains("db ready").not());
+}
+
+#[test]
+fn test_list_verbose_shows_commands() {
+    let fixture = get_fixture_path("tagged-steps.toml");
+
+    getset()
+        .arg("list")
+        .arg("--verbose")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("$ echo 'assets built'"));
+}
+
+#[test]
+fn test_init_writes_starter_config_without_overwriting() {
+    let dir = std::env::temp_dir().join(format!("getset-init-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let init = |force: bool| {
+        let mut cmd = getset();
+        cmd.arg("init").current_dir(&dir);
+        if force {
+            cmd.arg("--force");
+        }
+        cmd.assert()
+    };
+
+    init(false)
+        .success()
+        .stdout(predicate::str::contains("Created getset.toml"));
+    let starter = std::fs::read_to_string(dir.join("getset.toml")).unwrap();
+    assert!(starter.contains("[[commands]]"));
+    assert!(starter.contains("# [platformx]"));
+
+    std::fs::write(dir.join("getset.toml"), "# customized").unwrap();
+    init(false)
+        .failure()
+        .stderr(predicate::str::contains("already exists; use --force"));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("getset.toml")).unwrap(),
+        "# customized"
+    );
+
+    init(true).success();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("getset.toml")).unwrap(),
+        starter
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_import_makefile_writes_a_config_that_runs() {
+    let dir = std::env::temp_dir().join(format!("getset-import-make-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("Makefile"),
+        "GREETING = hello\n\nsetup: greet ## Set up\n\ngreet:\n\t@echo $(GREETING) > greeting.txt\n\nclean:\n\trm -f greeting.txt\n",
+    )
+    .unwrap();
+
+    let getset = || {
+        let mut cmd = getset();
+        cmd.current_dir(&dir);
+        cmd
+    };
+    getset()
+        .args(["import", "makefile", "--target", "setup"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Created getset.toml with 1 step from",
+        ));
+    getset()
+        .args(["import", "makefile"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists; use --force"));
+
+    getset().args(["up", "--no-logs"]).assert().success();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("greeting.txt")).unwrap(),
+        "hello\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_start_stops_every_service_when_one_exits() {
+    let dir = std::env::temp_dir().join(format!("getset-start-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("Procfile"),
+        "once: sleep 1; echo hello\nlong: sleep 30\n",
+    )
+    .unwrap();
+
+    let start = std::time::Instant::now();
+    getset()
+        .arg("start")
+        .current_dir(&dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("once | hello"))
+        .stdout(predicate::str::contains("long | stopped"))
+        .stdout(predicate::str::contains("Stopped 2 services"));
+    assert!(start.elapsed() < std::time::Duration::from_secs(20));
+
+    std::fs::write(dir.join("Procfile"), "broken: exit 3\nlong: sleep 30\n").unwrap();
+    getset()
+        .args(["start", "Procfile"])
+        .current_dir(&dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Service 'broken' exited with exit status: 3",
+        ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_validate_accepts_valid_config() {
+    let fixture = get_fixture_path("valid_config.toml");
+
+    getset()
+        .arg("validate")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("is valid"));
+}
+
+#[test]
+fn test_validate_reports_location_of_errors() {
+    let fixture = get_fixture_path("invalid_config.toml");
+
+    getset()
+        .arg("validate")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid config"))
+        .stderr(predicate::str::contains("invalid_config.toml:1:1"));
+}
+
+#[test]
+fn test_down_runs_teardown_steps() {
+    let fixture = get_fixture_path("teardown.toml");
+
+    getset()
+        .arg("down")
+        .arg("--step")
+        .arg("stop")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("stopping services"))
+        .stdout(predicate::str::contains("dropping database").not())
+        .stdout(predicate::str::contains("starting services").not())
+        .stdout(predicate::str::contains("All torn down!"));
+}
+
+#[test]
+fn test_up_ignores_teardown_steps() {
+    let fixture = get_fixture_path("teardown.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("starting services"))
+        .stdout(predicate::str::contains("stopping services").not());
+}
+
+#[test]
+fn test_doctor_prints_checklist() {
+    let fixture = get_fixture_path("doctor.toml");
+
+    getset()
+        .arg("doctor")
+        .arg("--no-ci")
+        .arg(&fixture)
+        .env("GETSET_DOCTOR_VAR", "1")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("✔︎ sh"))
+        .stdout(predicate::str::contains(
+            "✘ getset-missing-binary not found on PATH",
+        ))
+        .stdout(predicate::str::contains("✔︎ $GETSET_DOCTOR_VAR (set)"))
+        .stdout(predicate::str::contains("doctor ran a step").not())
+        .stderr(predicate::str::contains("1 of 3 doctor checks failed"));
+}
+
+#[test]
+fn test_completions_for_bash() {
+    getset()
+        .arg("completions")
+        .arg("bash")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("_getset()"))
+        .stdout(predicate::str::contains("--step"));
+}
+
+#[test]
+fn test_graph_formats() {
+    let fixture = get_fixture_path("needs-graph.toml");
+
+    getset()
+        .arg("graph")
+        .arg("--no-ci")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout("Start database\n└─▶ Migrate database\n");
+
+    getset()
+        .arg("graph")
+        .arg("--format")
+        .arg("mermaid")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("step1 --> step0"))
+        .stdout(predicate::str::contains("migrating").not());
+}
+
+#[test]
+fn test_vars_are_interpolated() {
+    let fixture = get_fixture_path("vars.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "hello from vars hello from vars via env",
+        ));
+}
+
+#[test]
+fn test_dotenv_variables_reach_steps() {
+    let fixture = get_fixture_path("dotenv/getset.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "from .env / from .env.local / from step",
+        ));
+}
+
+#[test]
+fn test_env_references_are_expanded() {
+    let fixture = get_fixture_path("env-reference.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .env("GETSET_TEST_REGION", "eu-west-1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("region: eu-west-1"));
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .env_remove("GETSET_TEST_REGION")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "uses unset environment variable 'GETSET_TEST_REGION'",
+        ));
+}
+
+#[test]
+fn test_export_warns_about_env_references() {
+    let fixture = get_fixture_path("env-reference.toml");
+
+    getset()
+        .arg("export")
+        .arg(&fixture)
+        .arg("--format")
+        .arg("just")
+        .env("GETSET_TEST_REGION", "eu-west-1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("echo 'region: eu-west-1'"))
+        .stderr(predicate::str::contains(
+            "Step 'Show region' reads ${env:GETSET_TEST_REGION}, which is written as its value on this machine",
+        ));
+}
+
+#[test]
+fn test_secret_references_are_redacted() {
+    let fixture = get_fixture_path("secret-reference.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .env("GETSET_TEST_SECRET", "abc123")
+        .env("GETSET_TEST_TOKEN", "def456")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("secret: [REDACTED]"))
+        .stdout(predicate::str::contains("token: [REDACTED]"))
+        .stdout(predicate::str::contains("abc123").not())
+        .stdout(predicate::str::contains("def456").not());
+}
+
+#[test]
+fn test_prompts_without_terminal_use_environment_and_defaults() {
+    let fixture = get_fixture_path("prompts.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .env_remove("GETSET_TEST_PROMPT_DEFAULT")
+        .env("GETSET_TEST_PROMPT_ENV", "zsh")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("editor=vim shell=zsh"));
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .env_remove("GETSET_TEST_PROMPT_ENV")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Prompt 'GETSET_TEST_PROMPT_ENV' needs an answer",
+        ));
+}
+
+#[test]
+fn test_matrix_runs_one_step_per_value() {
+    let fixture = get_fixture_path("matrix.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("setting up api"))
+        .stdout(predicate::str::contains("setting up web"))
+        .stdout(predicate::str::contains("setting up worker"))
+        .stdout(predicate::str::contains("Set up worker"));
+}
+
+#[test]
+fn test_run_by_id_runs_only_that_step() {
+    let fixture = get_fixture_path("step-ids.toml");
+
+    getset()
+        .arg("run")
+        .arg("db-reset")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("resetting database"))
+        .stdout(predicate::str::contains("installing deps").not());
+
+    getset()
+        .arg("run")
+        .arg("db")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No step with id 'db'"));
+}
+
+#[test]
+fn test_from_and_until_run_a_slice_of_steps() {
+    let fixture = get_fixture_path("step-range.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--from")
+        .arg("migrate")
+        .arg("--until")
+        .arg("Seed database")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("migrating"))
+        .stdout(predicate::str::contains("seeding"))
+        .stdout(predicate::str::contains("installing deps").not())
+        .stdout(predicate::str::contains("resetting").not());
+}
+
+#[test]
+fn test_resume_skips_steps_completed_before_failure() {
+    let dir = std::env::temp_dir().join(format!("getset-resume-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("getset.toml"),
+        r#"
+[[commands]]
+title = "Install deps"
+command = "echo 'Installing deps'"
+
+[[commands]]
+title = "Migrate"
+command = "test -f fixed && echo 'Migrating'"
+"#,
+    )
+    .unwrap();
+
+    let run = |resume: bool| {
+        let mut cmd = getset();
+        cmd.arg("up").arg("--no-ci").current_dir(&dir);
+        if resume {
+            cmd.arg("--resume");
+        }
+        cmd.assert()
+    };
+
+    run(false)
+        .failure()
+        .stdout(predicate::str::contains("Installing deps"))
+        .stdout(predicate::str::contains("getset up --resume"));
+    assert!(dir.join(".getset/state.json").exists());
+
+    getset()
+        .args(["up", "--no-ci", "--step", "Install"])
+        .current_dir(&dir)
+        .assert()
+        .success();
+    assert!(
+        dir.join(".getset/state.json").exists(),
+        "a filtered run keeps the progress to resume"
+    );
+
+    std::fs::write(dir.join("fixed"), "").unwrap();
+    run(true)
+        .success()
+        .stdout(predicate::str::contains("Installing deps").not())
+        .stdout(predicate::str::contains("⊝ Install deps"))
+        .stdout(predicate::str::contains("Migrating"));
+    assert!(!dir.join(".getset/state.json").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sources_skip_up_to_date_steps() {
+    let dir = std::env::temp_dir().join(format!("getset-sources-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("deps.lock"), "v1").unwrap();
+    std::fs::write(
+        dir.join("getset.toml"),
+        r#"
+[[commands]]
+title = "Install deps"
+command = "echo 'Installing deps' && mkdir -p vendor"
+sources = ["deps.lock"]
+outputs = ["vendor"]
+"#,
+    )
+    .unwrap();
+
+    let run = || getset().arg("up").current_dir(&dir).assert().success();
+
+    run().stdout(predicate::str::contains("Installing deps"));
+    assert!(dir.join(".getset/fingerprints.json").exists());
+
+    run()
+        .stdout(predicate::str::contains("Installing deps").not())
+        .stdout(predicate::str::contains("up to date"));
+
+    std::fs::remove_dir_all(dir.join("vendor")).unwrap();
+    run().stdout(predicate::str::contains("Installing deps"));
+
+    std::fs::write(dir.join("deps.lock"), "v2").unwrap();
+    run().stdout(predicate::str::contains("Installing deps"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cache_status_and_clear() {
+    let dir = std::env::temp_dir().join(format!("getset-cache-cmd-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("deps.lock"), "v1").unwrap();
+    std::fs::write(
+        dir.join("getset.toml"),
+        r#"
+[[commands]]
+title = "Install deps"
+command = "true"
+sources = ["deps.lock"]
+"#,
+    )
+    .unwrap();
+
+    let getset = |args: &[&str]| getset().args(args).current_dir(&dir).assert().success();
+
+    getset(&["cache", "status"])
+        .stdout(predicate::str::contains("runs: no successful run recorded"));
+    getset(&["up"]);
+    getset(&["cache", "status"]).stdout(predicate::str::contains(
+        "skipped: sources unchanged and outputs present",
+    ));
+    getset(&["cache", "clear"]).stdout(predicate::str::contains("fingerprints.json"));
+    getset(&["cache", "clear"]).stdout(predicate::str::contains("Nothing to clear"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_confirm_steps_need_yes_without_a_terminal() {
+    let fixture = get_fixture_path("confirm.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--no-logs")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Step 'Drop and recreate database' needs confirmation",
+        ))
+        .stdout(predicate::str::contains("dropped").not());
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--no-logs")
+        .arg("--yes")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("dropped"));
+}
+
+#[test]
+fn test_pick_needs_a_terminal() {
+    let fixture = get_fixture_path("confirm.toml");
+
+    getset()
+        .arg("pick")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("getset pick needs a terminal"));
+}
+
+#[test]
+fn test_tui_needs_a_terminal() {
+    let fixture = get_fixture_path("confirm.toml");
+
+    getset()
+        .arg("tui")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("getset tui needs a terminal"));
+}
+
+#[test]
+fn test_interactive_needs_a_terminal() {
+    let fixture = get_fixture_path("confirm.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--interactive")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--interactive needs a terminal"))
+        .stdout(predicate::str::contains("dropped").not());
+}
+
+#[test]
+fn test_quiet_shows_output_only_for_failed_steps() {
+    let fixture = get_fixture_path("quiet.toml");
+
+    getset()
+        .arg("up")
+        .arg("--no-ci")
+        .arg(&fixture)
+        .arg("--quiet")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("✔︎ Noisy success"))
+        .stdout(predicate::str::contains("lots of noise").not())
+        .stdout(predicate::str::contains("why it failed"));
+}
+
+#[test]
+fn test_quiet_failure_marks_or_keeps_only_stderr() {
+    let fixture = get_fixture_path("stderr.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--quiet")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "compiling\n[stderr] missing crate",
+        ));
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--quiet")
+        .arg("--stderr-only")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("missing crate"))
+        .stdout(predicate::str::contains("compiling").not());
+}
+
+#[test]
+fn test_failed_step_output_is_repeated_under_the_error() {
+    let fixture = get_fixture_path("quiet.toml");
+    let log_dir = std::env::temp_dir().join(format!("getset-tail-{}", std::process::id()));
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--log-dir")
+        .arg(&log_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Last lines of output from 'Noisy failure':\n  why it failed",
+        ))
+        .stderr(predicate::str::contains("lots of noise").not());
+
+    let _ = std::fs::remove_dir_all(&log_dir);
+}
+
+#[test]
+fn test_step_output_is_written_to_log_dir() {
+    let fixture = get_fixture_path("quiet.toml");
+    let log_dir = std::env::temp_dir().join(format!("getset-logs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&log_dir);
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--log-dir")
+        .arg(&log_dir)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("lots of noise"))
+        .stdout(predicate::str::contains("Step logs saved to"));
+
+    let run_dir = std::fs::read_dir(&log_dir)
+        .unwrap()
+        .next()
+        .expect("a run directory")
+        .unwrap()
+        .path();
+    let log = std::fs::read_to_string(run_dir.join("noisy-failure.log")).unwrap();
+    assert_eq!(log, "why it failed\n");
+
+    std::fs::remove_dir_all(&log_dir).unwrap();
+}
+
+#[test]
+fn test_parallel_step_output_is_prefixed() {
+    let fixture = get_fixture_path("parallel-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--jobs")
+        .arg("2")
+        .arg("--no-logs")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"gems\s+\| gems installed").unwrap())
+        .stdout(predicate::str::is_match(r"npm\s+\| packages installed").unwrap());
+}
+
+#[test]
+fn test_ctrl_c_stops_run_and_exits_130() {
+    let fixture = get_fixture_path("interrupt.toml");
+
+    let child = getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--no-logs")
+        .arg("--teardown-on-interrupt")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(130));
+    assert!(stdout.contains("Long step (interrupted after"));
+    assert!(stdout.contains("cleaning up"));
+    assert!(!stdout.contains("should not run"));
+    assert!(stderr.contains("Interrupted during step 'Long step'"));
+}
+
+#[test]
+fn test_html_report_includes_step_output() {
+    let fixture = get_fixture_path("valid.toml");
+    let dir = std::env::temp_dir().join(format!("getset-html-report-{}", std::process::id()));
+    let report = dir.join("report.html");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--log-dir")
+        .arg(dir.join("logs"))
+        .arg("--report-format")
+        .arg("html")
+        .arg("--report-file")
+        .arg(&report)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Report written to"));
+
+    let html = std::fs::read_to_string(&report).unwrap();
+    assert!(html.contains("<strong>Echo test 2</strong>"));
+    assert!(html.contains("<details><summary>Output</summary><pre>Test 2\n</pre></details>"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_github_step_summary_gets_markdown_table() {
+    let fixture = get_fixture_path("valid.toml");
+    let summary =
+        std::env::temp_dir().join(format!("getset-step-summary-{}.md", std::process::id()));
+    std::fs::write(&summary, "Earlier step\n").unwrap();
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--no-logs")
+        .env("GITHUB_STEP_SUMMARY", &summary)
+        .assert()
+        .success();
+
+    let markdown = std::fs::read_to_string(&summary).unwrap();
+    assert!(markdown.starts_with("Earlier step\n### ✅ getset: Succeeded"));
+    assert!(markdown.contains("| Echo test 3 | ✅ succeeded |"));
+    std::fs::remove_file(summary).unwrap();
+}
+
+#[test]
+fn test_github_actions_groups_steps_and_annotates_failures() {
+    let fixture = get_fixture_path("failing-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .env("GITHUB_ACTIONS", "true")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("::group::[1/6] Passing step 1\n"))
+        .stdout(predicate::str::contains("::endgroup::\n"))
+        .stdout(predicate::str::contains(
+            "::error title=Step failed%3A Failing step 1::'Failing step 1' failed running: exit 3\n",
+        ));
+}
+
+#[test]
+fn test_ci_format_gitlab_wraps_steps_in_sections() {
+    let fixture = get_fixture_path("valid_config.toml");
+
+    getset()
+        .arg("up")
+        .arg("--ci-format")
+        .arg("gitlab")
+        .arg(&fixture)
+        .env_remove("GITHUB_ACTIONS")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            ":getset_test_command_1[collapsed=true]\r\x1b[0K[1/2] Test Command 1\n",
+        ))
+        .stdout(predicate::str::contains("section_end:"))
+        .stdout(predicate::str::contains("::group::").not());
+}
+
+#[test]
+fn test_no_color_uses_plain_ascii_output() {
+    let fixture = get_fixture_path("valid.toml");
+
+    getset()
+        .arg("up")
+        .arg("--report")
+        .arg(&fixture)
+        .env("NO_COLOR", "1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\nAll set!"))
+        .stdout(predicate::str::contains("`--> "))
+        .stdout(predicate::str::is_match("[^\\x00-\\x7F]").unwrap().not())
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn test_ci_mode_timestamps_plain_output() {
+    let dir = std::env::temp_dir().join(format!("getset-ci-mode-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("getset.toml");
+    std::fs::write(
+        &config,
+        "[[commands]]\ntitle = \"Greet\"\ncommand = \"echo hello\"\n",
+    )
+    .unwrap();
+
+    getset()
+        .arg("up")
+        .arg("--ci")
+        .arg(&config)
+        .env_remove("GITHUB_ACTIONS")
+        .env_remove("GITLAB_CI")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"(?m)^\[\d{2}:\d{2}:\d{2}\] hello$").unwrap())
+        .stdout(predicate::str::is_match("[^\\x00-\\x7F]").unwrap().not());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_double_verbose_shows_step_env_and_skip_conditions() {
+    let fixture = get_fixture_path("conditional-steps.toml");
+
+    getset()
+        .arg("up")
+        .arg("-vv")
+        .arg(&fixture)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("    skip_if: true\n"))
+        .stdout(predicate::str::contains("    env: PRESENT=yes\n"));
+}
+
+#[test]
+fn test_log_file_records_debug_log_of_run() {
+    let fixture = get_fixture_path("valid.toml");
+    let log_file = std::env::temp_dir().join(format!("getset-debug-{}.log", std::process::id()));
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--log-file")
+        .arg(&log_file)
+        .env_remove("RUST_LOG")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("DEBUG").not());
+
+    let log = std::fs::read_to_string(&log_file).expect("log file is written");
+    std::fs::remove_file(&log_file).unwrap();
+    assert!(log.contains("DEBUG"));
+    assert!(log.contains("Read '"));
+    assert!(log.contains("Step 'Echo test 1' runs "));
+    assert!(log.contains("Step 'Echo test 1' exited with exit status: 0"));
+}
+
+#[test]
+fn test_no_telemetry_skips_platformx() {
+    let fixture = get_fixture_path("platformx-first.toml");
+    let log_file =
+        std::env::temp_dir().join(format!("getset-no-telemetry-{}.log", std::process::id()));
+
+    for (flag, env) in [("--no-telemetry", None), ("--quiet", Some("DO_NOT_TRACK"))] {
+        let mut cmd = getset();
+        if let Some(var) = env {
+            cmd.env(var, "1");
+        }
+        cmd.arg("up")
+            .arg(&fixture)
+            .arg(flag)
+            .arg("--log-file")
+            .arg(&log_file)
+            .assert()
+            .success();
+
+        let log = std::fs::read_to_string(&log_file).expect("log file is written");
+        std::fs::remove_file(&log_file).unwrap();
+        assert!(log.contains("Step 'First file command' exited"));
+        assert!(!log.contains("PlatformX"));
+    }
+}
+
+#[test]
+fn test_platformx_secret_is_only_needed_when_telemetry_is_sent() {
+    let fixture = get_fixture_path("platformx-secret-env.toml");
+
+    getset()
+        .arg("up")
+        .arg(&fixture)
+        .arg("--no-telemetry")
+        .assert()
+        .success();
+    getset()
+        .env("DO_NOT_TRACK", "1")
+        .arg("up")
+        .arg(&fixture)
+        .assert()
+        .success();
+
+    getset()
+        .env_remove("DO_NOT_TRACK")
+        .env_remove("GETSET_NO_TELEMETRY")
+        .arg("up")
+        .arg(&fixture)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "secret_key_env names environment variable 'GETSET_UNSET_DX_SECRET', which is not set",
+        ));
+}
+
+#[test]
+fn test_cancelled_library_run_stops_its_step() {
+    let config = getset::Config {
+        commands: ["sleep 30", "echo never"]
+            .map(|command| getset::config::CommandEntry {
+                title: command.to_string(),
+                command: Some(command.to_string()),
+                ..Default::default()
+            })
+            .to_vec(),
+        ..Default::default()
+    };
+
+    let handle = getset::Runner::new(config).spawn();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    handle.cancel();
+    let summary = handle.wait().expect("run should start");
+
+    assert!(summary.interrupted);
+    assert_eq!(summary.steps.len(), 1);
+    assert!(summary.duration < std::time::Duration::from_secs(3));
+}
+
+#[test]
+fn test_workspace_members_run_in_their_own_directory() {
+    let dir = std::env::temp_dir().join(format!("getset-workspace-cwd-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("web")).unwrap();
+    std::fs::write(
+        dir.join("getset.toml"),
+        "[workspaces]\nmembers = [\"web\"]\n\n[[commands]]\ntitle = \"Root\"\ncommand = \"touch root-marker\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("web/getset.toml"),
+        "[[commands]]\ntitle = \"Install\"\ncommand = \"touch web-marker\"\n",
+    )
+    .unwrap();
+
+    getset()
+        .arg("up")
+        .arg(dir.join("getset.toml"))
+        .arg("--report")
+        .current_dir(&dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"(?m)^\S+ web\n\S+ +\S+ Install ").unwrap())
+        .stdout(predicate::str::is_match(r"(?m)^  \S+ web \(1 executed\)$").unwrap());
+
+    assert!(dir.join("root-marker").is_file());
+    assert!(dir.join("web/web-marker").is_file());
+    assert!(!dir.join("web-marker").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_up_sets_up_required_projects_first() {
+    let dir = std::env::temp_dir().join(format!("getset-requires-up-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (project, requires) in [("api", "[]"), ("app", r#"["../api/getset.toml"]"#)] {
+        std::fs::create_dir_all(dir.join(project)).unwrap();
+        std::fs::write(
+            dir.join(project).join("getset.toml"),
+            format!(
+                "requires = {}\n\n[[commands]]\ntitle = \"Set up {}\"\ncommand = \"echo {} ready && touch {}-marker\"\n",
+                requires, project, project, project
+            ),
+        )
+        .unwrap();
+    }
+    let app = dir.join("app/getset.toml");
+
+    getset()
+        .arg("up")
+        .arg(&app)
+        .current_dir(&dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match("(?s)api ready.*app ready").unwrap());
+    assert!(dir.join("api/api-marker").is_file());
+    assert!(!dir.join("api-marker").exists());
+
+    getset()
+        .arg("up")
+        .arg(&app)
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(
+                "(?s)Required project .*api/getset.toml is set up first.*Set up api.*Set up app",
+            )
+            .unwrap(),
+        );
+
+    getset()
+        .arg("up")
+        .arg(&app)
+        .arg("--skip-requires")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("app ready"))
+        .stdout(predicate::str::contains("api ready").not());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_hooks_install_and_uninstall() {
+    let dir = std::env::temp_dir().join(format!("getset-hooks-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::process::Command::new("git")
+        .args(["init", "--quiet"])
+        .current_dir(&dir)
+        .status()
+        .unwrap();
+    std::fs::write(
+        dir.join("getset.toml"),
+        "[hooks]\ntags = [\"deps\"]\n\n[[commands]]\ntitle = \"Install\"\ncommand = \"true\"\ntags = [\"deps\"]\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(dir.join(".git/hooks")).unwrap();
+    std::fs::write(dir.join(".git/hooks/post-merge"), "#!/bin/sh\necho mine\n").unwrap();
+
+    let getset = || {
+        let mut cmd = getset();
+        cmd.current_dir(&dir);
+        cmd
+    };
+    getset()
+        .args(["hooks", "install"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists; use --force"));
+    getset()
+        .args(["hooks", "install", "--force"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("steps tagged deps"));
+    let hook = std::fs::read_to_string(dir.join(".git/hooks/post-checkout")).unwrap();
+    assert!(hook.ends_with("exec getset up getset.toml --tag deps\n"));
+
+    getset().args(["hooks", "uninstall"]).assert().success();
+    assert!(!dir.join(".git/hooks/post-merge").exists());
+    assert!(!dir.join(".git/hooks/post-checkout").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}